@@ -0,0 +1,339 @@
+// Generated by lr-analysis, do not edit.
+
+/// Terminals, `Terminal::Eof` marks the end of input and need not appear in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Terminal {
+    /// `(`
+    T0,
+    /// `)`
+    T1,
+    /// `*`
+    T2,
+    /// `+`
+    T3,
+    /// `-`
+    T4,
+    /// `/`
+    T5,
+    /// `;`
+    T6,
+    /// `<`
+    T7,
+    /// `=`
+    T8,
+    /// `>`
+    T9,
+    /// `{`
+    T10,
+    /// `}`
+    T11,
+    /// `<=`
+    T12,
+    /// `==`
+    T13,
+    /// `>=`
+    T14,
+    /// `ID`
+    Id,
+    /// `if`
+    If,
+    /// `NUM`
+    Num,
+    /// `else`
+    Else,
+    /// `then`
+    Then,
+    /// `while`
+    While,
+    /// `eof`
+    Eof,
+}
+
+impl Terminal {
+    pub const ALL: [Terminal; 22] = [Terminal::T0, Terminal::T1, Terminal::T2, Terminal::T3, Terminal::T4, Terminal::T5, Terminal::T6, Terminal::T7, Terminal::T8, Terminal::T9, Terminal::T10, Terminal::T11, Terminal::T12, Terminal::T13, Terminal::T14, Terminal::Id, Terminal::If, Terminal::Num, Terminal::Else, Terminal::Then, Terminal::While, Terminal::Eof];
+
+    /// The terminal as written in the grammar.
+    pub fn name(self) -> &'static str {
+        TERMINAL_NAMES[self as usize]
+    }
+}
+
+pub const TERMINAL_NAMES: [&str; 22] = ["(", ")", "*", "+", "-", "/", ";", "<", "=", ">", "{", "}", "<=", "==", ">=", "ID", "if", "NUM", "else", "then", "while", "eof"];
+
+pub const PRODUCTION_NAMES: [&str; 29] = [
+    "programprime -> program",
+    "program -> compoundstmt",
+    "stmt -> ifstmt",
+    "stmt -> whilestmt",
+    "stmt -> assgstmt",
+    "stmt -> compoundstmt",
+    "compoundstmt -> { stmts }",
+    "stmts -> stmt stmts",
+    "stmts -> E",
+    "ifstmt -> if ( boolexpr ) then stmt else stmt",
+    "whilestmt -> while ( boolexpr ) stmt",
+    "assgstmt -> ID = arithexpr ;",
+    "boolexpr -> arithexpr boolop arithexpr",
+    "boolop -> <",
+    "boolop -> >",
+    "boolop -> <=",
+    "boolop -> >=",
+    "boolop -> ==",
+    "arithexpr -> multexpr arithexprprime",
+    "arithexprprime -> + multexpr arithexprprime",
+    "arithexprprime -> - multexpr arithexprprime",
+    "arithexprprime -> E",
+    "multexpr -> simpleexpr multexprprime",
+    "multexprprime -> * simpleexpr multexprprime",
+    "multexprprime -> / simpleexpr multexprprime",
+    "multexprprime -> E",
+    "simpleexpr -> ID",
+    "simpleexpr -> NUM",
+    "simpleexpr -> ( arithexpr )",
+];
+
+/// Metadata of the grammar (`# version:` and `# meta:` directives), sorted by key.
+pub const GRAMMAR_METADATA: [(&str, &str); 0] = [];
+
+/// Version of the grammar (`# version:` directive).
+pub const GRAMMAR_VERSION: Option<&str> = None;
+
+/// Fingerprint of the grammar and the parse table this parser was generated from.
+pub const TABLE_FINGERPRINT: u64 = 0xdf0665d4177738d3;
+
+/// The augmented production, reducing by it accepts the input.
+pub const ACCEPT_PROD: usize = 0;
+
+// ACTION table, identical rows are stored once: 0 is an error, n > 0 shifts to
+// state n - 1, n < 0 reduces by production -n - 1 (production ACCEPT_PROD accepts).
+static ACTION_TABLE: [[i16; 22]; 89] = [
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, -9, 0, 0, 0, 6, 7, 0, 0, 0, 8, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [18, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, -5, -5, 0, 0, 0, -5, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -6, -6, 0, 0, 0, -6, -6, 0, 0, 0, -6, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -3, -3, 0, 0, 0, -3, -3, 0, 0, 0, -3, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -4, -4, 0, 0, 0, -4, -4, 0, 0, 0, -4, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [22, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 23, 0, 24, 0, 0, 0, 0],
+    [28, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 29, 0, 30, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -7],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -7, -7, 0, 0, 0, -7, -7, 0, 0, 0, -7, 0],
+    [36, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 37, 0, 38, 0, 0, 0, 0],
+    [0, 0, -27, -27, -27, -27, -27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, -28, -28, -28, -28, -28, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 43, 44, 0, -22, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 46, -26, -26, 47, -26, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, -27, -27, -27, -27, 0, -27, 0, -27, 0, 0, -27, -27, -27, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, -28, -28, -28, -28, 0, -28, 0, -28, 0, 0, -28, -28, -28, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 50, 0, 51, 0, 0, 52, 53, 54, 0, 0, 0, 0, 0, 0, 0],
+    [0, 56, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 57, 58, 0, 0, -22, 0, -22, 0, 0, -22, -22, -22, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 60, -26, -26, 61, 0, -26, 0, -26, 0, 0, -26, -26, -26, 0, 0, 0, 0, 0, 0, 0],
+    [0, 63, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -27, -27, -27, -27, -27, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -28, -28, -28, -28, -28, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 65, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -22, 0, 66, 67, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -26, 69, -26, -26, 70, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -12, -12, 0, 0, 0, -12, -12, 0, 0, 0, -12, 0],
+    [0, 0, 0, 0, 0, 0, -19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -23, -23, 0, -23, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 76, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [-14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -14, 0, -14, 0, 0, 0, 0],
+    [-15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -15, 0, -15, 0, 0, 0, 0],
+    [-16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -16, 0, -16, 0, 0, 0, 0],
+    [-18, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -18, 0, -18, 0, 0, 0, 0],
+    [-17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -17, 0, -17, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 78, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, -19, 0, -19, 0, 0, -19, -19, -19, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -23, -23, 0, 0, -23, 0, -23, 0, 0, -23, -23, -23, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 6, 7, 0, 0, 0, 8, 0],
+    [0, 84, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, -29, -29, -29, -29, -29, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -19, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -23, 0, -23, -23, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, -29, -29, -29, -29, 0, -29, 0, -29, 0, 0, -29, -29, -29, 0, 0, 0, 0, 0, 0, 0],
+    [0, -13, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 93, 0, 0, 0, 0, 94, 95, 0, 0, 0, 96, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -11, -11, 0, 0, 0, -11, -11, 0, 0, 0, -11, 0],
+    [0, -29, -29, -29, -29, -29, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, -20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, -21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -24, -24, 0, -24, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -25, -25, 0, -25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 111, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [112, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [113, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -5, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -6, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -3, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 114, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -4, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, -20, 0, -20, 0, 0, -20, -20, -20, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, -21, 0, -21, 0, 0, -21, -21, -21, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -24, -24, 0, 0, -24, 0, -24, 0, 0, -24, -24, -24, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, -25, -25, 0, 0, -25, 0, -25, 0, 0, -25, -25, -25, 0, 0, 0, 0, 0, 0, 0],
+    [0, -20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -21, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -24, 0, -24, -24, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, -25, 0, -25, -25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 115, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -7, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 120, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 121, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 122, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -10, -10, 0, 0, 0, -10, -10, 0, 0, 0, -10, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -12, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 123, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -11, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 126, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, -10, 0, 0, 0],
+];
+
+static ACTION_ROW: [u16; 127] = [0, 1, 2, 3, 1, 4, 5, 6, 7, 8, 9, 1, 10, 11, 12, 13, 14, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 18, 24, 25, 26, 27, 28, 29, 30, 18, 31, 32, 33, 34, 35, 36, 13, 13, 37, 13, 13, 38, 39, 40, 41, 42, 43, 44, 18, 45, 14, 14, 46, 14, 14, 47, 48, 49, 50, 18, 18, 51, 18, 18, 52, 22, 22, 23, 23, 53, 54, 55, 28, 28, 29, 29, 56, 57, 34, 34, 35, 35, 58, 59, 60, 61, 1, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 13, 14, 14, 48, 79, 80, 81, 82, 83, 84, 85, 55, 55, 86, 87, 55, 88];
+
+// GOTO table, -1 means no transition.
+static GOTO_TABLE: [[i16; 14]; 53] = [
+    [-1, -1, -1, -1, -1, 2, -1, -1, -1, 3, -1, -1, -1, -1],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 11, 12, 13],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 11, 14, 13],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 11, 18, 13],
+    [24, -1, -1, -1, -1, -1, -1, 25, -1, -1, 26, -1, -1, -1],
+    [30, -1, -1, 31, -1, -1, -1, 32, -1, -1, 33, -1, -1, -1],
+    [30, -1, -1, 34, -1, -1, -1, 32, -1, -1, 33, -1, -1, -1],
+    [38, -1, -1, -1, -1, -1, -1, 39, -1, -1, 40, -1, -1, -1],
+    [-1, 44, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 47, -1, -1, -1, -1, -1],
+    [48, -1, -1, -1, -1, -1, -1, 39, -1, -1, 40, -1, -1, -1],
+    [-1, -1, -1, -1, 54, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, 58, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 61, -1, -1, -1, -1, -1],
+    [63, -1, -1, -1, -1, -1, -1, 39, -1, -1, 40, -1, -1, -1],
+    [-1, 67, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 70, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, 71, -1, -1, 26, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, 72, -1, -1, 26, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 73, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 74, -1, -1, -1],
+    [76, -1, -1, -1, -1, -1, -1, 39, -1, -1, 40, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, 78, -1, -1, 33, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, 79, -1, -1, 33, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 80, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 81, -1, -1, -1],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 82, -1, 13],
+    [-1, -1, -1, -1, -1, -1, -1, 84, -1, -1, 40, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, 85, -1, -1, 40, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 86, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 87, -1, -1, -1],
+    [-1, 88, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, 89, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 90, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 91, -1, -1, -1, -1, -1],
+    [-1, -1, 96, -1, -1, 97, 98, -1, -1, -1, -1, 99, -1, 100],
+    [-1, 101, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, 102, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 103, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 104, -1, -1, -1, -1, -1],
+    [-1, 105, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, 106, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 107, -1, -1, -1, -1, -1],
+    [-1, -1, -1, -1, -1, -1, -1, -1, 108, -1, -1, -1, -1, -1],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 11, 109, 13],
+    [115, -1, -1, -1, -1, -1, -1, 25, -1, -1, 26, -1, -1, -1],
+    [30, -1, -1, 116, -1, -1, -1, 32, -1, -1, 33, -1, -1, -1],
+    [30, -1, -1, 117, -1, -1, -1, 32, -1, -1, 33, -1, -1, -1],
+    [-1, -1, 8, -1, -1, 9, 10, -1, -1, -1, -1, 118, -1, 13],
+    [-1, -1, 96, -1, -1, 97, 98, -1, -1, -1, -1, 123, -1, 100],
+    [-1, -1, 96, -1, -1, 97, 98, -1, -1, -1, -1, 124, -1, 100],
+    [-1, -1, 96, -1, -1, 97, 98, -1, -1, -1, -1, 126, -1, 100],
+];
+
+static GOTO_ROW: [u16; 127] = [0, 1, 2, 2, 3, 2, 2, 2, 2, 2, 2, 4, 2, 2, 2, 5, 6, 7, 2, 2, 2, 8, 2, 2, 2, 9, 10, 11, 2, 2, 12, 2, 13, 14, 2, 15, 2, 2, 2, 16, 17, 2, 18, 19, 2, 20, 21, 2, 2, 2, 2, 2, 2, 2, 22, 2, 23, 24, 2, 25, 26, 2, 27, 2, 2, 28, 29, 2, 30, 31, 2, 32, 33, 34, 35, 2, 2, 36, 37, 38, 39, 40, 2, 2, 41, 42, 43, 44, 2, 2, 2, 2, 45, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 46, 47, 48, 49, 2, 2, 2, 2, 2, 2, 2, 50, 51, 2, 2, 52, 2];
+
+static PROD_HEAD: [u16; 29] = [0, 9, 11, 11, 11, 11, 5, 12, 12, 6, 13, 2, 3, 4, 4, 4, 4, 4, 0, 1, 1, 1, 7, 8, 8, 8, 10, 10, 10];
+static PROD_LEN: [u16; 29] = [1, 1, 1, 1, 1, 1, 3, 2, 0, 8, 5, 4, 3, 1, 1, 1, 1, 1, 2, 3, 3, 0, 2, 3, 3, 0, 1, 1, 3];
+
+/// The terminal at index `at` (`Terminal::Eof` at the end of input) was unexpected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub at: usize,
+    pub found: Terminal,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unexpected {:?} at {}", self.found.name(), self.at)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// Parses `tokens`, turning every shifted token into a value with `shift` and every
+/// reduction (production id, values of the production's tail) into a value with `reduce`.
+/// Returns the value of the start symbol.
+pub fn parse_with<T, V>(
+    tokens: impl IntoIterator<Item = (Terminal, T)>,
+    mut shift: impl FnMut(Terminal, T) -> V,
+    mut reduce: impl FnMut(usize, Vec<V>) -> V,
+) -> Result<V, SyntaxError> {
+    let mut tokens = tokens.into_iter();
+    let mut next = tokens.next();
+    let mut states: Vec<usize> = vec![0];
+    let mut values: Vec<V> = Vec::new();
+    let mut pos = 0;
+    loop {
+        let found = next.as_ref().map_or(Terminal::Eof, |t| t.0);
+        let state = states[states.len() - 1];
+        let act = ACTION_TABLE[ACTION_ROW[state] as usize][found as usize];
+        if act == 0 {
+            return Err(SyntaxError { at: pos, found });
+        }
+        if act > 0 {
+            let Some((term, value)) = next.take() else {
+                return Err(SyntaxError { at: pos, found });
+            };
+            values.push(shift(term, value));
+            states.push(act as usize - 1);
+            next = tokens.next();
+            pos += 1;
+            continue;
+        }
+        let prod = (-act - 1) as usize;
+        if prod == ACCEPT_PROD {
+            return Ok(values.pop().expect("the start symbol is on the stack"));
+        }
+        reduce_by(prod, &mut states, &mut values, &mut reduce);
+    }
+}
+
+/// Pops the tail of production `prod`, pushes the reduced value and the GOTO state.
+fn reduce_by<V>(
+    prod: usize,
+    states: &mut Vec<usize>,
+    values: &mut Vec<V>,
+    reduce: &mut impl FnMut(usize, Vec<V>) -> V,
+) {
+    let len = PROD_LEN[prod] as usize;
+    let children = values.split_off(values.len() - len);
+    states.truncate(states.len() - len);
+    values.push(reduce(prod, children));
+    let state = states[states.len() - 1];
+    states.push(GOTO_TABLE[GOTO_ROW[state] as usize][PROD_HEAD[prod] as usize] as usize);
+}
+
+/// Parses `tokens`, calling `on_reduce` with the production id of every reduction.
+pub fn parse(
+    tokens: impl IntoIterator<Item = Terminal>,
+    mut on_reduce: impl FnMut(usize),
+) -> Result<(), SyntaxError> {
+    parse_with(tokens.into_iter().map(|t| (t, ())), |_, _| (), |prod, _| on_reduce(prod))
+}
@@ -0,0 +1,104 @@
+//! 生成的语法分析器和解释执行分析表的 [`Parser`] 的吞吐量对比.
+//!
+//! `generated.rs` 是使用 `codegen --lang rust` 根据 `input.txt` 生成的分析器 (测试会检查它是否和代码生成保持一致).
+//! 两边分析同一批随机生成的句子, 先检查两者的归约序列相同, 再分别计时, 输出每秒分析的终结符数量:
+//!
+//! ```shell
+//! cargo run --release --example parser_bench -- 2000 20
+//! ```
+//!
+//! 参数依次为句子数量 (默认 1000) 和重复次数 (默认 10).
+//!
+//! 解释执行的 [`Parser`] 会记录归约过程中的符号栈, 并在出错时进行恐慌恢复, 适合需要诊断信息的场合;
+//! 生成的分析器只保留压缩后的表, 遇到错误就停止, 适合只需要判断和构建语法树的热路径.
+
+// 生成的代码包含这里用不到的导出.
+#[allow(dead_code)]
+mod generated;
+
+use std::time::{Duration, Instant};
+
+use bumpalo::Bump;
+use lr_analysis::{Family, Grammar, Parser, Table, Terminal, generate::Generator};
+
+/// 把终结符转换为生成的分析器中的终结符.
+fn to_generated(term: Terminal) -> generated::Terminal {
+    let idx = generated::TERMINAL_NAMES
+        .iter()
+        .position(|&name| name == term.as_str())
+        .expect("the generated parser comes from the same grammar");
+    generated::Terminal::ALL[idx]
+}
+
+/// 重复 `rounds` 次调用 `f`, 返回总耗时.
+fn time(rounds: usize, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        f();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|a| a.parse::<usize>());
+    let count = args.next().and_then(Result::ok).unwrap_or(1000);
+    let rounds = args.next().and_then(Result::ok).unwrap_or(10);
+
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(include_str!("../../input.txt"), "program".into(), &bump)
+        .unwrap()
+        .augmented();
+    let family = Family::from_grammar(&grammar).unwrap();
+    let table = Table::build_from(&family).unwrap();
+    let parser = Parser::new(&table);
+
+    let mut generator = Generator::new(&grammar, 42);
+    let sentences: Vec<Vec<Terminal>> = (0..count)
+        .filter_map(|_| generator.sentence(200))
+        .collect();
+    let converted: Vec<Vec<generated::Terminal>> = sentences
+        .iter()
+        .map(|s| s.iter().copied().map(to_generated).collect())
+        .collect();
+    let tokens: usize = sentences.iter().map(Vec::len).sum();
+
+    for (sentence, converted) in sentences.iter().zip(&converted) {
+        let output = parser.parse(sentence.iter().copied()).unwrap();
+        let expected: Vec<_> = output.reductions().iter().map(|r| r.prod).collect();
+        let mut reductions = Vec::new();
+        generated::parse(converted.iter().copied(), |prod| reductions.push(prod)).unwrap();
+        // 生成的分析器不会报告接受时的增广产生式归约.
+        reductions.push(0);
+        assert_eq!(reductions, expected);
+    }
+
+    let interpreted = time(rounds, || {
+        for sentence in &sentences {
+            std::hint::black_box(parser.parse(sentence.iter().copied()).unwrap());
+        }
+    });
+    let compiled = time(rounds, || {
+        for sentence in &converted {
+            let mut reductions = 0;
+            generated::parse(sentence.iter().copied(), |_| reductions += 1).unwrap();
+            std::hint::black_box(reductions);
+        }
+    });
+    let throughput = |elapsed: Duration| (tokens * rounds) as f64 / elapsed.as_secs_f64();
+    println!(
+        "{} sentences, {tokens} tokens, {rounds} rounds",
+        sentences.len()
+    );
+    println!(
+        "interpreted: {interpreted:>10.2?} {:>14.0} tokens/s",
+        throughput(interpreted)
+    );
+    println!(
+        "generated:   {compiled:>10.2?} {:>14.0} tokens/s",
+        throughput(compiled)
+    );
+    println!(
+        "speedup:     {:.1}x",
+        interpreted.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
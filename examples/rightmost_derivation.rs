@@ -37,7 +37,7 @@ fn shift<'a, I>(
     family: &Family<'a>,
 ) {
     term_stream.next();
-    debug!("I_{state}: {:#?}", family.item_sets().get(state));
+    debug!("I_{state}: {:#?}", family.item_set(state));
     stack.push(state);
     step.push(term.into());
     debug!("step after shift: {step:?}");
@@ -84,7 +84,7 @@ fn reduce<'a>(
     info!("goto check, top: I_{}, prod head: {}", top, prod.head());
     if let Some(new_state) = table.goto(top, prod.head()).unwrap() {
         info!("reduce goto {new_state}");
-        debug!("I_{new_state}: {:#?}", family.item_sets().get(new_state));
+        debug!("I_{new_state}: {:#?}", family.item_set(new_state));
         stack.push(new_state);
     }
 }
@@ -129,9 +129,9 @@ simpleexpr -> ID | NUM | ( arithexpr )
     .unwrap()
     .augmented();
     // 计算集族
-    let family = Family::from_grammar(&grammar);
+    let family = Family::from_grammar(&grammar).unwrap();
     // 计算语法分析表
-    let table = Table::build_from(&family, &grammar);
+    let table = Table::build_from(&family).unwrap();
     assert!(!table.conflict());
 
     // 输入程序, 这个程序在 ID = NUM 这行出错, 少了个 `;`.
@@ -0,0 +1,144 @@
+//! 确定性审计.
+//!
+//! 对同一个文法多次从头运行完整的分析 (每次使用新的 arena), 对比每次的输出 (产生式, FOLLOW 集, 项集族,
+//! 分析表, GOTO 图, 给定种子生成的句子), 找出因为哈希表遍历顺序等原因导致的不确定输出.
+
+use std::fmt::Display;
+
+use bumpalo::Bump;
+
+use crate::{Family, Grammar, NonTerminal, Table, error::Error, generate::Generator};
+
+/// 某次运行的某项输出和第一次运行不同.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// 运行的序号, 从 0 开始, 总是大于 0.
+    pub run: usize,
+    /// 输出的名字, 见: [`snapshot`].
+    pub artifact: &'static str,
+    /// 第一个不同的行号, 从 1 开始.
+    pub line: usize,
+    /// 第一次运行中这一行的内容, 没有这一行时为空.
+    pub expected: String,
+    /// 这次运行中这一行的内容, 没有这一行时为空.
+    pub found: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "run {} diverges in {} at line {}:\n  expected: {}\n  found:    {}",
+            self.run, self.artifact, self.line, self.expected, self.found
+        )
+    }
+}
+
+/// 运行一次完整的分析, 返回所有输出 (名字, 内容), 名字依次为
+/// `grammar`, `follow`, `family`, `table`, `graph`, `sentences`.
+///
+/// # Errors
+/// 见: [`Grammar::from_cfg`], [`Family::from_grammar`], [`Table::build_from`].
+pub fn snapshot(src: &str, start: &str, seed: u64) -> Result<Vec<(&'static str, String)>, Error> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(src, NonTerminal::from(start), &bump)?.augmented();
+    let mut follows: Vec<_> = grammar.follow_sets()?.into_iter().collect();
+    follows.sort();
+    let follow = follows
+        .iter()
+        .map(|(nt, set)| {
+            let set: Vec<_> = set.iter().map(ToString::to_string).collect();
+            format!("{nt}: {}\n", set.join(" "))
+        })
+        .collect();
+    let family = Family::from_grammar(&grammar)?;
+    let table = Table::build_from(&family)?;
+    let mut generator = Generator::new(&grammar, seed);
+    let sentences = generator
+        .covering_sentences()
+        .into_iter()
+        .chain((0..20).filter_map(|_| generator.sentence(50)))
+        .map(|s| {
+            let s: Vec<_> = s.iter().map(|t| t.as_str()).collect();
+            s.join(" ") + "\n"
+        })
+        .collect();
+    Ok(vec![
+        ("grammar", format!("{grammar:#}")),
+        ("follow", follow),
+        ("family", family.to_typst()),
+        ("table", table.to_markdown()),
+        ("graph", family.to_dot(false)),
+        ("sentences", sentences),
+    ])
+}
+
+/// 第一个不同的行, 见: [`Divergence`].
+fn first_diff(expected: &str, found: &str) -> Option<(usize, String, String)> {
+    let mut expected = expected.lines();
+    let mut found = found.lines();
+    for line in 1.. {
+        match (expected.next(), found.next()) {
+            (None, None) => return None,
+            (e, f) if e != f => {
+                return Some((
+                    line,
+                    e.unwrap_or_default().to_string(),
+                    f.unwrap_or_default().to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 运行 `runs` 次 [`snapshot`], 返回每次运行中和第一次运行不同的输出, 每项输出只报告第一个不同的行.
+///
+/// # Errors
+/// 见: [`snapshot`].
+pub fn audit(src: &str, start: &str, runs: usize, seed: u64) -> Result<Vec<Divergence>, Error> {
+    let first = snapshot(src, start, seed)?;
+    let mut divergences = Vec::new();
+    for run in 1..runs {
+        for ((artifact, expected), (_, found)) in first.iter().zip(snapshot(src, start, seed)?) {
+            if let Some((line, expected, found)) = first_diff(expected, &found) {
+                divergences.push(Divergence {
+                    run,
+                    artifact,
+                    line,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    Ok(divergences)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::audit::{audit, first_diff, snapshot};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn deterministic() {
+        let src = include_str!("../input.txt");
+        assert_eq!(audit(src, "program", 5, 42).unwrap(), []);
+        let names: Vec<_> = snapshot(src, "program", 42)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            ["grammar", "follow", "family", "table", "graph", "sentences"]
+        );
+        assert_eq!(
+            first_diff("a\nb\nc", "a\nx\nc"),
+            Some((2, "b".into(), "x".into()))
+        );
+        assert_eq!(first_diff("a\nb", "a"), Some((2, "b".into(), "".into())));
+        assert_eq!(first_diff("a\nb", "a\nb"), None);
+    }
+}
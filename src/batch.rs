@@ -0,0 +1,258 @@
+//! 批量分析多个文法文件.
+//!
+//! 每个文件在独立的线程和 arena 中分析, 得到拥有所有权的 [`Summary`] (产生式, 状态和冲突的数量以及
+//! [`crate::lint`] 的检查结果), 可以汇总为一行一个文件的概要或者一个 JSON 报告.
+
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use bumpalo::Bump;
+
+use crate::{
+    Family, Grammar, Table,
+    diagnostic::Severity,
+    graph::json_str,
+    lint::{Lint, lint},
+};
+
+/// 一个文法文件的分析概要.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    pub file: PathBuf,
+    /// 产生式数量, 不包括增广产生式.
+    pub prods: usize,
+    /// 规范 LR(1) 项集族的状态数量.
+    pub states: usize,
+    /// 分析表中冲突格子的数量.
+    pub conflicts: usize,
+    pub lints: Vec<Lint>,
+    /// 无法读取文件, 或者无法构建项集族和分析表时的错误.
+    pub error: Option<String>,
+}
+
+impl Summary {
+    /// 严重程度为 [`Severity::Error`] 的问题数量.
+    #[must_use]
+    pub fn errors(&self) -> usize {
+        self.lints
+            .iter()
+            .filter(|l| l.severity() == Severity::Error)
+            .count()
+    }
+
+    #[must_use]
+    pub fn warnings(&self) -> usize {
+        self.lints
+            .iter()
+            .filter(|l| l.severity() == Severity::Warning)
+            .count()
+    }
+
+    /// 文法是否为没有错误的 LR(1) 文法.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.errors() == 0
+    }
+
+    /// 渲染为 JSON 对象.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let lints = self
+            .lints
+            .iter()
+            .map(|l| {
+                format!(
+                    "{{\"category\": {}, \"severity\": \"{}\", \"line\": {}, \"column\": {}, \"message\": {}}}",
+                    json_str(&format!("{:?}", l.category)),
+                    match l.severity() {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                        Severity::Info => "info",
+                    },
+                    l.span.line + 1,
+                    l.span.start + 1,
+                    json_str(&l.message)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\"file\": {}, \"passed\": {}, \"prods\": {}, \"states\": {}, \"conflicts\": {}, \"errors\": {}, \"warnings\": {}, \"error\": {}, \"lints\": [{lints}]}}",
+            json_str(&self.file.display().to_string()),
+            self.passed(),
+            self.prods,
+            self.states,
+            self.conflicts,
+            self.errors(),
+            self.warnings(),
+            self.error.as_deref().map_or("null".to_string(), json_str),
+        )
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.file.display())?;
+        if let Some(e) = &self.error {
+            return write!(f, "error: {e}");
+        }
+        write!(
+            f,
+            "{} productions, {} states, {} conflicts, {} errors, {} warnings",
+            self.prods,
+            self.states,
+            self.conflicts,
+            self.errors(),
+            self.warnings()
+        )
+    }
+}
+
+/// 分析文法 `src`, `file` 只用于报告.
+///
+/// `start` 为 [`None`] 时使用第一个产生式的头部作为开始符号.
+#[must_use]
+pub fn summarize(file: &Path, src: &str, start: Option<&str>) -> Summary {
+    let mut summary = Summary {
+        file: file.to_path_buf(),
+        prods: 0,
+        states: 0,
+        conflicts: 0,
+        lints: lint(src, start),
+        error: None,
+    };
+    let bump = Bump::new();
+    let start = start.unwrap_or_else(|| Grammar::cfg_start(src).unwrap_or_default());
+    let Ok(grammar) = Grammar::from_cfg(src, start.into(), &bump) else {
+        // 解析错误已经在检查结果中.
+        return summary;
+    };
+    summary.prods = grammar.prods().len();
+    // 有推导环时 FIRST 集无法收敛, 见: [`crate::lint`].
+    if summary
+        .lints
+        .iter()
+        .any(|l| l.category == crate::lint::LintCategory::EpsilonCycle)
+    {
+        return summary;
+    }
+    let grammar = grammar.augmented();
+    match Family::from_grammar(&grammar) {
+        Ok(family) => {
+            summary.states = family.len();
+            match Table::build_from(&family) {
+                Ok(table) => summary.conflicts = crate::conflict::conflicts(&table).len(),
+                Err(e) => summary.error = Some(e.to_string()),
+            }
+        }
+        Err(e) => summary.error = Some(e.to_string()),
+    }
+    summary
+}
+
+/// 使用 `jobs` 个线程分析多个文法文件, 结果的顺序和 `files` 相同.
+///
+/// 无法读取的文件在 [`Summary::error`] 中报告, 不影响其他文件.
+#[must_use]
+pub fn analyze(files: &[PathBuf], start: Option<&str>, jobs: usize) -> Vec<Summary> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Summary>>> = Mutex::new(files.iter().map(|_| None).collect());
+    std::thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, files.len().max(1)) {
+            s.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = files.get(i) else {
+                        break;
+                    };
+                    let summary = match fs::read_to_string(file) {
+                        Ok(src) => summarize(file, &src, start),
+                        Err(e) => Summary {
+                            file: file.clone(),
+                            prods: 0,
+                            states: 0,
+                            conflicts: 0,
+                            lints: Vec::new(),
+                            error: Some(e.to_string()),
+                        },
+                    };
+                    results.lock().unwrap()[i] = Some(summary);
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// 把所有文件的概要渲染为一个 JSON 报告, 包括文件数量, 通过的文件数量和每个文件的概要.
+#[must_use]
+pub fn to_json(summaries: &[Summary]) -> String {
+    let files = summaries
+        .iter()
+        .map(|s| format!("    {}", s.to_json()))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!(
+        "{{\n  \"total\": {},\n  \"passed\": {},\n  \"files\": [\n{files}\n  ]\n}}\n",
+        summaries.len(),
+        summaries.iter().filter(|s| s.passed()).count()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::batch::{analyze, summarize, to_json};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn batch() {
+        let summary = summarize(Path::new("input.txt"), include_str!("../input.txt"), None);
+        assert!(summary.passed());
+        assert_eq!(summary.conflicts, 0);
+        assert!(summary.states > 0);
+
+        let dir = std::env::temp_dir().join(format!("lr-analysis-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ambiguous = dir.join("ambiguous.txt");
+        std::fs::write(&ambiguous, "expr -> expr + expr | id").unwrap();
+        let files = [
+            PathBuf::from("input.txt"),
+            ambiguous.clone(),
+            dir.join("missing.txt"),
+        ];
+        let summaries = analyze(&files, None, 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<_> = summaries.iter().map(|s| s.file.clone()).collect();
+        assert_eq!(names, files);
+        assert_eq!(summaries[0], summary);
+        assert_eq!(
+            summaries[1].to_string(),
+            format!(
+                "{}: 2 productions, 5 states, 1 conflicts, 1 errors, 2 warnings",
+                ambiguous.display()
+            )
+        );
+        assert!(summaries[2].error.is_some() && !summaries[2].passed());
+
+        let json = to_json(&summaries);
+        assert!(json.starts_with("{\n  \"total\": 3,\n  \"passed\": 1,\n"));
+        assert!(json.contains(
+            "{\"category\": \"Conflict\", \"severity\": \"error\", \"line\": 1, \"column\": 9, "
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+//! 文法文件 (`.cfg`) 的语言服务器, 通过标准输入输出和编辑器通信.
+//!
+//! 打开或者修改文件时发布诊断信息 (解析错误, 不可达的非终结符, 冲突), 悬停时显示符号的 FIRST/FOLLOW 集,
+//! 具体内容见: [`lr_analysis::diagnostic`]. 开始符号由 `# start: S` 注释指定, 没有时为第一个产生式的头部.
+
+use std::{collections::HashMap, error::Error};
+
+use lr_analysis::diagnostic::{self, Severity, Span};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    MarkupContent, MarkupKind, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{HoverRequest, Request as _},
+};
+
+type BoxError = Box<dyn Error + Sync + Send>;
+
+/// 行内字节偏移转换为 LSP 使用的 UTF-16 偏移.
+fn utf16_col(line: &str, byte: usize) -> u32 {
+    line.get(..byte).unwrap_or(line).encode_utf16().count() as u32
+}
+
+/// LSP 使用的 UTF-16 偏移转换为行内字节偏移.
+fn byte_col(line: &str, utf16: u32) -> usize {
+    let mut units = 0;
+    for (byte, c) in line.char_indices() {
+        if units >= utf16 as usize {
+            return byte;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+fn range(src: &str, span: Span) -> Range {
+    let line = src.lines().nth(span.line).unwrap_or_default();
+    Range::new(
+        Position::new(span.line as u32, utf16_col(line, span.start)),
+        Position::new(span.line as u32, utf16_col(line, span.end)),
+    )
+}
+
+fn publish(connection: &Connection, uri: Url, src: &str) -> Result<(), BoxError> {
+    let diagnostics = diagnostic::check(src, None)
+        .into_iter()
+        .map(|d| Diagnostic {
+            range: range(src, d.span),
+            severity: Some(match d.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Info => DiagnosticSeverity::INFORMATION,
+            }),
+            source: Some("lr-analysis".to_string()),
+            message: d.message,
+            ..Diagnostic::default()
+        })
+        .collect();
+    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))?;
+    Ok(())
+}
+
+fn hover(docs: &HashMap<Url, String>, params: &HoverParams) -> Option<Hover> {
+    let pos = params.text_document_position_params.position;
+    let src = docs.get(&params.text_document_position_params.text_document.uri)?;
+    let line = src.lines().nth(pos.line as usize)?;
+    let col = byte_col(line, pos.character);
+    let (_, span) = diagnostic::word_at(src, pos.line as usize, col)?;
+    let text = diagnostic::hover(src, None, pos.line as usize, col)?;
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: text,
+        }),
+        range: Some(range(src, span)),
+    })
+}
+
+fn handle_request(
+    connection: &Connection,
+    docs: &HashMap<Url, String>,
+    req: Request,
+) -> Result<(), BoxError> {
+    let response = if req.method == HoverRequest::METHOD {
+        let (id, params) = req.extract::<HoverParams>(HoverRequest::METHOD)?;
+        Response::new_ok(id, hover(docs, &params))
+    } else {
+        Response::new_err(
+            req.id,
+            ErrorCode::MethodNotFound as i32,
+            format!("unsupported request: {}", req.method),
+        )
+    };
+    connection.sender.send(Message::Response(response))?;
+    Ok(())
+}
+
+fn handle_notification(
+    connection: &Connection,
+    docs: &mut HashMap<Url, String>,
+    not: Notification,
+) -> Result<(), BoxError> {
+    match not.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: lsp_types::DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+            let doc = params.text_document;
+            publish(connection, doc.uri.clone(), &doc.text)?;
+            docs.insert(doc.uri, doc.text);
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: lsp_types::DidChangeTextDocumentParams =
+                serde_json::from_value(not.params)?;
+            // 使用全量同步, 最后一次修改就是完整的文件内容.
+            if let Some(change) = params.content_changes.into_iter().last() {
+                let uri = params.text_document.uri;
+                publish(connection, uri.clone(), &change.text)?;
+                docs.insert(uri, change.text);
+            }
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: lsp_types::DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+            docs.remove(&params.text_document.uri);
+            publish(connection, params.text_document.uri, "")?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn main_loop(connection: Connection) -> Result<(), BoxError> {
+    let mut docs = HashMap::new();
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    break;
+                }
+                handle_request(&connection, &docs, req)?;
+            }
+            Message::Notification(not) => handle_notification(&connection, &mut docs, not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    })?;
+    connection.initialize(capabilities)?;
+    // 连接被释放之后输出线程才会结束.
+    main_loop(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,280 @@
+//! 文法的规范形式和同构判断.
+//!
+//! 把非终结符按照结构重新命名 (`N0`, `N1`, ...), 产生式按照规范的顺序排列, 得到和非终结符的名字无关的规范形式.
+//! 只是非终结符改名或者产生式顺序不同的两个文法有相同的规范形式, 可以用来发现结构相同的作业.
+//! 终结符一般由题目给定, 保留原来的名字.
+//!
+//! 规范编号的计算:
+//!
+//! 1. 颜色细分: 开始符号和其他非终结符初始颜色不同, 每一轮把非终结符的颜色细分为 (原来的颜色, 所有产生式右部的形状),
+//!    形状中的非终结符替换为它的颜色, 直到颜色的种类不再增加.
+//! 2. 从开始符号出发广度优先编号, 每个非终结符的产生式按照形状排序, 右部中的非终结符按照出现顺序编号.
+//!    不可达的非终结符按照颜色从小到大作为新的起点继续编号.
+//!
+//! 颜色相同的非终结符一般是对称的, 这时选择哪一个先编号不影响规范形式.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::{Grammar, NonTerminal, Production, Token};
+
+/// 产生式右部中的符号, 非终结符替换为颜色或者编号.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Shape<'a> {
+    Terminal(&'a str),
+    NonTerminal(usize),
+}
+
+/// 文法的规范形式.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canonical<'a> {
+    /// 按照规范编号排列的原来的非终结符.
+    order: Vec<NonTerminal<'a>>,
+    /// 规范名字, 和 `order` 一一对应.
+    names: Vec<String>,
+    /// 规范形式的产生式 (头部编号, 右部), 有序, 去掉了重复的产生式.
+    prods: BTreeSet<(usize, Vec<Shape<'a>>)>,
+}
+
+/// 文法原来的开始符号和产生式, 增广文法时去掉增广产生式.
+pub(crate) fn declared<'a>(grammar: &Grammar<'a>) -> (NonTerminal<'a>, Vec<&'a Production<'a>>) {
+    let prods: Vec<_> = (0..grammar.prods().len())
+        .filter(|&p| grammar.declared_index(p).is_some())
+        .filter_map(|p| grammar.prod(p))
+        .collect();
+    (grammar.original_start(), prods)
+}
+
+impl<'a> Canonical<'a> {
+    /// 计算文法的规范形式, 增广文法中的增广产生式会被忽略.
+    #[must_use]
+    pub fn new(grammar: &Grammar<'a>) -> Self {
+        let (start, prods) = declared(grammar);
+        let mut non_terms: Vec<NonTerminal<'a>> = vec![start];
+        for prod in &prods {
+            if !non_terms.contains(&prod.head()) {
+                non_terms.push(prod.head());
+            }
+        }
+        let shape =
+            |tail: &[Token<'a>], number: &HashMap<NonTerminal<'a>, usize>| -> Vec<Shape<'a>> {
+                tail.iter()
+                    .map(|tok| match tok {
+                        Token::Terminal(t) => Shape::Terminal(t.as_str()),
+                        Token::NonTerminal(nt) => {
+                            Shape::NonTerminal(number.get(nt).copied().unwrap_or(usize::MAX))
+                        }
+                    })
+                    .collect()
+            };
+
+        // 颜色细分.
+        let mut colors: HashMap<NonTerminal<'a>, usize> = non_terms
+            .iter()
+            .map(|&nt| (nt, usize::from(nt != start)))
+            .collect();
+        let mut count = 0;
+        loop {
+            let signatures: HashMap<NonTerminal<'a>, (usize, Vec<Vec<Shape<'a>>>)> = non_terms
+                .iter()
+                .map(|&nt| {
+                    let mut shapes: Vec<_> = prods
+                        .iter()
+                        .filter(|p| p.head() == nt)
+                        .map(|p| shape(p.tail(), &colors))
+                        .collect();
+                    shapes.sort();
+                    (nt, (colors[&nt], shapes))
+                })
+                .collect();
+            let ranks: Vec<_> = signatures
+                .values()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let refined: HashMap<_, _> = signatures
+                .iter()
+                .map(|(&nt, sig)| (nt, ranks.binary_search(&sig).unwrap_or_default()))
+                .collect();
+            colors = refined;
+            if ranks.len() == count {
+                break;
+            }
+            count = ranks.len();
+        }
+
+        // 广度优先编号.
+        let mut number: HashMap<NonTerminal<'a>, usize> = HashMap::new();
+        let mut order = Vec::new();
+        let mut roots = non_terms.clone();
+        roots.sort_by_key(|nt| (*nt != start, colors[nt]));
+        for root in roots {
+            if number.contains_key(&root) {
+                continue;
+            }
+            number.insert(root, order.len());
+            order.push(root);
+            let mut queue = VecDeque::from([root]);
+            while let Some(nt) = queue.pop_front() {
+                let mut tails: Vec<_> = prods
+                    .iter()
+                    .filter(|p| p.head() == nt)
+                    .map(|p| (shape(p.tail(), &colors), p.tail()))
+                    .collect();
+                tails.sort_by(|a, b| a.0.cmp(&b.0));
+                for (_, tail) in tails {
+                    for tok in tail {
+                        if let Token::NonTerminal(next) = *tok
+                            && !number.contains_key(&next)
+                        {
+                            number.insert(next, order.len());
+                            order.push(next);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
+        }
+
+        let terms: BTreeSet<&str> = prods
+            .iter()
+            .flat_map(|p| p.tail())
+            .filter_map(|tok| match tok {
+                Token::Terminal(t) => Some(t.as_str()),
+                Token::NonTerminal(_) => None,
+            })
+            .collect();
+        let names = (0..order.len())
+            .map(|i| {
+                // 避免和终结符重名.
+                let mut name = format!("N{i}");
+                while terms.contains(name.as_str()) {
+                    name.push('\'');
+                }
+                name
+            })
+            .collect();
+        let prods = prods
+            .iter()
+            .map(|p| (number[&p.head()], shape(p.tail(), &number)))
+            .collect();
+        Self {
+            order,
+            names,
+            prods,
+        }
+    }
+
+    /// 按照规范编号排列的原来的非终结符, 开始符号的编号为 0.
+    #[must_use]
+    pub fn non_terms(&self) -> &[NonTerminal<'a>] {
+        &self.order
+    }
+
+    /// 非终结符的规范名字, 不在文法中时返回 [`None`].
+    #[must_use]
+    pub fn name_of(&self, nt: NonTerminal<'_>) -> Option<&str> {
+        let idx = self.order.iter().position(|n| n.as_str() == nt.as_str())?;
+        Some(&self.names[idx])
+    }
+}
+
+impl Display for Canonical<'_> {
+    /// 规范形式的文法文件, 每个非终结符一行, 可以再次使用 [`Grammar::from_cfg`] 解析.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (head, tail) in &self.prods {
+            let tail: Vec<_> = tail
+                .iter()
+                .map(|s| match s {
+                    Shape::Terminal(t) => *t,
+                    Shape::NonTerminal(n) => &self.names[*n],
+                })
+                .collect();
+            lines.entry(*head).or_default().push(tail.join(" "));
+        }
+        for (head, tails) in lines {
+            writeln!(f, "{} -> {}", self.names[head], tails.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 判断两个文法是否同构 (只有非终结符的名字和产生式的顺序不同),
+/// 同构时返回非终结符的对应关系 (`a` 中的非终结符, `b` 中的非终结符), 按照规范编号排列.
+#[must_use]
+pub fn isomorphism<'a, 'b>(
+    a: &Grammar<'a>,
+    b: &Grammar<'b>,
+) -> Option<Vec<(NonTerminal<'a>, NonTerminal<'b>)>> {
+    let (ca, cb) = (Canonical::new(a), Canonical::new(b));
+    (ca.to_string() == cb.to_string()).then(|| ca.order.into_iter().zip(cb.order).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Grammar, NonTerminal,
+        canonical::{Canonical, isomorphism},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn canonical_form() {
+        let bump = Bump::new();
+        let a = Grammar::from_cfg(
+            "expr -> expr + term | term\nterm -> term * factor | factor\nfactor -> ( expr ) | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap();
+        let b = Grammar::from_cfg(
+            "F -> id | ( S )\nS -> T | S + T\nT -> F | T * F",
+            "S".into(),
+            &bump,
+        )
+        .unwrap();
+        let canonical = Canonical::new(&a);
+        assert_eq!(
+            canonical.to_string(),
+            "N0 -> N0 + N1 | N1\nN1 -> N1 * N2 | N2\nN2 -> ( N0 ) | id\n"
+        );
+        assert_eq!(canonical.name_of("factor".into()), Some("N2"));
+        assert_eq!(Canonical::new(&b).to_string(), canonical.to_string());
+        // 增广产生式不影响规范形式.
+        assert_eq!(
+            Canonical::new(&b.clone_into(&bump).augmented()).to_string(),
+            canonical.to_string()
+        );
+        let mapping: Vec<_> = isomorphism(&a, &b)
+            .unwrap()
+            .into_iter()
+            .map(|(x, y)| (x.to_string(), y.to_string()))
+            .collect();
+        assert_eq!(
+            mapping,
+            [
+                ("expr".into(), "S".into()),
+                ("term".into(), "T".into()),
+                ("factor".into(), "F".into())
+            ]
+        );
+
+        // 运算符交换了位置, 结构不同.
+        let c = Grammar::from_cfg(
+            "expr -> expr * term | term\nterm -> term + factor | factor\nfactor -> ( expr ) | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap();
+        assert_eq!(isomorphism(&a, &c), None);
+        assert_eq!(
+            Canonical::new(&c).non_terms(),
+            [NonTerminal::from("expr"), "term".into(), "factor".into()]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! 语法分析器代码生成.
+//!
+//! 先把 [`Table`] 压缩成 [`CompressedTable`]: ACTION 表和 GOTO 表中相同的行只保留一份,
+//! 每个状态通过行号表找到自己的行. 然后由各个目标语言的后端输出分析表和分析循环.
+//!
+//! 恐慌模式错误恢复 (恢复表和恢复分析函数) 需要通过 [`Options::recovery`] 显式开启,
+//! 不需要错误恢复的嵌入式场景可以省掉这部分代码.
+
+pub mod c;
+pub mod rust;
+
+use crate::{
+    ActionCell, EPSILON, NonTerminal, ProdId, Table, Terminal, error::Error, panic::PanicAction,
+    replay::fingerprint,
+};
+
+/// 代码生成选项.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// 生成恐慌模式错误恢复, 见: [`CompressedTable::recovery`].
+    pub recovery: bool,
+    /// 为每个非终结符生成 AST 枚举, 仅用于 Rust 后端.
+    pub ast: bool,
+}
+
+/// 压缩后的语法分析表.
+///
+/// ACTION 表格子的编码:
+/// - `0` 表示出错.
+/// - `n > 0` 表示移入状态 `n - 1`.
+/// - `n < 0` 表示使用产生式 `-n - 1` 归约, 使用增广产生式 ([`CompressedTable::accept_prod`]) 归约就是接受.
+///
+/// GOTO 表格子为到达的状态编号, `-1` 表示没有转换.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedTable<'a> {
+    /// ACTION 表的列对应的终结符, 不包括 [`EPSILON`], [`crate::EOF`] 在最后.
+    pub terms: Vec<Terminal<'a>>,
+    /// 所有的非终结符.
+    pub non_terms: Vec<NonTerminal<'a>>,
+    /// GOTO 表的列对应的非终结符, 不包括整列为空的非终结符 (例如增广文法的开始符号).
+    pub goto_non_terms: Vec<NonTerminal<'a>>,
+    /// 去重之后的 ACTION 表的行.
+    pub action_rows: Vec<Vec<i32>>,
+    /// 每个状态对应的 ACTION 表的行号.
+    pub action_row_of: Vec<usize>,
+    /// 去重之后的 GOTO 表的行.
+    pub goto_rows: Vec<Vec<i32>>,
+    /// 每个状态对应的 GOTO 表的行号.
+    pub goto_row_of: Vec<usize>,
+    /// 每个产生式头部在 [`CompressedTable::non_terms`] 中的下标.
+    pub prod_heads: Vec<usize>,
+    /// 每个产生式头部在 GOTO 表中的列, 即在 [`CompressedTable::goto_non_terms`] 中的下标,
+    /// 头部的列被去掉时为 `0` (这些产生式归约之后不会查询 GOTO 表, 例如增广产生式).
+    pub prod_gotos: Vec<usize>,
+    /// 每个产生式尾部的长度, 不包括 [`EPSILON`].
+    pub prod_lens: Vec<usize>,
+    /// 每个产生式的文本, 用于生成注释.
+    pub prod_texts: Vec<String>,
+    /// 文法的元数据, 按照键排列, 见: [`crate::Grammar::metadata`].
+    pub metadata: Vec<(String, String)>,
+    /// 分析表的指纹, 见: [`crate::replay::fingerprint`].
+    pub fingerprint: u64,
+    /// 增广产生式的编号, 使用它归约就是接受, 见: [`crate::Grammar::augmented_production`].
+    pub accept_prod: ProdId,
+}
+
+/// 压缩后的恐慌恢复表, 见: [`CompressedTable::recovery`].
+///
+/// 格子的编码和 ACTION 表相同, 列和 [`CompressedTable::terms`] 一致:
+/// - `0` 表示无法恢复, 跳过当前的终结符.
+/// - `n > 0` 表示插入一个期望的终结符并移入状态 `n - 1`.
+/// - `n < 0` 表示不考虑当前的终结符, 强制使用产生式 `-n - 1` 归约.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedRecovery {
+    /// 去重之后的恢复表的行.
+    pub rows: Vec<Vec<i32>>,
+    /// 每个状态对应的恢复表的行号.
+    pub row_of: Vec<usize>,
+    /// 插入终结符之后到达的状态对应的终结符在 [`CompressedTable::terms`] 中的下标, 其他状态为 `-1`.
+    pub inserted: Vec<i32>,
+}
+
+/// 把行去重, 返回去重后的行和每一行对应的新行号.
+fn dedup_rows(rows: Vec<Vec<i32>>) -> (Vec<Vec<i32>>, Vec<usize>) {
+    let mut unique: Vec<Vec<i32>> = Vec::new();
+    let mut row_of = Vec::with_capacity(rows.len());
+    for row in rows {
+        let idx = match unique.iter().position(|r| *r == row) {
+            Some(idx) => idx,
+            None => {
+                unique.push(row);
+                unique.len() - 1
+            }
+        };
+        row_of.push(idx);
+    }
+    (unique, row_of)
+}
+
+impl<'a> CompressedTable<'a> {
+    /// 压缩语法分析表.
+    ///
+    /// # Errors
+    /// - [`Error::AmbiguousGrammar`] 分析表中有冲突, 无法生成确定的分析器.
+    pub fn from_table(table: &Table<'a>) -> Result<Self, Error> {
+        if table.conflict() {
+            Err(Error::AmbiguousGrammar)?
+        }
+        let terms: Vec<_> = table
+            .terms()
+            .iter()
+            .copied()
+            .filter(|&t| t != EPSILON)
+            .collect();
+        let non_terms = table.non_terms().to_vec();
+        let goto_non_terms = table.goto_columns();
+        let accept_prod = table.grammar().augmented_production().unwrap_or_default();
+        let action = (0..table.rows())
+            .map(|state| {
+                terms
+                    .iter()
+                    .map(|&t| match table.action(state, t) {
+                        Some(ActionCell::Shift(to)) => *to as i32 + 1,
+                        Some(ActionCell::Reduce(prod)) => -(*prod as i32) - 1,
+                        Some(ActionCell::Accept) => -(accept_prod as i32) - 1,
+                        _ => 0,
+                    })
+                    .collect()
+            })
+            .collect();
+        let goto = (0..table.rows())
+            .map(|state| {
+                goto_non_terms
+                    .iter()
+                    .map(|&nt| table.goto(state, nt).flatten().map_or(-1, |to| to as i32))
+                    .collect()
+            })
+            .collect();
+        let (action_rows, action_row_of) = dedup_rows(action);
+        let (goto_rows, goto_row_of) = dedup_rows(goto);
+        let prods = table.grammar().prods();
+        let reduces: Vec<_> = (0..prods.len())
+            .filter_map(|p| table.reduce_info(p))
+            .collect();
+        let prod_gotos = prods
+            .iter()
+            .map(|p| {
+                goto_non_terms
+                    .iter()
+                    .position(|&nt| nt == p.head())
+                    .unwrap_or(0)
+            })
+            .collect();
+        Ok(Self {
+            terms,
+            non_terms,
+            goto_non_terms,
+            action_rows,
+            action_row_of,
+            goto_rows,
+            goto_row_of,
+            prod_heads: reduces.iter().map(|r| r.head).collect(),
+            prod_gotos,
+            prod_lens: reduces.iter().map(|r| r.len).collect(),
+            prod_texts: prods.iter().map(ToString::to_string).collect(),
+            metadata: table
+                .grammar()
+                .metadata()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            fingerprint: fingerprint(table),
+            accept_prod,
+        })
+    }
+
+    /// 压缩恐慌恢复表, 用于生成带有错误恢复的分析函数.
+    ///
+    /// 生成的分析器在运行时不再试探候选动作, 每个格子预先选择在到达这个状态的最短路径
+    /// (见: [`crate::Family::access_path`]) 对应的状态栈上, [`Table::panic_candidates`] 中评分最高的动作.
+    ///
+    /// # Errors
+    /// 见: [`Table::panic_candidates`].
+    pub fn recovery(&self, table: &Table<'a>) -> Result<CompressedRecovery, Error> {
+        let mut inserted = vec![-1; self.states()];
+        let mut rows = Vec::with_capacity(self.states());
+        for state in 0..self.states() {
+            let stack: Vec<_> = match table.family().access_path(state) {
+                Some(path) => std::iter::once(0)
+                    .chain(path.iter().map(|&(_, to)| to))
+                    .collect(),
+                None => vec![state],
+            };
+            let mut row = Vec::with_capacity(self.terms.len());
+            for &term in &self.terms {
+                let action = table
+                    .panic_candidates(&stack, &[term])?
+                    .into_iter()
+                    .next()
+                    .map(|c| c.action);
+                row.push(match action {
+                    Some(PanicAction::Shift(expected, to)) => {
+                        inserted[to] = self
+                            .terms
+                            .iter()
+                            .position(|&t| t == expected)
+                            .map_or(-1, |col| col as i32);
+                        to as i32 + 1
+                    }
+                    Some(PanicAction::Reduce(prod)) => -(prod as i32) - 1,
+                    Some(PanicAction::Accept) => -1,
+                    _ => 0,
+                });
+            }
+            rows.push(row);
+        }
+        let (rows, row_of) = dedup_rows(rows);
+        Ok(CompressedRecovery {
+            rows,
+            row_of,
+            inserted,
+        })
+    }
+
+    #[must_use]
+    pub fn states(&self) -> usize {
+        self.action_row_of.len()
+    }
+
+    /// 所有表项的绝对值的最大值, 用于选择表项的整数类型.
+    #[must_use]
+    pub fn max_value(&self) -> u32 {
+        self.action_rows
+            .iter()
+            .chain(&self.goto_rows)
+            .flatten()
+            .map(|v| v.unsigned_abs())
+            .chain(
+                self.action_row_of
+                    .iter()
+                    .chain(&self.goto_row_of)
+                    .map(|&v| v as u32),
+            )
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// 把符号转换成目标语言中的标识符, 如果符号本身不是合法的标识符, 那么使用其下标.
+pub(crate) fn ident(prefix: &str, name: &str, idx: usize) -> String {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        format!("{prefix}{name}")
+    } else {
+        format!("{prefix}{idx}")
+    }
+}
+
+/// 把符号转换成 `CamelCase` 的类型名: 按 `_` 分段, 每段首字母大写, 全大写的段其余字母小写.
+///
+/// 符号不是合法的标识符时返回 [`None`].
+pub(crate) fn camel(name: &str) -> Option<String> {
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    if !valid {
+        return None;
+    }
+    let mut out = String::new();
+    for part in name.split('_').filter(|p| !p.is_empty()) {
+        let upper = part.chars().all(|c| !c.is_ascii_lowercase());
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            out.push(first.to_ascii_uppercase());
+        }
+        if upper {
+            out.extend(chars.map(|c| c.to_ascii_lowercase()));
+        } else {
+            out.extend(chars);
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// 给重复的名字依次加上下标后缀, 保证所有名字互不相同.
+pub(crate) fn unique(names: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if seen.insert(name.clone()) {
+                name
+            } else {
+                let name = format!("{name}{i}");
+                seen.insert(name.clone());
+                name
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{EOF, Family, Grammar, Table, codegen::CompressedTable};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn compress_rows() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let compressed = CompressedTable::from_table(&table).unwrap();
+        assert_eq!(compressed.states(), table.rows());
+        assert_eq!(compressed.terms.last(), Some(&EOF));
+        assert!(compressed.action_rows.len() < table.rows());
+        assert!(compressed.goto_rows.len() < table.rows());
+        for state in 0..table.rows() {
+            let row = &compressed.action_rows[compressed.action_row_of[state]];
+            for (col, &term) in compressed.terms.iter().enumerate() {
+                let cell = table.action(state, term).unwrap();
+                assert_eq!(row[col] == 0, cell.is_empty());
+            }
+        }
+        // I_3: programprime -> program dot, 在 eof 上接受.
+        let accept = compressed.action_row_of[table.goto(0, "program".into()).unwrap().unwrap()];
+        assert_eq!(compressed.accept_prod, 0);
+        assert_eq!(compressed.action_rows[accept].last(), Some(&-1));
+        let recovery = compressed.recovery(&table).unwrap();
+        assert_eq!(recovery.row_of.len(), table.rows());
+        assert!(recovery.rows.len() < table.rows());
+        // 插入终结符之后到达的状态都记录了插入的终结符.
+        for row in &recovery.rows {
+            for &cell in row.iter().filter(|&&c| c > 0) {
+                let to = cell as usize - 1;
+                let term = compressed.terms[recovery.inserted[to] as usize];
+                assert!(
+                    (0..table.rows())
+                        .any(|s| table.action(s, term) == Some(&crate::ActionCell::Shift(to)))
+                );
+            }
+        }
+    }
+}
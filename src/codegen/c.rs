@@ -0,0 +1,589 @@
+//! C 语言后端: 输出一个头文件和一个源文件, 只依赖 C99 标准库.
+//!
+//! 生成的 `<prefix>_parse` 函数接受终结符编号的数组 (不需要以 EOF 结尾),
+//! 每次归约都会调用回调函数, 遇到语法错误时返回出错的位置.
+//! 开启 [`Options::recovery`] 时额外生成 `<prefix>_parse_recover`, 在恐慌模式下从语法错误中恢复.
+
+use std::fmt::Write;
+
+use crate::{
+    EOF, Table,
+    codegen::{CompressedTable, Options, ident},
+    error::Error,
+    grammar::VERSION_KEY,
+};
+
+/// 生成的 C 代码.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CSource {
+    /// 头文件 `<prefix>.h` 的内容.
+    pub header: String,
+    /// 源文件 `<prefix>.c` 的内容.
+    pub source: String,
+}
+
+/// 把二维表输出为 C 的数组初始化列表, 每行一个 `{...}`.
+fn rows_text(rows: &[Vec<i32>]) -> String {
+    rows.iter()
+        .map(|row| {
+            let cells = row
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("    {{{cells}}},")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn list_text(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(", ")
+}
+
+/// C 字符串字面量, 只转义 C 中需要转义的字符.
+fn c_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '?' => out += "\\?",
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 生成 C 语言的语法分析器, `prefix` 为所有导出符号的前缀 (同时也是文件名), 应该是合法的 C 标识符.
+///
+/// # Errors
+/// 见: [`CompressedTable::from_table`], [`CompressedTable::recovery`].
+pub fn generate(table: &Table, prefix: &str, options: &Options) -> Result<CSource, Error> {
+    let compressed = CompressedTable::from_table(table)?;
+    let recovery = if options.recovery {
+        Some(compressed.recovery(table)?)
+    } else {
+        None
+    };
+    let lower = prefix.to_lowercase();
+    let upper = prefix.to_uppercase();
+    let (cell_type, index_type) = if compressed.max_value() <= i16::MAX as u32 {
+        ("int16_t", "uint16_t")
+    } else {
+        ("int32_t", "uint32_t")
+    };
+
+    let mut header = String::new();
+    // 写入 String 不会失败.
+    let _ = writeln!(header, "/* Generated by lr-analysis, do not edit. */");
+    let _ = writeln!(header, "#ifndef {upper}_H\n#define {upper}_H\n");
+    let _ = writeln!(header, "#include <stddef.h>\n");
+    let _ = writeln!(
+        header,
+        "/* Terminal ids, {upper}_T_EOF marks the end of input and need not appear in it. */"
+    );
+    let _ = writeln!(header, "enum {lower}_terminal {{");
+    for (i, term) in compressed.terms.iter().enumerate() {
+        let name = if *term == EOF {
+            format!("{upper}_T_EOF")
+        } else {
+            ident(&format!("{upper}_T_"), term.as_str(), i)
+        };
+        let _ = writeln!(header, "    {name} = {i}, /* {} */", c_str(term.as_str()));
+    }
+    let _ = writeln!(header, "}};\n");
+    let _ = writeln!(
+        header,
+        "#define {upper}_NUM_TERMINALS {}",
+        compressed.terms.len()
+    );
+    let _ = writeln!(
+        header,
+        "#define {upper}_NUM_PRODUCTIONS {}",
+        compressed.prod_lens.len()
+    );
+    let _ = writeln!(
+        header,
+        "#define {upper}_ACCEPT_PROD {}",
+        compressed.accept_prod
+    );
+    let _ = writeln!(
+        header,
+        "#define {upper}_NUM_METADATA {}",
+        compressed.metadata.len()
+    );
+    let _ = writeln!(
+        header,
+        "#define {upper}_TABLE_FINGERPRINT {:#018x}ULL\n",
+        compressed.fingerprint
+    );
+    let _ = writeln!(
+        header,
+        "/* Version of the grammar (`# version:` directive), NULL if absent. */\nextern const char *const {lower}_grammar_version;"
+    );
+    let _ = writeln!(
+        header,
+        "/* Grammar metadata as {{key, value}} pairs sorted by key, ended by {{NULL, NULL}}. */\nextern const char *const {lower}_metadata[{upper}_NUM_METADATA + 1][2];\n"
+    );
+    let _ = writeln!(
+        header,
+        "/* Called on every reduction with the production id, {upper}_ACCEPT_PROD means accept. */"
+    );
+    let _ = writeln!(
+        header,
+        "typedef void (*{lower}_reduce_fn)(int production, void *ctx);\n"
+    );
+    let _ = writeln!(
+        header,
+        "extern const char *const {lower}_terminal_names[{upper}_NUM_TERMINALS];"
+    );
+    let _ = writeln!(
+        header,
+        "extern const char *const {lower}_production_names[{upper}_NUM_PRODUCTIONS];\n"
+    );
+    let _ = writeln!(
+        header,
+        concat!(
+            "/*\n",
+            " * Parses `len` terminal ids, `on_reduce` may be NULL.\n",
+            " * Returns 1 if accepted, 0 on syntax error (`*error_at` is set to the index of\n",
+            " * the offending terminal, `len` for the end of input), -1 if out of memory.\n",
+            " */"
+        )
+    );
+    let _ = writeln!(
+        header,
+        "int {lower}_parse(const int *tokens, size_t len, {lower}_reduce_fn on_reduce, void *ctx,\n    size_t *error_at);\n"
+    );
+    if recovery.is_some() {
+        let _ = writeln!(
+            header,
+            concat!(
+                "/*\n",
+                " * Recovery steps passed to the error callback: a skipped terminal, an inserted\n",
+                " * terminal, a forced reduction (the production id) or giving up at the end of input.\n",
+                " */"
+            )
+        );
+        let _ = writeln!(
+            header,
+            "enum {lower}_recovery {{\n    {upper}_R_SKIPPED,\n    {upper}_R_INSERTED,\n    {upper}_R_REDUCED,\n    {upper}_R_ESCAPED,\n}};\n"
+        );
+        let _ = writeln!(
+            header,
+            "typedef void (*{lower}_error_fn)(enum {lower}_recovery kind, size_t at, int id, void *ctx);\n"
+        );
+        let _ = writeln!(
+            header,
+            concat!(
+                "/*\n",
+                " * Like `{lower}_parse`, but recovers from syntax errors in panic mode and reports\n",
+                " * every step to `on_error` (may be NULL). Returns 1 if accepted, 0 if the parser\n",
+                " * gave up at the end of input, -1 if out of memory.\n",
+                " */"
+            ),
+            lower = lower
+        );
+        let _ = writeln!(
+            header,
+            "int {lower}_parse_recover(const int *tokens, size_t len, {lower}_reduce_fn on_reduce,\n    {lower}_error_fn on_error, void *ctx);\n"
+        );
+    }
+    let _ = write!(header, "#endif /* {upper}_H */");
+
+    let mut source = String::new();
+    let _ = writeln!(source, "/* Generated by lr-analysis, do not edit. */");
+    let _ = writeln!(
+        source,
+        "#include \"{lower}.h\"\n\n#include <stdint.h>\n#include <stdlib.h>\n"
+    );
+    let _ = writeln!(
+        source,
+        "const char *const {lower}_terminal_names[{upper}_NUM_TERMINALS] = {{{}}};\n",
+        list_text(compressed.terms.iter().map(|t| c_str(t.as_str())))
+    );
+    let _ = writeln!(
+        source,
+        "const char *const {lower}_grammar_version = {};\n",
+        compressed
+            .metadata
+            .iter()
+            .find(|(k, _)| k == VERSION_KEY)
+            .map_or("NULL".to_string(), |(_, v)| c_str(v))
+    );
+    let _ = writeln!(
+        source,
+        "const char *const {lower}_metadata[{upper}_NUM_METADATA + 1][2] = {{\n{}    {{NULL, NULL}},\n}};\n",
+        compressed
+            .metadata
+            .iter()
+            .map(|(k, v)| format!("    {{{}, {}}},\n", c_str(k), c_str(v)))
+            .collect::<String>()
+    );
+    let _ = writeln!(
+        source,
+        "const char *const {lower}_production_names[{upper}_NUM_PRODUCTIONS] = {{\n{}\n}};\n",
+        compressed
+            .prod_texts
+            .iter()
+            .map(|p| format!("    {},", c_str(p)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let _ = writeln!(
+        source,
+        concat!(
+            "/*\n",
+            " * ACTION table, identical rows are stored once: 0 is an error, n > 0 shifts to\n",
+            " * state n - 1, n < 0 reduces by production -n - 1 ({upper}_ACCEPT_PROD accepts).\n",
+            " */"
+        ),
+        upper = upper
+    );
+    let _ = writeln!(
+        source,
+        "static const {cell_type} action_table[{}][{upper}_NUM_TERMINALS] = {{\n{}\n}};\n",
+        compressed.action_rows.len(),
+        rows_text(&compressed.action_rows)
+    );
+    let _ = writeln!(
+        source,
+        "static const {index_type} action_row[{}] = {{{}}};\n",
+        compressed.states(),
+        list_text(compressed.action_row_of.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(source, "/* GOTO table, -1 means no transition. */");
+    let _ = writeln!(
+        source,
+        "static const {cell_type} goto_table[{}][{}] = {{\n{}\n}};\n",
+        compressed.goto_rows.len(),
+        compressed.goto_non_terms.len().max(1),
+        rows_text(&compressed.goto_rows)
+    );
+    let _ = writeln!(
+        source,
+        "static const {index_type} goto_row[{}] = {{{}}};\n",
+        compressed.states(),
+        list_text(compressed.goto_row_of.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(
+        source,
+        "static const {index_type} prod_head[{upper}_NUM_PRODUCTIONS] = {{{}}};",
+        list_text(compressed.prod_gotos.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(
+        source,
+        "static const {index_type} prod_len[{upper}_NUM_PRODUCTIONS] = {{{}}};\n",
+        list_text(compressed.prod_lens.iter().map(ToString::to_string))
+    );
+    let _ = write!(
+        source,
+        r#"int {lower}_parse(const int *tokens, size_t len, {lower}_reduce_fn on_reduce, void *ctx,
+    size_t *error_at)
+{{
+    size_t cap = 64, top = 0, pos = 0;
+    int *stack = malloc(cap * sizeof *stack);
+    if (stack == NULL) {{
+        return -1;
+    }}
+    stack[0] = 0;
+    for (;;) {{
+        int term = pos < len ? tokens[pos] : {upper}_T_EOF;
+        int act = 0;
+        if (term >= 0 && term < {upper}_NUM_TERMINALS) {{
+            act = action_table[action_row[stack[top]]][term];
+        }}
+        if (act == 0) {{
+            if (error_at != NULL) {{
+                *error_at = pos;
+            }}
+            free(stack);
+            return 0;
+        }}
+        if (act < 0) {{
+            int prod = -act - 1;
+            if (on_reduce != NULL) {{
+                on_reduce(prod, ctx);
+            }}
+            if (prod == {upper}_ACCEPT_PROD) {{
+                free(stack);
+                return 1;
+            }}
+            top -= prod_len[prod];
+            act = goto_table[goto_row[stack[top]]][prod_head[prod]] + 1;
+        }} else {{
+            pos++;
+        }}
+        if (top + 1 == cap) {{
+            int *grown = realloc(stack, cap * 2 * sizeof *stack);
+            if (grown == NULL) {{
+                free(stack);
+                return -1;
+            }}
+            stack = grown;
+            cap *= 2;
+        }}
+        stack[++top] = act - 1;
+    }}
+}}
+"#
+    );
+    if let Some(recovery) = recovery {
+        let _ = writeln!(
+            source,
+            concat!(
+                "\n/*\n",
+                " * Panic mode recovery table: 0 skips the terminal, n > 0 inserts a terminal and\n",
+                " * shifts to state n - 1, n < 0 forces a reduction by production -n - 1.\n",
+                " */"
+            )
+        );
+        let _ = writeln!(
+            source,
+            "static const {cell_type} recovery_table[{}][{upper}_NUM_TERMINALS] = {{\n{}\n}};\n",
+            recovery.rows.len(),
+            rows_text(&recovery.rows)
+        );
+        let _ = writeln!(
+            source,
+            "static const {index_type} recovery_row[{}] = {{{}}};\n",
+            compressed.states(),
+            list_text(recovery.row_of.iter().map(ToString::to_string))
+        );
+        let _ = writeln!(
+            source,
+            "/* The terminal inserted to reach each state, -1 if it is not reached by a terminal. */"
+        );
+        let _ = writeln!(
+            source,
+            "static const {cell_type} inserted_terminal[{}] = {{{}}};\n",
+            compressed.states(),
+            list_text(recovery.inserted.iter().map(ToString::to_string))
+        );
+        let _ = writeln!(
+            source,
+            "/* Recovery steps in a row without consuming input before a terminal is skipped. */"
+        );
+        let _ = writeln!(
+            source,
+            "static const size_t stall_limit = {};\n",
+            compressed.states() * 2 + 16
+        );
+        let _ = write!(
+            source,
+            r#"int {lower}_parse_recover(const int *tokens, size_t len, {lower}_reduce_fn on_reduce,
+    {lower}_error_fn on_error, void *ctx)
+{{
+    size_t cap = 64, top = 0, pos = 0, stall = 0;
+    int *stack = malloc(cap * sizeof *stack);
+    if (stack == NULL) {{
+        return -1;
+    }}
+    stack[0] = 0;
+    for (;;) {{
+        int term = pos < len ? tokens[pos] : {upper}_T_EOF;
+        int valid = term >= 0 && term < {upper}_NUM_TERMINALS;
+        int act = valid ? action_table[action_row[stack[top]]][term] : 0;
+        int consume = act > 0;
+        if (act == 0) {{
+            if (valid && ++stall <= stall_limit) {{
+                act = recovery_table[recovery_row[stack[top]]][term];
+            }}
+            if (act == 0) {{
+                if (pos >= len) {{
+                    if (on_error != NULL) {{
+                        on_error({upper}_R_ESCAPED, pos, {upper}_T_EOF, ctx);
+                    }}
+                    free(stack);
+                    return 0;
+                }}
+                if (on_error != NULL) {{
+                    on_error({upper}_R_SKIPPED, pos, term, ctx);
+                }}
+                pos++;
+                stall = 0;
+                continue;
+            }}
+            if (on_error != NULL) {{
+                if (act > 0) {{
+                    on_error({upper}_R_INSERTED, pos, inserted_terminal[act - 1], ctx);
+                }} else {{
+                    on_error({upper}_R_REDUCED, pos, -act - 1, ctx);
+                }}
+            }}
+        }}
+        if (act < 0) {{
+            int prod = -act - 1;
+            if (on_reduce != NULL) {{
+                on_reduce(prod, ctx);
+            }}
+            if (prod == {upper}_ACCEPT_PROD) {{
+                free(stack);
+                return 1;
+            }}
+            top -= prod_len[prod];
+            act = goto_table[goto_row[stack[top]]][prod_head[prod]] + 1;
+        }} else if (consume) {{
+            pos++;
+            stall = 0;
+        }}
+        if (top + 1 == cap) {{
+            int *grown = realloc(stack, cap * 2 * sizeof *stack);
+            if (grown == NULL) {{
+                free(stack);
+                return -1;
+            }}
+            stack = grown;
+            cap *= 2;
+        }}
+        stack[++top] = act - 1;
+    }}
+}}
+"#
+        );
+    }
+    Ok(CSource { header, source })
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Table,
+        codegen::{Options, c::generate},
+    };
+
+    #[test]
+    fn c_source() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let c = generate(&table, "paren", &Options::default()).unwrap();
+        assert!(c.header.contains("    PAREN_T_0 = 0, /* \"(\" */\n"));
+        assert!(c.header.contains("    PAREN_T_EOF = 2, /* \"eof\" */\n"));
+        assert!(
+            c.header
+                .contains("#define PAREN_NUM_PRODUCTIONS 3\n#define PAREN_ACCEPT_PROD 0\n#define PAREN_NUM_METADATA 0\n")
+        );
+        assert!(
+            c.source
+                .contains("            if (prod == PAREN_ACCEPT_PROD) {\n")
+        );
+        assert!(
+            c.source
+                .contains("const char *const paren_grammar_version = NULL;\n")
+        );
+        assert!(
+            c.source
+                .starts_with("/* Generated by lr-analysis, do not edit. */\n#include \"paren.h\"")
+        );
+        assert!(c.source.contains("static const int16_t action_table["));
+        assert!(
+            c.source.contains(
+                "int paren_parse(const int *tokens, size_t len, paren_reduce_fn on_reduce"
+            )
+        );
+        assert!(!c.source.contains("recovery_table"));
+        let options = Options {
+            recovery: true,
+            ..Options::default()
+        };
+        let mut grammar = grammar.clone_into(&bump);
+        grammar.set_metadata("version", "1.0");
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let c = generate(&table, "paren", &options).unwrap();
+        assert!(
+            c.source
+                .contains("const char *const paren_grammar_version = \"1.0\";\n")
+        );
+        assert!(c.source.contains(
+            "paren_metadata[PAREN_NUM_METADATA + 1][2] = {\n    {\"version\", \"1.0\"},\n    {NULL, NULL},\n};"
+        ));
+        assert!(c.header.contains("    PAREN_R_INSERTED,\n"));
+        assert!(c.source.contains("static const int16_t recovery_table["));
+        assert!(
+            c.source
+                .contains("int paren_parse_recover(const int *tokens, size_t len,")
+        );
+    }
+
+    /// 使用 `-std=c99 -Wall -Werror` 编译生成的分析器并分析合法和不合法的输入, 没有 `cc` 时跳过.
+    #[test]
+    fn compiled_parser() {
+        if std::process::Command::new("cc")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let options = Options {
+            recovery: true,
+            ..Options::default()
+        };
+        let c = generate(&table, "paren", &options).unwrap();
+        let dir = std::env::temp_dir().join(format!("lr-analysis-c-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("paren.h"), c.header).unwrap();
+        std::fs::write(dir.join("paren.c"), c.source).unwrap();
+        std::fs::write(
+            dir.join("main.c"),
+            r#"#include <stdio.h>
+#include "paren.h"
+
+static void on_reduce(int production, void *ctx) {
+    (void)ctx;
+    printf("r%d ", production);
+}
+
+static void on_error(enum paren_recovery kind, size_t at, int id, void *ctx) {
+    (void)ctx;
+    printf("e%d@%lu:%d ", (int)kind, (unsigned long)at, id);
+}
+
+int main(void) {
+    const int valid[] = {PAREN_T_0, PAREN_T_1, PAREN_T_0, PAREN_T_1};
+    const int invalid[] = {PAREN_T_0, PAREN_T_1, PAREN_T_1};
+    size_t at = 0;
+    int ok = paren_parse(valid, 4, on_reduce, NULL, &at);
+    printf("= %d\n", ok);
+    ok = paren_parse(invalid, 3, NULL, NULL, &at);
+    printf("= %d at %lu\n", ok, (unsigned long)at);
+    ok = paren_parse_recover(invalid, 3, NULL, on_error, NULL);
+    printf("= %d\n", ok);
+    return 0;
+}
+"#,
+        )
+        .unwrap();
+        let status = std::process::Command::new("cc")
+            .current_dir(&dir)
+            .args(["-std=c99", "-Wall", "-Werror", "-o", "parser"])
+            .args(["main.c", "paren.c"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let output = std::process::Command::new(dir.join("parser"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "r2 r2 r2 r1 r1 r0 = 1\n= 0 at 2\ne1@2:0 = 1\n"
+        );
+    }
+}
@@ -0,0 +1,808 @@
+//! Rust 后端: 输出一个不依赖任何 crate 的模块文件.
+//!
+//! 生成的模块包括终结符枚举 `Terminal`, 压缩后的分析表和通用的分析函数 `parse_with`.
+//! 可选地为每个非终结符生成一个 AST 枚举 (每个产生式一个变体, 每个尾部符号一个字段),
+//! 放在子模块 `ast` 中, 通过 `ast::parse` 直接得到类型化的语法树.
+//! 开启 [`Options::recovery`] 时额外生成恐慌模式错误恢复的分析函数 `parse_with_recovery`.
+
+use std::fmt::Write;
+
+use bumpalo::Bump;
+
+use crate::{
+    EOF, Family, Grammar, NonTerminal, Table, Token,
+    codegen::{CompressedTable, Options, camel, unique},
+    conflict::ConflictPolicy,
+    error::Error,
+    grammar::VERSION_KEY,
+};
+
+/// 生成的 AST 中不能用作类型名的名字, 非终结符转换后与之重复时加上 `Node` 后缀.
+const RESERVED: [&str; 10] = [
+    "Token",
+    "Value",
+    "Terminal",
+    "SyntaxError",
+    "Box",
+    "Vec",
+    "String",
+    "Option",
+    "Result",
+    "Self",
+];
+
+/// 把二维表输出为 Rust 的数组表达式, 每行一个 `[...]`.
+fn rows_text(rows: &[Vec<i32>]) -> String {
+    rows.iter()
+        .map(|row| {
+            let cells = row
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("    [{cells}],")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn list_text(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(", ")
+}
+
+/// 终结符枚举的变体名, 与 [`CompressedTable::terms`] 一一对应.
+fn terminal_variants(compressed: &CompressedTable) -> Vec<String> {
+    unique(
+        compressed
+            .terms
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                if *t == EOF {
+                    "Eof".to_string()
+                } else {
+                    camel(t.as_str())
+                        .filter(|name| name != "Self")
+                        .unwrap_or_else(|| format!("T{i}"))
+                }
+            })
+            .collect(),
+    )
+}
+
+/// 非终结符对应的 AST 类型名, 与 [`CompressedTable::non_terms`] 一一对应.
+fn non_terminal_types(compressed: &CompressedTable) -> Vec<String> {
+    unique(
+        compressed
+            .non_terms
+            .iter()
+            .enumerate()
+            .map(|(i, nt)| match camel(nt.as_str()) {
+                Some(name) if RESERVED.contains(&name.as_str()) => format!("{name}Node"),
+                Some(name) => name,
+                None => format!("N{i}"),
+            })
+            .collect(),
+    )
+}
+
+/// 产生式对应的变体名: 尾部符号的类型名拼接, 空产生式为 `Empty`.
+fn production_variant(tail: &[Token], idx: usize) -> String {
+    if tail.is_empty() {
+        return "Empty".to_string();
+    }
+    let name: String = tail.iter().filter_map(|t| camel(t.as_str())).collect();
+    if name.is_empty() || name == "Self" {
+        format!("P{idx}")
+    } else {
+        name
+    }
+}
+
+/// 生成 Rust 语言的语法分析器模块.
+///
+/// [`Options::ast`] 为 `true` 时额外生成 `ast` 子模块, 包含每个非终结符的 AST 枚举和构建语法树的归约动作.
+///
+/// # Errors
+/// - [`Error::GrammarNotAugmented`] 文法没有增广, 无法确定开始符号.
+/// - 其他见: [`CompressedTable::from_table`], [`CompressedTable::recovery`].
+pub fn generate(table: &Table, options: &Options) -> Result<String, Error> {
+    let compressed = CompressedTable::from_table(table)?;
+    let (cell_type, index_type) = if compressed.max_value() <= i16::MAX as u32 {
+        ("i16", "u16")
+    } else {
+        ("i32", "u32")
+    };
+    let terms = terminal_variants(&compressed);
+
+    let mut out = String::new();
+    // 写入 String 不会失败.
+    let _ = writeln!(out, "// Generated by lr-analysis, do not edit.\n");
+    let _ = writeln!(
+        out,
+        "/// Terminals, `Terminal::Eof` marks the end of input and need not appear in it."
+    );
+    let _ = writeln!(
+        out,
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]\npub enum Terminal {{"
+    );
+    for (variant, term) in terms.iter().zip(&compressed.terms) {
+        let _ = writeln!(out, "    /// `{}`", term.as_str().replace('`', "\\`"));
+        let _ = writeln!(out, "    {variant},");
+    }
+    let _ = writeln!(out, "}}\n");
+    let _ = writeln!(
+        out,
+        "impl Terminal {{\n    pub const ALL: [Terminal; {}] = [{}];\n",
+        terms.len(),
+        list_text(terms.iter().map(|v| format!("Terminal::{v}")))
+    );
+    let _ = writeln!(
+        out,
+        "    /// The terminal as written in the grammar.\n    pub fn name(self) -> &'static str {{\n        TERMINAL_NAMES[self as usize]\n    }}\n}}\n"
+    );
+    let _ = writeln!(
+        out,
+        "pub const TERMINAL_NAMES: [&str; {}] = [{}];\n",
+        compressed.terms.len(),
+        list_text(compressed.terms.iter().map(|t| format!("{:?}", t.as_str())))
+    );
+    let _ = writeln!(
+        out,
+        "pub const PRODUCTION_NAMES: [&str; {}] = [\n{}\n];\n",
+        compressed.prod_texts.len(),
+        compressed
+            .prod_texts
+            .iter()
+            .map(|p| format!("    {p:?},"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let _ = writeln!(
+        out,
+        "/// Metadata of the grammar (`# version:` and `# meta:` directives), sorted by key.\npub const GRAMMAR_METADATA: [(&str, &str); {}] = [{}];\n",
+        compressed.metadata.len(),
+        list_text(
+            compressed
+                .metadata
+                .iter()
+                .map(|(k, v)| format!("({k:?}, {v:?})"))
+        )
+    );
+    let _ = writeln!(
+        out,
+        "/// Version of the grammar (`# version:` directive).\npub const GRAMMAR_VERSION: Option<&str> = {};\n",
+        compressed
+            .metadata
+            .iter()
+            .find(|(k, _)| k == VERSION_KEY)
+            .map_or("None".to_string(), |(_, v)| format!("Some({v:?})"))
+    );
+    let _ = writeln!(
+        out,
+        "/// Fingerprint of the grammar and the parse table this parser was generated from.\npub const TABLE_FINGERPRINT: u64 = {:#018x};\n",
+        compressed.fingerprint
+    );
+    let _ = writeln!(
+        out,
+        "/// The augmented production, reducing by it accepts the input.\npub const ACCEPT_PROD: usize = {};\n",
+        compressed.accept_prod
+    );
+    let _ = writeln!(
+        out,
+        concat!(
+            "// ACTION table, identical rows are stored once: 0 is an error, n > 0 shifts to\n",
+            "// state n - 1, n < 0 reduces by production -n - 1 (production ACCEPT_PROD accepts)."
+        )
+    );
+    let _ = writeln!(
+        out,
+        "static ACTION_TABLE: [[{cell_type}; {}]; {}] = [\n{}\n];\n",
+        compressed.terms.len(),
+        compressed.action_rows.len(),
+        rows_text(&compressed.action_rows)
+    );
+    let _ = writeln!(
+        out,
+        "static ACTION_ROW: [{index_type}; {}] = [{}];\n",
+        compressed.states(),
+        list_text(compressed.action_row_of.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(out, "// GOTO table, -1 means no transition.");
+    let _ = writeln!(
+        out,
+        "static GOTO_TABLE: [[{cell_type}; {}]; {}] = [\n{}\n];\n",
+        compressed.goto_non_terms.len(),
+        compressed.goto_rows.len(),
+        rows_text(&compressed.goto_rows)
+    );
+    let _ = writeln!(
+        out,
+        "static GOTO_ROW: [{index_type}; {}] = [{}];\n",
+        compressed.states(),
+        list_text(compressed.goto_row_of.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(
+        out,
+        "static PROD_HEAD: [{index_type}; {}] = [{}];",
+        compressed.prod_gotos.len(),
+        list_text(compressed.prod_gotos.iter().map(ToString::to_string))
+    );
+    let _ = writeln!(
+        out,
+        "static PROD_LEN: [{index_type}; {}] = [{}];\n",
+        compressed.prod_lens.len(),
+        list_text(compressed.prod_lens.iter().map(ToString::to_string))
+    );
+    out += DRIVER;
+    if options.recovery {
+        let recovery = compressed.recovery(table)?;
+        let _ = writeln!(
+            out,
+            concat!(
+                "\n// Panic mode recovery table: 0 skips the terminal, n > 0 inserts a terminal and\n",
+                "// shifts to state n - 1, n < 0 forces a reduction by production -n - 1."
+            )
+        );
+        let _ = writeln!(
+            out,
+            "static RECOVERY_TABLE: [[{cell_type}; {}]; {}] = [\n{}\n];\n",
+            compressed.terms.len(),
+            recovery.rows.len(),
+            rows_text(&recovery.rows)
+        );
+        let _ = writeln!(
+            out,
+            "static RECOVERY_ROW: [{index_type}; {}] = [{}];\n",
+            compressed.states(),
+            list_text(recovery.row_of.iter().map(ToString::to_string))
+        );
+        let _ = writeln!(
+            out,
+            "// The terminal inserted to reach each state, -1 if it is not reached by a terminal."
+        );
+        let _ = writeln!(
+            out,
+            "static INSERTED_TERMINAL: [{cell_type}; {}] = [{}];\n",
+            compressed.states(),
+            list_text(recovery.inserted.iter().map(ToString::to_string))
+        );
+        let _ = writeln!(
+            out,
+            "// Recovery steps in a row without consuming input before a terminal is skipped.\nconst STALL_LIMIT: usize = {};\n",
+            compressed.states() * 2 + 16
+        );
+        out += RECOVERY_DRIVER;
+    }
+    if options.ast {
+        out += "\n";
+        out += &ast_module(table, &compressed, options)?;
+    }
+    Ok(out)
+}
+
+/// 与文法无关的分析函数.
+const DRIVER: &str = r#"/// The terminal at index `at` (`Terminal::Eof` at the end of input) was unexpected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub at: usize,
+    pub found: Terminal,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unexpected {:?} at {}", self.found.name(), self.at)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// Parses `tokens`, turning every shifted token into a value with `shift` and every
+/// reduction (production id, values of the production's tail) into a value with `reduce`.
+/// Returns the value of the start symbol.
+pub fn parse_with<T, V>(
+    tokens: impl IntoIterator<Item = (Terminal, T)>,
+    mut shift: impl FnMut(Terminal, T) -> V,
+    mut reduce: impl FnMut(usize, Vec<V>) -> V,
+) -> Result<V, SyntaxError> {
+    let mut tokens = tokens.into_iter();
+    let mut next = tokens.next();
+    let mut states: Vec<usize> = vec![0];
+    let mut values: Vec<V> = Vec::new();
+    let mut pos = 0;
+    loop {
+        let found = next.as_ref().map_or(Terminal::Eof, |t| t.0);
+        let state = states[states.len() - 1];
+        let act = ACTION_TABLE[ACTION_ROW[state] as usize][found as usize];
+        if act == 0 {
+            return Err(SyntaxError { at: pos, found });
+        }
+        if act > 0 {
+            let Some((term, value)) = next.take() else {
+                return Err(SyntaxError { at: pos, found });
+            };
+            values.push(shift(term, value));
+            states.push(act as usize - 1);
+            next = tokens.next();
+            pos += 1;
+            continue;
+        }
+        let prod = (-act - 1) as usize;
+        if prod == ACCEPT_PROD {
+            return Ok(values.pop().expect("the start symbol is on the stack"));
+        }
+        reduce_by(prod, &mut states, &mut values, &mut reduce);
+    }
+}
+
+/// Pops the tail of production `prod`, pushes the reduced value and the GOTO state.
+fn reduce_by<V>(
+    prod: usize,
+    states: &mut Vec<usize>,
+    values: &mut Vec<V>,
+    reduce: &mut impl FnMut(usize, Vec<V>) -> V,
+) {
+    let len = PROD_LEN[prod] as usize;
+    let children = values.split_off(values.len() - len);
+    states.truncate(states.len() - len);
+    values.push(reduce(prod, children));
+    let state = states[states.len() - 1];
+    states.push(GOTO_TABLE[GOTO_ROW[state] as usize][PROD_HEAD[prod] as usize] as usize);
+}
+
+/// Parses `tokens`, calling `on_reduce` with the production id of every reduction.
+pub fn parse(
+    tokens: impl IntoIterator<Item = Terminal>,
+    mut on_reduce: impl FnMut(usize),
+) -> Result<(), SyntaxError> {
+    parse_with(tokens.into_iter().map(|t| (t, ())), |_, _| (), |prod, _| on_reduce(prod))
+}
+"#;
+
+/// 恐慌模式错误恢复的分析函数, 和 [`crate::Parser`] 一样: 先查恢复表, 无法恢复时跳过当前终结符,
+/// 连续多次恢复都没有消耗输入时也跳过当前终结符, 防止死循环.
+const RECOVERY_DRIVER: &str = r#"/// A step taken by `parse_with_recovery`, `at` is the index of the current token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The unexpected token was dropped.
+    Skipped { at: usize, terminal: Terminal },
+    /// A missing terminal was inserted before the current token.
+    Inserted { at: usize, terminal: Terminal },
+    /// A reduction was forced regardless of the current token.
+    Reduced { at: usize, production: usize },
+}
+
+/// Like `parse_with`, but recovers from syntax errors in panic mode and reports every step
+/// to `on_error`. Inserted terminals are shifted with `T::default()`. Fails only if the
+/// parser gives up at the end of input.
+pub fn parse_with_recovery<T: Default, V>(
+    tokens: impl IntoIterator<Item = (Terminal, T)>,
+    mut shift: impl FnMut(Terminal, T) -> V,
+    mut reduce: impl FnMut(usize, Vec<V>) -> V,
+    mut on_error: impl FnMut(Recovery),
+) -> Result<V, SyntaxError> {
+    let mut tokens = tokens.into_iter();
+    let mut next = tokens.next();
+    let mut states: Vec<usize> = vec![0];
+    let mut values: Vec<V> = Vec::new();
+    let mut pos = 0;
+    let mut stall = 0;
+    loop {
+        let found = next.as_ref().map_or(Terminal::Eof, |t| t.0);
+        let state = states[states.len() - 1];
+        let mut act = ACTION_TABLE[ACTION_ROW[state] as usize][found as usize];
+        if act == 0 {
+            stall += 1;
+            if stall <= STALL_LIMIT {
+                act = RECOVERY_TABLE[RECOVERY_ROW[state] as usize][found as usize];
+            }
+            if act == 0 {
+                let Some((terminal, _)) = next.take() else {
+                    return Err(SyntaxError { at: pos, found });
+                };
+                on_error(Recovery::Skipped { at: pos, terminal });
+                next = tokens.next();
+                pos += 1;
+                stall = 0;
+                continue;
+            }
+            if act > 0 {
+                let terminal = Terminal::ALL[INSERTED_TERMINAL[act as usize - 1] as usize];
+                on_error(Recovery::Inserted { at: pos, terminal });
+                values.push(shift(terminal, T::default()));
+                states.push(act as usize - 1);
+                continue;
+            }
+            on_error(Recovery::Reduced {
+                at: pos,
+                production: (-act - 1) as usize,
+            });
+        } else if act > 0 {
+            let Some((term, value)) = next.take() else {
+                return Err(SyntaxError { at: pos, found });
+            };
+            values.push(shift(term, value));
+            states.push(act as usize - 1);
+            next = tokens.next();
+            pos += 1;
+            stall = 0;
+            continue;
+        }
+        let prod = (-act - 1) as usize;
+        if prod == ACCEPT_PROD {
+            return Ok(values.pop().expect("the start symbol is on the stack"));
+        }
+        reduce_by(prod, &mut states, &mut values, &mut reduce);
+    }
+}
+"#;
+
+/// 生成 `ast` 子模块.
+fn ast_module(
+    table: &Table,
+    compressed: &CompressedTable,
+    options: &Options,
+) -> Result<String, Error> {
+    let grammar = table.grammar();
+    let prods = grammar.prods();
+    let accept = grammar
+        .augmented_production()
+        .ok_or(Error::GrammarNotAugmented)?;
+    let augmented = Some(prods[accept].head());
+    let types = non_terminal_types(compressed);
+    let type_of = |nt: NonTerminal| {
+        compressed
+            .non_terms
+            .iter()
+            .position(|&n| n == nt)
+            .map(|i| types[i].as_str())
+            .ok_or_else(|| Error::NonTerminalNotFound(nt.to_string()))
+    };
+    let start = grammar.original_start();
+
+    // 每个产生式的变体名, 增广产生式没有变体.
+    let mut variants = vec![String::new(); prods.len()];
+    for (i, nt) in compressed.non_terms.iter().enumerate() {
+        if Some(*nt) == augmented {
+            continue;
+        }
+        let ids: Vec<_> = (0..prods.len())
+            .filter(|&p| compressed.prod_heads[p] == i)
+            .collect();
+        let names = unique(
+            ids.iter()
+                .map(|&p| {
+                    let tail: Vec<_> = prods[p].tail_without_eps().copied().collect();
+                    production_variant(&tail, p)
+                })
+                .collect(),
+        );
+        for (p, name) in ids.into_iter().zip(names) {
+            variants[p] = name;
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "/// Typed syntax tree, one enum per nonterminal and one variant per production."
+    );
+    let _ = writeln!(out, "pub mod ast {{");
+    let _ = writeln!(out, "    use super::{{SyntaxError, Terminal}};\n");
+    let _ = writeln!(
+        out,
+        "    /// A shifted terminal with its source text.\n    #[derive(Debug, Clone, PartialEq, Eq)]\n    pub struct Token {{\n        pub terminal: Terminal,\n        pub text: String,\n    }}"
+    );
+    for (i, nt) in compressed.non_terms.iter().enumerate() {
+        if Some(*nt) == augmented {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "\n    /// `{}`\n    #[derive(Debug, Clone, PartialEq, Eq)]\n    pub enum {} {{",
+            nt.as_str(),
+            types[i]
+        );
+        for (p, prod) in prods.iter().enumerate() {
+            if compressed.prod_heads[p] != i {
+                continue;
+            }
+            let fields = prod
+                .tail_without_eps()
+                .map(|t| match t {
+                    Token::Terminal(_) => Ok("Token".to_string()),
+                    Token::NonTerminal(nt) => Ok(format!("Box<{}>", type_of(*nt)?)),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let _ = writeln!(out, "        /// `{prod}`");
+            if fields.is_empty() {
+                let _ = writeln!(out, "        {},", variants[p]);
+            } else {
+                let _ = writeln!(out, "        {}({}),", variants[p], fields.join(", "));
+            }
+        }
+        let _ = writeln!(out, "    }}");
+    }
+
+    let _ = writeln!(out, "\n    enum Value {{\n        Token(Token),");
+    for (i, nt) in compressed.non_terms.iter().enumerate() {
+        if Some(*nt) != augmented {
+            let _ = writeln!(out, "        {0}({0}),", types[i]);
+        }
+    }
+    let _ = writeln!(out, "    }}\n");
+    let _ = writeln!(
+        out,
+        "    fn token(value: Option<Value>) -> Token {{\n        match value {{\n            Some(Value::Token(t)) => t,\n            _ => unreachable!(\"the parse table only reduces matching values\"),\n        }}\n    }}"
+    );
+    // 只为出现在产生式尾部的非终结符生成取值函数.
+    for (i, nt) in compressed.non_terms.iter().enumerate() {
+        let used = prods
+            .iter()
+            .enumerate()
+            .any(|(p, prod)| p != accept && prod.tail().contains(&Token::NonTerminal(*nt)));
+        if !used {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "\n    fn take_{i}(value: Option<Value>) -> Box<{0}> {{\n        match value {{\n            Some(Value::{0}(v)) => Box::new(v),\n            _ => unreachable!(\"the parse table only reduces matching values\"),\n        }}\n    }}",
+            types[i]
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "\n    fn reduce(prod: usize, values: Vec<Value>) -> Value {{\n        #[allow(unused_mut, unused_variables)]\n        let mut values = values.into_iter();\n        match prod {{"
+    );
+    for (p, prod) in prods.iter().enumerate().filter(|&(p, _)| p != accept) {
+        let ty = &types[compressed.prod_heads[p]];
+        let args = prod
+            .tail_without_eps()
+            .map(|t| match t {
+                Token::Terminal(_) => Ok("token(values.next())".to_string()),
+                Token::NonTerminal(nt) => {
+                    let i = compressed
+                        .non_terms
+                        .iter()
+                        .position(|n| n == nt)
+                        .ok_or_else(|| Error::NonTerminalNotFound(nt.to_string()))?;
+                    Ok(format!("take_{i}(values.next())"))
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let ctor = if args.is_empty() {
+            format!("{ty}::{}", variants[p])
+        } else {
+            format!("{ty}::{}({})", variants[p], args.join(", "))
+        };
+        let _ = writeln!(out, "            {p} => Value::{ty}({ctor}),");
+    }
+    let _ = writeln!(
+        out,
+        "            _ => unreachable!(\"ACCEPT_PROD accepts\"),\n        }}\n    }}\n"
+    );
+    let start_ty = type_of(start)?;
+    let _ = writeln!(
+        out,
+        "    /// Parses `(terminal, text)` pairs into the syntax tree of `{}`.",
+        start.as_str()
+    );
+    let _ = writeln!(
+        out,
+        "    pub fn parse(\n        tokens: impl IntoIterator<Item = (Terminal, String)>,\n    ) -> Result<{start_ty}, SyntaxError> {{\n        let value = super::parse_with(\n            tokens,\n            |terminal, text| Value::Token(Token {{ terminal, text }}),\n            reduce,\n        )?;\n        match value {{\n            Value::{start_ty}(v) => Ok(v),\n            _ => unreachable!(\"the start symbol is accepted\"),\n        }}\n    }}"
+    );
+    if options.recovery {
+        let _ = writeln!(
+            out,
+            "\n    /// Like `parse`, but recovers from syntax errors, inserted tokens have empty text.\n    pub fn parse_with_recovery(\n        tokens: impl IntoIterator<Item = (Terminal, String)>,\n        on_error: impl FnMut(super::Recovery),\n    ) -> Result<{start_ty}, SyntaxError> {{\n        let value = super::parse_with_recovery(\n            tokens,\n            |terminal, text| Value::Token(Token {{ terminal, text }}),\n            reduce,\n            on_error,\n        )?;\n        match value {{\n            Value::{start_ty}(v) => Ok(v),\n            _ => unreachable!(\"the start symbol is accepted\"),\n        }}\n    }}"
+        );
+    }
+    out += "}";
+    Ok(out)
+}
+
+/// 从文法文本直接生成 Rust 语法分析器模块, 供构建脚本 (`build.rs`) 使用:
+/// 文法分析在编译期完成, 生成的分析器在运行时不依赖 bumpalo 和这个 crate.
+///
+/// 分析表有冲突时失败, 而不是生成带有冲突的分析器.
+///
+/// # Errors
+/// - [`Error::ConflictingTable`] 分析表中有无法通过优先级解决的冲突.
+/// - 其他见: [`Grammar::from_cfg`], [`Family::from_grammar`], [`generate`].
+pub fn generate_from_cfg(src: &str, start: &str, options: &Options) -> Result<String, Error> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(src, start.into(), &bump)?.augmented();
+    let family = Family::from_grammar(&grammar)?;
+    let table = Table::build_with_policy(&family, ConflictPolicy::Error)?;
+    generate(&table, options)
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Parser, Table, Terminal,
+        codegen::{
+            Options,
+            rust::{generate, generate_from_cfg},
+        },
+        error::Error,
+    };
+
+    #[test]
+    fn rust_source() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let plain = generate(&table, &Options::default()).unwrap();
+        assert!(plain.starts_with("// Generated by lr-analysis, do not edit.\n"));
+        assert!(plain.contains("    /// `(`\n    T0,\n"));
+        assert!(plain.contains("    Eof,\n}"));
+        assert!(plain.contains("pub fn parse_with<T, V>("));
+        assert!(plain.contains("pub const GRAMMAR_VERSION: Option<&str> = None;\n"));
+        assert!(plain.contains(&format!(
+            "pub const TABLE_FINGERPRINT: u64 = {:#018x};\n",
+            crate::replay::fingerprint(&table)
+        )));
+        assert!(plain.contains("pub const ACCEPT_PROD: usize = 0;\n"));
+        assert!(plain.contains("        if prod == ACCEPT_PROD {\n"));
+        assert!(!plain.contains("pub mod ast"));
+        assert!(!plain.contains("parse_with_recovery"));
+        let ast = generate(
+            &table,
+            &Options {
+                ast: true,
+                ..Options::default()
+            },
+        )
+        .unwrap();
+        assert!(ast.starts_with(&plain));
+        assert!(ast.contains("    pub enum S {\n        /// `S -> ( S ) S`\n        SS(Token, Box<S>, Token, Box<S>),\n        /// `S -> E`\n        Empty,\n    }"));
+        assert!(ast.contains("            1 => Value::S(S::SS(token(values.next()), take_"));
+        assert!(ast.contains("    ) -> Result<S, SyntaxError> {"));
+        let recovery = generate(
+            &table,
+            &Options {
+                recovery: true,
+                ast: true,
+            },
+        )
+        .unwrap();
+        assert!(recovery.contains("static RECOVERY_TABLE: [[i16; 3]; "));
+        assert!(recovery.contains("pub fn parse_with_recovery<T: Default, V>("));
+        assert!(recovery.contains("        on_error: impl FnMut(super::Recovery),\n"));
+
+        let options = Options::default();
+        assert_eq!(
+            generate_from_cfg("S -> ( S ) S | E", "S", &options).unwrap(),
+            plain
+        );
+        assert!(matches!(
+            generate_from_cfg("S -> S S | a", "S", &options),
+            Err(Error::ConflictingTable { .. })
+        ));
+    }
+
+    /// 编译并运行生成的分析器 (包括错误恢复和 `ast` 模块), 检查合法和不合法的输入上的结果.
+    #[test]
+    fn compiled_parser() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let options = Options {
+            recovery: true,
+            ast: true,
+        };
+        let dir = std::env::temp_dir().join(format!("lr-analysis-rust-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("generated.rs"),
+            generate(&table, &options).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.rs"),
+            r#"#[allow(dead_code)]
+mod generated;
+
+use generated::{Terminal, ast};
+
+fn tokens(src: &str) -> Vec<(Terminal, String)> {
+    src.split_whitespace()
+        .map(|t| {
+            let idx = generated::TERMINAL_NAMES.iter().position(|&n| n == t).unwrap();
+            (Terminal::ALL[idx], t.to_string())
+        })
+        .collect()
+}
+
+fn main() {
+    for src in ["( ) ( )", "( ) )", "("] {
+        let mut reductions = Vec::new();
+        let result = generated::parse(tokens(src).into_iter().map(|t| t.0), |p| reductions.push(p));
+        println!("{reductions:?} {result:?}");
+    }
+    println!("{:?}", ast::parse(tokens("( )")));
+    println!("{}", ast::parse(tokens("( ) )")).unwrap_err());
+    let mut steps = Vec::new();
+    let tree = ast::parse_with_recovery(tokens("( ) )"), |r| steps.push(r));
+    println!("{steps:?} {}", tree.is_ok());
+}
+"#,
+        )
+        .unwrap();
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let status = std::process::Command::new(rustc)
+            .current_dir(&dir)
+            .args(["--edition=2024", "-Dwarnings", "-o", "parser", "main.rs"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let output = std::process::Command::new(dir.join("parser"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let mut lines = stdout.lines();
+
+        // 归约序列和解释执行的分析器相同, 生成的分析器不报告接受时的增广产生式归约.
+        let parser = Parser::new(&table);
+        let reductions = parser
+            .parse("( ) ( )".split_whitespace().map(Terminal::from))
+            .unwrap()
+            .reductions()
+            .iter()
+            .map(|r| r.prod)
+            .filter(|&p| Some(p) != grammar.augmented_production())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.next().unwrap(), format!("{reductions:?} Ok(())"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "[2] Err(SyntaxError { at: 2, found: T1 })"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "[] Err(SyntaxError { at: 1, found: Eof })"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            r#"Ok(SS(Token { terminal: T0, text: "(" }, Empty, Token { terminal: T1, text: ")" }, Empty))"#
+        );
+        assert_eq!(lines.next().unwrap(), r#"unexpected ")" at 2"#);
+        assert_eq!(
+            lines.next().unwrap(),
+            "[Inserted { at: 2, terminal: T0 }] true"
+        );
+        assert_eq!(lines.next(), None);
+    }
+
+    /// `examples/parser_bench/generated.rs` 需要和代码生成保持一致, 设置环境变量 `UPDATE_CODEGEN` 运行测试可以重新生成.
+    #[test]
+    fn bench_parser() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let source = generate(&table, &Options::default()).unwrap();
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("examples/parser_bench/generated.rs");
+        if std::env::var_os("UPDATE_CODEGEN").is_some() {
+            std::fs::write(&path, &source).unwrap();
+        }
+        assert!(
+            std::fs::read_to_string(&path).unwrap() == source,
+            "{} is outdated, rerun with UPDATE_CODEGEN=1",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,654 @@
+//! LR 分析方法对比.
+//!
+//! 对同一个文法, 根据规范 LR(1) 项集族推导出 LR(0), SLR(1), LALR(1) 的分析表:
+//! - LR(0) 项集族就是 LR(1) 项集按照核心 (去掉前瞻符的项) 合并的结果.
+//! - LR(0) 在完成项的所有终结符上归约, SLR(1) 只在产生式头部的 follow 集上归约.
+//! - LALR(1) 在合并后的前瞻符上归约.
+//!
+//! 和规范 LR(1) 分析表一样, 每个方法的分析表都按照文法的优先级声明解决移入/归约冲突
+//! (见: [`crate::precedence::resolve`]), 冲突数量可以直接比较.
+//!
+//! 然后把各个方法的状态数量, 冲突数量和分析表并排输出, 并高亮和上一个方法不同的格子.
+//!
+//! [`Comparison::merge_report`] 列出只有前瞻符不同的 LR(1) 状态, LALR(1) 合并它们可以节省的状态数量,
+//! 以及合并是否会引入新的冲突, 用来在选择分析方法之前做决定.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
+
+use crate::{
+    ActionCell, EOF, EPSILON, Production, StateId, Table, Terminal, Token, error::Error, precedence,
+};
+
+/// LR 分析方法, 按照分析能力从弱到强排列.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Algorithm {
+    Lr0,
+    Slr,
+    Lalr,
+    Lr1,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Self::Lr0, Self::Slr, Self::Lalr, Self::Lr1];
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Lr0 => "LR(0)",
+            Self::Slr => "SLR(1)",
+            Self::Lalr => "LALR(1)",
+            Self::Lr1 => "LR(1)",
+        })
+    }
+}
+
+/// 某个分析方法的语法分析表, 列的顺序和 [`Table`] 相同.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlgorithmTable {
+    algorithm: Algorithm,
+    action: Vec<Vec<ActionCell>>,
+    goto: Vec<Vec<Option<StateId>>>,
+    /// 每一行对应的 LR(0) 项集 (核心) 编号.
+    cores: Vec<StateId>,
+}
+
+impl AlgorithmTable {
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    #[must_use]
+    pub fn states(&self) -> usize {
+        self.action.len()
+    }
+
+    /// 包含冲突的 ACTION 表格子数量.
+    #[must_use]
+    pub fn conflicts(&self) -> usize {
+        self.action
+            .iter()
+            .flatten()
+            .filter(|c| c.is_conflict())
+            .count()
+    }
+
+    #[must_use]
+    pub fn action(&self, state: StateId, col: usize) -> Option<&ActionCell> {
+        self.action.get(state)?.get(col)
+    }
+
+    #[must_use]
+    pub fn goto(&self, state: StateId, col: usize) -> Option<Option<StateId>> {
+        self.goto.get(state)?.get(col).copied()
+    }
+
+    /// 状态对应的 LR(0) 项集编号, 对于 LR(0), SLR(1), LALR(1) 就是状态本身.
+    #[must_use]
+    pub fn core_of(&self, state: StateId) -> Option<StateId> {
+        self.cores.get(state).copied()
+    }
+}
+
+/// 核心相同 (只有前瞻符不同) 的一组 LR(1) 状态, LALR(1) 把它们合并为一个状态.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeGroup<'a> {
+    /// 合并之后的 LALR(1) 状态编号.
+    pub core: StateId,
+    /// 被合并的 LR(1) 状态, 至少有两个.
+    pub states: Vec<StateId>,
+    /// 合并之后才出现冲突的终结符, 合并之前这些状态在这些终结符上都没有冲突.
+    pub conflicts: Vec<Terminal<'a>>,
+}
+
+/// LR(1) 状态按照 LALR(1) 合并的报告, 见: [`Comparison::merge_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeReport<'a> {
+    /// 规范 LR(1) 项集族的状态数量.
+    pub lr1_states: usize,
+    /// 按照核心合并之后 LALR(1) 的状态数量.
+    pub lalr_states: usize,
+    /// 按照核心编号排列.
+    pub groups: Vec<MergeGroup<'a>>,
+}
+
+impl MergeReport<'_> {
+    /// 合并节省的状态数量.
+    #[must_use]
+    pub fn saved(&self) -> usize {
+        self.lr1_states - self.lalr_states
+    }
+
+    /// 合并是否会引入新的冲突 (只可能是归约/归约冲突).
+    #[must_use]
+    pub fn introduces_conflicts(&self) -> bool {
+        self.groups.iter().any(|g| !g.conflicts.is_empty())
+    }
+}
+
+impl Display for MergeReport<'_> {
+    /// 第一行为状态数量的汇总, 之后每行一组被合并的状态, 最后一行说明合并是否会引入冲突.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} LR(1) states, {} LALR(1) states, merging saves {}",
+            self.lr1_states,
+            self.lalr_states,
+            self.saved()
+        )?;
+        for group in &self.groups {
+            let states: Vec<_> = group.states.iter().map(|s| format!("I{s}")).collect();
+            write!(f, "core {}: {}", group.core, states.join(", "))?;
+            if !group.conflicts.is_empty() {
+                let terms: Vec<_> = group.conflicts.iter().map(|t| format!("`{t}`")).collect();
+                write!(f, " (new conflicts on {})", terms.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        if self.introduces_conflicts() {
+            write!(
+                f,
+                "merging introduces conflicts, the grammar is not LALR(1)"
+            )
+        } else {
+            write!(f, "merging introduces no conflicts")
+        }
+    }
+}
+
+/// 放入新的动作, 已经存在的相同动作不会被视为冲突.
+fn put(cell: &mut ActionCell, new: ActionCell) {
+    if !cell.flatten().any(|c| *c == new) {
+        cell.update(new);
+    }
+}
+
+/// 输出格子的内容, 冲突的格子使用 `/` 分隔每个动作.
+fn cell_text(cell: &ActionCell) -> String {
+    cell.flatten()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 格子和用来对比的上一个方法的表中的格子的差异.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Same,
+    Diff,
+    Conflict,
+}
+
+/// 同一个文法在各个 LR 分析方法下的分析表对比.
+#[derive(Debug, Clone)]
+pub struct Comparison<'t, 'a> {
+    table: &'t Table<'a>,
+    tables: Vec<AlgorithmTable>,
+}
+
+impl<'t, 'a> Comparison<'t, 'a> {
+    /// 根据规范 LR(1) 分析表 (及其项集族和增广文法) 构建所有方法的分析表.
+    ///
+    /// # Errors
+    /// - [`Error::TerminalNotFound`] / [`Error::NonTerminalNotFound`] 项集族中出现了文法中没有的符号.
+    /// - [`Error::ProductionNotFound`] 项集族中的项对应的产生式在文法中不存在.
+    /// - 其他见: [`crate::Grammar::follow_sets`].
+    pub fn new(table: &'t Table<'a>) -> Result<Self, Error> {
+        let family = table.family();
+        let grammar = table.grammar();
+        // 按照第一次出现的顺序给核心编号, 于是 I_0 的核心编号也是 0.
+        let mut core_idxes: HashMap<BTreeSet<(&'a Production<'a>, usize)>, StateId> =
+            HashMap::new();
+        let mut cores = Vec::with_capacity(family.len());
+        let mut members: Vec<Vec<StateId>> = Vec::new();
+        for (state, is) in family.item_sets().iter().enumerate() {
+            let core: BTreeSet<_> = is.items().map(|item| item.core()).collect();
+            let next = core_idxes.len();
+            let idx = *core_idxes.entry(core).or_insert(next);
+            if idx == members.len() {
+                members.push(Vec::new());
+            }
+            members[idx].push(state);
+            cores.push(idx);
+        }
+        let follows = grammar.follow_sets()?;
+        let accept = grammar.augmented_production();
+        let term_idx = |t| {
+            table
+                .term_index(t)
+                .ok_or_else(|| Error::TerminalNotFound(t.to_string()))
+        };
+        let prod_idx = |prod: &Production<'a>| {
+            grammar
+                .index_of_prod(prod)
+                .ok_or_else(|| Error::ProductionNotFound(prod.to_string()))
+        };
+        let non_term_idxes: HashMap<_, _> = table
+            .non_terms()
+            .iter()
+            .enumerate()
+            .map(|(i, &nt)| (nt, i))
+            .collect();
+
+        let mut tables = Vec::new();
+        for algorithm in [Algorithm::Lr0, Algorithm::Slr, Algorithm::Lalr] {
+            let mut action = vec![vec![ActionCell::Empty; table.action_cols()]; members.len()];
+            let mut goto = vec![vec![None; table.goto_cols()]; members.len()];
+            for (core, states) in members.iter().enumerate() {
+                // 同一个核心的项集的转换也有相同的核心, 所以只需要看第一个项集.
+                for (&tok, dests) in family.gotos_of(states[0]).into_iter().flatten() {
+                    for &to in dests {
+                        match tok {
+                            Token::Terminal(t) => {
+                                put(
+                                    &mut action[core][term_idx(t)?],
+                                    ActionCell::Shift(cores[to]),
+                                );
+                            }
+                            Token::NonTerminal(nt) => {
+                                let col = *non_term_idxes
+                                    .get(&nt)
+                                    .ok_or_else(|| Error::NonTerminalNotFound(nt.to_string()))?;
+                                goto[core][col] = Some(cores[to]);
+                            }
+                        }
+                    }
+                }
+                let mut reduces = Vec::new();
+                for &state in states {
+                    for item in family.item_sets()[state].items() {
+                        let Some(look_aheads) = item.reduces() else {
+                            continue;
+                        };
+                        let prod = prod_idx(item.prod())?;
+                        match algorithm {
+                            Algorithm::Lalr => reduces.extend(look_aheads.map(|t| (prod, t))),
+                            Algorithm::Lr0 if Some(prod) != accept => reduces.extend(
+                                table
+                                    .terms()
+                                    .iter()
+                                    .filter(|&&t| t != EPSILON)
+                                    .map(|&t| (prod, t)),
+                            ),
+                            Algorithm::Slr if Some(prod) != accept => reduces.extend(
+                                follows
+                                    .get(&item.prod().head())
+                                    .into_iter()
+                                    .flatten()
+                                    .map(|&t| (prod, t)),
+                            ),
+                            _ => reduces.push((prod, EOF)),
+                        }
+                    }
+                }
+                for (prod, t) in reduces {
+                    let cell = if Some(prod) == accept {
+                        ActionCell::Accept
+                    } else {
+                        ActionCell::Reduce(prod)
+                    };
+                    put(&mut action[core][term_idx(t)?], cell);
+                }
+            }
+            for row in &mut action {
+                for (cell, &t) in row.iter_mut().zip(table.terms()) {
+                    if cell.is_conflict()
+                        && let Some(chosen) = precedence::resolve(grammar, t, cell)
+                    {
+                        *cell = chosen;
+                    }
+                }
+            }
+            tables.push(AlgorithmTable {
+                algorithm,
+                action,
+                goto,
+                cores: (0..members.len()).collect(),
+            });
+        }
+        let rows = table.rows();
+        tables.push(AlgorithmTable {
+            algorithm: Algorithm::Lr1,
+            action: (0..rows)
+                .map(|state| {
+                    table
+                        .terms()
+                        .iter()
+                        .map(|&t| table.action(state, t).cloned().unwrap_or_default())
+                        .collect()
+                })
+                .collect(),
+            goto: (0..rows)
+                .map(|state| {
+                    table
+                        .non_terms()
+                        .iter()
+                        .map(|&nt| table.goto(state, nt).flatten())
+                        .collect()
+                })
+                .collect(),
+            cores,
+        });
+        Ok(Self { table, tables })
+    }
+
+    #[must_use]
+    pub fn table(&self) -> &'t Table<'a> {
+        self.table
+    }
+
+    /// 按照 [`Algorithm::ALL`] 的顺序获取所有方法的分析表.
+    #[must_use]
+    pub fn tables(&self) -> &[AlgorithmTable] {
+        &self.tables
+    }
+
+    #[must_use]
+    pub fn get(&self, algorithm: Algorithm) -> &AlgorithmTable {
+        &self.tables[algorithm as usize]
+    }
+
+    /// 文法属于的最弱的分析方法, 也就是第一个没有冲突的方法, 如果都有冲突, 那么返回 [`None`].
+    #[must_use]
+    pub fn weakest(&self) -> Option<Algorithm> {
+        self.tables
+            .iter()
+            .find(|t| t.conflicts() == 0)
+            .map(|t| t.algorithm)
+    }
+
+    /// LR(1) 状态按照 LALR(1) 合并的报告: 核心相同的状态组, 节省的状态数量, 以及合并之后新出现冲突的终结符.
+    #[must_use]
+    pub fn merge_report(&self) -> MergeReport<'a> {
+        let lr1 = self.get(Algorithm::Lr1);
+        let lalr = self.get(Algorithm::Lalr);
+        let mut members = vec![Vec::new(); lalr.states()];
+        for state in 0..lr1.states() {
+            members[lr1.cores[state]].push(state);
+        }
+        let groups = members
+            .into_iter()
+            .enumerate()
+            .filter(|(_, states)| states.len() > 1)
+            .map(|(core, states)| {
+                let conflicts = self
+                    .table
+                    .terms()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(col, _)| {
+                        lalr.action[core][col].is_conflict()
+                            && !states.iter().any(|&s| lr1.action[s][col].is_conflict())
+                    })
+                    .map(|(_, &t)| t)
+                    .collect();
+                MergeGroup {
+                    core,
+                    states,
+                    conflicts,
+                }
+            })
+            .collect();
+        MergeReport {
+            lr1_states: lr1.states(),
+            lalr_states: lalr.states(),
+            groups,
+        }
+    }
+
+    /// 格子和上一个方法中对应格子的差异, LR(1) 和其核心在 LALR(1) 中的状态比较.
+    fn mark(&self, algorithm: Algorithm, state: StateId, col: usize) -> Mark {
+        let this = self.get(algorithm);
+        let cell = &this.action[state][col];
+        if cell.is_conflict() {
+            return Mark::Conflict;
+        }
+        let Some(prev) = Algorithm::ALL.get((algorithm as usize).wrapping_sub(1)) else {
+            return Mark::Same;
+        };
+        let prev = self.get(*prev);
+        let prev_cell = &prev.action[this.cores[state]][col];
+        let same = match (cell, prev_cell) {
+            // LR(1) 的状态编号和 LALR(1) 不同, 只比较动作的类型和核心.
+            (ActionCell::Shift(a), ActionCell::Shift(b)) if algorithm == Algorithm::Lr1 => {
+                this.cores[*a] == *b
+            }
+            (a, b) => a == b,
+        };
+        if same { Mark::Same } else { Mark::Diff }
+    }
+
+    /// 行标签, LR(1) 的状态附带其核心编号.
+    fn row_label(&self, algorithm: Algorithm, state: StateId) -> String {
+        match algorithm {
+            Algorithm::Lr1 => format!("I{state} (core {})", self.get(algorithm).cores[state]),
+            _ => format!("I{state}"),
+        }
+    }
+
+    fn header(&self) -> Vec<String> {
+        self.table
+            .terms()
+            .iter()
+            .map(|t| t.as_str().to_string())
+            .chain(
+                self.table
+                    .non_terms()
+                    .iter()
+                    .map(|nt| nt.as_str().to_string()),
+            )
+            .collect()
+    }
+
+    /// 使用 markdown 输出对比报告: 先是状态数量和冲突数量的汇总表, 然后是每个方法的分析表.
+    ///
+    /// 和上一个方法不同的格子使用粗体, 冲突的格子使用 `⚠` 标记.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("| |");
+        for t in &self.tables {
+            out += &format!(" {} |", t.algorithm);
+        }
+        out += "\n| - |";
+        out += &" - |".repeat(self.tables.len());
+        out += "\n| states |";
+        for t in &self.tables {
+            out += &format!(" {} |", t.states());
+        }
+        out += "\n| conflicts |";
+        for t in &self.tables {
+            out += &format!(" {} |", t.conflicts());
+        }
+        out += "\n";
+        let header = self.header();
+        for t in &self.tables {
+            out += &format!("\n### {}\n\n| |", t.algorithm);
+            out += &header
+                .iter()
+                .map(|h| format!(" `{h}` |"))
+                .collect::<String>();
+            out += "\n| - |";
+            out += &" - |".repeat(header.len());
+            out += "\n";
+            for state in 0..t.states() {
+                out += &format!("| {} |", self.row_label(t.algorithm, state));
+                for (col, cell) in t.action[state].iter().enumerate() {
+                    let text = cell_text(cell);
+                    out += &match self.mark(t.algorithm, state, col) {
+                        Mark::Same => format!(" {text} |"),
+                        Mark::Diff if text.is_empty() => " **-** |".to_string(),
+                        Mark::Diff => format!(" **{text}** |"),
+                        Mark::Conflict => format!(" ⚠ **{text}** |"),
+                    };
+                }
+                for to in &t.goto[state] {
+                    out += &to.map_or("  |".to_string(), |to| format!(" {to} |"));
+                }
+                out += "\n";
+            }
+        }
+        out.trim_end().to_string()
+    }
+
+    /// 使用 HTML 输出对比报告, 各个方法的分析表并排显示.
+    ///
+    /// 和上一个方法不同的格子使用 `diff` 类, 冲突的格子使用 `conflict` 类.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+            "<style>\n",
+            ".tables { display: flex; gap: 2em; align-items: flex-start; }\n",
+            "table { border-collapse: collapse; font-family: monospace; }\n",
+            "td, th { border: 1px solid #999; padding: 0 0.4em; text-align: center; }\n",
+            "td.diff { background: #fff3b0; }\n",
+            "td.conflict { background: #ffb3b3; font-weight: bold; }\n",
+            "</style>\n</head>\n<body>\n",
+        ));
+        out += "<table>\n<tr><th></th>";
+        for t in &self.tables {
+            out += &format!("<th>{}</th>", t.algorithm);
+        }
+        out += "</tr>\n<tr><th>states</th>";
+        for t in &self.tables {
+            out += &format!("<td>{}</td>", t.states());
+        }
+        out += "</tr>\n<tr><th>conflicts</th>";
+        for t in &self.tables {
+            out += &format!("<td>{}</td>", t.conflicts());
+        }
+        out += "</tr>\n</table>\n<div class=\"tables\">\n";
+        let header = self.header();
+        for t in &self.tables {
+            out += &format!("<div>\n<h3>{}</h3>\n<table>\n<tr><th></th>", t.algorithm);
+            for h in &header {
+                out += &format!("<th>{}</th>", html_escape(h));
+            }
+            out += "</tr>\n";
+            for state in 0..t.states() {
+                out += &format!("<tr><th>{}</th>", self.row_label(t.algorithm, state));
+                for (col, cell) in t.action[state].iter().enumerate() {
+                    let class = match self.mark(t.algorithm, state, col) {
+                        Mark::Same => "",
+                        Mark::Diff => " class=\"diff\"",
+                        Mark::Conflict => " class=\"conflict\"",
+                    };
+                    out += &format!("<td{class}>{}</td>", cell_text(cell));
+                }
+                for to in &t.goto[state] {
+                    out += &format!(
+                        "<td>{}</td>",
+                        to.map(|to| to.to_string()).unwrap_or_default()
+                    );
+                }
+                out += "</tr>\n";
+            }
+            out += "</table>\n</div>\n";
+        }
+        out += "</div>\n</body>\n</html>";
+        out
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Table, Terminal,
+        compare::{Algorithm, Comparison},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn counts(comparison: &Comparison) -> Vec<(usize, usize)> {
+        comparison
+            .tables()
+            .iter()
+            .map(|t| (t.states(), t.conflicts()))
+            .collect()
+    }
+
+    #[test]
+    fn lr_hierarchy() {
+        let bump = Bump::new();
+        // 龙书中的 LALR(1) 但不是 SLR(1) 的文法.
+        let grammar = Grammar::from_cfg("S -> L = R | R\nL -> * R | id\nR -> L", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let comparison = Comparison::new(&table).unwrap();
+        assert_eq!(counts(&comparison), [(10, 1), (10, 1), (10, 0), (14, 0)]);
+        assert_eq!(comparison.weakest(), Some(Algorithm::Lalr));
+        let report = comparison.merge_report();
+        assert_eq!(report.saved(), 4);
+        assert_eq!(report.groups.len(), 4);
+        assert!(report.groups.iter().all(|g| g.states.len() == 2));
+        assert!(!report.introduces_conflicts());
+        let markdown = comparison.to_markdown();
+        assert!(markdown.starts_with(
+            "| | LR(0) | SLR(1) | LALR(1) | LR(1) |\n| - | - | - | - | - |\n| states | 10 | 10 | 10 | 14 |"
+        ));
+        assert!(markdown.contains("⚠ **"));
+        assert!(markdown.contains("| I13 (core "));
+
+        // 龙书中的 LR(1) 但不是 LALR(1) 的文法.
+        let grammar = Grammar::from_cfg(
+            "S -> a A d | b B d | a B e | b A e\nA -> c\nB -> c",
+            "S".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let comparison = Comparison::new(&table).unwrap();
+        assert_eq!(comparison.weakest(), Some(Algorithm::Lr1));
+        assert!(comparison.get(Algorithm::Lalr).conflicts() > 0);
+        let report = comparison.merge_report();
+        let group = report
+            .groups
+            .iter()
+            .find(|g| !g.conflicts.is_empty())
+            .unwrap();
+        assert_eq!(group.conflicts, [Terminal::from("d"), "e".into()]);
+        assert!(report.introduces_conflicts());
+        assert!(report.to_string().ends_with(
+            "(new conflicts on `d`, `e`)\nmerging introduces conflicts, the grammar is not LALR(1)"
+        ));
+        assert!(
+            comparison
+                .to_html()
+                .contains("<td class=\"conflict\">r5/r6</td>")
+        );
+
+        // 优先级声明解决了所有方法中的移入/归约冲突, 而不只是 LR(1).
+        let grammar = Grammar::from_cfg(
+            "%left +\n%left *\nT -> T + T | T * T | id",
+            "T".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let comparison = Comparison::new(&table).unwrap();
+        assert!(
+            counts(&comparison)
+                .iter()
+                .all(|&(_, conflicts)| conflicts == 0)
+        );
+        assert_eq!(comparison.weakest(), Some(Algorithm::Lr0));
+    }
+}
@@ -0,0 +1,175 @@
+//! 编辑器自动补全数据导出.
+//!
+//! 对每个非终结符给出可以开始它的终结符 (FIRST 集) 和它的所有产生式, 产生式同时渲染为代码片段:
+//! 终结符原样输出, 非终结符是按顺序编号的占位符 (`${1:stmt}`, LSP / VS Code 的片段语法).
+//! 结果是拥有所有权的, 可以通过 [`to_json`] 渲染为 JSON 交给编辑器的补全或者片段引擎.
+
+use crate::{
+    EPSILON, Grammar, NonTerminal, ProdId, Production, Token, error::Error, graph::json_str,
+};
+
+/// 一个非终结符的补全数据.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Completion {
+    pub non_term: String,
+    /// 可以开始这个非终结符的终结符, 按照名字排序, 不包括 [`EPSILON`].
+    pub first: Vec<String>,
+    /// 是否可以推导出空串.
+    pub nullable: bool,
+    pub prods: Vec<Alternative>,
+}
+
+/// 非终结符的一个产生式.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Alternative {
+    pub prod: ProdId,
+    /// 产生式右部, 空产生式为 [`EPSILON`].
+    pub tail: String,
+    /// 代码片段, 空产生式为空字符串.
+    pub snippet: String,
+}
+
+/// 产生式右部的代码片段, 终结符中的 `$`, `}` 和 `\` 会被转义.
+fn snippet(prod: &Production<'_>) -> String {
+    let mut placeholder = 0;
+    prod.tail_without_eps()
+        .map(|tok| match tok {
+            Token::Terminal(t) => t
+                .as_str()
+                .chars()
+                .flat_map(|c| match c {
+                    '$' | '}' | '\\' => vec!['\\', c],
+                    c => vec![c],
+                })
+                .collect(),
+            Token::NonTerminal(nt) => {
+                placeholder += 1;
+                format!("${{{placeholder}:{nt}}}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// 文法中每个非终结符 (不包括增广产生式的头部) 的补全数据, 按照第一次定义的顺序排列.
+///
+/// # Errors
+/// 见: [`Grammar::first_set`].
+pub fn completions(grammar: &Grammar<'_>) -> Result<Vec<Completion>, Error> {
+    let mut heads: Vec<NonTerminal<'_>> = Vec::new();
+    for (id, prod) in grammar.prods().iter().enumerate() {
+        if grammar.declared_index(id).is_some() && !heads.contains(&prod.head()) {
+            heads.push(prod.head());
+        }
+    }
+    heads
+        .into_iter()
+        .map(|nt| {
+            let first = grammar.first_set(std::iter::once(nt.into()))?;
+            let mut names: Vec<_> = first
+                .iter()
+                .filter(|&&t| t != EPSILON)
+                .map(ToString::to_string)
+                .collect();
+            names.sort_unstable();
+            Ok(Completion {
+                non_term: nt.to_string(),
+                first: names,
+                nullable: first.contains(&EPSILON),
+                prods: grammar
+                    .producers_of(nt)
+                    .iter()
+                    .map(|&prod| {
+                        let p = grammar.prods()[prod];
+                        let tail: Vec<_> = p.tail().iter().map(ToString::to_string).collect();
+                        Alternative {
+                            prod,
+                            tail: tail.join(" "),
+                            snippet: snippet(p),
+                        }
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// 渲染为 JSON 对象, `nonterminals` 中每个非终结符一个对象.
+#[must_use]
+pub fn to_json(completions: &[Completion]) -> String {
+    let strings = |items: &[String]| {
+        items
+            .iter()
+            .map(|s| json_str(s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let non_terms = completions
+        .iter()
+        .map(|c| {
+            let prods = c
+                .prods
+                .iter()
+                .map(|a| {
+                    format!(
+                        "{{\"prod\": {}, \"tail\": {}, \"snippet\": {}}}",
+                        a.prod,
+                        json_str(&a.tail),
+                        json_str(&a.snippet)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "    {{\"name\": {}, \"first\": [{}], \"nullable\": {}, \"prods\": [{prods}]}}",
+                json_str(&c.non_term),
+                strings(&c.first),
+                c.nullable
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!("{{\n  \"nonterminals\": [\n{non_terms}\n  ]\n}}\n")
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Grammar,
+        completion::{completions, to_json},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn completion_data() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "stmt -> if ( expr ) stmt | { stmts } | id = expr ;\nstmts -> stmt stmts | E\nexpr -> id | num",
+            "stmt".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let found = completions(&grammar).unwrap();
+        let names: Vec<_> = found.iter().map(|c| c.non_term.as_str()).collect();
+        assert_eq!(names, ["stmt", "stmts", "expr"]);
+        assert_eq!(found[0].first, ["id", "if", "{"]);
+        assert!(!found[0].nullable);
+        assert_eq!(found[0].prods[0].prod, 1);
+        assert_eq!(found[0].prods[0].snippet, "if ( ${1:expr} ) ${2:stmt}");
+        assert_eq!(found[0].prods[1].snippet, "{ ${1:stmts} \\}");
+        assert!(found[1].nullable);
+        assert_eq!(found[1].prods[1].tail, "E");
+        assert_eq!(found[1].prods[1].snippet, "");
+
+        let json = to_json(&found);
+        assert!(json.starts_with("{\n  \"nonterminals\": [\n"));
+        assert!(json.contains(
+            "{\"name\": \"expr\", \"first\": [\"id\", \"num\"], \"nullable\": false, \"prods\": [{\"prod\": 6, \"tail\": \"id\", \"snippet\": \"id\"}, "
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+//! 文法组合.
+//!
+//! 把库文法 (例如表达式, 字面量等子文法) 合并进主文法, 在多个 DSL 之间复用同一个子文法.
+//! 给定命名空间时库文法的所有非终结符加上前缀 `ns::` (例如 `expr::term`), 避免和主文法的非终结符重名;
+//! 主文法中直接使用 `expr::term` 引用库文法的非终结符 (单独解析主文法时它是终结符, 组合之后成为非终结符).
+//! 不给定命名空间时同名的非终结符合并它们的产生式.
+//!
+//! 终结符不加前缀, 两个文法共享同一套词法. 组合后的文法使用主文法的开始符号, 元数据和优先级声明, 保留产生式的权重.
+
+use std::fmt::Display;
+
+use bumpalo::Bump;
+
+use crate::{Grammar, NonTerminal, Production, Token, canonical::declared, error::Error};
+
+/// 命名空间和非终结符名字之间的分隔符.
+pub const SEPARATOR: &str = "::";
+
+/// 组合的结果.
+#[derive(Debug, Clone)]
+pub struct Composition<'a> {
+    pub grammar: Grammar<'a>,
+    /// 库文法的非终结符和组合之后的名字, 按照库文法中第一次出现的顺序.
+    pub renamed: Vec<(String, String)>,
+    /// 两个文法中都有产生式的非终结符 (组合之后的名字), 它们的产生式被合并.
+    pub merged: Vec<String>,
+}
+
+impl Display for Composition<'_> {
+    /// 映射报告: 每行一个库文法的非终结符 `term => expr::term`, 之后每行一个被合并的非终结符.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (from, to) in &self.renamed {
+            writeln!(f, "{from} => {to}")?;
+        }
+        for nt in &self.merged {
+            writeln!(f, "merged: {nt}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 一个产生式在组合后的文法文件中的一行, `rename` 转换非终结符的名字.
+fn line(prod: &Production<'_>, rename: impl Fn(NonTerminal<'_>) -> String) -> String {
+    let tail: Vec<_> = prod
+        .tail()
+        .iter()
+        .map(|tok| match tok {
+            Token::NonTerminal(nt) => rename(*nt),
+            Token::Terminal(t) => t.to_string(),
+        })
+        .collect();
+    format!("{} -> {}", rename(prod.head()), tail.join(" "))
+}
+
+/// 声明的产生式的权重, 忽略增广产生式.
+fn declared_weights(grammar: &Grammar<'_>) -> Vec<u32> {
+    (0..grammar.prods().len())
+        .filter(|&p| grammar.declared_index(p).is_some())
+        .map(|p| grammar.weight(p))
+        .collect()
+}
+
+/// 把 `library` 合并进 `base`, 组合后的文法分配在 `bump` 中, 增广产生式会被忽略.
+///
+/// 组合后的产生式编号: 先是 `base` 的产生式, 然后是 `library` 的产生式, 各自保持声明顺序.
+///
+/// # Errors
+/// - [`Error::InvalidSymbol`] 命名空间为空, 包含空白字符或者 `|`.
+/// - 其他见: [`Grammar::from_cfg`].
+pub fn compose<'a>(
+    base: &Grammar<'_>,
+    library: &Grammar<'_>,
+    namespace: Option<&str>,
+    bump: &'a Bump,
+) -> Result<Composition<'a>, Error> {
+    if let Some(ns) = namespace {
+        NonTerminal::new(ns)?;
+        if ns.contains('|') {
+            Err(Error::InvalidSymbol {
+                ident: ns.to_string(),
+                cause: "namespace contains `|`".to_string(),
+            })?;
+        }
+    }
+    let rename = |nt: NonTerminal<'_>| match namespace {
+        Some(ns) => format!("{ns}{SEPARATOR}{nt}"),
+        None => nt.to_string(),
+    };
+    let (start, base_prods) = declared(base);
+    let (_, library_prods) = declared(library);
+
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    for prod in &library_prods {
+        let non_terms =
+            std::iter::once(prod.head()).chain(prod.tail().iter().filter_map(|tok| match tok {
+                Token::NonTerminal(nt) => Some(*nt),
+                Token::Terminal(_) => None,
+            }));
+        for nt in non_terms {
+            if !renamed.iter().any(|(from, _)| from == nt.as_str()) {
+                renamed.push((nt.to_string(), rename(nt)));
+            }
+        }
+    }
+    let mut merged = Vec::new();
+    for prod in &base_prods {
+        let head = prod.head().to_string();
+        if !merged.contains(&head) && library_prods.iter().any(|p| rename(p.head()) == head) {
+            merged.push(head);
+        }
+    }
+
+    let lines: Vec<_> = base_prods
+        .iter()
+        .map(|p| line(p, |nt| nt.to_string()))
+        .chain(library_prods.iter().map(|p| line(p, rename)))
+        .collect();
+    let src = bump.alloc_str(&lines.join("\n"));
+    let start = bump.alloc_str(start.as_str());
+    let mut grammar = Grammar::from_cfg(src, NonTerminal::from(&*start), bump)?;
+    let weights = declared_weights(base)
+        .into_iter()
+        .chain(declared_weights(library));
+    for (id, weight) in weights.enumerate() {
+        grammar.set_weight(id, weight)?;
+    }
+    for (key, value) in base.metadata() {
+        grammar.set_metadata(key.clone(), value.clone());
+    }
+    grammar.set_precedence(base.precedence_levels())?;
+    Ok(Composition {
+        grammar,
+        renamed,
+        merged,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Grammar, NonTerminal, Token, compose::compose, equiv::recognizes};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn composition() {
+        let bump = Bump::new();
+        let base = Grammar::from_cfg(
+            "stmt -> id = expr::expr ; | print term ;\nterm -> id",
+            "stmt".into(),
+            &bump,
+        )
+        .unwrap();
+        let library = Grammar::from_cfg(
+            "# weight: 3 term -> num\nexpr -> expr + term | term\nterm -> num | ( expr )",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let composed = compose(&base, &library, Some("expr"), &bump).unwrap();
+        let grammar = &composed.grammar;
+        assert_eq!(grammar.symbol_start(), NonTerminal::from("stmt"));
+        assert_eq!(
+            grammar
+                .prods()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "stmt -> id = expr::expr ;",
+                "stmt -> print term ;",
+                "term -> id",
+                "expr::expr -> expr::expr + expr::term",
+                "expr::expr -> expr::term",
+                "expr::term -> num",
+                "expr::term -> ( expr::expr )",
+            ]
+        );
+        assert!(matches!(
+            grammar.get_token("expr::expr"),
+            Some(Token::NonTerminal(_))
+        ));
+        assert_eq!(grammar.weight(5), 3);
+        assert!(recognizes(
+            grammar,
+            &["id", "=", "num", "+", "(", "num", ")", ";"]
+        ));
+        assert_eq!(
+            composed.to_string(),
+            "expr => expr::expr\nterm => expr::term\n"
+        );
+        assert!(composed.merged.is_empty());
+
+        // 不使用命名空间时同名的非终结符合并产生式.
+        let composed = compose(&base, &library, None, &bump).unwrap();
+        assert_eq!(composed.merged, ["term"]);
+        assert!(recognizes(&composed.grammar, &["print", "num", ";"]));
+        assert!(compose(&base, &library, Some("a b"), &bump).is_err());
+    }
+}
@@ -0,0 +1,595 @@
+//! 分析表冲突的分诊.
+//!
+//! 对分析表中的每个冲突格子, 找出参与冲突的项, 到达冲突状态的最短活前缀 (见: [`Family::access_path`]),
+//! 并根据冲突的形式给出修改建议:
+//!
+//! - 悬挂 else: `S -> if c then S | if c then S else S` 这样短的产生式是长的产生式的前缀,
+//!   长的产生式在这个前缀之后移入冲突的终结符, 给出专门的解释和常见的解决方法.
+//! - 其他移入/归约冲突: 一般是运算符优先级或者结合性的二义性, 可以声明优先级解决.
+//! - 归约/归约冲突, 参与冲突的产生式右部有公共前缀: 提取左公因子, 把公共部分合并为同一个非终结符.
+//! - 其他: 需要超过一个前瞻符才能决定, 只能调整文法的结构.
+//!
+//! 构建分析表时可以使用 [`ConflictPolicy`] 选择把冲突当作错误, 保留冲突格子或者自动解决,
+//! 也可以实现 [`ConflictResolver`] 自定义每个冲突格子的处理方式 (例如最长匹配, 关键字优先), 见: [`Table::build_with_resolver`].
+//!
+//! [`Family::access_path`]: crate::Family::access_path
+
+use std::{collections::BTreeSet, fmt::Display};
+
+use crate::{
+    ActionCell, CellRef, Grammar, Item, ItemSet, NonTerminal, ProdId, StateId, Table, Terminal,
+    Token,
+    counterexample::{self, Counterexample},
+    error::Error,
+};
+
+/// 冲突的种类.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+impl Display for ConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::ShiftReduce => "shift/reduce",
+            Self::ReduceReduce => "reduce/reduce",
+        })
+    }
+}
+
+/// 构建分析表时如何处理冲突, 见: [`Table::build_with_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 有冲突时构建失败.
+    Error,
+    /// 保留冲突格子 ([`ActionCell::Conflict`]), 由 [`Table::conflict`] 报告.
+    #[default]
+    Warn,
+    /// 按照给定的方式选择格子中的一个动作, 分析表中不再有冲突.
+    Allow(Resolution),
+}
+
+/// 自动解决冲突的方式, 两种方式都优先接受.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Resolution {
+    /// 移入优先, 归约/归约冲突时选择编号最小 (最先声明) 的产生式, 同 yacc 的默认行为.
+    #[default]
+    PreferShift,
+    /// 归约优先, 多个归约时选择编号最小的产生式.
+    PreferReduce,
+}
+
+impl Resolution {
+    /// 从冲突格子中选出一个动作.
+    pub(crate) fn resolve(self, cell: &ActionCell) -> ActionCell {
+        let leaves: Vec<_> = cell.flatten().collect();
+        let shift = leaves
+            .iter()
+            .find(|leaf| matches!(leaf, ActionCell::Shift(_)));
+        let reduce = leaves
+            .iter()
+            .filter(|leaf| matches!(leaf, ActionCell::Reduce(_)))
+            .min_by_key(|leaf| match leaf {
+                ActionCell::Reduce(prod) => *prod,
+                _ => ProdId::MAX,
+            });
+        let preferred = match self {
+            Self::PreferShift => shift.or(reduce),
+            Self::PreferReduce => reduce.or(shift),
+        };
+        leaves
+            .iter()
+            .find(|leaf| matches!(leaf, ActionCell::Accept))
+            .or(preferred)
+            .map_or(ActionCell::Empty, |&leaf| leaf.clone())
+    }
+}
+
+/// 构建分析表时交给 [`ConflictResolver`] 的冲突格子.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictCell<'a> {
+    pub state: StateId,
+    pub term: Terminal<'a>,
+    /// 格子中的所有候选动作, 先移入 (或者接受), 再按照产生式编号归约.
+    pub actions: Vec<ActionCell>,
+    /// 产生候选动作的项: dot 之后为 `term` 的移入项和前瞻符包含 `term` 的归约项.
+    pub items: Vec<Item<'a>>,
+}
+
+impl<'a> ConflictCell<'a> {
+    /// 收集冲突格子 `cell` 中的候选动作和产生这些动作的项, [`conflicts`] 也使用这里的结果.
+    pub(crate) fn new(
+        grammar: &Grammar<'a>,
+        is: &ItemSet<'a>,
+        state: StateId,
+        term: Terminal<'a>,
+        cell: &ActionCell,
+    ) -> Self {
+        let mut actions: Vec<ActionCell> = cell.flatten().cloned().collect();
+        actions.sort_by_key(|leaf| match leaf {
+            ActionCell::Reduce(prod) => (1, *prod),
+            _ => (0, 0),
+        });
+        let accept = actions.contains(&ActionCell::Accept);
+        let items = is
+            .items()
+            .filter(|item| {
+                item.expected() == Some(term.into())
+                    || item.reduces().is_some_and(|mut la| la.any(|t| t == term))
+                        && grammar.index_of_prod(item.prod()).is_some_and(|p| {
+                            actions.contains(&ActionCell::Reduce(p))
+                                || accept && Some(p) == grammar.augmented_production()
+                        })
+            })
+            .cloned()
+            .collect();
+        Self {
+            state,
+            term,
+            actions,
+            items,
+        }
+    }
+}
+
+/// 构建分析表时自定义的冲突处理, 在 [`ConflictPolicy`] 之前对每个冲突格子调用一次, 见: [`Table::build_with_resolver`].
+///
+/// 闭包 `FnMut(&ConflictCell) -> Option<ActionCell>` 也实现了这个 trait.
+pub trait ConflictResolver<'a> {
+    /// 返回格子中最终的动作, 一般是 [`ConflictCell::actions`] 之一, 返回 [`ActionCell::Empty`] 表示在这里报告语法错误
+    /// (例如不可结合的运算符). 返回 [`None`] 表示不处理, 交给 [`ConflictPolicy`].
+    fn resolve(&mut self, cell: &ConflictCell<'a>) -> Option<ActionCell>;
+}
+
+impl<'a, F> ConflictResolver<'a> for F
+where
+    F: FnMut(&ConflictCell<'a>) -> Option<ActionCell>,
+{
+    fn resolve(&mut self, cell: &ConflictCell<'a>) -> Option<ActionCell> {
+        self(cell)
+    }
+}
+
+/// 解决冲突的建议.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Suggestion<'a> {
+    /// 声明终结符 `term` 和产生式 `prod` 之间的优先级 (或者结合性), 决定移入 `term` 还是归约 `prod`.
+    Precedence { prod: ProdId, term: Terminal<'a> },
+    /// 悬挂 else: 归约 `short` (`S -> if c then S`) 和在 `short` 的右部之后移入 `term` 的 `long`
+    /// (`S -> if c then S else S`) 冲突.
+    DanglingElse {
+        short: ProdId,
+        long: ProdId,
+        term: Terminal<'a>,
+    },
+    /// 产生式 `prods` 的右部有公共前缀 `prefix`, 提取左公因子.
+    LeftFactor {
+        prods: Vec<ProdId>,
+        prefix: Vec<Token<'a>>,
+    },
+    /// 冲突需要更多的前瞻符才能决定, 需要调整这些非终结符的结构.
+    Restructure { heads: Vec<NonTerminal<'a>> },
+}
+
+/// 分析表中的一个冲突格子.
+#[derive(Debug, Clone)]
+pub struct Conflict<'a> {
+    pub state: StateId,
+    pub term: Terminal<'a>,
+    pub kind: ConflictKind,
+    /// 格子中的每个候选动作 (见: [`ActionCell::flatten`]) 和产生这个动作的项, 先移入 (或者接受), 再按照产生式编号归约.
+    pub actions: Vec<(ActionCell, Vec<Item<'a>>)>,
+    /// 参与冲突的项: 前瞻符包含 `term` 的归约项和 dot 之后为 `term` 的移入项.
+    pub items: Vec<Item<'a>>,
+    /// 参与冲突的产生式编号, 有序.
+    pub prods: BTreeSet<ProdId>,
+    /// 从 I_0 到达冲突状态的最短活前缀.
+    pub path: Vec<Token<'a>>,
+    pub suggestion: Suggestion<'a>,
+}
+
+/// 产生式右部 (不包括 epsilon) 的最长公共前缀.
+fn common_prefix<'a>(table: &Table<'a>, prods: &BTreeSet<ProdId>) -> Vec<Token<'a>> {
+    let tails: Vec<Vec<Token<'a>>> = prods
+        .iter()
+        .filter_map(|&p| table.grammar().prod(p))
+        .map(|p| p.tail_without_eps().copied().collect())
+        .collect();
+    let Some((first, rest)) = tails.split_first() else {
+        return Vec::new();
+    };
+    first
+        .iter()
+        .enumerate()
+        .take_while(|&(i, tok)| rest.iter().all(|t| t.get(i) == Some(tok)))
+        .map(|(_, &tok)| tok)
+        .collect()
+}
+
+/// 悬挂 else 模式: `items` 中有归约 `short` 的项, 以及和 `short` 头部相同, 右部以 `short` 的右部开头,
+/// 紧接着是 `term` 的移入项, 返回 (`short`, `long`).
+fn dangling_else<'a>(
+    table: &Table<'a>,
+    items: &[Item<'a>],
+    term: Terminal<'a>,
+) -> Option<(ProdId, ProdId)> {
+    let grammar = table.grammar();
+    items
+        .iter()
+        .filter(|item| item.reduces().is_some())
+        .find_map(|short| {
+            let long = items.iter().find(|long| {
+                let tail: Vec<_> = long.prod().tail_without_eps().collect();
+                long.prod().head() == short.prod().head()
+                    && long.expected() == Some(term.into())
+                    && long.dot() == short.dot()
+                    && tail.starts_with(&short.prod().tail_without_eps().collect::<Vec<_>>())
+            })?;
+            Some((
+                grammar.index_of_prod(short.prod())?,
+                grammar.index_of_prod(long.prod())?,
+            ))
+        })
+}
+
+/// 找出分析表中所有的冲突, 按照状态和终结符的顺序排列.
+#[must_use]
+pub fn conflicts<'a>(table: &Table<'a>) -> Vec<Conflict<'a>> {
+    let grammar = table.grammar();
+    let family = table.family();
+    let mut result = Vec::new();
+    for (state, tok, cell) in table.cells() {
+        let (CellRef::Action(cell), Token::Terminal(term)) = (cell, tok) else {
+            continue;
+        };
+        if !cell.is_conflict() {
+            continue;
+        }
+        let reduces: BTreeSet<_> = cell
+            .flatten()
+            .filter_map(|leaf| match leaf {
+                ActionCell::Reduce(prod) => Some(*prod),
+                _ => None,
+            })
+            .collect();
+        let shift = cell
+            .flatten()
+            .any(|leaf| matches!(leaf, ActionCell::Shift(_)));
+        let ConflictCell {
+            actions: leaves,
+            items,
+            ..
+        } = ConflictCell::new(grammar, &family.item_sets()[state], state, term, cell);
+        let prods: BTreeSet<_> = items
+            .iter()
+            .filter_map(|item| grammar.index_of_prod(item.prod()))
+            .collect();
+        let actions = leaves
+            .into_iter()
+            .map(|leaf| {
+                let causes = items
+                    .iter()
+                    .filter(|item| match (&leaf, item.reduces()) {
+                        (ActionCell::Shift(_), None) => true,
+                        (ActionCell::Reduce(prod), Some(_)) => {
+                            grammar.index_of_prod(item.prod()) == Some(*prod)
+                        }
+                        (ActionCell::Accept, Some(_)) => {
+                            grammar.index_of_prod(item.prod()) == grammar.augmented_production()
+                        }
+                        _ => false,
+                    })
+                    .cloned()
+                    .collect();
+                (leaf, causes)
+            })
+            .collect();
+        let (kind, suggestion) = if shift {
+            let suggestion = match dangling_else(table, &items, term) {
+                Some((short, long)) => Suggestion::DanglingElse { short, long, term },
+                None => Suggestion::Precedence {
+                    prod: reduces.first().copied().unwrap_or_default(),
+                    term,
+                },
+            };
+            (ConflictKind::ShiftReduce, suggestion)
+        } else {
+            let prefix = common_prefix(table, &reduces);
+            let suggestion = if prefix.is_empty() {
+                let heads: BTreeSet<_> = reduces
+                    .iter()
+                    .filter_map(|&p| grammar.prod(p))
+                    .map(|p| p.head())
+                    .collect();
+                Suggestion::Restructure {
+                    heads: heads.into_iter().collect(),
+                }
+            } else {
+                Suggestion::LeftFactor {
+                    prods: reduces.iter().copied().collect(),
+                    prefix,
+                }
+            };
+            (ConflictKind::ReduceReduce, suggestion)
+        };
+        let path = family
+            .access_path(state)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(tok, _)| tok)
+            .collect();
+        result.push(Conflict {
+            state,
+            term,
+            kind,
+            actions,
+            items,
+            prods,
+            path,
+            suggestion,
+        });
+    }
+    result
+}
+
+impl<'a> Conflict<'a> {
+    /// 冲突格子中每个动作的反例推导, `table` 为构造出这个冲突的分析表, 见: [`crate::counterexample`].
+    ///
+    /// # Errors
+    /// 见: [`crate::Grammar::first_set`].
+    pub fn counterexamples(&self, table: &Table<'a>) -> Result<Vec<Counterexample<'a>>, Error> {
+        counterexample::counterexamples(table, self)
+    }
+
+    /// 渲染冲突的报告, 包括冲突的项, 活前缀, 反例和建议, `table` 为构造出这个冲突的分析表.
+    #[must_use]
+    pub fn report(&self, table: &Table<'a>) -> String {
+        let prod = |p: ProdId| {
+            table
+                .grammar()
+                .prod(p)
+                .map_or_else(|| format!("#{p}"), |p| format!("`{p}`"))
+        };
+        let join =
+            |toks: &[Token<'_>]| toks.iter().map(Token::as_str).collect::<Vec<_>>().join(" ");
+        let mut out = format!(
+            "{} conflict on `{}` in I_{}\n",
+            self.kind, self.term, self.state
+        );
+        out += "  items:\n";
+        for item in &self.items {
+            out += &format!("    {item}\n");
+        }
+        out += &format!("  viable prefix: {}\n", join(&self.path));
+        let examples = self.counterexamples(table).unwrap_or_default();
+        if !examples.is_empty() {
+            out += "  counterexamples:\n";
+            for example in examples {
+                for line in example.to_string().lines() {
+                    out += &format!("    {line}\n");
+                }
+            }
+        }
+        out += "  suggestion: ";
+        out += &match &self.suggestion {
+            Suggestion::Precedence { prod: p, term } => format!(
+                "declare the precedence of `{term}` against {}: higher precedence for `{term}` shifts, lower reduces, \
+                 equal precedence picks by associativity",
+                prod(*p)
+            ),
+            Suggestion::DanglingElse { short, long, term } => {
+                let (short, long) = (prod(*short), prod(*long));
+                format!(
+                    "dangling `{term}`: the parser cannot tell whether `{term}` belongs to the inner or the outer \
+                     construct, both reducing {short} and shifting for {long} are valid.\n\
+                     \x20   1. prefer shift, binding `{term}` to the nearest unmatched construct \
+                     (what most languages and yacc's default do), e.g. give `{term}` higher precedence than {short};\n\
+                     \x20   2. split the nonterminal into matched / unmatched forms so only matched ones may appear before `{term}`;\n\
+                     \x20   3. require a closing keyword (e.g. `end`) for {short}"
+                )
+            }
+            Suggestion::LeftFactor { prods, prefix } => format!(
+                "left-factor {}: move the common prefix `{}` into a shared nonterminal",
+                prods
+                    .iter()
+                    .map(|&p| prod(p))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                join(prefix)
+            ),
+            Suggestion::Restructure { heads } => format!(
+                "restructure {}: deciding between them needs more than one token of look-ahead",
+                heads
+                    .iter()
+                    .map(|h| format!("`{h}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        out += "\n";
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        ActionCell, Family, Grammar, Table, Terminal, Token,
+        conflict::{
+            ConflictCell, ConflictKind, ConflictPolicy, ConflictResolver, Resolution, Suggestion,
+            conflicts,
+        },
+        error::Error,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn triage() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> if c then S | if c then S else S | A x | B x | o\nA -> a\nB -> a",
+            "S".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let found = conflicts(&table);
+        assert_eq!(found.len(), 2);
+
+        let dangling = found
+            .iter()
+            .find(|c| c.kind == ConflictKind::ShiftReduce)
+            .unwrap();
+        assert_eq!(dangling.term, Terminal::from("else"));
+        assert_eq!(
+            dangling.suggestion,
+            Suggestion::DanglingElse {
+                short: 1,
+                long: 2,
+                term: "else".into()
+            }
+        );
+        let report = dangling.report(&table);
+        assert!(report.contains("suggestion: dangling `else`: the parser cannot tell"));
+        assert!(report.contains("    1. prefer shift"));
+        assert!(report.contains(
+            "): if c then if c then S ⋅ else S\n      derivation: S -> [ if c then S -> [ if c then S ⋅ else S ] ]\n"
+        ));
+        assert_eq!(dangling.prods, [1, 2].into());
+        let actions: Vec<_> = dangling
+            .actions
+            .iter()
+            .map(|(action, items)| {
+                let items: Vec<_> = items.iter().map(ToString::to_string).collect();
+                (action.clone(), items)
+            })
+            .collect();
+        assert_eq!(
+            actions[1],
+            (
+                ActionCell::Reduce(1),
+                vec!["S -> if c then S ⋅ 〈else, eof〉".to_string()]
+            )
+        );
+        assert!(matches!(actions[0].0, ActionCell::Shift(_)));
+        assert_eq!(actions[0].1, ["S -> if c then S ⋅ else S 〈else, eof〉"]);
+        assert_eq!(table.conflicts().count(), found.len());
+        let path: Vec<_> = dangling.path.iter().map(Token::as_str).collect();
+        assert_eq!(path, ["if", "c", "then", "if", "c", "then", "S"]);
+
+        let rr = found
+            .iter()
+            .find(|c| c.kind == ConflictKind::ReduceReduce)
+            .unwrap();
+        assert_eq!(
+            rr.suggestion,
+            Suggestion::LeftFactor {
+                prods: vec![6, 7],
+                prefix: vec![Terminal::from("a").into()]
+            }
+        );
+        let report = rr.report(&table);
+        assert!(report.starts_with("reduce/reduce conflict on `x` in I_"));
+        assert!(report.contains("    A -> a ⋅ 〈x〉\n"));
+        assert!(report.contains("  viable prefix: a\n"));
+
+        // 右部没有公共前缀的归约/归约冲突.
+        let grammar = Grammar::from_cfg("S -> c A x | B x\nA -> a\nB -> c a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let found = conflicts(&table);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].suggestion,
+            Suggestion::Restructure {
+                heads: vec!["A".into(), "B".into()]
+            }
+        );
+        assert!(found[0].report(&table).contains("restructure `A`, `B`"));
+    }
+
+    #[test]
+    fn resolver() {
+        /// `*` 比 `+` 优先并且左结合, `+` 上的冲突交给默认的策略.
+        struct Times<'a>(Vec<ConflictCell<'a>>);
+        impl<'a> ConflictResolver<'a> for Times<'a> {
+            fn resolve(&mut self, cell: &ConflictCell<'a>) -> Option<ActionCell> {
+                self.0.push(cell.clone());
+                (cell.term.as_str() == "*")
+                    .then(|| cell.actions[usize::from(reduces_times(cell))].clone())
+            }
+        }
+        fn reduces_times(cell: &ConflictCell<'_>) -> bool {
+            cell.items
+                .iter()
+                .any(|i| i.reduces().is_some() && i.prod().to_string() == "e -> e * e")
+        }
+
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("e -> e + e | e * e | id", "e".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let mut times = Times(Vec::new());
+        let table = Table::build_with_resolver(&family, ConflictPolicy::Warn, &mut times).unwrap();
+        let seen = times.0;
+        assert_eq!(seen.len(), 4);
+        for cell in &seen {
+            assert!(matches!(
+                cell.actions[..],
+                [ActionCell::Shift(_), ActionCell::Reduce(_)]
+            ));
+            assert_eq!(cell.items.len(), 2);
+        }
+        assert_eq!(table.resolved(), 2);
+        assert!(table.conflict());
+        let remaining = conflicts(&table);
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.iter().all(|c| c.term.as_str() == "+"));
+        for cell in seen.iter().filter(|c| c.term.as_str() == "*") {
+            let expected = &cell.actions[usize::from(reduces_times(cell))];
+            assert_eq!(table.action(cell.state, cell.term), Some(expected));
+        }
+    }
+
+    #[test]
+    fn policy() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("expr -> expr + expr | id", "expr".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let warned = Table::build_with_policy(&family, ConflictPolicy::Warn).unwrap();
+        assert!(warned.conflict());
+        let (state, term) = (conflicts(&warned)[0].state, conflicts(&warned)[0].term);
+        assert_eq!(
+            Table::build_with_policy(&family, ConflictPolicy::Error).unwrap_err(),
+            Error::ConflictingTable {
+                count: 1,
+                state,
+                term: "+".into()
+            }
+        );
+
+        let shift = ConflictPolicy::Allow(Resolution::PreferShift);
+        let table = Table::build_with_policy(&family, shift).unwrap();
+        assert!(!table.conflict());
+        assert_eq!(table.resolved(), 1);
+        assert!(conflicts(&table).is_empty());
+        assert!(matches!(
+            table.action(state, term),
+            Some(ActionCell::Shift(_))
+        ));
+        let reduce = ConflictPolicy::Allow(Resolution::PreferReduce);
+        let table = Table::build_with_policy(&family, reduce).unwrap();
+        assert_eq!(table.action(state, term), Some(&ActionCell::Reduce(1)));
+    }
+}
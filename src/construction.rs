@@ -0,0 +1,314 @@
+//! 构造过程日志.
+//!
+//! 按照构造算法的顺序重放项集族和分析表的构造: 从 I_0 开始, 依次处理每个项集的每个符号的 GOTO,
+//! 第一次到达的项集作为新的状态加入 (记录它的来源和内核项), 然后由每个项填写分析表的格子,
+//! 最后给出冲突格子最终的动作. 项集的编号就是它们被发现的顺序, 所以日志和实际的构造过程一致.
+//!
+//! [`to_json`] 把日志渲染为 JSON, 可视化工具可以逐步重放, 做成算法的动画.
+
+use std::collections::BTreeMap;
+
+use crate::{ActionCell, Item, StateId, Table, Terminal, Token, graph::json_str};
+
+/// 构造过程中的一步.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<'a> {
+    /// 发现新的项集状态, `source` 为 GOTO 得到它的 (项集状态, 符号), I_0 没有来源.
+    State {
+        state: StateId,
+        source: Option<(StateId, Token<'a>)>,
+        kernel: Vec<Item<'a>>,
+        /// 闭包之后的所有项, 包括内核项.
+        items: Vec<Item<'a>>,
+    },
+    /// 项集族的一条边, 经过非终结符的边同时是 GOTO 表的格子.
+    Goto {
+        from: StateId,
+        token: Token<'a>,
+        to: StateId,
+    },
+    /// 项 `item` 在 ACTION 表的格子中填入 `action`.
+    Action {
+        state: StateId,
+        term: Terminal<'a>,
+        action: ActionCell,
+        item: Item<'a>,
+    },
+    /// 填入了多个动作的格子最终的动作 (通过优先级声明, [`crate::conflict::ConflictResolver`]
+    /// 或者 [`crate::conflict::ConflictPolicy`] 解决之后), 没有解决时为 [`ActionCell::Conflict`].
+    Resolve {
+        state: StateId,
+        term: Terminal<'a>,
+        action: ActionCell,
+    },
+}
+
+/// 重放 `table` 的构造过程.
+#[must_use]
+pub fn construction_log<'a>(table: &Table<'a>) -> Vec<Step<'a>> {
+    let family = table.family();
+    let grammar = table.grammar();
+    let item_sets = family.item_sets();
+    let state = |state: StateId, source: Option<(StateId, Token<'a>)>| {
+        let is = &item_sets[state];
+        Step::State {
+            state,
+            source,
+            kernel: is.kernel().cloned().collect(),
+            items: is.items().cloned().collect(),
+        }
+    };
+    let mut steps = vec![state(0, None)];
+    let mut discovered = 1;
+    for from in 0..family.len() {
+        for (tok, dests) in family.gotos_of(from).into_iter().flatten() {
+            for &to in dests {
+                steps.push(Step::Goto {
+                    from,
+                    token: *tok,
+                    to,
+                });
+                if to == discovered {
+                    steps.push(state(to, Some((from, *tok))));
+                    discovered += 1;
+                }
+            }
+        }
+    }
+    for (row, is) in item_sets.iter().enumerate() {
+        // 每个格子由哪些项填入了哪些不同的动作.
+        let mut cells: BTreeMap<Terminal<'a>, Vec<ActionCell>> = BTreeMap::new();
+        for item in is.items() {
+            let filled: Vec<(Terminal<'a>, ActionCell)> = match item.expected() {
+                Some(Token::Terminal(term)) => family
+                    .gotos_of(row)
+                    .and_then(|gotos| gotos.get(&term.into()))
+                    .into_iter()
+                    .flatten()
+                    .map(|&to| (term, ActionCell::Shift(to)))
+                    .collect(),
+                Some(Token::NonTerminal(_)) => Vec::new(),
+                None => {
+                    let prod = grammar.index_of_prod(item.prod()).unwrap_or_default();
+                    let action = if Some(prod) == grammar.augmented_production() {
+                        ActionCell::Accept
+                    } else {
+                        ActionCell::Reduce(prod)
+                    };
+                    item.look_aheads()
+                        .iter()
+                        .map(|&term| (term, action.clone()))
+                        .collect()
+                }
+            };
+            for (term, action) in filled {
+                let actions = cells.entry(term).or_default();
+                if !actions.contains(&action) {
+                    actions.push(action.clone());
+                }
+                steps.push(Step::Action {
+                    state: row,
+                    term,
+                    action,
+                    item: item.clone(),
+                });
+            }
+        }
+        for (term, actions) in cells {
+            if actions.len() > 1 {
+                steps.push(Step::Resolve {
+                    state: row,
+                    term,
+                    action: table.action(row, term).cloned().unwrap_or_default(),
+                });
+            }
+        }
+    }
+    steps
+}
+
+fn item_json<'a>(table: &Table<'a>, item: &Item<'a>) -> String {
+    let look_aheads = item
+        .look_aheads()
+        .iter()
+        .map(|t| json_str(t.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{{\"prod\": {}, \"dot\": {}, \"look_aheads\": [{look_aheads}], \"text\": {}}}",
+        table
+            .grammar()
+            .index_of_prod(item.prod())
+            .map_or("null".to_string(), |p| p.to_string()),
+        item.dot(),
+        json_str(&item.to_string())
+    )
+}
+
+/// 渲染为 JSON 对象, `steps` 中每一步一个对象, 按照 `kind` (`state`, `goto`, `action`, `resolve`) 区分种类.
+///
+/// 动作使用分析表中的文本 (`s3`, `r2`, `acc`, 没有解决的冲突为 `[conflict]`), 项为
+/// `{"prod": 1, "dot": 2, "look_aheads": ["eof"], "text": "..."}`.
+#[must_use]
+pub fn to_json<'a>(table: &Table<'a>, steps: &[Step<'a>]) -> String {
+    let items = |items: &[Item<'a>]| {
+        items
+            .iter()
+            .map(|item| item_json(table, item))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let steps = steps
+        .iter()
+        .map(|step| match step {
+            Step::State {
+                state,
+                source,
+                kernel,
+                items: all,
+            } => {
+                let (from, token) = source.map_or_else(
+                    || ("null".to_string(), "null".to_string()),
+                    |(from, tok)| (from.to_string(), json_str(tok.as_str())),
+                );
+                format!(
+                    "    {{\"kind\": \"state\", \"state\": {state}, \"from\": {from}, \"token\": {token}, \"kernel\": [{}], \"items\": [{}]}}",
+                    items(kernel),
+                    items(all)
+                )
+            }
+            Step::Goto { from, token, to } => format!(
+                "    {{\"kind\": \"goto\", \"from\": {from}, \"token\": {}, \"terminal\": {}, \"to\": {to}}}",
+                json_str(token.as_str()),
+                token.is_term()
+            ),
+            Step::Action {
+                state,
+                term,
+                action,
+                item,
+            } => format!(
+                "    {{\"kind\": \"action\", \"state\": {state}, \"term\": {}, \"action\": {}, \"item\": {}}}",
+                json_str(term.as_str()),
+                json_str(&action.to_string()),
+                item_json(table, item)
+            ),
+            Step::Resolve {
+                state,
+                term,
+                action,
+            } => format!(
+                "    {{\"kind\": \"resolve\", \"state\": {state}, \"term\": {}, \"action\": {}}}",
+                json_str(term.as_str()),
+                json_str(&action.to_string())
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!("{{\n  \"steps\": [\n{steps}\n  ]\n}}\n")
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        ActionCell, Family, Grammar, Table, Token,
+        construction::{Step, construction_log, to_json},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn replay() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("T -> T + T | n", "T".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let steps = construction_log(&table);
+
+        // 状态按照编号的顺序被发现, 每个新状态紧跟在发现它的边之后.
+        let states: Vec<_> = steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::State { state, .. } => Some(*state),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(states, (0..family.len()).collect::<Vec<_>>());
+        for (i, step) in steps.iter().enumerate().skip(1) {
+            if let Step::State {
+                state,
+                source: Some((from, token)),
+                kernel,
+                ..
+            } = step
+            {
+                assert_eq!(
+                    steps[i - 1],
+                    Step::Goto {
+                        from: *from,
+                        token: *token,
+                        to: *state
+                    }
+                );
+                assert!(kernel.iter().all(|item| item.dot() > 0));
+            }
+        }
+        let gotos = steps
+            .iter()
+            .filter(|step| matches!(step, Step::Goto { .. }))
+            .count();
+        assert_eq!(gotos, family.gotos().count());
+
+        // 移入/归约冲突: 两个动作各自由对应的项填入, 最后保留冲突.
+        let resolve = steps
+            .iter()
+            .position(|step| matches!(step, Step::Resolve { .. }))
+            .unwrap();
+        let Step::Resolve {
+            state,
+            term,
+            action,
+        } = &steps[resolve]
+        else {
+            unreachable!()
+        };
+        assert_eq!(term.as_str(), "+");
+        assert!(action.is_conflict());
+        let filled: Vec<_> = steps[..resolve]
+            .iter()
+            .filter_map(|step| match step {
+                Step::Action {
+                    state: s,
+                    term: t,
+                    action,
+                    item,
+                } if s == state && t == term => Some((action.clone(), item.to_string())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(filled.len(), 2);
+        assert!(filled.contains(&(ActionCell::Reduce(1), "T -> T + T ⋅ 〈+, eof〉".to_string())));
+        assert!(
+            filled
+                .iter()
+                .any(|(action, _)| matches!(action, ActionCell::Shift(_)))
+        );
+
+        let json = to_json(&table, &steps);
+        assert!(json.starts_with(
+            "{\n  \"steps\": [\n    {\"kind\": \"state\", \"state\": 0, \"from\": null, \"token\": null, \"kernel\": [{\"prod\": 0, \"dot\": 0, \"look_aheads\": [\"eof\"], \"text\": "
+        ));
+        assert!(json.contains("{\"kind\": \"resolve\", \"state\": "));
+        assert!(matches!(
+            steps[1],
+            Step::Goto {
+                from: 0,
+                token: Token::Terminal(_),
+                ..
+            }
+        ));
+    }
+}
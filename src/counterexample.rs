@@ -0,0 +1,404 @@
+//! 冲突的反例推导.
+//!
+//! 对冲突格子中的每个项 (dot 之后为冲突终结符的移入项, 或者前瞻符包含冲突终结符的归约项),
+//! 在前瞻符敏感的项图上从这个项反向广度优先搜索到增广产生式的初始项 `S' -> ⋅ S 〈eof〉`, 得到最短的推导:
+//! 句型 `前缀 ⋅ 冲突终结符 后缀`, 以及从开始符号推导出这个句型的过程.
+//!
+//! 项图的节点是 (状态, 带点产生式, 前瞻符), 边有两种:
+//! - 转移: 状态 p 中的 `A -> α ⋅ X β 〈t〉` 经过 X 到达状态 s 中的 `A -> α X ⋅ β 〈t〉`.
+//! - 展开: 状态 s 中的 `B -> β ⋅ C γ 〈t'〉` 到 `C -> ⋅ δ 〈t〉`, 其中 t ∈ FIRST(γ t').
+//!
+//! 同一个冲突的几个反例一般有相同的前缀, 说明分析器读到同一个前缀, 看到同一个终结符时几个动作都是合理的.
+//! 和 Bison 的 `-Wcounterexamples` 不同, 这里不搜索同时满足所有动作的同一个句型 (统一反例).
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::{
+    ActionCell, EOF, EPSILON, Item, NonTerminal, ProdId, StateId, Table, Terminal, Token,
+    conflict::Conflict, error::Error,
+};
+
+/// 项图的节点: (状态, 产生式, dot 的位置, 前瞻符).
+type Node<'a> = (StateId, ProdId, usize, Terminal<'a>);
+
+/// 推导中的一层: 使用的产生式, 以及 dot 的位置 (外层为进入下一层的非终结符的位置).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub prod: ProdId,
+    pub dot: usize,
+}
+
+/// 冲突格子中一个动作的反例.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample<'a> {
+    /// 产生这个动作的项.
+    pub item: Item<'a>,
+    /// 移入, 归约或者接受.
+    pub action: ActionCell,
+    /// dot 之前的符号, 也就是到达冲突状态的活前缀.
+    pub prefix: Vec<Token<'a>>,
+    /// dot 之后的符号, 第一个是冲突的终结符, 不包括结尾的 [`EOF`].
+    pub suffix: Vec<Token<'a>>,
+    /// 推导使用的产生式, 从增广产生式开始由外向内, 最后一层是 `item` 的产生式和 dot.
+    pub frames: Vec<Frame>,
+    /// 推导的文本, 例如 `E -> [ E -> [ E + E ⋅ ] + E ]`, 省略增广产生式.
+    pub derivation: String,
+}
+
+impl Display for Counterexample<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            ActionCell::Shift(_) => "shift",
+            ActionCell::Accept => "accept",
+            _ => "reduce",
+        };
+        let example: Vec<_> = self
+            .prefix
+            .iter()
+            .map(Token::as_str)
+            .chain(std::iter::once("⋅"))
+            .chain(self.suffix.iter().map(Token::as_str))
+            .collect();
+        writeln!(f, "{action} ({}): {}", self.action, example.join(" "))?;
+        writeln!(f, "  derivation: {}", self.derivation)
+    }
+}
+
+/// `toks` 推导出的以 `term` 开头的句型: 能推导出空串的符号被省略, 非终结符按照最左推导展开到 `term` 出现为止.
+///
+/// 先对每个非终结符求出它推导出的以 `term` 开头的一个句型 (不动点迭代, 得到的句型较短), 不存在时返回 [`None`].
+fn leading<'a>(
+    table: &Table<'a>,
+    toks: &[Token<'a>],
+    term: Terminal<'a>,
+) -> Result<Option<Vec<Token<'a>>>, Error> {
+    let grammar = table.grammar();
+    let nullable = |tok: Token<'a>| -> Result<bool, Error> {
+        Ok(grammar.first_set(std::iter::once(tok))?.contains(&EPSILON))
+    };
+    let mut leads: HashMap<NonTerminal<'a>, Vec<Token<'a>>> = HashMap::new();
+    // 以 `term` 开头的展开, 非终结符使用 `leads` 中已有的句型.
+    let expand = |leads: &HashMap<NonTerminal<'a>, Vec<Token<'a>>>,
+                  toks: &[Token<'a>]|
+     -> Result<Option<Vec<Token<'a>>>, Error> {
+        for (i, &tok) in toks.iter().enumerate() {
+            let head = match tok {
+                Token::Terminal(EPSILON) => continue,
+                Token::Terminal(t) if t == term => Some(vec![tok]),
+                Token::NonTerminal(nt) => leads.get(&nt).cloned(),
+                Token::Terminal(_) => None,
+            };
+            if let Some(head) = head {
+                return Ok(Some([head.as_slice(), &toks[i + 1..]].concat()));
+            }
+            if !nullable(tok)? {
+                return Ok(None);
+            }
+        }
+        Ok(None)
+    };
+    loop {
+        let mut changed = false;
+        for prod in grammar.prods() {
+            if leads.contains_key(&prod.head()) {
+                continue;
+            }
+            if let Some(lead) = expand(&leads, prod.tail())? {
+                leads.insert(prod.head(), lead);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    expand(&leads, toks)
+}
+
+/// 从 `start` 反向搜索到增广产生式的初始项, 返回从初始项到 `start` 的节点序列.
+///
+/// `preds[s]` 为状态 s 的所有入边 (前驱状态, 经过的符号).
+fn search<'a>(
+    table: &Table<'a>,
+    preds: &[Vec<(StateId, Token<'a>)>],
+    start: Node<'a>,
+) -> Result<Option<Vec<Node<'a>>>, Error> {
+    let grammar = table.grammar();
+    let item_sets = table.family().item_sets();
+    let mut next: HashMap<Node<'a>, Node<'a>> = HashMap::new();
+    let accept = grammar.augmented_production().unwrap_or_default();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        let (state, prod, dot, term) = node;
+        if (state, prod, dot, term) == (0, accept, 0, EOF) {
+            let mut path = vec![node];
+            while let Some(&to) = path.last().and_then(|last| next.get(last)) {
+                path.push(to);
+            }
+            return Ok(Some(path));
+        }
+        let mut found = Vec::new();
+        if dot > 0 {
+            let Some(&tok) = grammar.prods()[prod].tail_without_eps().nth(dot - 1) else {
+                continue;
+            };
+            for &(from, _) in preds[state].iter().filter(|(_, t)| *t == tok) {
+                let has_item = item_sets[from].items().any(|item| {
+                    item.dot() == dot - 1
+                        && grammar.index_of_prod(item.prod()) == Some(prod)
+                        && item.look_aheads().contains(&term)
+                });
+                if has_item {
+                    found.push((from, prod, dot - 1, term));
+                }
+            }
+        } else {
+            let head = Token::NonTerminal(grammar.prods()[prod].head());
+            for item in item_sets[state]
+                .items()
+                .filter(|item| item.expected() == Some(head))
+            {
+                let Some(outer) = grammar.index_of_prod(item.prod()) else {
+                    continue;
+                };
+                let first = grammar.suffix_first(item.prod(), item.dot() + 1)?;
+                if first.contains(&term) {
+                    found.extend(
+                        item.look_aheads()
+                            .iter()
+                            .map(|&la| (state, outer, item.dot(), la)),
+                    );
+                }
+                if first.contains(&EPSILON) && item.look_aheads().contains(&term) {
+                    found.push((state, outer, item.dot(), term));
+                }
+            }
+        }
+        for pred in found {
+            if pred != start && !next.contains_key(&pred) {
+                next.insert(pred, node);
+                queue.push_back(pred);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// 推导的文本, 每层为 `头部 -> [ 右部 ]`, 下一层替换这一层 dot 之后的非终结符.
+fn derivation(table: &Table<'_>, frames: &[Frame]) -> String {
+    let Some((last, outer)) = frames.split_last() else {
+        return String::new();
+    };
+    let tail = |frame: &Frame| -> Vec<String> {
+        table.grammar().prods()[frame.prod]
+            .tail_without_eps()
+            .map(ToString::to_string)
+            .collect()
+    };
+    let head = |frame: &Frame| table.grammar().prods()[frame.prod].head().to_string();
+    let syms = tail(last);
+    let mut text = [&syms[..last.dot], &["⋅".to_string()], &syms[last.dot..]]
+        .concat()
+        .join(" ");
+    text = format!("{} -> [ {text} ]", head(last));
+    // 省略增广产生式.
+    for frame in outer.iter().skip(1).rev() {
+        let syms = tail(frame);
+        let parts = [&syms[..frame.dot], &[text], &syms[frame.dot + 1..]].concat();
+        text = format!("{} -> [ {} ]", head(frame), parts.join(" "));
+    }
+    text
+}
+
+/// 搜索得到的节点序列对应的推导.
+fn frames(path: &[Node<'_>]) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    for &(_, prod, dot, _) in path {
+        if dot == 0 {
+            frames.push(Frame { prod, dot });
+        } else if let Some(frame) = frames.last_mut() {
+            frame.dot = dot;
+        }
+    }
+    frames
+}
+
+/// 推导得到的句型, 分为 dot 之前和之后两部分, dot 之后的部分展开到以 `term` 开头.
+fn sentential<'a>(
+    table: &Table<'a>,
+    frames: &[Frame],
+    term: Terminal<'a>,
+) -> Result<(Vec<Token<'a>>, Vec<Token<'a>>), Error> {
+    let mut prefix = Vec::new();
+    let mut rest: Vec<Token<'a>> = vec![EOF.into()];
+    for (i, frame) in frames.iter().enumerate() {
+        let tail: Vec<_> = table.grammar().prods()[frame.prod]
+            .tail_without_eps()
+            .copied()
+            .collect();
+        prefix.extend_from_slice(&tail[..frame.dot]);
+        // 外层 dot 之后的非终结符由内层推导.
+        let skip = if i + 1 == frames.len() {
+            frame.dot
+        } else {
+            frame.dot + 1
+        };
+        rest.splice(0..0, tail[skip..].iter().copied());
+    }
+    let mut suffix = leading(table, &rest, term)?.unwrap_or(rest);
+    if suffix.len() > 1 && suffix.last() == Some(&EOF.into()) {
+        suffix.pop();
+    }
+    Ok((prefix, suffix))
+}
+
+/// 冲突格子中每个参与冲突的项的反例, 顺序同 [`Conflict::items`].
+///
+/// 移入和前瞻符无关, 移入项从它的每个前瞻符出发搜索, 选择最短的句型.
+/// 找不到推导的项 (不会出现在从 I_0 构造的项集族中) 被忽略.
+///
+/// # Errors
+/// 见: [`crate::Grammar::first_set`].
+pub fn counterexamples<'a>(
+    table: &Table<'a>,
+    conflict: &Conflict<'a>,
+) -> Result<Vec<Counterexample<'a>>, Error> {
+    let grammar = table.grammar();
+    let family = table.family();
+    let mut preds: Vec<Vec<(StateId, Token<'a>)>> = vec![Vec::new(); family.len()];
+    for (from, tok, to) in family.gotos() {
+        preds[to].push((from, tok));
+    }
+    let cell = table.action(conflict.state, conflict.term);
+    let mut result = Vec::new();
+    for item in &conflict.items {
+        let Some(prod) = grammar.index_of_prod(item.prod()) else {
+            continue;
+        };
+        let look_aheads: Vec<_> = if item.reduces().is_some() {
+            vec![conflict.term]
+        } else {
+            item.look_aheads().iter().copied().collect()
+        };
+        let mut best: Option<(Vec<Frame>, Vec<Token<'a>>, Vec<Token<'a>>)> = None;
+        for la in look_aheads {
+            let Some(path) = search(table, &preds, (conflict.state, prod, item.dot(), la))? else {
+                continue;
+            };
+            let frames = frames(&path);
+            let (prefix, suffix) = sentential(table, &frames, conflict.term)?;
+            let len = prefix.len() + suffix.len();
+            if best
+                .as_ref()
+                .is_none_or(|(_, p, s)| len < p.len() + s.len())
+            {
+                best = Some((frames, prefix, suffix));
+            }
+        }
+        let Some((frames, prefix, suffix)) = best else {
+            continue;
+        };
+        let action = if item.reduces().is_none() {
+            cell.into_iter()
+                .flat_map(ActionCell::flatten)
+                .find(|leaf| matches!(leaf, ActionCell::Shift(_)))
+                .cloned()
+                .unwrap_or_default()
+        } else if Some(prod) == grammar.augmented_production() {
+            ActionCell::Accept
+        } else {
+            ActionCell::Reduce(prod)
+        };
+        result.push(Counterexample {
+            item: item.clone(),
+            action,
+            prefix,
+            suffix,
+            derivation: derivation(table, &frames),
+            frames,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        ActionCell, Family, Grammar, Table, Token,
+        conflict::conflicts,
+        counterexample::{Frame, counterexamples},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn ambiguous_sum() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("T -> T + T | n", "T".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let found = conflicts(&table);
+        assert_eq!(found.len(), 1);
+        let examples = counterexamples(&table, &found[0]).unwrap();
+        assert_eq!(examples.len(), 2);
+        let text = |toks: &[Token]| {
+            toks.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        for example in &examples {
+            assert_eq!(text(&example.prefix), "T + T");
+            assert_eq!(text(&example.suffix), "+ T");
+        }
+        let reduce = examples
+            .iter()
+            .find(|e| e.action == ActionCell::Reduce(1))
+            .unwrap();
+        assert_eq!(reduce.derivation, "T -> [ T -> [ T + T ⋅ ] + T ]");
+        assert_eq!(
+            reduce.frames,
+            [
+                Frame { prod: 0, dot: 0 },
+                Frame { prod: 1, dot: 0 },
+                Frame { prod: 1, dot: 3 }
+            ]
+        );
+        let shift = examples
+            .iter()
+            .find(|e| matches!(e.action, ActionCell::Shift(_)))
+            .unwrap();
+        assert_eq!(shift.derivation, "T -> [ T + T -> [ T ⋅ + T ] ]");
+        assert!(shift.to_string().starts_with("shift (s"));
+        assert!(reduce.to_string().starts_with("reduce (r1): T + T ⋅ + T\n"));
+    }
+
+    #[test]
+    fn nested_look_ahead() {
+        // 归约/归约冲突, 冲突终结符来自外层产生式.
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A x y | B x z\nA -> a\nB -> a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let found = conflicts(&table);
+        let examples = counterexamples(&table, &found[0]).unwrap();
+        let derivations: Vec<_> = examples.iter().map(|e| e.derivation.as_str()).collect();
+        assert_eq!(
+            derivations,
+            ["S -> [ A -> [ a ⋅ ] x y ]", "S -> [ B -> [ a ⋅ ] x z ]"]
+        );
+        let suffixes: Vec<_> = examples
+            .iter()
+            .map(|e| e.suffix.iter().map(ToString::to_string).collect::<Vec<_>>())
+            .collect();
+        assert_eq!(suffixes, [["x", "y"], ["x", "z"]]);
+    }
+}
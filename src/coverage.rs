@@ -0,0 +1,275 @@
+//! 语法分析覆盖率统计.
+//!
+//! 在多次语法分析中记录被使用过的 ACTION/GOTO 表格子和被归约过的产生式,
+//! 用来找出测试输入从来没有到达过的文法部分.
+//!
+//! 同时统计每个产生式被归约的次数和每个状态被进入的次数, 在真实语料上运行之后可以看出分析过程中的热点,
+//! 见: [`Coverage::profile`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{CellRef, Production, StateId, Table, Token};
+
+/// 覆盖率记录器, 通过 [`crate::Parser::parse_with_coverage`] 在语法分析的过程中记录.
+#[derive(Debug, Clone)]
+pub struct Coverage<'t, 'a> {
+    table: &'t Table<'a>,
+    cells: HashSet<(StateId, Token<'a>)>,
+    /// 每个产生式被归约的次数.
+    prods: HashMap<usize, usize>,
+    /// 每个状态被压入状态栈的次数.
+    states: HashMap<StateId, usize>,
+    parses: usize,
+}
+
+impl<'t, 'a> Coverage<'t, 'a> {
+    #[must_use]
+    pub fn new(table: &'t Table<'a>) -> Self {
+        Self {
+            table,
+            cells: HashSet::new(),
+            prods: HashMap::new(),
+            states: HashMap::new(),
+            parses: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn table(&self) -> &'t Table<'a> {
+        self.table
+    }
+
+    /// 已经记录的语法分析次数.
+    #[must_use]
+    pub fn parses(&self) -> usize {
+        self.parses
+    }
+
+    /// 标记格子 (项集状态, 列符号) 被使用过了.
+    pub fn mark_cell(&mut self, state: StateId, tok: impl Into<Token<'a>>) {
+        self.cells.insert((state, tok.into()));
+    }
+
+    /// 标记编号为 `prod` 的产生式被归约了一次.
+    pub fn mark_prod(&mut self, prod: usize) {
+        *self.prods.entry(prod).or_default() += 1;
+    }
+
+    /// 标记状态 `state` 被压入状态栈一次 (初始状态, 移入或者 GOTO).
+    pub fn mark_state(&mut self, state: StateId) {
+        *self.states.entry(state).or_default() += 1;
+    }
+
+    pub(crate) fn finish_parse(&mut self) {
+        self.parses += 1;
+    }
+
+    #[must_use]
+    pub fn is_cell_covered(&self, state: StateId, tok: impl Into<Token<'a>>) -> bool {
+        self.cells.contains(&(state, tok.into()))
+    }
+
+    #[must_use]
+    pub fn is_prod_covered(&self, prod: usize) -> bool {
+        self.prods.contains_key(&prod)
+    }
+
+    /// 产生式 `prod` 被归约的次数.
+    #[must_use]
+    pub fn prod_count(&self, prod: usize) -> usize {
+        self.prods.get(&prod).copied().unwrap_or(0)
+    }
+
+    /// 状态 `state` 被进入的次数.
+    #[must_use]
+    pub fn state_count(&self, state: StateId) -> usize {
+        self.states.get(&state).copied().unwrap_or(0)
+    }
+
+    /// 没有被使用过的非空格子, 顺序同 [`Table::cells`].
+    pub fn untouched_cells(&self) -> impl Iterator<Item = (StateId, Token<'a>, CellRef<'t>)> + '_ {
+        self.table
+            .cells()
+            .filter(|(state, tok, _)| !self.cells.contains(&(*state, *tok)))
+    }
+
+    /// 没有被归约过的产生式及其编号.
+    pub fn untouched_prods(&self) -> impl Iterator<Item = (usize, &'a Production<'a>)> + '_ {
+        self.table
+            .grammar()
+            .prods()
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !self.prods.contains_key(idx))
+            .map(|(idx, &prod)| (idx, prod))
+    }
+
+    /// 被使用过的非空格子数量和非空格子总数.
+    #[must_use]
+    pub fn cell_ratio(&self) -> (usize, usize) {
+        let total = self.table.cells().count();
+        (total - self.untouched_cells().count(), total)
+    }
+
+    /// 被归约过的产生式数量和产生式总数.
+    #[must_use]
+    pub fn prod_ratio(&self) -> (usize, usize) {
+        let total = self.table.grammar().prods().len();
+        (total - self.untouched_prods().count(), total)
+    }
+
+    /// 合并另一个覆盖率记录器的结果, 两者应该基于同一个语法分析表.
+    pub fn merge(&mut self, other: &Self) {
+        self.cells.extend(other.cells.iter().copied());
+        for (&prod, &count) in &other.prods {
+            *self.prods.entry(prod).or_default() += count;
+        }
+        for (&state, &count) in &other.states {
+            *self.states.entry(state).or_default() += count;
+        }
+        self.parses += other.parses;
+    }
+
+    /// 输出归约次数和状态进入次数的统计, 两部分都按照次数从高到低排列 (次数相同时按照编号),
+    /// 每行为次数, 占比和产生式 (或者状态), 没有出现过的也会列出, 用于找出热点产生式和可以使用默认归约的状态.
+    #[must_use]
+    pub fn profile(&self) -> String {
+        let prods = self.table.grammar().prods();
+        let mut out = format!("{} parses\n", self.parses);
+        let percent = |count: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                count as f64 * 100.0 / total as f64
+            }
+        };
+        let mut by_prod: Vec<_> = (0..prods.len()).map(|p| (self.prod_count(p), p)).collect();
+        by_prod.sort_by_key(|&(count, p)| (std::cmp::Reverse(count), p));
+        let total: usize = by_prod.iter().map(|(count, _)| count).sum();
+        out += &format!("reductions ({total}):\n");
+        for (count, p) in by_prod {
+            out += &format!(
+                "{count:>8} {:>5.1}%  r{p}: {}\n",
+                percent(count, total),
+                prods[p]
+            );
+        }
+        let mut by_state: Vec<_> = (0..self.table.rows())
+            .map(|s| (self.state_count(s), s))
+            .collect();
+        by_state.sort_by_key(|&(count, s)| (std::cmp::Reverse(count), s));
+        let total: usize = by_state.iter().map(|(count, _)| count).sum();
+        out += &format!("state visits ({total}):\n");
+        for (count, s) in by_state {
+            out += &format!("{count:>8} {:>5.1}%  I_{s}\n", percent(count, total));
+        }
+        out
+    }
+}
+
+/// 输出覆盖率摘要以及没有被覆盖的产生式和格子.
+impl Display for Coverage<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (prods, prods_total) = self.prod_ratio();
+        let (cells, cells_total) = self.cell_ratio();
+        writeln!(
+            f,
+            "{} parses, productions {prods}/{prods_total}, cells {cells}/{cells_total}",
+            self.parses
+        )?;
+        for (idx, prod) in self.untouched_prods() {
+            writeln!(f, "untouched r{idx}: {prod}")?;
+        }
+        for (state, tok, cell) in self.untouched_cells() {
+            match cell {
+                CellRef::Action(action) => writeln!(f, "untouched I_{state} {tok}: {action}")?,
+                CellRef::Goto(to) => writeln!(f, "untouched I_{state} {tok}: {to}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, NonTerminal, Parser, Table, Terminal, coverage::Coverage};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn untouched_parts() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b | c", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let mut coverage = Coverage::new(&table);
+        let input = |s: &'static str| s.split_whitespace().map(Terminal::from);
+
+        parser
+            .parse_with_coverage(input("a a b"), &mut coverage)
+            .unwrap();
+        assert_eq!(coverage.parses(), 1);
+        let untouched: Vec<_> = coverage.untouched_prods().map(|(idx, _)| idx).collect();
+        assert_eq!(untouched, [3]);
+        assert!(coverage.is_cell_covered(0, NonTerminal::from("S")));
+        assert!(!coverage.is_cell_covered(0, Terminal::from("c")));
+
+        parser
+            .parse_with_coverage(input("c"), &mut coverage)
+            .unwrap();
+        parser
+            .parse_with_coverage(input("a c"), &mut coverage)
+            .unwrap();
+        assert_eq!(coverage.untouched_prods().count(), 0);
+        let (covered, total) = coverage.cell_ratio();
+        assert_eq!((covered + 1, total), (total, 12));
+        assert_eq!(
+            coverage.to_string().lines().last(),
+            Some("untouched I_0 b: s2")
+        );
+
+        parser
+            .parse_with_coverage(input("b"), &mut coverage)
+            .unwrap();
+        assert_eq!(coverage.untouched_cells().count(), 0);
+    }
+
+    #[test]
+    fn profile() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let mut coverage = Coverage::new(&table);
+        for input in ["a a a b", "b"] {
+            parser
+                .parse_with_coverage(input.split_whitespace().map(Terminal::from), &mut coverage)
+                .unwrap();
+        }
+        assert_eq!(
+            [0, 1, 2].map(|p| coverage.prod_count(p)),
+            [2, 3, 2]
+        );
+        // 每次分析从 I_0 开始, 归约之后再次经过 GOTO 进入 I_0 的后继.
+        assert_eq!(coverage.state_count(0), 2);
+        let a = table.goto(0, "S".into()).flatten().unwrap();
+        assert_eq!(coverage.state_count(a), 2);
+        let profile = coverage.profile();
+        let lines: Vec<_> = profile.lines().take(3).collect();
+        assert_eq!(
+            lines,
+            ["2 parses", "reductions (7):", "       3  42.9%  r1: S -> a S"]
+        );
+        assert!(profile.contains("state visits ("));
+    }
+}
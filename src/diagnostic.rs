@@ -0,0 +1,366 @@
+//! 文法文件的诊断信息和悬停提示.
+//!
+//! 把文法解析错误, 从开始符号不可达的非终结符和分析表冲突定位到文法文件中的具体位置,
+//! 并给出光标下符号的 FIRST/FOLLOW 集, 供编辑器 (`lr-analysis-lsp`) 使用.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use bumpalo::Bump;
+
+use crate::{
+    Family, Grammar, NonTerminal, Table, Terminal, Token, error::Error, grammar::is_comment,
+};
+
+/// 文法文件 (或者源代码) 中的一段位置, 行号从 0 开始, 列为行内的字节偏移.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Severity {
+    Error,
+    Warning,
+    /// 提示, 不是问题.
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// 文法文件中符号和产生式的位置, 产生式按照 [`Grammar::from_cfg`] 的编号顺序 (不包括增广产生式).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceMap {
+    /// 每个产生式尾部的位置.
+    pub prods: Vec<Span>,
+    /// 每个产生式头部的位置, 和 [`SourceMap::prods`] 一一对应.
+    pub heads: Vec<Span>,
+}
+
+/// 去掉首尾空白之后的子串在 `s` 中的字节范围, 全是空白时为开头处的空范围.
+fn trimmed(s: &str, offset: usize) -> (usize, usize) {
+    let start = s.len() - s.trim_start().len();
+    let end = s.trim_end().len().max(start);
+    (offset + start, offset + end)
+}
+
+/// 一行中所有的符号及其起始字节偏移, 和 [`Grammar::from_cfg`] 一样先按照 `->` 和 `|` 分割, 再按照空白分割.
+pub(crate) fn words(line: &str) -> Vec<(usize, &str)> {
+    let Some((head, tails)) = line.split_once("->").filter(|_| !is_comment(line)) else {
+        return Vec::new();
+    };
+    let (start, end) = trimmed(head, 0);
+    let mut words = vec![(start, &line[start..end])];
+    let mut offset = head.len() + 2;
+    for tail in tails.split('|') {
+        let mut pos = offset;
+        for word in tail.split_ascii_whitespace() {
+            let at = pos + line[pos..].find(word).unwrap_or(0);
+            words.push((at, word));
+            pos = at + word.len();
+        }
+        offset += tail.len() + 1;
+    }
+    words
+}
+
+impl SourceMap {
+    #[must_use]
+    pub fn new(src: &str) -> Self {
+        let mut map = Self::default();
+        for (line_num, line) in src.lines().enumerate() {
+            let Some((head, tails)) = line.split_once("->").filter(|_| !is_comment(line)) else {
+                continue;
+            };
+            let (start, end) = trimmed(head, 0);
+            let head = Span {
+                line: line_num,
+                start,
+                end,
+            };
+            let mut offset = head_len(line);
+            for tail in tails.split('|') {
+                let (start, end) = trimmed(tail, offset);
+                map.heads.push(head);
+                map.prods.push(Span {
+                    line: line_num,
+                    start,
+                    end,
+                });
+                offset += tail.len() + 1;
+            }
+        }
+        map
+    }
+
+    /// 产生式编号 (增广文法中的编号) 对应的位置, 增广产生式没有位置.
+    #[must_use]
+    pub fn prod(&self, augmented_idx: usize) -> Option<Span> {
+        self.prods.get(augmented_idx.checked_sub(1)?).copied()
+    }
+}
+
+/// `->` 之后的字节偏移.
+fn head_len(line: &str) -> usize {
+    line.find("->").map_or(0, |i| i + 2)
+}
+
+/// 获取文法文件中 `line` 行 `col` 列 (字节偏移) 处的符号及其位置.
+#[must_use]
+pub fn word_at(src: &str, line: usize, col: usize) -> Option<(&str, Span)> {
+    let text = src.lines().nth(line)?;
+    words(text)
+        .into_iter()
+        .find(|(start, word)| (*start..=start + word.len()).contains(&col) && !word.is_empty())
+        .map(|(start, word)| {
+            (
+                word,
+                Span {
+                    line,
+                    start,
+                    end: start + word.len(),
+                },
+            )
+        })
+}
+
+/// 默认的开始符号: 第一个产生式的头部.
+pub(crate) fn default_start(src: &str) -> &str {
+    Grammar::cfg_start(src).unwrap_or_default()
+}
+
+/// 整行 (去掉首尾空白) 的位置.
+pub(crate) fn line_span(src: &str, line: usize) -> Span {
+    let (start, end) = trimmed(src.lines().nth(line).unwrap_or_default(), 0);
+    Span { line, start, end }
+}
+
+fn error_at(span: Span, message: String) -> Diagnostic {
+    Diagnostic {
+        span,
+        severity: Severity::Error,
+        message,
+    }
+}
+
+/// 检查文法文件, 返回按照位置排序的诊断信息:
+/// - 文法解析错误 (没有 `->`, 找不到开始符号等).
+/// - 从开始符号不可达的非终结符 (警告).
+/// - 规范 LR(1) 分析表中的冲突, 标注在每个参与冲突的产生式上.
+///
+/// `start` 为 [`None`] 时使用第一个产生式的头部作为开始符号. 没有任何产生式的文件没有诊断信息.
+#[must_use]
+pub fn check<'s>(src: &'s str, start: Option<&'s str>) -> Vec<Diagnostic> {
+    if src.trim().is_empty() {
+        return Vec::new();
+    }
+    let bump = Bump::new();
+    let start = start.unwrap_or_else(|| default_start(src));
+    let grammar = match Grammar::from_cfg(src, start.into(), &bump) {
+        Ok(grammar) => grammar,
+        Err(e @ Error::ParseProductionError { line, .. }) => {
+            return vec![error_at(line_span(src, line), e.to_string())];
+        }
+        Err(e) => return vec![error_at(line_span(src, 0), e.to_string())],
+    };
+    let map = SourceMap::new(src);
+    let mut diagnostics: BTreeSet<Diagnostic> = unreachable(&grammar)
+        .into_iter()
+        .flat_map(|nt| {
+            grammar
+                .prods()
+                .iter()
+                .zip(&map.heads)
+                .filter(move |(p, _)| p.head() == nt)
+                .map(move |(_, &span)| Diagnostic {
+                    span,
+                    severity: Severity::Warning,
+                    message: format!("`{nt}` is unreachable from the start symbol `{start}`"),
+                })
+        })
+        .collect();
+    let grammar = grammar.augmented();
+    let family = match Family::from_grammar(&grammar) {
+        Ok(family) => family,
+        Err(e) => {
+            diagnostics.insert(error_at(line_span(src, 0), e.to_string()));
+            return diagnostics.into_iter().collect();
+        }
+    };
+    match Table::build_from(&family) {
+        Ok(table) => diagnostics.extend(conflicts(&table, &map)),
+        Err(e) => {
+            diagnostics.insert(error_at(line_span(src, 0), e.to_string()));
+        }
+    }
+    diagnostics.into_iter().collect()
+}
+
+/// 从开始符号出发不可达的非终结符, 按照第一次定义的顺序排列.
+pub(crate) fn unreachable<'a>(grammar: &Grammar<'a>) -> Vec<NonTerminal<'a>> {
+    let mut reached = HashSet::from([grammar.symbol_start()]);
+    let mut queue = VecDeque::from([grammar.symbol_start()]);
+    while let Some(nt) = queue.pop_front() {
+        for prod in grammar.prods().iter().filter(|p| p.head() == nt) {
+            for tok in prod.tail() {
+                if let Token::NonTerminal(next) = *tok
+                    && reached.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    let mut result = Vec::new();
+    for prod in grammar.prods() {
+        if !reached.contains(&prod.head()) && !result.contains(&prod.head()) {
+            result.push(prod.head());
+        }
+    }
+    result
+}
+
+/// 分析表中的每个冲突格子 (见: [`crate::conflict::conflicts`]), 在参与冲突的每个产生式上生成一条诊断信息.
+fn conflicts(table: &Table, map: &SourceMap) -> Vec<Diagnostic> {
+    let grammar = table.grammar();
+    let mut diagnostics = Vec::new();
+    for conflict in crate::conflict::conflicts(table) {
+        for &prod in &conflict.prods {
+            let Some(span) = map.prod(prod) else {
+                continue;
+            };
+            let others = conflict
+                .prods
+                .iter()
+                .filter(|&&p| p != prod)
+                .map(|&p| format!("`{}`", grammar.prods()[p]))
+                .collect::<Vec<_>>()
+                .join(", ");
+            diagnostics.push(error_at(
+                span,
+                format!(
+                    "{} conflict on `{}` in I_{} with {others}",
+                    conflict.kind, conflict.term, conflict.state
+                ),
+            ));
+        }
+    }
+    diagnostics
+}
+
+fn set_text(set: &BTreeSet<Terminal<'_>>) -> String {
+    set.iter()
+        .map(Terminal::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 文法文件中 `line` 行 `col` 列处的符号的悬停提示 (markdown): 符号种类, 非终结符的 FIRST 集和 FOLLOW 集,
+/// 以及使用这个符号的产生式 (见: [`Grammar::usages`]).
+///
+/// 位置上没有符号或者文法无法解析时返回 [`None`].
+#[must_use]
+pub fn hover<'s>(src: &'s str, start: Option<&'s str>, line: usize, col: usize) -> Option<String> {
+    let (word, _) = word_at(src, line, col)?;
+    let bump = Bump::new();
+    let start = start.unwrap_or_else(|| default_start(src));
+    let grammar = Grammar::from_cfg(src, start.into(), &bump).ok()?;
+    let token = grammar.get_token(word)?;
+    let mut text = match token {
+        Token::Terminal(term) => format!("**{term}** (terminal)"),
+        Token::NonTerminal(nt) => {
+            let first = grammar.first_of(nt).ok()?;
+            let follow = grammar.follow_of(nt).unwrap_or_default();
+            format!(
+                "**{nt}** (non-terminal)\n\nFIRST = {{{}}}\n\nFOLLOW = {{{}}}",
+                set_text(&first),
+                set_text(&follow)
+            )
+        }
+    };
+    // 同一个产生式中多次出现只列出一次.
+    let prods = grammar.consumers_of(token);
+    if !prods.is_empty() {
+        text += "\n\nused in:";
+        for &prod in prods {
+            text += &format!("\n- `{}`", grammar.prods()[prod]);
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostic::{Severity, Span, check, hover, word_at};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn grammar_diagnostics() {
+        let src = "S -> if C then S | if C then S else S | a\nC -> b\nU -> c";
+        let diagnostics = check(src, None);
+        let unreachable: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Warning)
+            .collect();
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(
+            unreachable[0].span,
+            Span {
+                line: 2,
+                start: 0,
+                end: 1
+            }
+        );
+        let conflicts: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect();
+        assert_eq!(
+            conflicts.iter().map(|d| d.span).collect::<Vec<_>>(),
+            [
+                Span {
+                    line: 0,
+                    start: 5,
+                    end: 16
+                },
+                Span {
+                    line: 0,
+                    start: 19,
+                    end: 37
+                }
+            ]
+        );
+        assert!(
+            conflicts[0]
+                .message
+                .starts_with("shift/reduce conflict on `else`")
+        );
+
+        let broken = check("S -> a\nS a", None);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].span.line, 1);
+
+        assert_eq!(word_at(src, 0, 20).map(|(w, _)| w), Some("if"));
+        assert_eq!(word_at(src, 0, 17), None);
+        assert_eq!(
+            hover(src, None, 1, 0).unwrap(),
+            "**C** (non-terminal)\n\nFIRST = {b}\n\nFOLLOW = {then}\n\nused in:\n- `S -> if C then S`\n- `S -> if C then S else S`"
+        );
+        assert_eq!(
+            hover(src, None, 0, 40).unwrap(),
+            "**a** (terminal)\n\nused in:\n- `S -> a`"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! 两个文法语言等价的有界抽查.
+//!
+//! 分别从两个文法中取出长度不超过 `max_len` 的句子 (按照长度从短到长枚举的句子和随机生成的句子),
+//! 检查它们是否属于另一个文法的语言, 找出最短的区分两个文法的句子. 重构文法之后可以用来确认语言没有改变.
+//!
+//! 判断句子是否属于文法的语言使用 Earley 识别器 ([`recognizes`]), 对任意上下文无关文法 (包括二义文法
+//! 和有冲突的文法) 都适用, 两个文法的终结符按照名字对应.
+//!
+//! 这只是抽查: 没有找到区分的句子不能证明两个文法等价 (一般的上下文无关文法的等价性是不可判定的).
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use crate::{Grammar, NonTerminal, Terminal, Token, generate::Generator};
+
+/// 可以推导出空串的非终结符.
+fn nullable<'a>(grammar: &Grammar<'a>) -> HashSet<NonTerminal<'a>> {
+    let mut nullable = HashSet::new();
+    loop {
+        let mut changed = false;
+        for prod in grammar.prods() {
+            if !nullable.contains(&prod.head())
+                && prod.tail_without_eps().all(|tok| match tok {
+                    Token::NonTerminal(nt) => nullable.contains(nt),
+                    Token::Terminal(_) => false,
+                })
+            {
+                nullable.insert(prod.head());
+                changed = true;
+            }
+        }
+        if !changed {
+            return nullable;
+        }
+    }
+}
+
+/// 使用 Earley 算法判断 `input` (终结符的名字, 不包括 [`crate::EOF`]) 是否可以从开始符号推导出来.
+#[must_use]
+pub fn recognizes(grammar: &Grammar<'_>, input: &[&str]) -> bool {
+    let tails: Vec<Vec<Token<'_>>> = grammar
+        .prods()
+        .iter()
+        .map(|p| p.tail_without_eps().copied().collect())
+        .collect();
+    let nullable = nullable(grammar);
+    let start = grammar.symbol_start();
+    // 项 (产生式编号, dot 的位置, 起始位置).
+    let mut sets: Vec<Vec<(usize, usize, usize)>> = vec![Vec::new(); input.len() + 1];
+    let mut seen: Vec<HashSet<(usize, usize, usize)>> = vec![HashSet::new(); input.len() + 1];
+    let add = |sets: &mut Vec<Vec<_>>, seen: &mut Vec<HashSet<_>>, i: usize, item| {
+        if seen[i].insert(item) {
+            sets[i].push(item);
+        }
+    };
+    for (p, prod) in grammar.prods().iter().enumerate() {
+        if prod.head() == start {
+            add(&mut sets, &mut seen, 0, (p, 0, 0));
+        }
+    }
+    for i in 0..=input.len() {
+        let mut j = 0;
+        while let Some(&(p, dot, origin)) = sets[i].get(j) {
+            j += 1;
+            match tails[p].get(dot) {
+                Some(Token::NonTerminal(nt)) => {
+                    for &q in grammar.producers_of(*nt) {
+                        add(&mut sets, &mut seen, i, (q, 0, i));
+                    }
+                    // 可以推导出空串的非终结符直接跳过 (Aycock-Horspool).
+                    if nullable.contains(nt) {
+                        add(&mut sets, &mut seen, i, (p, dot + 1, origin));
+                    }
+                }
+                Some(Token::Terminal(t)) => {
+                    if input.get(i) == Some(&t.as_str()) {
+                        add(&mut sets, &mut seen, i + 1, (p, dot + 1, origin));
+                    }
+                }
+                None => {
+                    let head = Token::NonTerminal(grammar.prods()[p].head());
+                    let waiting: Vec<_> = sets[origin]
+                        .iter()
+                        .filter(|&&(q, d, _)| tails[q].get(d) == Some(&head))
+                        .copied()
+                        .collect();
+                    for (q, d, o) in waiting {
+                        add(&mut sets, &mut seen, i, (q, d + 1, o));
+                    }
+                }
+            }
+        }
+    }
+    sets[input.len()].iter().any(|&(p, dot, origin)| {
+        origin == 0 && dot == tails[p].len() && grammar.prods()[p].head() == start
+    })
+}
+
+/// 按照长度从短到长 (长度相同时按照字典序) 枚举长度不超过 `max_len` 的句子, 最多 `limit` 个.
+///
+/// 从开始符号出发展开最左侧的非终结符, 剪掉最短也会超过 `max_len` 的句型.
+/// 展开的句型数量也有上限, 所以含有很多空产生式的文法可能枚举不全.
+#[must_use]
+pub fn sentences<'a>(
+    grammar: &Grammar<'a>,
+    max_len: usize,
+    limit: usize,
+) -> Vec<Vec<Terminal<'a>>> {
+    let min_lens = grammar.min_lens();
+    let min_len = |form: &[Token<'a>]| Grammar::seq_min_len(&min_lens, form);
+    let mut found: BTreeSet<(usize, Vec<Terminal<'a>>)> = BTreeSet::new();
+    let mut visited: HashSet<Vec<Token<'a>>> = HashSet::new();
+    let mut queue = VecDeque::from([vec![Token::NonTerminal(grammar.symbol_start())]]);
+    let mut budget = limit.saturating_mul(100);
+    while let Some(form) = queue.pop_front() {
+        if budget == 0 {
+            break;
+        }
+        budget -= 1;
+        let Some(pos) = form.iter().position(Token::is_non_term) else {
+            let sentence: Vec<_> = form
+                .iter()
+                .filter_map(|tok| match tok {
+                    Token::Terminal(t) => Some(*t),
+                    Token::NonTerminal(_) => None,
+                })
+                .collect();
+            found.insert((sentence.len(), sentence));
+            continue;
+        };
+        let Token::NonTerminal(nt) = form[pos] else {
+            continue;
+        };
+        for prod in grammar.prods().iter().filter(|p| p.head() == nt) {
+            let mut next = form[..pos].to_vec();
+            next.extend(prod.tail_without_eps());
+            next.extend(&form[pos + 1..]);
+            if min_len(&next).is_some_and(|len| len <= max_len) && visited.insert(next.clone()) {
+                queue.push_back(next);
+            }
+        }
+    }
+    found.into_iter().take(limit).map(|(_, s)| s).collect()
+}
+
+/// 只属于其中一个文法的语言的句子.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub sentence: Vec<String>,
+    /// 句子是否属于第一个文法的语言, 否则属于第二个文法的语言.
+    pub in_first: bool,
+}
+
+/// 语言等价抽查的结果.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Equivalence {
+    /// 检查过的不同句子的数量.
+    pub checked: usize,
+    /// 找到的最短的区分两个文法的句子.
+    pub difference: Option<Difference>,
+}
+
+/// 从 `grammar` 中取出的句子: 最短的 `limit` 个句子和 `limit` 个随机句子.
+fn samples(grammar: &Grammar<'_>, max_len: usize, limit: usize, seed: u64) -> Vec<Vec<String>> {
+    let mut generator = Generator::new(grammar, seed);
+    sentences(grammar, max_len, limit)
+        .into_iter()
+        .chain((0..limit).filter_map(|_| generator.sentence(max_len)))
+        .map(|s| s.iter().map(ToString::to_string).collect())
+        .collect()
+}
+
+/// 抽查两个文法的语言是否相同, 见: [`sentences`], [`recognizes`].
+///
+/// 每个文法取出长度不超过 `max_len` 的最短的 `limit` 个句子和 `limit` 个随机句子, 给定 `seed` 时结果是确定的.
+#[must_use]
+pub fn check(
+    a: &Grammar<'_>,
+    b: &Grammar<'_>,
+    max_len: usize,
+    limit: usize,
+    seed: u64,
+) -> Equivalence {
+    let mut candidates: BTreeSet<(usize, Vec<String>, bool)> = BTreeSet::new();
+    for sentence in samples(a, max_len, limit, seed) {
+        candidates.insert((sentence.len(), sentence, true));
+    }
+    for sentence in samples(b, max_len, limit, seed) {
+        candidates.insert((sentence.len(), sentence, false));
+    }
+    let mut checked: HashSet<Vec<String>> = HashSet::new();
+    let mut result = Equivalence::default();
+    for (_, sentence, from_first) in candidates {
+        if !checked.insert(sentence.clone()) {
+            continue;
+        }
+        result.checked += 1;
+        let words: Vec<&str> = sentence.iter().map(String::as_str).collect();
+        let in_other = if from_first {
+            recognizes(b, &words)
+        } else {
+            recognizes(a, &words)
+        };
+        if !in_other {
+            result.difference = Some(Difference {
+                sentence,
+                in_first: from_first,
+            });
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Grammar, Terminal,
+        equiv::{Difference, check, recognizes, sentences},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn equivalence() {
+        let bump = Bump::new();
+        let ambiguous = Grammar::from_cfg("e -> e + e | e * e | id", "e".into(), &bump).unwrap();
+        let layered = Grammar::from_cfg(
+            "expr -> expr + term | term\nterm -> term * id | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap();
+        assert!(recognizes(&ambiguous, &["id", "+", "id", "*", "id"]));
+        assert!(!recognizes(&layered, &["id", "+"]));
+        let short: Vec<Vec<_>> = sentences(&layered, 3, 10)
+            .iter()
+            .map(|s| s.iter().map(Terminal::as_str).collect())
+            .collect();
+        assert_eq!(
+            short,
+            [vec!["id"], vec!["id", "*", "id"], vec!["id", "+", "id"]]
+        );
+
+        let result = check(&ambiguous, &layered, 7, 50, 1);
+        assert_eq!(result.difference, None);
+        assert!(result.checked > 10);
+
+        // 乘法的右操作数只能是 id.
+        let broken = Grammar::from_cfg(
+            "expr -> expr + term | term\nterm -> id * term | ( expr ) | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap();
+        let result = check(&layered, &broken, 7, 50, 1);
+        assert_eq!(
+            result.difference,
+            Some(Difference {
+                sentence: vec!["(".into(), "id".into(), ")".into()],
+                in_first: false
+            })
+        );
+
+        // 空产生式.
+        let list = Grammar::from_cfg("L -> x L | E", "L".into(), &bump).unwrap();
+        assert!(recognizes(&list, &[]));
+        assert!(recognizes(&list, &["x", "x"]));
+    }
+}
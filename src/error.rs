@@ -1,4 +1,5 @@
-#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Error {
     #[error("Error parsing productions, line: {line}, cause: {cause:?}.")]
     ParseProductionError {
@@ -7,19 +8,66 @@ pub enum Error {
     },
     #[error("Grammar may be not augmented")]
     GrammarNotAugmented,
-    #[error("First set state is calculating, maybe some errors occurred.")]
-    InvalidFirstSetState,
     #[error("Grammar does not contain the non-terminal: {0}.")]
     NonTerminalNotFound(String),
-    #[error("Grammar unresolvable first set, this should not present.")]
-    UnresolvableFirstSet,
+    #[error("Grammar does not contain the terminal: {0}.")]
+    TerminalNotFound(String),
+    #[error("Grammar does not contain the production: {0}.")]
+    ProductionNotFound(String),
     #[error("ItemSet state not found: {0}.")]
     StateNotFound(usize),
     #[error("Grammar is ambiguous.")]
     AmbiguousGrammar,
+    #[error("Table has {count} conflicting cells, the first one is I_{state} on {term}.")]
+    ConflictingTable {
+        /// 冲突格子的数量.
+        count: usize,
+        state: usize,
+        term: String,
+    },
+    #[error(
+        "Unexpected token {token} on I_{state}, expected one of: {expected:?}.{}",
+        context_lines(.context)
+    )]
+    UnexpectedToken {
+        state: usize,
+        token: String,
+        expected: Vec<String>,
+        /// 出错时正在分析的结构, 从内到外.
+        context: Vec<ParseContext>,
+    },
+    #[error(
+        "Unexpected end of input on I_{state}, expected one of: {expected:?}.{}",
+        context_lines(.context)
+    )]
+    UnexpectedEof {
+        state: usize,
+        expected: Vec<String>,
+        /// 出错时正在分析的结构, 从内到外.
+        context: Vec<ParseContext>,
+    },
+    #[error("Invalid pattern for terminal {term}: {cause}")]
+    InvalidPattern { term: String, cause: String },
+    #[error("Unknown lexeme {lexeme:?} at line {line}.")]
+    UnknownLexeme { lexeme: String, line: usize },
+    #[error("Input ends in lexer mode {mode} at line {line}.")]
+    UnterminatedMode { mode: String, line: usize },
+    #[error("Invalid symbol {ident:?}: {cause}.")]
+    InvalidSymbol { ident: String, cause: String },
+    #[error("Invalid recording at line {line}: {cause}.")]
+    InvalidRecording { line: usize, cause: String },
+    #[error("Recording was made with table {expected:016x}, but the current table is {found:016x}.")]
+    TableMismatch { expected: u64, found: u64 },
+    #[error("Table does not fit the fixed size: {found} {what}, expected {expected}.")]
+    TableSize {
+        what: String,
+        expected: usize,
+        found: usize,
+    },
 }
 
-#[derive(thiserror::Error, Debug, Eq, PartialEq)]
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ParseProductionError {
     #[error("No arrow in production line")]
     NoArrow,
@@ -27,6 +75,39 @@ pub enum ParseProductionError {
     TokenTypeMisMatch(String),
     #[error("Start symbol not found")]
     StartSymbolNotFound,
+    #[error("Invalid weight directive, expected `# weight: N A -> α`: {0}")]
+    InvalidWeight(String),
+    #[error("Invalid metadata directive, expected `# meta: key = value`: {0}")]
+    InvalidMetadata(String),
+    #[error("Invalid precedence declaration, expected `%left`, `%right` or `%nonassoc` followed by terminals: {0}")]
+    InvalidPrecedence(String),
+    #[error("Symbol {symbol} collides with the built-in {builtin}, rename it")]
+    ReservedSymbol { symbol: String, builtin: String },
+}
+
+/// 语法错误发生时正在分析的一个结构: 分析栈上 dot 在产生式右部中间的项.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParseContext {
+    /// 正在分析的产生式头部.
+    pub head: String,
+    /// 这个结构的第一个符号所在的行号.
+    pub line: usize,
+}
+
+impl std::fmt::Display for ParseContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "while parsing `{}` started at line {}",
+            self.head, self.line
+        )
+    }
+}
+
+/// 每个结构一行, 见: [`ParseContext`].
+fn context_lines(context: &[ParseContext]) -> String {
+    context.iter().map(|c| format!("\n  {c}")).collect()
 }
 
 impl Error {
@@ -34,3 +115,82 @@ impl Error {
         Self::ParseProductionError { line, cause }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Parser, Table, Terminal, error::Error, lexer::LexerSpec};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn not_found() {
+        let bump = Bump::new();
+        let mut grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let Err(e) = LexerSpec::new(&grammar).regex("num", "[0-9]+") else {
+            panic!("`num` is not a terminal of the grammar");
+        };
+        assert_eq!(e, Error::TerminalNotFound("num".to_string()));
+        assert_eq!(e.to_string(), "Grammar does not contain the terminal: num.");
+        // 增广产生式不能删除.
+        let e = grammar.remove_production(0).unwrap_err();
+        assert_eq!(e, Error::ProductionNotFound("#0".to_string()));
+        assert_eq!(
+            e.to_string(),
+            "Grammar does not contain the production: #0."
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&e).unwrap(),
+            r##"{"ProductionNotFound":"#0"}"##
+        );
+    }
+
+    #[test]
+    fn unexpected() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let parse = |src: &'static str| {
+            parser
+                .parse_tree(src.split_whitespace().map(Terminal::from))
+                .unwrap_err()
+        };
+        let e = parse("( ) )");
+        assert!(matches!(
+            &e,
+            Error::UnexpectedToken { state: 6, token, expected, .. }
+                if token == ")" && expected == &["(", "eof"]
+        ));
+        assert_eq!(
+            e.to_string(),
+            "Unexpected token ) on I_6, expected one of: [\"(\", \"eof\"].\n  while parsing `S` started at line 0"
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&e).unwrap(),
+            r#"{"UnexpectedToken":{"state":6,"token":")","expected":["(","eof"],"context":[{"head":"S","line":0}]}}"#
+        );
+
+        let e = parse("(");
+        assert!(matches!(
+            &e,
+            Error::UnexpectedEof { state: 4, expected, .. } if expected == &[")"]
+        ));
+        assert_eq!(
+            e.to_string(),
+            "Unexpected end of input on I_4, expected one of: [\")\"].\n  while parsing `S` started at line 0"
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&e).unwrap(),
+            r#"{"UnexpectedEof":{"state":4,"expected":[")"],"context":[{"head":"S","line":0}]}}"#
+        );
+    }
+}
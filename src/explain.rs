@@ -0,0 +1,223 @@
+//! 项集闭包的逐步讲解.
+//!
+//! 从项集的内核项出发, 重新计算闭包, 记录每一步由哪个项触发, 加入 (或者扩充) 了哪些项,
+//! 以及前瞻符是怎么计算出来的, 相当于闭包的文字证明.
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    fmt::Display,
+};
+
+use crate::{EPSILON, Item, ItemSet, NonTerminal, Production, Terminal, Token, error::Error};
+
+/// 闭包计算中的一步: 处理一个 dot 之后是非终结符的项.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosureStep<'a> {
+    /// 触发这一步的项, 前瞻符为处理时的前瞻符.
+    pub trigger: Item<'a>,
+    /// dot 之后的非终结符 B.
+    pub non_term: NonTerminal<'a>,
+    /// B 之后的符号串 β.
+    pub rest: Vec<Token<'a>>,
+    /// FIRST(β), 不包括 [`EPSILON`].
+    pub first: BTreeSet<Terminal<'a>>,
+    /// β 是否能推导出空串, 此时新项继承触发项的前瞻符.
+    pub nullable: bool,
+    /// 新项的前瞻符, 也就是 FIRST(βa), a 为触发项的前瞻符.
+    pub look_aheads: BTreeSet<Terminal<'a>>,
+    /// 新加入闭包的项.
+    pub added: Vec<Item<'a>>,
+    /// 已经在闭包中, 但是前瞻符被扩充的项 (扩充后的前瞻符).
+    pub extended: Vec<Item<'a>>,
+}
+
+fn set_text(set: &BTreeSet<Terminal<'_>>) -> String {
+    set.iter()
+        .map(Terminal::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 输出形如:
+/// ```text
+/// S -> ⋅ A b 〈eof〉
+///   B = A, β = b, FIRST(β) = {b}, look-aheads = {b}
+///   + A -> ⋅ a 〈b〉
+/// ```
+impl Display for ClosureStep<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.trigger)?;
+        let rest = if self.rest.is_empty() {
+            EPSILON.to_string()
+        } else {
+            self.rest
+                .iter()
+                .map(|t| t.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        write!(
+            f,
+            "  B = {}, β = {rest}, FIRST(β) = {{{}}}",
+            self.non_term,
+            set_text(&self.first)
+        )?;
+        if self.nullable {
+            write!(
+                f,
+                ", β ⇒* {EPSILON} so inherit {{{}}}",
+                set_text(self.trigger.look_aheads())
+            )?;
+        }
+        write!(f, ", look-aheads = {{{}}}", set_text(&self.look_aheads))?;
+        for item in &self.added {
+            write!(f, "\n  + {item}")?;
+        }
+        for item in &self.extended {
+            write!(f, "\n  ~ {item}")?;
+        }
+        if self.added.is_empty() && self.extended.is_empty() {
+            write!(f, "\n  (nothing new)")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ItemSet<'a> {
+    /// 项集的内核项: dot 不在最左边的项, 以及增广产生式的初始项.
+    pub fn kernel(&self) -> impl Iterator<Item = &Item<'a>> {
+        let grammar = self.grammar();
+        let start = grammar.augmented_production().and_then(|p| grammar.prod(p));
+        self.items()
+            .filter(move |item| item.dot() > 0 || Some(item.prod()) == start)
+    }
+
+    /// 从内核项出发逐步计算闭包, 返回每一步的讲解, 最终得到的项和 [`ItemSet::items`] 相同.
+    ///
+    /// 每一步处理一个 dot 之后是非终结符的项, 前瞻符被扩充的项会重新处理.
+    /// # Errors
+    /// 见: [`crate::Grammar::first_set`].
+    pub fn closure_steps(&self) -> Result<Vec<ClosureStep<'a>>, Error> {
+        let grammar = self.grammar();
+        let mut items: HashMap<(&'a Production<'a>, usize), BTreeSet<Terminal<'a>>> =
+            HashMap::new();
+        let mut queue = VecDeque::new();
+        for item in self.kernel() {
+            items.insert(item.core(), item.look_aheads().clone());
+            queue.push_back(item.core());
+        }
+        let mut steps = Vec::new();
+        while let Some(core) = queue.pop_front() {
+            let trigger = Item::new(core.0, core.1, items[&core].clone());
+            let Some(Token::NonTerminal(non_term)) = trigger.expected() else {
+                continue;
+            };
+            let rest: Vec<_> = trigger.future_seq().copied().collect();
+            let mut first: BTreeSet<_> = grammar
+                .first_set(rest.iter().copied())?
+                .into_iter()
+                .collect();
+            let nullable = first.remove(&EPSILON);
+            let mut look_aheads = first.clone();
+            if nullable {
+                look_aheads.extend(trigger.look_aheads());
+            }
+            let mut added = Vec::new();
+            let mut extended = Vec::new();
+            // 按照产生式编号排序, 保证输出稳定.
+            let mut prods: Vec<_> = grammar.prods_of(non_term).into_iter().collect();
+            prods.sort_by_key(|p| grammar.index_of_prod(p));
+            for prod in prods {
+                let core = (prod, 0);
+                match items.get_mut(&core) {
+                    None => {
+                        items.insert(core, look_aheads.clone());
+                        added.push(Item::new(prod, 0, look_aheads.clone()));
+                        queue.push_back(core);
+                    }
+                    Some(existing) if !existing.is_superset(&look_aheads) => {
+                        existing.extend(look_aheads.iter().copied());
+                        extended.push(Item::new(prod, 0, existing.clone()));
+                        if !queue.contains(&core) {
+                            queue.push_back(core);
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+            steps.push(ClosureStep {
+                trigger,
+                non_term,
+                rest,
+                first,
+                nullable,
+                look_aheads,
+                added,
+                extended,
+            });
+        }
+        Ok(steps)
+    }
+
+    /// 把 [`ItemSet::closure_steps`] 渲染为文本: 先列出内核项, 然后每一步之间空一行.
+    ///
+    /// # Errors
+    /// 见: [`ItemSet::closure_steps`].
+    pub fn explain(&self) -> Result<String, Error> {
+        let mut out = String::from("kernel:\n");
+        for item in self.kernel() {
+            out += &format!("  {item}\n");
+        }
+        for (i, step) in self.closure_steps()?.iter().enumerate() {
+            out += &format!("\nstep {}: {step}\n", i + 1);
+        }
+        Ok(out.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Item};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn closure_walkthrough() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        for is in family.item_sets() {
+            let mut items = BTreeMap::new();
+            let mut put = |item: &Item| {
+                items.insert((item.prod().to_string(), item.dot()), item.to_string());
+            };
+            is.kernel().for_each(&mut put);
+            for step in is.closure_steps().unwrap() {
+                step.added.iter().chain(&step.extended).for_each(&mut put);
+            }
+            assert_eq!(
+                items.into_values().collect::<BTreeSet<_>>(),
+                is.items().map(ToString::to_string).collect::<BTreeSet<_>>()
+            );
+        }
+        assert_eq!(
+            family.item_sets()[0].explain().unwrap(),
+            "kernel:
+  Sprime -> ⋅ S 〈eof〉
+
+step 1: Sprime -> ⋅ S 〈eof〉
+  B = S, β = E, FIRST(β) = {}, β ⇒* E so inherit {eof}, look-aheads = {eof}
+  + S -> ⋅ C C 〈eof〉
+
+step 2: S -> ⋅ C C 〈eof〉
+  B = C, β = C, FIRST(β) = {c, d}, look-aheads = {c, d}
+  + C -> ⋅ c C 〈c, d〉
+  + C -> ⋅ d 〈c, d〉"
+        );
+    }
+}
@@ -0,0 +1,536 @@
+//! 根据文法随机生成句子 (终结符序列), 可用于生成模糊测试的语料.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ActionCell, EOF, EPSILON, Grammar, NonTerminal, Production, StateId, Table, Terminal, Token,
+    grammar::DEFAULT_WEIGHT,
+};
+
+/// 简单的伪随机数生成器 (SplitMix64), 给定种子时输出是确定的.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 生成 `0..n` 范围内的随机数, `n` 不能为 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// 随机句子生成器.
+///
+/// 从开始符号出发, 每次展开最左侧的非终结符, 在不超过最大长度的前提下随机选择产生式.
+#[derive(Debug, Clone)]
+pub struct Generator<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    /// 每个非终结符能推导出的最短终结符串长度.
+    min_lens: HashMap<NonTerminal<'a>, usize>,
+    /// 每个非终结符推导出终结符串所需的最小推导树高度, 用于在展开次数过多时强制收敛.
+    heights: HashMap<NonTerminal<'a>, usize>,
+    /// 每个非终结符的最短推导所使用的产生式.
+    shortest: HashMap<NonTerminal<'a>, &'a Production<'a>>,
+    rng: Rng,
+}
+
+impl<'g, 'a> Generator<'g, 'a> {
+    #[must_use]
+    pub fn new(grammar: &'g Grammar<'a>, seed: u64) -> Self {
+        Self {
+            grammar,
+            min_lens: grammar.min_lens(),
+            heights: Self::heights(grammar),
+            shortest: Self::shortest(grammar),
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// 为每个非终结符选择最短推导的产生式, 长度相同时选择推导树高度较小的,
+    /// 由于子节点的推导树高度总是小于父节点, 按照这些产生式展开一定会结束.
+    fn shortest(grammar: &Grammar<'a>) -> HashMap<NonTerminal<'a>, &'a Production<'a>> {
+        let mut best: HashMap<NonTerminal<'a>, ((usize, usize), &'a Production<'a>)> =
+            HashMap::new();
+        loop {
+            let mut changed = false;
+            for &prod in grammar.prods() {
+                let Some(key) =
+                    prod.tail_without_eps()
+                        .try_fold((0, 0), |(len, h), tok| match tok {
+                            Token::Terminal(_) => Some((len + 1, h)),
+                            Token::NonTerminal(nt) => {
+                                best.get(nt).map(|&((l, nh), _)| (len + l, h.max(nh)))
+                            }
+                        })
+                else {
+                    continue;
+                };
+                let key = (key.0, key.1 + 1);
+                if best.get(&prod.head()).is_none_or(|&(k, _)| key < k) {
+                    best.insert(prod.head(), (key, prod));
+                    changed = true;
+                }
+            }
+            if !changed {
+                break best.into_iter().map(|(nt, (_, p))| (nt, p)).collect();
+            }
+        }
+    }
+
+    fn heights(grammar: &Grammar<'a>) -> HashMap<NonTerminal<'a>, usize> {
+        let mut heights: HashMap<NonTerminal<'a>, usize> = HashMap::new();
+        loop {
+            let mut changed = false;
+            for prod in grammar.prods() {
+                let Some(h) = Self::prod_height(&heights, prod) else {
+                    continue;
+                };
+                let entry = heights.entry(prod.head()).or_insert(usize::MAX);
+                if h < *entry {
+                    *entry = h;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break heights;
+            }
+        }
+    }
+
+    fn prod_height(
+        heights: &HashMap<NonTerminal<'a>, usize>,
+        prod: &Production<'a>,
+    ) -> Option<usize> {
+        prod.tail_without_eps()
+            .try_fold(0, |acc, tok| match tok {
+                Token::Terminal(_) => Some(acc),
+                Token::NonTerminal(nt) => heights.get(nt).map(|&h| acc.max(h)),
+            })
+            .map(|h| h + 1)
+    }
+
+    /// 按照权重 (见: [`Grammar::weight`]) 随机选择一个产生式, `prods` 不能为空.
+    ///
+    /// 权重都相同时等概率选择, 权重都为 0 时也等概率选择.
+    fn pick(&mut self, prods: &[&'a Production<'a>]) -> &'a Production<'a> {
+        let weights: Vec<u64> = prods
+            .iter()
+            .map(|p| {
+                self.grammar
+                    .index_of_prod(p)
+                    .map_or(u64::from(DEFAULT_WEIGHT), |id| {
+                        u64::from(self.grammar.weight(id))
+                    })
+            })
+            .collect();
+        let total: u64 = weights.iter().sum();
+        if total == 0 || weights.iter().all(|&w| w == weights[0]) {
+            return prods[self.rng.below(prods.len())];
+        }
+        let mut r = self.rng.next_u64() % total;
+        for (prod, w) in prods.iter().zip(weights) {
+            if r < w {
+                return prod;
+            }
+            r -= w;
+        }
+        prods[prods.len() - 1]
+    }
+
+    /// 一个非终结符能推导出的最短终结符串长度, 不可终止的非终结符返回 [`None`].
+    #[must_use]
+    pub fn min_len(&self, nt: NonTerminal<'a>) -> Option<usize> {
+        self.min_lens.get(&nt).copied()
+    }
+
+    /// 从开始符号生成一个长度不超过 `max_len` 的句子,
+    /// 如果开始符号不可终止或者最短的句子也超过了 `max_len`, 那么返回 [`None`].
+    pub fn sentence(&mut self, max_len: usize) -> Option<Vec<Terminal<'a>>> {
+        self.sentence_from(self.grammar.symbol_start(), max_len)
+    }
+
+    /// 从非终结符 `nt` 生成一个长度不超过 `max_len` 的终结符串, 见: [`Generator::sentence`].
+    pub fn sentence_from(
+        &mut self,
+        nt: NonTerminal<'a>,
+        max_len: usize,
+    ) -> Option<Vec<Terminal<'a>>> {
+        if self.min_len(nt)? > max_len {
+            return None;
+        }
+        let mut output = Vec::new();
+        // 待展开的 tokens, 栈顶为最左侧.
+        let mut pending: Vec<Token<'a>> = vec![nt.into()];
+        // 待展开 tokens 的最短长度之和.
+        let mut pending_len = self.min_len(nt)?;
+        // 超过这个展开次数之后, 总是选择最短且推导树高度最小的产生式, 保证生成过程结束.
+        let mut expansions_left = max_len.saturating_mul(32).max(1024);
+        while let Some(tok) = pending.pop() {
+            match tok {
+                Token::Terminal(EPSILON) => {}
+                Token::Terminal(t) => {
+                    output.push(t);
+                    pending_len -= 1;
+                }
+                Token::NonTerminal(nt) => {
+                    pending_len -= self.min_len(nt)?;
+                    let budget = max_len - output.len() - pending_len;
+                    let prods = self
+                        .grammar
+                        .prods()
+                        .iter()
+                        .filter(|p| p.head() == nt)
+                        .filter(|p| {
+                            Grammar::seq_min_len(&self.min_lens, p.tail())
+                                .is_some_and(|l| l <= budget)
+                        });
+                    let prod = if expansions_left == 0 {
+                        prods.min_by_key(|p| {
+                            (
+                                Grammar::seq_min_len(&self.min_lens, p.tail()),
+                                Self::prod_height(&self.heights, p),
+                            )
+                        })?
+                    } else {
+                        expansions_left -= 1;
+                        let prods: Vec<_> = prods.copied().collect();
+                        if prods.is_empty() {
+                            return None;
+                        }
+                        self.pick(&prods)
+                    };
+                    pending_len += Grammar::seq_min_len(&self.min_lens, prod.tail())?;
+                    pending.extend(prod.tail().iter().rev());
+                }
+            }
+        }
+        Some(output)
+    }
+}
+
+impl<'a> Generator<'_, 'a> {
+    /// 按照最短推导把 token 序列展开为终结符串, 并记录用到的产生式编号.
+    fn expand_min(
+        &self,
+        seq: &[Token<'a>],
+        output: &mut Vec<Terminal<'a>>,
+        used: &mut HashSet<usize>,
+    ) -> Option<()> {
+        for tok in seq {
+            match *tok {
+                Token::Terminal(EPSILON) => {}
+                Token::Terminal(t) => output.push(t),
+                Token::NonTerminal(nt) => {
+                    let prod = self.shortest.get(&nt)?;
+                    used.extend(self.grammar.index_of_prod(prod));
+                    self.expand_min(prod.tail(), output, used)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// 对于每个从开始符号可达的非终结符, 记录包含它的最短句型的来源 (产生式, 在产生式尾部中的位置).
+    fn contexts(&self) -> HashMap<NonTerminal<'a>, (usize, &'a Production<'a>, usize)> {
+        let start = self.grammar.symbol_start();
+        // 非终结符 -> (周围终结符串的最短长度, 来源产生式, 位置)
+        let mut contexts: HashMap<NonTerminal<'a>, (usize, &'a Production<'a>, usize)> =
+            HashMap::new();
+        let dist = |contexts: &HashMap<_, (usize, _, _)>, nt| {
+            if nt == start {
+                Some(0)
+            } else {
+                contexts.get(&nt).map(|&(d, _, _)| d)
+            }
+        };
+        loop {
+            let mut changed = false;
+            for &prod in self.grammar.prods() {
+                let Some(base) = dist(&contexts, prod.head()) else {
+                    continue;
+                };
+                let Some(total) = Grammar::seq_min_len(&self.min_lens, prod.tail()) else {
+                    continue;
+                };
+                for (i, tok) in prod.tail().iter().enumerate() {
+                    let Token::NonTerminal(nt) = *tok else {
+                        continue;
+                    };
+                    if nt == start {
+                        continue;
+                    }
+                    let d = base + total - self.min_lens[&nt];
+                    if contexts.get(&nt).is_none_or(|&(old, _, _)| d < old) {
+                        contexts.insert(nt, (d, prod, i));
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break contexts;
+            }
+        }
+    }
+
+    /// 贪心地生成一组句子, 使得文法中每个可达且可终止的产生式都至少在某个句子的推导中被使用一次.
+    ///
+    /// 每个句子都是在开始符号的最短句型中使用一个还没有被覆盖的产生式, 其余部分按照最短推导展开得到的.
+    #[must_use]
+    pub fn covering_sentences(&self) -> Vec<Vec<Terminal<'a>>> {
+        let start = self.grammar.symbol_start();
+        let contexts = self.contexts();
+        let mut covered = HashSet::new();
+        let mut sentences = Vec::new();
+        for (idx, &prod) in self.grammar.prods().iter().enumerate() {
+            if covered.contains(&idx)
+                || (prod.head() != start && !contexts.contains_key(&prod.head()))
+            {
+                continue;
+            }
+            let mut used = HashSet::from([idx]);
+            let mut sentence = Vec::new();
+            if self
+                .expand_min(prod.tail(), &mut sentence, &mut used)
+                .is_none()
+            {
+                continue;
+            }
+            let mut nt = prod.head();
+            while nt != start {
+                let (_, parent, i) = contexts[&nt];
+                used.extend(self.grammar.index_of_prod(parent));
+                let mut outer = Vec::new();
+                self.expand_min(&parent.tail()[..i], &mut outer, &mut used);
+                outer.append(&mut sentence);
+                self.expand_min(&parent.tail()[i + 1..], &mut outer, &mut used);
+                sentence = outer;
+                nt = parent.head();
+            }
+            covered.extend(used);
+            sentences.push(sentence);
+        }
+        sentences
+    }
+
+    /// 在 [`Generator::covering_sentences`] 的基础上, 补充句子使得语法分析表 `table` 中每个非空的格子都尽量被使用一次.
+    ///
+    /// 对于没有被覆盖的格子, 从到达其项集状态的最短活前缀出发, 接上这个格子对应的符号, 然后贪心地补全成句子.
+    /// 无法构造出句子的格子会被忽略.
+    #[must_use]
+    pub fn covering_cell_sentences(&self, table: &Table<'a>) -> Vec<Vec<Terminal<'a>>> {
+        let mut sentences = Vec::new();
+        let mut covered = HashSet::new();
+        for sentence in self.covering_sentences() {
+            if let Some(cells) = trace(table, &sentence) {
+                covered.extend(cells);
+                sentences.push(sentence);
+            }
+        }
+        for (state, tok, _) in table.cells() {
+            if covered.contains(&(state, tok)) {
+                continue;
+            }
+            let Some(path) = table.family().access_path(state) else {
+                continue;
+            };
+            let mut sentence = Vec::new();
+            let mut used = HashSet::new();
+            let path: Vec<_> = path.into_iter().map(|(tok, _)| tok).collect();
+            if self.expand_min(&path, &mut sentence, &mut used).is_none() {
+                continue;
+            }
+            match tok {
+                Token::Terminal(EOF) => {}
+                Token::Terminal(t) => sentence.push(t),
+                Token::NonTerminal(_) => {
+                    if self.expand_min(&[tok], &mut sentence, &mut used).is_none() {
+                        continue;
+                    }
+                }
+            }
+            let Some(mut completion) = self.completion(table, &sentence) else {
+                continue;
+            };
+            sentence.append(&mut completion);
+            if let Some(cells) = trace(table, &sentence)
+                && cells.contains(&(state, tok))
+            {
+                covered.extend(cells);
+                sentences.push(sentence);
+            }
+        }
+        sentences
+    }
+
+    /// 贪心地寻找能够使 `prefix` 成为完整句子的后缀, 每一步选择代价最小的终结符.
+    fn completion(&self, table: &Table<'a>, prefix: &[Terminal<'a>]) -> Option<Vec<Terminal<'a>>> {
+        let mut stack = vec![0];
+        for &t in prefix {
+            feed(table, &mut stack, t, &mut |_, _| {})?;
+        }
+        let mut completion = Vec::new();
+        for _ in 0..table.rows() * 4 + 64 {
+            if feed(table, &mut stack.clone(), EOF, &mut |_, _| {}).is_some() {
+                return Some(completion);
+            }
+            let t = table.cheapest_terminal(&stack, &self.min_lens)?;
+            feed(table, &mut stack, t, &mut |_, _| {})?;
+            completion.push(t);
+        }
+        None
+    }
+}
+
+/// 在状态栈上读入一个终结符 (进行必要的归约之后移入, 读入 [`EOF`] 时进行归约直到接受),
+/// 每使用一个 ACTION 或者 GOTO 表格子都会调用 `on_cell`. 出错时返回 [`None`].
+fn feed<'a>(
+    table: &Table<'a>,
+    stack: &mut Vec<StateId>,
+    term: Terminal<'a>,
+    on_cell: &mut impl FnMut(StateId, Token<'a>),
+) -> Option<()> {
+    loop {
+        let top = *stack.last()?;
+        let action = table.action(top, term)?;
+        on_cell(top, term.into());
+        match action {
+            ActionCell::Shift(to) => {
+                stack.push(*to);
+                return Some(());
+            }
+            ActionCell::Reduce(prod) => {
+                let prod = table.grammar().prod(*prod)?;
+                stack.truncate(stack.len().checked_sub(prod.len())?);
+                let top = *stack.last()?;
+                on_cell(top, prod.head().into());
+                stack.push(table.goto(top, prod.head())??);
+            }
+            ActionCell::Accept => return Some(()),
+            _ => return None,
+        }
+    }
+}
+
+/// 使用语法分析表分析整个句子, 返回用到的所有格子 (项集状态, 列符号), 如果句子不被接受, 那么返回 [`None`].
+fn trace<'a>(
+    table: &Table<'a>,
+    sentence: &[Terminal<'a>],
+) -> Option<HashSet<(StateId, Token<'a>)>> {
+    let mut cells = HashSet::new();
+    let mut stack = vec![0];
+    for &t in sentence.iter().chain(std::iter::once(&EOF)) {
+        feed(table, &mut stack, t, &mut |state, tok| {
+            cells.insert((state, tok));
+        })?;
+    }
+    Some(cells)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Parser, Table, generate::Generator};
+
+    #[test]
+    fn generated_sentences_are_accepted() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let mut generator = Generator::new(&grammar, 42);
+        assert_eq!(generator.min_len(grammar.symbol_start()), Some(2));
+        assert_eq!(generator.sentence(1), None);
+        for _ in 0..100 {
+            let sentence = generator.sentence(40).unwrap();
+            assert!(sentence.len() <= 40);
+            let output = parser.parse(sentence).unwrap();
+            assert!(output.accepted());
+            assert_eq!(output.report().errors(), 0);
+        }
+    }
+
+    #[test]
+    fn coverage_sentences() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let generator = Generator::new(&grammar, 0);
+
+        let sentences = generator.covering_sentences();
+        let mut used = HashSet::new();
+        for sentence in &sentences {
+            let output = parser.parse(sentence.iter().copied()).unwrap();
+            assert!(output.accepted());
+            assert_eq!(output.report().errors(), 0);
+            used.extend(output.reductions().iter().map(|r| r.prod));
+        }
+        assert_eq!(used.len(), grammar.prods().len());
+        assert!(sentences.len() < grammar.prods().len());
+
+        let sentences = generator.covering_cell_sentences(&table);
+        let mut cells = HashSet::new();
+        for sentence in &sentences {
+            cells.extend(super::trace(&table, sentence).unwrap());
+        }
+        let all: HashSet<_> = table.cells().map(|(state, tok, _)| (state, tok)).collect();
+        assert!(cells.is_subset(&all));
+        assert_eq!(cells.len(), all.len());
+    }
+
+    #[test]
+    fn same_seed_same_sentences() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> S + S | ( S ) | a", "S".into(), &bump).unwrap();
+        let mut a = Generator::new(&grammar, 7);
+        let mut b = Generator::new(&grammar, 7);
+        for _ in 0..20 {
+            assert_eq!(a.sentence(30), b.sentence(30));
+        }
+    }
+
+    #[test]
+    fn weighted_choice() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> a | b | c\n# weight: 8 S -> a\n# weight: 0 S -> c",
+            "S".into(),
+            &bump,
+        )
+        .unwrap();
+        let mut generator = Generator::new(&grammar, 7);
+        let mut counts = [0; 3];
+        for _ in 0..900 {
+            let sentence = generator.sentence(1).unwrap();
+            counts[["a", "b", "c"]
+                .iter()
+                .position(|t| *t == sentence[0].as_str())
+                .unwrap()] += 1;
+        }
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > counts[1] * 4);
+    }
+}
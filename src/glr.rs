@@ -0,0 +1,532 @@
+//! GLR 语法分析.
+//!
+//! 在含有 [`ActionCell::Conflict`] 的语法分析表上进行 Tomita 式的广义 LR 分析: 遇到冲突的格子时分叉,
+//! 各个分支的分析栈共享相同的部分, 组成图结构栈 (graph-structured stack, GSS). 分析结果是共享压缩语法分析森林
+//! (shared packed parse forest, SPPF): 推导出同一段输入的同一个非终结符只有一个节点, 它的每种推导方式是一个 [`Packed`].
+//!
+//! 同一个输入位置上的归约反复进行直到 GSS 不再变化, 所以空产生式和隐藏的左递归也能正确处理.
+//! 分析表没有冲突时森林中只有一棵树, 和 [`crate::Parser`] 的结果相同. GLR 分析不进行错误恢复.
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    ActionCell, EOF, NonTerminal, ProdId, StateId, Table, Terminal, error::Error,
+    parser::InputTerm, tree::Node,
+};
+
+/// 森林节点编号, 即 [`Forest::nodes`] 中的下标.
+pub type ForestId = usize;
+
+/// 符号节点的一种推导方式.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Packed {
+    /// 使用的产生式编号.
+    pub prod: ProdId,
+    /// 产生式右部每个符号对应的节点, 空产生式没有子节点.
+    pub children: Vec<ForestId>,
+}
+
+/// 共享压缩语法分析森林中的节点.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForestNode<'a> {
+    /// 输入中下标为 `pos` 的终结符.
+    Leaf { pos: usize, term: InputTerm<'a> },
+    /// 推导出输入中 `start..end` 的非终结符, 有多个 [`Packed`] 时这段输入是有歧义的.
+    Symbol {
+        head: NonTerminal<'a>,
+        start: usize,
+        end: usize,
+        packed: Vec<Packed>,
+    },
+}
+
+impl ForestNode<'_> {
+    /// 节点推导出的输入范围, 是输入终结符的下标.
+    #[must_use]
+    pub fn span(&self) -> (usize, usize) {
+        match *self {
+            Self::Leaf { pos, .. } => (pos, pos + 1),
+            Self::Symbol { start, end, .. } => (start, end),
+        }
+    }
+}
+
+/// GLR 分析的结果.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forest<'a> {
+    nodes: Vec<ForestNode<'a>>,
+    root: ForestId,
+}
+
+impl<'a> Forest<'a> {
+    /// 所有节点, 子节点总是在父节点之前创建, 但是循环推导 (例如 `A -> A`) 的节点可以是自己的后代.
+    #[must_use]
+    pub fn nodes(&self) -> &[ForestNode<'a>] {
+        &self.nodes
+    }
+
+    #[must_use]
+    pub fn node(&self, id: ForestId) -> Option<&ForestNode<'a>> {
+        self.nodes.get(id)
+    }
+
+    /// 开始符号对应的节点 (不包括增广产生式).
+    #[must_use]
+    pub fn root(&self) -> ForestId {
+        self.root
+    }
+
+    /// 有多种推导方式的节点.
+    pub fn ambiguities(&self) -> impl Iterator<Item = ForestId> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(id, node)| match node {
+                ForestNode::Symbol { packed, .. } if packed.len() > 1 => Some(id),
+                _ => None,
+            })
+    }
+
+    /// 输入是否有多种推导方式, 只考虑从根节点可达的节点.
+    #[must_use]
+    pub fn is_ambiguous(&self) -> bool {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            if let ForestNode::Symbol { packed, .. } = &self.nodes[id] {
+                if packed.len() > 1 {
+                    return true;
+                }
+                stack.extend(packed.iter().flat_map(|p| p.children.iter().copied()));
+            }
+        }
+        false
+    }
+
+    /// 展开森林中的语法分析树, 最多 `limit` 棵, 按照 [`Packed`] 的顺序.
+    ///
+    /// 循环推导 (节点是自己的后代) 会被跳过, 所以结果总是有限的.
+    #[must_use]
+    pub fn trees(&self, limit: usize) -> Vec<Node<'a>> {
+        self.expand(self.root, &mut Vec::new(), limit)
+    }
+
+    fn expand(&self, id: ForestId, path: &mut Vec<ForestId>, limit: usize) -> Vec<Node<'a>> {
+        let (head, packed) = match &self.nodes[id] {
+            ForestNode::Leaf { term, .. } => return vec![Node::Leaf(*term)],
+            ForestNode::Symbol { head, packed, .. } => (*head, packed),
+        };
+        if path.contains(&id) {
+            return Vec::new();
+        }
+        path.push(id);
+        let mut trees = Vec::new();
+        for p in packed {
+            // 子节点展开结果的笛卡尔积.
+            let mut rows: Vec<Vec<Node<'a>>> = vec![Vec::new()];
+            for &child in &p.children {
+                let options = self.expand(child, path, limit);
+                rows = rows
+                    .iter()
+                    .flat_map(|row| {
+                        options.iter().map(move |node| {
+                            let mut row = row.clone();
+                            row.push(node.clone());
+                            row
+                        })
+                    })
+                    .take(limit - trees.len())
+                    .collect();
+            }
+            trees.extend(rows.into_iter().map(|children| Node::Inner {
+                head,
+                prod: p.prod,
+                children: if children.is_empty() {
+                    vec![Node::Epsilon]
+                } else {
+                    children
+                },
+            }));
+            if trees.len() >= limit {
+                break;
+            }
+        }
+        path.pop();
+        trees
+    }
+}
+
+impl Display for Forest<'_> {
+    /// 每行一个从根节点可达的符号节点: `#3 S[0..2] -> r1(#0 #2) | r2(#1)`, 叶子节点写作 `#0 a[0..1]`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        let mut order = Vec::new();
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            order.push(id);
+            if let ForestNode::Symbol { packed, .. } = &self.nodes[id] {
+                stack.extend(packed.iter().flat_map(|p| p.children.iter().copied()));
+            }
+        }
+        order.sort_unstable();
+        for id in order {
+            match &self.nodes[id] {
+                ForestNode::Leaf { pos, term } => {
+                    writeln!(f, "#{id} {}[{pos}..{}]", term.text, pos + 1)?
+                }
+                ForestNode::Symbol {
+                    head,
+                    start,
+                    end,
+                    packed,
+                } => {
+                    let alternatives: Vec<_> = packed
+                        .iter()
+                        .map(|p| {
+                            let children: Vec<_> =
+                                p.children.iter().map(|c| format!("#{c}")).collect();
+                            format!("r{}({})", p.prod, children.join(" "))
+                        })
+                        .collect();
+                    writeln!(
+                        f,
+                        "#{id} {head}[{start}..{end}] -> {}",
+                        alternatives.join(" | ")
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 图结构栈中的节点.
+#[derive(Debug)]
+struct GssNode {
+    state: StateId,
+    /// 节点被创建时的输入位置.
+    pos: usize,
+    /// 指向栈中下一个节点的边, 边上是两个节点之间的符号对应的森林节点.
+    edges: Vec<(usize, ForestId)>,
+}
+
+/// 一次 GLR 分析的状态.
+struct Run<'t, 'a> {
+    table: &'t Table<'a>,
+    gss: Vec<GssNode>,
+    nodes: Vec<ForestNode<'a>>,
+    symbols: HashMap<(NonTerminal<'a>, usize, usize), ForestId>,
+}
+
+impl<'a> Run<'_, 'a> {
+    /// 从 `v` 出发长度为 `len` 的所有路径, 返回路径的终点和路径上的森林节点 (从左到右).
+    fn paths(&self, v: usize, len: usize) -> Vec<(usize, Vec<ForestId>)> {
+        let mut paths = vec![(v, Vec::new())];
+        for _ in 0..len {
+            paths = paths
+                .iter()
+                .flat_map(|(node, labels)| {
+                    self.gss[*node].edges.iter().map(move |&(to, label)| {
+                        let mut labels = labels.clone();
+                        labels.insert(0, label);
+                        (to, labels)
+                    })
+                })
+                .collect();
+        }
+        paths
+    }
+
+    /// 在 `frontier` 上使用产生式 `prod` 把路径 `children` 归约到 `u` 上, 返回 GSS 或者森林是否发生了变化.
+    fn reduce(
+        &mut self,
+        frontier: &mut HashMap<StateId, usize>,
+        u: usize,
+        prod: ProdId,
+        children: Vec<ForestId>,
+        pos: usize,
+    ) -> bool {
+        let Some(head) = self.table.grammar().prod(prod).map(|p| p.head()) else {
+            return false;
+        };
+        let Some(Some(to)) = self.table.goto(self.gss[u].state, head) else {
+            return false;
+        };
+        let mut changed = false;
+        let start = self.gss[u].pos;
+        let sym = *self.symbols.entry((head, start, pos)).or_insert_with(|| {
+            changed = true;
+            self.nodes.push(ForestNode::Symbol {
+                head,
+                start,
+                end: pos,
+                packed: Vec::new(),
+            });
+            self.nodes.len() - 1
+        });
+        if let ForestNode::Symbol { packed, .. } = &mut self.nodes[sym] {
+            let p = Packed { prod, children };
+            if !packed.contains(&p) {
+                packed.push(p);
+                changed = true;
+            }
+        }
+        let w = *frontier.entry(to).or_insert_with(|| {
+            changed = true;
+            self.gss.push(GssNode {
+                state: to,
+                pos,
+                edges: Vec::new(),
+            });
+            self.gss.len() - 1
+        });
+        if !self.gss[w].edges.contains(&(u, sym)) {
+            self.gss[w].edges.push((u, sym));
+            changed = true;
+        }
+        changed
+    }
+
+    fn expected(&self, frontier: &HashMap<StateId, usize>) -> Vec<String> {
+        let mut expected: Vec<_> = frontier
+            .keys()
+            .flat_map(|&state| self.table.actions(state).into_iter().flatten())
+            .map(|(t, _)| t.to_string())
+            .collect();
+        expected.sort_unstable();
+        expected.dedup();
+        expected
+    }
+}
+
+/// GLR 语法分析驱动程序, 可以使用含有冲突的语法分析表.
+#[derive(Debug, Clone, Copy)]
+pub struct GlrParser<'t, 'a> {
+    table: &'t Table<'a>,
+}
+
+impl<'t, 'a> GlrParser<'t, 'a> {
+    #[must_use]
+    pub fn new(table: &'t Table<'a>) -> Self {
+        Self { table }
+    }
+
+    #[must_use]
+    pub fn table(&self) -> &'t Table<'a> {
+        self.table
+    }
+
+    /// 分析输入的终结符序列, 输入不需要以 [`EOF`] 结尾, 返回所有推导方式组成的森林.
+    ///
+    /// # Errors
+    /// - [`Error::UnexpectedToken`] 所有分支都不能继续分析某个终结符, `state` 是其中一个分支的状态.
+    /// - [`Error::UnexpectedEof`] 输入结束时没有分支接受.
+    pub fn parse<I>(&self, input: I) -> Result<Forest<'a>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        let terms: Vec<InputTerm<'a>> = input.into_iter().map(Into::into).collect();
+        let mut run = Run {
+            table: self.table,
+            gss: vec![GssNode {
+                state: 0,
+                pos: 0,
+                edges: Vec::new(),
+            }],
+            nodes: Vec::new(),
+            symbols: HashMap::new(),
+        };
+        let mut frontier = HashMap::from([(0, 0)]);
+        for pos in 0..=terms.len() {
+            let term = terms.get(pos).map_or(EOF, |t| t.term);
+            let mut accepted = None;
+            // 归约直到 GSS 和森林不再变化, 新加入的边可能让之前处理过的节点产生新的路径.
+            loop {
+                let mut changed = false;
+                let mut pending: Vec<usize> = frontier.values().copied().collect();
+                pending.sort_unstable();
+                while let Some(v) = pending.pop() {
+                    let cell = self.cell(run.gss[v].state, term);
+                    for action in cell.flatten() {
+                        match *action {
+                            ActionCell::Reduce(prod) => {
+                                let len = self.table.reduce_info(prod).map_or(0, |r| r.len);
+                                for (u, children) in run.paths(v, len) {
+                                    let before = frontier.len();
+                                    changed |= run.reduce(&mut frontier, u, prod, children, pos);
+                                    if frontier.len() > before {
+                                        pending.push(run.gss.len() - 1);
+                                    }
+                                }
+                            }
+                            ActionCell::Accept => accepted = Some(v),
+                            _ => {}
+                        }
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            if term == EOF {
+                // 接受状态中开始符号的边指向栈底.
+                let root = accepted
+                    .and_then(|v| run.gss[v].edges.iter().find(|(u, _)| *u == 0))
+                    .map(|&(_, root)| root);
+                return match root {
+                    Some(root) => Ok(Forest {
+                        nodes: run.nodes,
+                        root,
+                    }),
+                    None => Err(Error::UnexpectedEof {
+                        state: frontier.keys().min().copied().unwrap_or(0),
+                        expected: run.expected(&frontier),
+                        context: Vec::new(),
+                    }),
+                };
+            }
+            let leaf = run.nodes.len();
+            run.nodes.push(ForestNode::Leaf {
+                pos,
+                term: terms[pos],
+            });
+            let mut next: HashMap<StateId, usize> = HashMap::new();
+            let mut sources: Vec<_> = frontier.values().copied().collect();
+            sources.sort_unstable();
+            for v in sources {
+                for action in self.cell(run.gss[v].state, term).flatten() {
+                    if let ActionCell::Shift(to) = *action {
+                        let w = *next.entry(to).or_insert_with(|| {
+                            run.gss.push(GssNode {
+                                state: to,
+                                pos: pos + 1,
+                                edges: Vec::new(),
+                            });
+                            run.gss.len() - 1
+                        });
+                        run.gss[w].edges.push((v, leaf));
+                    }
+                }
+            }
+            if next.is_empty() {
+                Err(Error::UnexpectedToken {
+                    state: frontier.keys().min().copied().unwrap_or(0),
+                    token: term.to_string(),
+                    expected: run.expected(&frontier),
+                    context: Vec::new(),
+                })?;
+            }
+            frontier = next;
+        }
+        unreachable!("the loop returns when it reaches EOF")
+    }
+
+    fn cell(&self, state: StateId, term: Terminal<'a>) -> &'t ActionCell {
+        self.table.action(state, term).unwrap_or(&ActionCell::Empty)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Parser, Table, Terminal,
+        error::Error,
+        glr::{ForestNode, GlrParser},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn terms<'a>(input: &'a str) -> impl Iterator<Item = Terminal<'a>> {
+        input.split_whitespace().map(Terminal::from)
+    }
+
+    #[test]
+    fn ambiguous_forest() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("e -> e + e | n", "e".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert!(table.cells().any(|(_, _, cell)| matches!(
+            cell,
+            crate::CellRef::Action(a) if a.is_conflict()
+        )));
+        let parser = GlrParser::new(&table);
+        let forest = parser.parse(terms("n + n + n")).unwrap();
+        assert!(forest.is_ambiguous());
+        assert_eq!(forest.ambiguities().count(), 1);
+        let root = forest.node(forest.root()).unwrap();
+        assert_eq!(root.span(), (0, 5));
+        let trees = forest.trees(16);
+        assert_eq!(trees.len(), 2);
+        assert_eq!(
+            trees.iter().map(|t| t.to_ascii()).collect::<Vec<_>>(),
+            [
+                "e\n+-- e\n|   +-- e\n|   |   `-- n\n|   +-- +\n|   `-- e\n|       `-- n\n+-- +\n`-- e\n    `-- n",
+                "e\n+-- e\n|   `-- n\n+-- +\n`-- e\n    +-- e\n    |   `-- n\n    +-- +\n    `-- e\n        `-- n",
+            ]
+        );
+        assert_eq!(forest.trees(1).len(), 1);
+        assert!(!parser.parse(terms("n + n")).unwrap().is_ambiguous());
+        assert!(matches!(
+            parser.parse(terms("n + + n")),
+            Err(Error::UnexpectedToken { token, .. }) if token == "+"
+        ));
+        assert!(matches!(
+            parser.parse(terms("n +")),
+            Err(Error::UnexpectedEof { .. })
+        ));
+    }
+
+    #[test]
+    fn cyclic_and_epsilon() {
+        let bump = Bump::new();
+        // S => A S => S, 无穷多种推导, 森林是有环的.
+        let grammar = Grammar::from_cfg("S -> A S | a\nA -> E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let forest = GlrParser::new(&table).parse(terms("a")).unwrap();
+        assert!(forest.is_ambiguous());
+        assert_eq!(
+            forest
+                .trees(16)
+                .iter()
+                .map(|t| t.to_ascii())
+                .collect::<Vec<_>>(),
+            ["S\n`-- a"]
+        );
+        assert!(forest.nodes().iter().any(|node| matches!(
+            node,
+            ForestNode::Symbol {
+                start: 0,
+                end: 0,
+                ..
+            }
+        )));
+
+        // 没有冲突时和 LR(1) 分析的结果相同.
+        let grammar = Grammar::from_cfg("S -> a S | b T\nT -> c | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let forest = GlrParser::new(&table).parse(terms("a a b")).unwrap();
+        let output = Parser::new(&table).parse(terms("a a b")).unwrap();
+        assert_eq!(forest.trees(16), [output.tree(&grammar).unwrap()]);
+        assert_eq!(
+            forest.to_string(),
+            "#0 a[0..1]\n#1 a[1..2]\n#2 b[2..3]\n#3 T[3..3] -> r4()\n#4 S[2..3] -> r2(#2 #3)\n#5 S[1..3] -> r1(#1 #4)\n#6 S[0..3] -> r1(#0 #5)\n"
+        );
+    }
+}
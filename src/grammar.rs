@@ -1,17 +1,29 @@
 use bumpalo::Bump;
 use std::{
-    cell::RefCell,
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::{Debug, Display},
+    sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError},
+    thread::ThreadId,
 };
 
 use crate::{
     NonTerminal, Terminal, Token,
     error::{Error, ParseProductionError},
+    precedence::{Assoc, OperatorLevel},
     token::{EOF, EPSILON},
 };
 
+/// 产生式编号, 即 [`Grammar::prods`] 中的下标, 增广之后增广产生式的编号为 0.
+pub type ProdId = usize;
+
+/// 版本号在元数据中的键, 见: [`Grammar::version`].
+pub const VERSION_KEY: &str = "version";
+
+/// 产生式的默认权重, 见: [`Grammar::weight`].
+pub const DEFAULT_WEIGHT: u32 = 1;
+
 #[derive(Clone, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Production<'a> {
     // 产生式 `->` 左侧内容.
     head: NonTerminal<'a>,
@@ -35,14 +47,20 @@ impl Debug for Production<'_> {
     }
 }
 
+/// 使用 `{:#}` 时右部的终结符 (除了 [`EPSILON`]) 加上单引号, 例如 `compoundstmt -> '{' stmts '}'`,
+/// 用来区分同名的终结符和非终结符.
 impl Display for Production<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let quote = f.alternate();
         f.pad(&format!(
             "{} -> {}",
             self.head,
             self.tail
                 .iter()
-                .map(|t| format!("{} ", t))
+                .map(|t| match t {
+                    Token::Terminal(term) if quote && *term != EPSILON => format!("'{term}' "),
+                    _ => format!("{} ", t),
+                })
                 .collect::<String>()
                 .trim_end()
         ))
@@ -91,24 +109,194 @@ impl<'a> Production<'a> {
     }
 }
 
+/// 检查 CFG 文法中的符号是否和内置的 [`EPSILON`] / [`EOF`] 冲突.
+///
+/// 非终结符不能命名为 [`EPSILON`] 或者 [`EOF`], 产生式尾部也不能出现 [`EOF`],
+/// 否则计算出的 FIRST 集和 FOLLOW 集会混入这些内置终结符.
+fn reserved(line: usize, symbol: &str) -> Result<(), Error> {
+    let builtin = match symbol {
+        s if s == EPSILON.as_str() => "epsilon",
+        s if s == EOF.as_str() => "end of input",
+        _ => return Ok(()),
+    };
+    Err(Error::parse_production_error(
+        line,
+        ParseProductionError::ReservedSymbol {
+            symbol: symbol.to_string(),
+            builtin: builtin.to_string(),
+        },
+    ))
+}
+
+/// 文法文件中的注释行: 去掉开头的空白之后以 `#` 开头.
+pub(crate) fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with('#')
+}
+
+/// 文法文件中的优先级声明行: 去掉开头的空白之后以 `%` 开头, 见: [`Grammar::precedence`].
+pub(crate) fn is_declaration(line: &str) -> bool {
+    line.trim_start().starts_with('%')
+}
+
+/// 缓存的命中统计, 见: [`Grammar::suffix_first_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 直接使用缓存结果的次数.
+    pub hits: usize,
+    /// 需要计算并加入缓存的次数.
+    pub misses: usize,
+}
+
+/// 符号到产生式编号的索引, 见: [`Grammar::producers_of`], [`Grammar::consumers_of`].
 #[derive(Debug, Clone, Default)]
-enum FirstSet<'a> {
-    Presense(HashSet<Terminal<'a>>),
-    #[default]
-    Calculating,
-    NotPresense,
+struct SymbolIndex<'a> {
+    /// 非终结符到以它为头部的产生式.
+    producers: HashMap<NonTerminal<'a>, Vec<ProdId>>,
+    /// 符号到尾部中出现了它的产生式, 每个产生式只出现一次.
+    consumers: HashMap<Token<'a>, Vec<ProdId>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Grammar<'a> {
+impl<'a> SymbolIndex<'a> {
+    fn new(prods: &[&'a Production<'a>]) -> Self {
+        let mut index = Self::default();
+        for (id, prod) in prods.iter().enumerate() {
+            index.producers.entry(prod.head()).or_default().push(id);
+            for &tok in prod.tail() {
+                let consumers = index.consumers.entry(tok).or_default();
+                if consumers.last() != Some(&id) {
+                    consumers.push(id);
+                }
+            }
+        }
+        index
+    }
+}
+
+/// (产生式, 后缀起始下标) 到后缀 first 集的缓存.
+type SuffixFirsts<'a> = HashMap<(&'a Production<'a>, usize), Arc<HashSet<Terminal<'a>>>>;
+
+/// 非终结符到缓存的 follow 集, [`None`] 表示需要重新计算.
+type FollowSets<'a> = HashMap<NonTerminal<'a>, Option<BTreeSet<Terminal<'a>>>>;
+
+/// 文法引用的 arena 和创建文法的线程.
+///
+/// [`Bump`] 不是 `Sync`, 只允许在创建文法的线程上通过 [`Arena::get`] 分配, 其他线程只能查询文法.
+#[derive(Clone, Copy)]
+struct Arena<'a> {
     bump: &'a Bump,
+    owner: ThreadId,
+}
+
+// SAFETY: `bump` 只在 `owner` 线程上被访问 (见: [`Arena::get`]), 其他线程持有 `&Arena` 时不会接触 `Bump`.
+unsafe impl Sync for Arena<'_> {}
+
+impl<'a> Arena<'a> {
+    fn new(bump: &'a Bump) -> Self {
+        Self {
+            bump,
+            owner: std::thread::current().id(),
+        }
+    }
+
+    /// # Panics
+    /// 不在创建文法的线程上调用.
+    fn get(&self) -> &'a Bump {
+        assert_eq!(
+            std::thread::current().id(),
+            self.owner,
+            "the grammar arena can only be used on the thread that created the grammar"
+        );
+        self.bump
+    }
+}
+
+/// 不输出 `bump`, 读取它的状态需要在创建文法的线程上.
+impl Debug for Arena<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Arena")
+            .field("owner", &self.owner)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 锁住缓存, 缓存中的内容总是完整的, 所以忽略其他线程 panic 留下的中毒状态.
+fn lock<T>(cache: &Mutex<T>) -> MutexGuard<'_, T> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 文法, 产生式和符号分配在创建时给定的 arena 中.
+///
+/// 缓存 (first 集, 后缀 first 集, follow 集和前瞻符集合池) 使用 [`OnceLock`] 和 [`Mutex`], arena 只在创建文法的线程上使用,
+/// 所以 [`Grammar`] 是 `Sync` 的: 可以在多个线程中同时查询 first 集和 follow 集, 以及构建项集族
+/// ([`crate::Family`] 持有自己的项集, 不使用 arena). 只有向 arena 中加入符号的操作 ([`Grammar::augmented`],
+/// [`Grammar::add_production`], 以及 [`crate::lexer::TermReader`] 和 [`crate::replay::Recording::parse`]
+/// 遇到文法中没有的词素时) 需要在创建文法的线程上进行, 否则 panic.
+#[derive(Debug)]
+pub struct Grammar<'a> {
+    arena: Arena<'a>,
     prods: Vec<&'a Production<'a>>,
     prod_indexes: HashMap<&'a Production<'a>, usize>,
+    /// 产生式编号变化时 (例如 [`Grammar::augmented`]) 需要重建.
+    symbol_index: SymbolIndex<'a>,
     tokens: BTreeSet<Token<'a>>,
     start: NonTerminal<'a>,
-    /// 缓存的各个非终结符的 first 集,
-    /// 在 [`Grammar`] 创建的时候为每个 [`NonTerminal`] 初始化为 [`FirstSet::None`],
-    first_sets: HashMap<NonTerminal<'a>, RefCell<FirstSet<'a>>>,
+    /// [`Grammar::augmented`] 在最前面插入的产生式数量, 声明顺序编号加上它就是产生式编号.
+    augment_offset: usize,
+    /// 缓存的各个非终结符的 first 集, 每个 [`NonTerminal`] 一个 [`OnceLock`], 在 [`Grammar`] 创建的时候为空.
+    ///
+    /// 计算时没有 "正在计算" 的中间状态 (见: [`Grammar::calc_first`]), 多个线程同时查询时最多重复计算, 写入的结果相同.
+    first_sets: HashMap<NonTerminal<'a>, OnceLock<HashSet<Terminal<'a>>>>,
+    /// 项的前瞻符集合池, 内容相同的集合只保存一份, 见: [`Grammar::intern_look_aheads`].
+    look_aheads: Mutex<HashSet<Arc<BTreeSet<Terminal<'a>>>>>,
+    /// 缓存的产生式尾部后缀的 first 集, 键为 (产生式, 后缀起始下标), 见: [`Grammar::suffix_first`].
+    suffix_firsts: Mutex<SuffixFirsts<'a>>,
+    suffix_first_stats: Mutex<CacheStats>,
+    /// 缓存的各个非终结符的 follow 集, 见: [`Grammar::follow_sets`].
+    follows: Mutex<FollowSets<'a>>,
+    /// 每个产生式的权重, 和 `prods` 一一对应, 见: [`Grammar::weight`].
+    weights: Vec<u32>,
+    /// 文法的元数据, 见: [`Grammar::metadata`].
+    metadata: BTreeMap<String, String>,
+    /// 运算符的优先级声明, 从低到高排列, 见: [`Grammar::precedence`].
+    precedence: Vec<OperatorLevel<'a>>,
+}
+
+/// 序列化为开始符号, 按照编号排列的产生式, 权重, 元数据和优先级声明, 不包括缓存.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Grammar<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("Grammar", 5)?;
+        s.serialize_field("start", &self.start)?;
+        s.serialize_field("prods", &self.prods)?;
+        s.serialize_field("weights", &self.weights)?;
+        s.serialize_field("metadata", &self.metadata)?;
+        s.serialize_field("precedence", &self.precedence)?;
+        s.end()
+    }
+}
+
+/// 复制文法和已有的缓存, 复制出的文法和原来的文法使用同一个 arena.
+impl Clone for Grammar<'_> {
+    fn clone(&self) -> Self {
+        Self {
+            arena: self.arena,
+            prods: self.prods.clone(),
+            prod_indexes: self.prod_indexes.clone(),
+            symbol_index: self.symbol_index.clone(),
+            tokens: self.tokens.clone(),
+            start: self.start,
+            augment_offset: self.augment_offset,
+            first_sets: self.first_sets.clone(),
+            look_aheads: Mutex::new(lock(&self.look_aheads).clone()),
+            suffix_firsts: Mutex::new(lock(&self.suffix_firsts).clone()),
+            suffix_first_stats: Mutex::new(*lock(&self.suffix_first_stats)),
+            follows: Mutex::new(lock(&self.follows).clone()),
+            weights: self.weights.clone(),
+            metadata: self.metadata.clone(),
+            precedence: self.precedence.clone(),
+        }
+    }
 }
 
 impl PartialEq for Grammar<'_> {
@@ -119,10 +307,43 @@ impl PartialEq for Grammar<'_> {
 
 impl Eq for Grammar<'_> {}
 
+/// 输出可以被 [`Grammar::from_cfg`] 重新解析的文法: 第一行为 `# start: S` 注释,
+/// 之后每行一个产生式, 按照产生式编号排列.
+///
+/// 使用 `{:#}` 时每行为带有编号的产生式, 例如 `   1 S -> a S`, 仅用于阅读.
+impl Display for Grammar<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            for (idx, prod) in self.prods.iter().enumerate() {
+                writeln!(f, "{idx:>4} {prod}")?;
+            }
+        } else {
+            writeln!(f, "# start: {}", self.start)?;
+            for (key, value) in &self.metadata {
+                if key == VERSION_KEY {
+                    writeln!(f, "# version: {value}")?;
+                } else {
+                    writeln!(f, "# meta: {key} = {value}")?;
+                }
+            }
+            write!(f, "{}", crate::precedence::declarations(&self.precedence))?;
+            for prod in &self.prods {
+                writeln!(f, "{prod}")?;
+            }
+            for (prod, weight) in self.prods.iter().zip(&self.weights) {
+                if *weight != DEFAULT_WEIGHT {
+                    writeln!(f, "# weight: {weight} {prod}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'a> Grammar<'a> {
     #[must_use]
-    pub(crate) fn bump(&self) -> &Bump {
-        self.bump
+    pub(crate) fn bump(&self) -> &'a Bump {
+        self.arena.get()
     }
 
     /// 按产生式编号遍历产生式.
@@ -131,11 +352,88 @@ impl<'a> Grammar<'a> {
     }
 
     /// 获取产生式的编号, 如果产生式在文法中不存在, 那么返回 [`None`].
+    ///
+    /// 重复声明的产生式返回第一次声明的编号.
     #[must_use]
     pub fn index_of_prod(&self, prod: &Production<'a>) -> Option<usize> {
         self.prod_indexes.get(prod).copied()
     }
 
+    /// 根据产生式编号获取产生式, 编号超出范围时返回 [`None`].
+    #[must_use]
+    pub fn prod(&self, id: ProdId) -> Option<&'a Production<'a>> {
+        self.prods.get(id).copied()
+    }
+
+    /// 产生式在文法文件中的声明顺序编号, 不受 [`Grammar::augmented`] 影响.
+    ///
+    /// 增广产生式或者编号超出范围时返回 [`None`].
+    #[must_use]
+    pub fn declared_index(&self, id: ProdId) -> Option<usize> {
+        id.checked_sub(self.augment_offset)
+            .filter(|_| id < self.prods.len())
+    }
+
+    /// 声明顺序编号对应的产生式编号, 是 [`Grammar::declared_index`] 的逆运算.
+    ///
+    /// 下游以声明顺序编号作为键的表 (例如语义动作) 可以通过它找到分析表中使用的产生式编号.
+    #[must_use]
+    pub fn prod_id(&self, declared: usize) -> Option<ProdId> {
+        let id = declared + self.augment_offset;
+        (id < self.prods.len()).then_some(id)
+    }
+
+    /// 增广产生式 (`Sprime -> S`) 的编号, 文法没有增广时返回 [`None`].
+    ///
+    /// 在 [`EOF`] 上使用增广产生式归约就是接受, 多次增广时为最后一次加入的增广产生式.
+    #[must_use]
+    pub fn augmented_production(&self) -> Option<ProdId> {
+        (self.augment_offset > 0).then_some(0)
+    }
+
+    /// 增广之前的开始符号, 也就是文法文件中的开始符号, 文法没有增广时同 [`Grammar::symbol_start`].
+    #[must_use]
+    pub fn original_start(&self) -> NonTerminal<'a> {
+        let first = self
+            .augment_offset
+            .checked_sub(1)
+            .and_then(|p| self.prods[p].tail().first());
+        match first {
+            Some(Token::NonTerminal(nt)) => *nt,
+            _ => self.start,
+        }
+    }
+
+    /// 符号在产生式尾部出现的所有位置 (产生式编号, 在尾部中的下标), 按照产生式编号和下标排序.
+    pub fn usages(&self, token: Token<'a>) -> impl Iterator<Item = (ProdId, usize)> + '_ {
+        self.prods.iter().enumerate().flat_map(move |(id, prod)| {
+            prod.tail()
+                .iter()
+                .enumerate()
+                .filter(move |&(_, &t)| t == token)
+                .map(move |(pos, _)| (id, pos))
+        })
+    }
+
+    /// 以 `nt` 为头部的产生式 (可以推导出 `nt` 的产生式) 的编号, 按照编号排序.
+    #[must_use]
+    pub fn producers_of(&self, nt: NonTerminal<'a>) -> &[ProdId] {
+        self.symbol_index
+            .producers
+            .get(&nt)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// 尾部中出现了 `token` 的产生式的编号, 按照编号排序, 多次出现的产生式只出现一次.
+    /// 需要出现的位置时见: [`Grammar::usages`].
+    #[must_use]
+    pub fn consumers_of(&self, token: Token<'a>) -> &[ProdId] {
+        self.symbol_index
+            .consumers
+            .get(&token)
+            .map_or(&[], Vec::as_slice)
+    }
+
     #[must_use]
     pub fn symbol_start(&self) -> NonTerminal<'a> {
         self.start
@@ -146,45 +444,357 @@ impl<'a> Grammar<'a> {
         &self.tokens
     }
 
+    /// 把文法复制到另一个 arena 中, 符号和产生式都重新分配, 复制出的文法不再引用原来的 arena.
+    ///
+    /// [`crate::Family::from_grammar`] 会把项集分配在文法的 arena 中, 同一个 arena 反复构建项集族会一直增长.
+    /// 长时间运行的服务可以把文法复制到临时的 arena 中再构建项集族和分析表, 用完之后 [`Bump::reset`] 临时的 arena.
+    ///
+    /// 缓存的 first 集和 follow 集不会被复制.
+    #[must_use]
+    pub fn clone_into<'b>(&self, bump: &'b Bump) -> Grammar<'b> {
+        let symbols: HashMap<&str, &'b str> = self
+            .tokens
+            .iter()
+            .map(|t| (t.as_str(), &*bump.alloc_str(t.as_str())))
+            .collect();
+        let non_term = |nt: NonTerminal<'_>| NonTerminal::from(symbols[nt.as_str()]);
+        let token = |tok: &Token<'_>| match tok {
+            Token::Terminal(t) => Token::from(Terminal::from(symbols[t.as_str()])),
+            Token::NonTerminal(nt) => Token::from(non_term(*nt)),
+        };
+        let prods: Vec<&'b Production<'b>> = self
+            .prods
+            .iter()
+            .map(|p| {
+                let tail = p.tail().iter().map(token).collect();
+                &*bump.alloc(Production::new(non_term(p.head()), tail))
+            })
+            .collect();
+        Grammar {
+            arena: Arena::new(bump),
+            prod_indexes: self.prod_indexes.values().map(|&i| (prods[i], i)).collect(),
+            symbol_index: SymbolIndex::new(&prods),
+            prods,
+            tokens: self.tokens.iter().map(token).collect(),
+            start: non_term(self.start),
+            augment_offset: self.augment_offset,
+            first_sets: self
+                .first_sets
+                .keys()
+                .map(|&nt| (non_term(nt), OnceLock::new()))
+                .collect(),
+            look_aheads: Mutex::default(),
+            suffix_firsts: Mutex::default(),
+            suffix_first_stats: Mutex::default(),
+            follows: Mutex::new(
+                self.first_sets
+                    .keys()
+                    .map(|&nt| (non_term(nt), None))
+                    .collect(),
+            ),
+            weights: self.weights.clone(),
+            metadata: self.metadata.clone(),
+            precedence: self
+                .precedence
+                .iter()
+                .map(|level| OperatorLevel {
+                    operators: level
+                        .operators
+                        .iter()
+                        .map(|t| Terminal::from(symbols[t.as_str()]))
+                        .collect(),
+                    ..*level
+                })
+                .collect(),
+        }
+    }
+
+    /// 获取和 `set` 内容相同的共享前瞻符集合, 池中没有时加入.
+    ///
+    /// 大的项集族中很多项的前瞻符集合相同 (例如 `{eof}`), 共享之后每个项只保存一个指针.
+    pub(crate) fn intern_look_aheads(
+        &self,
+        set: BTreeSet<Terminal<'a>>,
+    ) -> Arc<BTreeSet<Terminal<'a>>> {
+        let mut pool = lock(&self.look_aheads);
+        if let Some(shared) = pool.get(&set) {
+            return shared.clone();
+        }
+        let shared = Arc::new(set);
+        pool.insert(shared.clone());
+        shared
+    }
+
+    /// 前瞻符集合池中不同集合的数量.
+    #[must_use]
+    pub fn look_ahead_sets(&self) -> usize {
+        lock(&self.look_aheads).len()
+    }
+
+    /// 产生式尾部 (不包括 [`EPSILON`]) 从下标 `start` 开始的后缀的 first 集, 见: [`Grammar::first_set`].
+    ///
+    /// 构造项集族时同一个带点产生式会在很多项集的闭包中出现, 结果按照 (产生式, `start`) 缓存,
+    /// 不再重复计算.
+    ///
+    /// # Errors
+    /// 见: [`Grammar::first_set`].
+    pub(crate) fn suffix_first(
+        &self,
+        prod: &'a Production<'a>,
+        start: usize,
+    ) -> Result<Arc<HashSet<Terminal<'a>>>, Error> {
+        let cached = lock(&self.suffix_firsts).get(&(prod, start)).cloned();
+        if let Some(first) = cached {
+            lock(&self.suffix_first_stats).hits += 1;
+            return Ok(first);
+        }
+        // 计算时不持有锁, 多个线程同时计算时结果相同.
+        let first = Arc::new(self.first_set(prod.tail_without_eps().skip(start).copied())?);
+        lock(&self.suffix_firsts).insert((prod, start), first.clone());
+        lock(&self.suffix_first_stats).misses += 1;
+        Ok(first)
+    }
+
+    /// 产生式后缀的 FIRST 集缓存的命中统计, 可以用来观察闭包计算中重复的工作量.
+    #[must_use]
+    pub fn suffix_first_stats(&self) -> CacheStats {
+        *lock(&self.suffix_first_stats)
+    }
+
+    /// 增广文法: 在编号 0 处插入增广产生式 `Sprime -> S`, 其他产生式的编号加一,
+    /// 声明顺序编号 (见: [`Grammar::declared_index`]) 保持不变.
+    ///
+    /// 开始符号出现在产生式尾部 (例如 `S -> ( S ) | a`) 时不需要手动增广, 新的开始符号只出现在增广产生式中,
+    /// 接受状态唯一. 项集族中 I_0 是 `Sprime -> ⋅ S` 的闭包, 在 I_0 上读入 `S` 到达的状态含有 `Sprime -> S ⋅`,
+    /// 在 [`EOF`] 上接受. `Sprime` 已经是文法中的符号时继续追加 `prime`, 直到不和已有的符号重名.
+    ///
+    /// # Panics
+    /// 不在创建文法的线程上调用, 见: [`Grammar`].
     #[must_use]
     pub fn augmented(mut self) -> Self {
-        let new_start = self.bump.alloc(format!("{}prime", self.start.as_str()));
+        let mut name = format!("{}prime", self.start.as_str());
+        while self.get_token(&name).is_some() {
+            name += "prime";
+        }
+        let new_start = self.bump().alloc(name);
         let augmented_start = NonTerminal::from(new_start.as_str());
         self.prod_indexes.values_mut().for_each(|x| *x += 1);
         let augmented_prod = &*self
-            .bump
+            .bump()
             .alloc(Production::new(augmented_start, vec![self.start.into()]));
         self.prods.insert(0, augmented_prod);
+        self.weights.insert(0, DEFAULT_WEIGHT);
         self.prod_indexes.insert(augmented_prod, 0);
         self.tokens.insert(augmented_start.into());
-        self.first_sets
-            .insert(augmented_start, RefCell::new(FirstSet::NotPresense));
+        self.first_sets.insert(augmented_start, OnceLock::new());
+        // 开始符号变了, 所有的 follow 集都需要重新计算.
+        let follows = self.first_sets.keys().map(|&nt| (nt, None)).collect();
         Self {
-            bump: self.bump,
+            arena: self.arena,
+            symbol_index: SymbolIndex::new(&self.prods),
             prods: self.prods,
             prod_indexes: self.prod_indexes,
             tokens: self.tokens,
             start: augmented_start,
+            augment_offset: self.augment_offset + 1,
             first_sets: self.first_sets,
+            look_aheads: self.look_aheads,
+            suffix_firsts: self.suffix_firsts,
+            suffix_first_stats: self.suffix_first_stats,
+            follows: Mutex::new(follows),
+            weights: self.weights,
+            metadata: self.metadata,
+            precedence: self.precedence,
+        }
+    }
+
+    /// 在最后加入一个产生式, 返回它的产生式编号, 权重为默认值.
+    ///
+    /// 产生式中的符号会被复制到文法的 arena 中, 可以直接使用 [`Grammar::parse_production`] 的结果,
+    /// 文法中没有的符号按照 `prod` 中的类型加入. 只有受到影响的 first 集和 follow 集缓存会失效, 见: [`Grammar::invalidate`].
+    ///
+    /// # Errors
+    /// - [`Error::ParseProductionError`] 中的 [`ParseProductionError::TokenTypeMisMatch`] 符号在文法中是另一种类型.
+    /// - [`Error::ParseProductionError`] 中的 [`ParseProductionError::ReservedSymbol`] 见: [`Grammar::from_cfg`].
+    ///
+    /// # Panics
+    /// 不在创建文法的线程上调用, 见: [`Grammar`].
+    pub fn add_production(&mut self, prod: &Production<'_>) -> Result<ProdId, Error> {
+        let mismatch = |tok: &str| {
+            Error::parse_production_error(
+                0,
+                ParseProductionError::TokenTypeMisMatch(tok.to_string()),
+            )
+        };
+        reserved(0, prod.head().as_str())?;
+        let head = match self.get_token(prod.head().as_str()) {
+            Some(Token::NonTerminal(nt)) => nt,
+            Some(Token::Terminal(_)) => Err(mismatch(prod.head().as_str()))?,
+            None => NonTerminal::from(&*self.bump().alloc_str(prod.head().as_str())),
+        };
+        let mut tail = Vec::new();
+        for &tok in prod.tail() {
+            if tok.as_str() == EOF.as_str() {
+                reserved(0, tok.as_str())?;
+            }
+            tail.push(match (self.get_token(tok.as_str()), tok) {
+                (Some(found), _) if found.is_term() == tok.is_term() => found,
+                (Some(_), _) => Err(mismatch(tok.as_str()))?,
+                (None, Token::Terminal(t)) => {
+                    Terminal::from(&*self.bump().alloc_str(t.as_str())).into()
+                }
+                (None, Token::NonTerminal(nt)) => {
+                    NonTerminal::from(&*self.bump().alloc_str(nt.as_str())).into()
+                }
+            });
+        }
+        let prod = &*self.bump().alloc(Production::new(head, tail));
+        for &tok in std::iter::once(&head.into()).chain(prod.tail()) {
+            if self.tokens.insert(tok)
+                && let Token::NonTerminal(nt) = tok
+            {
+                self.first_sets.insert(nt, OnceLock::new());
+                self.follows
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(nt, None);
+            }
+        }
+        let id = self.prods.len();
+        self.prod_indexes.entry(prod).or_insert(id);
+        self.prods.push(prod);
+        self.weights.push(DEFAULT_WEIGHT);
+        self.symbol_index = SymbolIndex::new(&self.prods);
+        self.invalidate(prod);
+        Ok(id)
+    }
+
+    /// 删除一个产生式, 之后的产生式编号减一, 返回被删除的产生式.
+    ///
+    /// 不再出现在任何产生式中的符号 (开始符号除外) 会从文法中删除. 只有受到影响的 first 集和 follow 集缓存会失效,
+    /// 见: [`Grammar::invalidate`].
+    ///
+    /// # Errors
+    /// - [`Error::ProductionNotFound`] 产生式编号超出范围, 或者是增广产生式.
+    pub fn remove_production(&mut self, id: ProdId) -> Result<&'a Production<'a>, Error> {
+        if self.declared_index(id).is_none() {
+            Err(Error::ProductionNotFound(format!("#{id}")))?;
+        }
+        let prod = self.prods.remove(id);
+        self.weights.remove(id);
+        self.prod_indexes.clear();
+        for (i, &p) in self.prods.iter().enumerate() {
+            self.prod_indexes.entry(p).or_insert(i);
+        }
+        self.symbol_index = SymbolIndex::new(&self.prods);
+        for &tok in std::iter::once(&prod.head().into()).chain(prod.tail()) {
+            let used = tok == self.start.into()
+                || tok == EPSILON.into()
+                || self
+                    .prods
+                    .iter()
+                    .any(|p| Token::from(p.head()) == tok || p.tail().contains(&tok));
+            if !used
+                && self.tokens.remove(&tok)
+                && let Token::NonTerminal(nt) = tok
+            {
+                self.first_sets.remove(&nt);
+                self.follows
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&nt);
+            }
+        }
+        self.invalidate(prod);
+        Ok(prod)
+    }
+
+    /// `prod` 被加入或者删除之后, 使受到影响的 first 集和 follow 集缓存失效, 下次使用时重新计算.
+    ///
+    /// first 集受到影响的是 `prod` 的头部, 以及尾部中出现了受影响的非终结符的产生式的头部 (传递闭包).
+    /// follow 集受到影响的是 `prod` 尾部中的非终结符, 在尾部中出现在 first 集受影响的非终结符之前的非终结符,
+    /// 以及 follow 集受影响的非终结符的产生式尾部中的非终结符 (传递闭包). 其他缓存保持不变.
+    fn invalidate(&mut self, prod: &Production<'a>) {
+        let non_terms = |prod: &Production<'a>| -> Vec<NonTerminal<'a>> {
+            prod.tail()
+                .iter()
+                .filter_map(|tok| tok.as_non_term().copied())
+                .collect()
+        };
+        let mut firsts = HashSet::from([prod.head()]);
+        let mut pending = vec![prod.head()];
+        while let Some(nt) = pending.pop() {
+            for &id in self.consumers_of(nt.into()) {
+                let head = self.prods[id].head();
+                if firsts.insert(head) {
+                    pending.push(head);
+                }
+            }
+        }
+        let mut follows: HashSet<_> = non_terms(prod).into_iter().collect();
+        for &nt in &firsts {
+            for (id, pos) in self.usages(nt.into()) {
+                let before = &self.prods[id].tail()[..pos];
+                follows.extend(before.iter().filter_map(|tok| tok.as_non_term().copied()));
+            }
+        }
+        let mut pending: Vec<_> = follows.iter().copied().collect();
+        while let Some(nt) = pending.pop() {
+            for &id in self.producers_of(nt) {
+                for tail_nt in non_terms(self.prods[id]) {
+                    if follows.insert(tail_nt) {
+                        pending.push(tail_nt);
+                    }
+                }
+            }
+        }
+
+        for nt in &firsts {
+            if let Some(first) = self.first_sets.get_mut(nt) {
+                first.take();
+            }
+        }
+        self.suffix_firsts
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(p, start), _| {
+                p.tail_without_eps()
+                    .skip(*start)
+                    .all(|tok| tok.as_non_term().is_none_or(|nt| !firsts.contains(nt)))
+            });
+        let cache = self
+            .follows
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for nt in follows {
+            if let Some(follow) = cache.get_mut(&nt) {
+                *follow = None;
+            }
         }
     }
 
+    /// 解析 CFG 文法, 每行为 `A -> α | β ...`, 以 `#` 开头的行为注释.
+    ///
+    /// 产生式按照声明顺序从 0 开始编号: 按行的顺序, 同一行中按照 `|` 分隔的顺序.
+    /// # Errors
+    /// - [`Error::ParseProductionError`] 某行没有 `->`, 或者找不到开始符号.
+    /// - [`Error::ParseProductionError`] 中的 [`ParseProductionError::ReservedSymbol`]
+    ///   [`EPSILON`] 被用作非终结符, 或者 [`EOF`] 出现在产生式中.
     pub fn from_cfg(s: &'a str, start: NonTerminal<'a>, bump: &'a Bump) -> Result<Self, Error> {
         let mut tokens: BTreeSet<Token<'_>> = [EPSILON.into(), EOF.into()].into();
         let mut non_terminals = HashSet::new();
-        let mut splitted: Vec<(&str, &str)> = Vec::new();
+        let mut splitted: Vec<(usize, &str, &str)> = Vec::new();
         // 找出所有的非终结符.
-        for (line_num, line) in s
-            .lines()
-            .enumerate()
-            .filter(|(_, s)| !s.is_empty() && s.chars().any(|c| !c.is_whitespace()))
-        {
+        for (line_num, line) in s.lines().enumerate().filter(|(_, s)| {
+            s.chars().any(|c| !c.is_whitespace()) && !is_comment(s) && !is_declaration(s)
+        }) {
             let parts = line.split_once("->").ok_or(Error::parse_production_error(
                 line_num,
                 ParseProductionError::NoArrow,
             ))?;
             let head_ident = parts.0.trim();
-            splitted.push((head_ident, parts.1));
+            reserved(line_num, head_ident)?;
+            splitted.push((line_num, head_ident, parts.1));
             non_terminals.insert(head_ident);
             tokens.insert(NonTerminal::from(head_ident).into());
         }
@@ -198,24 +808,29 @@ impl<'a> Grammar<'a> {
         // 解析所有产生式.
         let mut prods = Vec::new();
         let mut prod_indexes = HashMap::new();
-        for (head_ident, tails) in splitted {
+        for (line_num, head_ident, tails) in splitted {
             for tail_s in tails.split('|') {
                 let tail = tail_s
                     .split_ascii_whitespace()
                     .map(|s| {
                         let s = s.trim();
-                        if non_terminals.contains(&s) {
+                        if s == EOF.as_str() {
+                            reserved(line_num, s)?;
+                        }
+                        Ok(if non_terminals.contains(&s) {
                             Token::from(NonTerminal::from(s))
                         } else {
                             Token::from(Terminal::from(s))
-                        }
+                        })
                     })
                     .inspect(|tok| {
-                        tokens.insert(*tok);
+                        if let Ok(tok) = tok {
+                            tokens.insert(*tok);
+                        }
                     })
-                    .collect();
+                    .collect::<Result<_, Error>>()?;
                 let prod = &*bump.alloc(Production::new(NonTerminal::from(head_ident), tail));
-                prod_indexes.insert(prod, prods.len());
+                prod_indexes.entry(prod).or_insert(prods.len());
                 prods.push(prod);
             }
         }
@@ -226,139 +841,447 @@ impl<'a> Grammar<'a> {
                 Token::NonTerminal(nt) => Some(nt),
                 _ => None,
             })
-            .map(|t| (t, RefCell::new(FirstSet::NotPresense)))
-            .collect();
-        Ok(Grammar {
+            .map(|t| (t, OnceLock::new()))
+            .collect::<HashMap<_, _>>();
+        let follows = first_sets.keys().map(|&nt| (nt, None)).collect();
+        let mut grammar = Grammar {
+            weights: vec![DEFAULT_WEIGHT; prods.len()],
+            metadata: BTreeMap::new(),
+            precedence: Vec::new(),
             prod_indexes,
+            symbol_index: SymbolIndex::new(&prods),
             prods,
             start,
-            bump,
+            augment_offset: 0,
+            arena: Arena::new(bump),
             tokens,
             first_sets,
+            look_aheads: Mutex::default(),
+            suffix_firsts: Mutex::default(),
+            suffix_first_stats: Mutex::default(),
+            follows: Mutex::new(follows),
+        };
+        grammar.parse_weights(s)?;
+        grammar.parse_metadata(s)?;
+        grammar.parse_precedence(s)?;
+        Ok(grammar)
+    }
+
+    /// 解析 `%left + -`, `%right ^`, `%nonassoc <` 声明, 先声明的优先级较低.
+    fn parse_precedence(&mut self, s: &str) -> Result<(), Error> {
+        let mut levels = Vec::new();
+        for (line_num, line) in s.lines().enumerate().filter(|(_, l)| is_declaration(l)) {
+            let invalid = || {
+                Error::parse_production_error(
+                    line_num,
+                    ParseProductionError::InvalidPrecedence(line.trim().to_string()),
+                )
+            };
+            let mut words = line.split_whitespace();
+            let assoc = match words.next() {
+                Some("%left") => Assoc::Left,
+                Some("%right") => Assoc::Right,
+                Some("%nonassoc") => Assoc::NonAssoc,
+                _ => Err(invalid())?,
+            };
+            let operators = words
+                .map(|op| match self.get_token(op) {
+                    Some(Token::Terminal(t)) => Ok(t),
+                    _ => Err(invalid()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if operators.is_empty() {
+                Err(invalid())?;
+            }
+            levels.push(OperatorLevel {
+                level: levels.len() + 1,
+                assoc,
+                operators,
+                guessed: false,
+            });
+        }
+        self.precedence = levels;
+        Ok(())
+    }
+
+    /// 解析 `# version: 1.2` 和 `# meta: key = value` 注释.
+    fn parse_metadata(&mut self, s: &str) -> Result<(), Error> {
+        for (line_num, line) in s.lines().enumerate() {
+            let Some(directive) = line.trim().strip_prefix('#').map(str::trim) else {
+                continue;
+            };
+            if let Some(version) = directive.strip_prefix("version:") {
+                self.set_metadata(VERSION_KEY, version.trim());
+            } else if let Some(entry) = directive.strip_prefix("meta:") {
+                let (key, value) = entry
+                    .split_once('=')
+                    .filter(|(key, _)| !key.trim().is_empty())
+                    .ok_or_else(|| {
+                        Error::parse_production_error(
+                            line_num,
+                            ParseProductionError::InvalidMetadata(entry.trim().to_string()),
+                        )
+                    })?;
+                self.set_metadata(key.trim(), value.trim());
+            }
+        }
+        Ok(())
+    }
+
+    /// 解析 `# weight: 5 A -> α | β` 注释, 把右侧每个产生式的权重设置为 5.
+    fn parse_weights(&mut self, s: &'a str) -> Result<(), Error> {
+        for (line_num, line) in s.lines().enumerate() {
+            let Some(rest) = line
+                .trim()
+                .strip_prefix('#')
+                .and_then(|l| l.trim().strip_prefix("weight:"))
+            else {
+                continue;
+            };
+            let (weight, prods) = rest
+                .trim()
+                .split_once(char::is_whitespace)
+                .unwrap_or((rest.trim(), ""));
+            let invalid = || {
+                Error::parse_production_error(
+                    line_num,
+                    ParseProductionError::InvalidWeight(rest.trim().to_string()),
+                )
+            };
+            let weight: u32 = weight.parse().map_err(|_| invalid())?;
+            let (head, tails) = prods.split_once("->").ok_or_else(invalid)?;
+            for tail in tails.split('|') {
+                let tail = tail
+                    .split_ascii_whitespace()
+                    .map(|s| {
+                        self.get_token(s)
+                            .ok_or_else(|| Error::ProductionNotFound(prods.trim().to_string()))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let prod = Production::new(NonTerminal::from(head.trim()), tail);
+                let ids: Vec<_> = (0..self.prods.len())
+                    .filter(|&i| *self.prods[i] == prod)
+                    .collect();
+                if ids.is_empty() {
+                    Err(Error::ProductionNotFound(prod.to_string()))?;
+                }
+                for id in ids {
+                    self.weights[id] = weight;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 产生式的权重, 默认为 1, 编号超出范围时也为 1.
+    ///
+    /// 权重越大, [`crate::generate::Generator`] 随机生成句子时越常选择这个产生式 (概率和权重成正比, 权重为 0 时不会被选择,
+    /// 除非没有其他可选的产生式), 恐慌恢复中评分相同的强制归约也优先选择权重大的产生式, 见: [`Table::panic_candidates`].
+    ///
+    /// 在文法文件中可以使用 `# weight: 5 A -> α` 注释设置, `|` 分隔的多个产生式设置为相同的权重.
+    ///
+    /// [`Table::panic_candidates`]: crate::Table::panic_candidates
+    #[must_use]
+    pub fn weight(&self, id: ProdId) -> u32 {
+        self.weights.get(id).copied().unwrap_or(DEFAULT_WEIGHT)
+    }
+
+    /// 设置产生式的权重, 见: [`Grammar::weight`].
+    ///
+    /// # Errors
+    /// - [`Error::ProductionNotFound`] 产生式编号超出范围.
+    pub fn set_weight(&mut self, id: ProdId, weight: u32) -> Result<(), Error> {
+        *self
+            .weights
+            .get_mut(id)
+            .ok_or_else(|| Error::ProductionNotFound(format!("#{id}")))? = weight;
+        Ok(())
+    }
+
+    /// 文法的元数据 (版本号, 作者等), 按照键排列.
+    ///
+    /// 在文法文件中使用 `# meta: key = value` 注释设置, `# version: 1.2` 是 `# meta: version = 1.2` 的简写.
+    /// 元数据会被带到 [`crate::owned::OwnedTable`] 和生成的分析器代码中, 部署的分析器可以报告它是由哪个版本的文法生成的.
+    #[must_use]
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// 文法的版本号, 即键为 `version` 的元数据, 见: [`Grammar::metadata`].
+    #[must_use]
+    pub fn version(&self) -> Option<&str> {
+        self.metadata.get(VERSION_KEY).map(String::as_str)
+    }
+
+    /// 设置一项元数据, 已经存在时覆盖, 见: [`Grammar::metadata`].
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.metadata.insert(key.into(), value.into());
+    }
+
+    /// 运算符的优先级声明, 从低到高排列.
+    ///
+    /// 在文法文件中使用 yacc 风格的 `%left + -`, `%right ^`, `%nonassoc <` 声明, 每行一级, 先声明的优先级较低.
+    /// [`crate::Table::build_from`] 按照声明解决移入/归约冲突, 见: [`crate::precedence::resolve`].
+    #[must_use]
+    pub fn precedence_levels(&self) -> &[OperatorLevel<'a>] {
+        &self.precedence
+    }
+
+    /// 终结符的优先级 (从 1 开始, 越大结合得越紧) 和结合性, 没有声明时返回 [`None`].
+    #[must_use]
+    pub fn precedence(&self, term: Terminal<'_>) -> Option<(usize, Assoc)> {
+        self.precedence.iter().find_map(|level| {
+            level
+                .operators
+                .iter()
+                .any(|op| op.as_str() == term.as_str())
+                .then_some((level.level, level.assoc))
         })
     }
 
+    /// 替换所有的优先级声明, 例如使用 [`crate::precedence::infer`] 推断出的运算符表, 见: [`Grammar::precedence_levels`].
+    ///
+    /// # Errors
+    /// - [`Error::TerminalNotFound`] 运算符不是文法中的终结符.
+    pub fn set_precedence(&mut self, levels: &[OperatorLevel<'_>]) -> Result<(), Error> {
+        self.precedence = levels
+            .iter()
+            .map(|level| {
+                let operators = level
+                    .operators
+                    .iter()
+                    .map(|op| match self.get_token(op.as_str()) {
+                        Some(Token::Terminal(t)) => Ok(t),
+                        _ => Err(Error::TerminalNotFound(op.to_string())),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(OperatorLevel {
+                    level: level.level,
+                    assoc: level.assoc,
+                    operators,
+                    guessed: level.guessed,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(())
+    }
+
+    /// CFG 文法文本的开始符号: `# start: S` 注释指定的符号, 没有时为第一个产生式的头部.
+    #[must_use]
+    pub fn cfg_start(s: &str) -> Option<&str> {
+        let mut lines = s.lines().map(str::trim);
+        lines
+            .clone()
+            .find_map(|line| line.strip_prefix('#')?.trim().strip_prefix("start:"))
+            .map(str::trim)
+            .or_else(|| {
+                lines.find_map(|line| {
+                    (!is_comment(line))
+                        .then(|| line.split_once("->"))
+                        .flatten()
+                        .map(|(head, _)| head.trim())
+                })
+            })
+    }
+
     /// 获取以某个非终结符为头部的所有产生式, 结果可能为空.
     #[must_use]
     pub(crate) fn prods_of(&self, nt: NonTerminal<'a>) -> HashSet<&'a Production<'a>> {
-        self.prods
+        self.producers_of(nt)
             .iter()
-            .copied()
-            .filter(|p| p.head == nt)
+            .map(|&id| self.prods[id])
             .collect()
     }
 
-    /// 计算一个非终结符的 first 集.
-    /// # Parameters
-    /// - `recalc`: 是否重新计算.
-    /// # Returns
-    /// (是否需要重新计算, first 集).
-    /// # Errors
-    /// - [`Error::NonTerminalNotFound`]: `nt` 在文法中不存在.
-    /// - [`Error::InvalidFirstSetState`]: `nt` 正在被计算 first 集, 不能重复进入计算状态.
-    fn calc_first(
-        &self,
-        nt: NonTerminal<'a>,
-        recalc: bool,
-    ) -> Result<(bool, HashSet<Terminal<'a>>), Error> {
-        let mut first_set = self
-            .first_sets
-            .get(&nt)
-            .ok_or(Error::NonTerminalNotFound(nt.as_str().to_string()))?
-            .borrow_mut();
-        match &*first_set {
-            FirstSet::Calculating => Err(Error::InvalidFirstSetState)?,
-            FirstSet::Presense(first_set) => {
-                // 如果是正在重新计算, 那么跳过缓存.
-                if !recalc {
-                    return Ok((false, first_set.clone()));
+    /// 计算每个非终结符能够推导出的最短终结符串的长度 ([`EPSILON`] 不算长度).
+    ///
+    /// 无法推导出终结符串的非终结符 (不可终止的) 不会出现在结果中.
+    #[must_use]
+    pub(crate) fn min_lens(&self) -> HashMap<NonTerminal<'a>, usize> {
+        let mut lens: HashMap<NonTerminal<'a>, usize> = HashMap::new();
+        loop {
+            let mut changed = false;
+            for prod in &self.prods {
+                let Some(len) = Self::seq_min_len(&lens, prod.tail_without_eps()) else {
+                    continue;
+                };
+                let entry = lens.entry(prod.head).or_insert(usize::MAX);
+                if len < *entry {
+                    *entry = len;
+                    changed = true;
                 }
             }
-            _ => (),
+            if !changed {
+                break;
+            }
         }
-        *first_set = FirstSet::Calculating;
-        drop(first_set);
-        let mut first_set = HashSet::new();
-        let mut should_recalc = false; // 标记自身 first 集是否需要重新计算.
-        let mut need_recalc = HashSet::new(); // 需要重新计算 first 集的 productions.
-        for prod in self.prods_of(nt) {
-            let mut tail = prod.tail().iter();
-            let mut should_break = false;
-            while !should_break {
-                should_break = true;
-                match tail.next() {
-                    None => {
-                        first_set.insert(EPSILON);
+        lens
+    }
+
+    /// 根据 [`Grammar::min_lens`] 的结果计算 token 序列能够推导出的最短终结符串的长度,
+    /// 如果序列中有不可终止的非终结符, 那么返回 [`None`].
+    pub(crate) fn seq_min_len<'b>(
+        lens: &HashMap<NonTerminal<'a>, usize>,
+        seq: impl IntoIterator<Item = &'b Token<'a>>,
+    ) -> Option<usize>
+    where
+        'a: 'b,
+    {
+        seq.into_iter().try_fold(0usize, |acc, tok| match tok {
+            Token::Terminal(EPSILON) => Some(acc),
+            Token::Terminal(_) => Some(acc + 1),
+            Token::NonTerminal(nt) => lens.get(nt).map(|l| acc + l),
+        })
+    }
+
+    /// 计算每个非终结符的 follow 集, 开始符号的 follow 集包含 [`EOF`].
+    ///
+    /// 规范 LR(1) 分析不需要 follow 集, 这里用于 SLR 分析表的对比, 文法诊断和编辑器中的悬停提示.
+    /// 只需要一个非终结符时见: [`Grammar::follow_of`].
+    ///
+    /// 结果会被缓存, [`Grammar::add_production`] 和 [`Grammar::remove_production`] 之后只重新计算受到影响的非终结符,
+    /// 其他非终结符的 follow 集作为已知的值参与不动点迭代.
+    /// # Errors
+    /// 见: [`Grammar::first_set`].
+    pub fn follow_sets(&self) -> Result<HashMap<NonTerminal<'a>, BTreeSet<Terminal<'a>>>, Error> {
+        let mut cache = lock(&self.follows);
+        let stale: HashSet<_> = cache
+            .iter()
+            .filter(|(_, follow)| follow.is_none())
+            .map(|(&nt, _)| nt)
+            .collect();
+        if stale.is_empty() {
+            return Ok(cache
+                .iter()
+                .map(|(&nt, follow)| (nt, follow.clone().unwrap_or_default()))
+                .collect());
+        }
+        let mut follows: HashMap<NonTerminal<'a>, BTreeSet<Terminal<'a>>> = cache
+            .iter()
+            .map(|(&nt, follow)| (nt, follow.clone().unwrap_or_default()))
+            .collect();
+        if stale.contains(&self.start) {
+            follows.entry(self.start).or_default().insert(EOF);
+        }
+        loop {
+            let mut changed = false;
+            for prod in &self.prods {
+                let tail: Vec<_> = prod.tail_without_eps().copied().collect();
+                for (i, tok) in tail.iter().enumerate() {
+                    let Token::NonTerminal(nt) = *tok else {
+                        continue;
+                    };
+                    if !stale.contains(&nt) {
+                        continue;
                     }
-                    Some(Token::Terminal(EPSILON)) => {
-                        // pass through
-                        should_break = false;
+                    let mut set = self.first_set(tail[i + 1..].iter().copied())?;
+                    if set.remove(&EPSILON) {
+                        set.extend(follows.get(&prod.head).into_iter().flatten().copied());
                     }
-                    Some(Token::Terminal(t)) => {
-                        first_set.insert(*t);
+                    let entry = follows.entry(nt).or_default();
+                    for t in set {
+                        changed |= entry.insert(t);
                     }
-                    Some(Token::NonTerminal(nt)) => match self.calc_first(*nt, false) {
-                        Ok((recalc, s)) => {
-                            first_set.extend(s.iter().filter(|t| **t != EPSILON));
-                            if s.contains(&EPSILON) {
-                                should_break = false;
-                            }
-                            if recalc {
-                                need_recalc.insert(prod);
-                            }
-                        }
-                        Err(Error::InvalidFirstSetState) => {
-                            // 遇到了左递归, 暂时不使用这个产生式的内容, 延迟计算 first 集.
-                            should_recalc = true;
-                        }
-                        Err(e) => Err(e)?,
-                    },
                 }
             }
+            if !changed {
+                break;
+            }
+        }
+        for nt in stale {
+            cache.insert(nt, follows.get(&nt).cloned());
         }
+        Ok(follows)
+    }
 
-        // 先提供一个临时的 first set 给子递归使用.
-        *self.first_sets.get(&nt).unwrap().borrow_mut() = FirstSet::Presense(first_set.clone());
+    /// 非终结符 `nt` 的 follow 集, 按照名字排序, 见: [`Grammar::follow_sets`].
+    ///
+    /// # Errors
+    /// - [`Error::NonTerminalNotFound`]: `nt` 在文法中不存在.
+    /// - 其他见: [`Grammar::follow_sets`].
+    pub fn follow_of(&self, nt: NonTerminal<'a>) -> Result<BTreeSet<Terminal<'a>>, Error> {
+        self.follow_sets()?
+            .remove(&nt)
+            .ok_or_else(|| Error::NonTerminalNotFound(nt.as_str().to_string()))
+    }
 
-        for prod in need_recalc {
-            let mut tail = prod.tail().iter();
-            let mut should_break = false;
-            while !should_break {
-                should_break = true;
-                match tail.next() {
-                    None => {
-                        first_set.insert(EPSILON);
-                    }
-                    Some(Token::Terminal(EPSILON)) => {
-                        // pass through
-                        should_break = false;
-                    }
-                    Some(Token::Terminal(t)) => {
-                        first_set.insert(*t);
+    /// 计算一个非终结符的 first 集.
+    ///
+    /// 缓存中没有时, 找出从 `nt` 经过产生式尾部可以到达的所有还没有缓存的非终结符, 以已经缓存的 first 集为常量
+    /// 一起做不动点迭代, 左递归和相互递归在迭代中自然收敛. 迭代结束之后写入缓存, 其他线程先写入的结果和这里的相同.
+    /// # Errors
+    /// - [`Error::NonTerminalNotFound`]: `nt` 或者产生式尾部中的非终结符在文法中不存在.
+    fn calc_first(&self, nt: NonTerminal<'a>) -> Result<HashSet<Terminal<'a>>, Error> {
+        let cell = |nt: NonTerminal<'a>| {
+            self.first_sets
+                .get(&nt)
+                .ok_or_else(|| Error::NonTerminalNotFound(nt.as_str().to_string()))
+        };
+        if let Some(first) = cell(nt)?.get() {
+            return Ok(first.clone());
+        }
+        let mut firsts: HashMap<NonTerminal<'a>, HashSet<Terminal<'a>>> =
+            HashMap::from([(nt, HashSet::new())]);
+        let mut pending = vec![nt];
+        while let Some(cur) = pending.pop() {
+            for &id in self.producers_of(cur) {
+                for tok in self.prods[id].tail() {
+                    if let Token::NonTerminal(next) = *tok
+                        && cell(next)?.get().is_none()
+                        && !firsts.contains_key(&next)
+                    {
+                        firsts.insert(next, HashSet::new());
+                        pending.push(next);
                     }
-                    Some(Token::NonTerminal(nt)) => match self.calc_first(*nt, true) {
-                        Ok((recalc, s)) => {
-                            first_set.extend(s.iter().filter(|t| **t != EPSILON));
-                            if s.contains(&EPSILON) {
-                                should_break = false;
-                            }
-                            if recalc {
-                                // 已经给这个非终结符 (nt) 提供了自身的 first 集, 但是其还是说自身需要重新计算,
-                                // 那么说明问题不出在自身, 无法在此处解决, 标记自身需要重新计算, 等待 caller 重新计算.
-                                should_recalc = true;
+                }
+            }
+        }
+        let non_terms: Vec<_> = firsts.keys().copied().collect();
+        let empty = HashSet::new();
+        loop {
+            let mut changed = false;
+            for &cur in &non_terms {
+                for &id in self.producers_of(cur) {
+                    let mut set = HashSet::new();
+                    let mut nullable = true;
+                    for tok in self.prods[id].tail() {
+                        let first = match *tok {
+                            Token::Terminal(EPSILON) => continue,
+                            Token::Terminal(t) => {
+                                set.insert(t);
+                                nullable = false;
+                                break;
                             }
+                            // 不在 `firsts` 中的非终结符扫描时已经有缓存.
+                            Token::NonTerminal(next) => match firsts.get(&next) {
+                                Some(first) => first,
+                                None => cell(next)?.get().unwrap_or(&empty),
+                            },
+                        };
+                        set.extend(first.iter().filter(|t| **t != EPSILON));
+                        if !first.contains(&EPSILON) {
+                            nullable = false;
+                            break;
                         }
-                        Err(Error::InvalidFirstSetState) => {
-                            // 遇到了左递归, 暂时不使用这个产生式的内容, 延迟计算 first 集.
-                            should_recalc = true;
-                        }
-                        Err(e) => Err(e)?,
-                    },
+                    }
+                    if nullable {
+                        set.insert(EPSILON);
+                    }
+                    let entry = firsts.get_mut(&cur).unwrap();
+                    for t in set {
+                        changed |= entry.insert(t);
+                    }
                 }
             }
+            if !changed {
+                break;
+            }
         }
-        *self.first_sets.get(&nt).unwrap().borrow_mut() = FirstSet::Presense(first_set.clone());
-        Ok((should_recalc, first_set))
+        for (cur, first) in firsts {
+            // 已经被其他线程写入时结果相同, 忽略.
+            let _ = cell(cur)?.set(first);
+        }
+        Ok(cell(nt)?.get().cloned().unwrap_or_default())
     }
 
     /// 计算一个 token 序列的 first 集
@@ -367,7 +1290,6 @@ impl<'a> Grammar<'a> {
     ///
     /// # Errors
     /// - [`Error::NonTerminalNotFound`]: `seq` 中存在文法中没有的非终结符.
-    /// - [`Error::UnresolvableFirstSet`]: first 集无法收敛, 无法计算, 一般不会出现这种情况.
     pub fn first_set(
         &self,
         mut seq: impl Iterator<Item = Token<'a>>,
@@ -387,14 +1309,7 @@ impl<'a> Grammar<'a> {
                     first_set.insert(t);
                 }
                 Some(Token::NonTerminal(nt)) => {
-                    let (recalc, mut fs) = self.calc_first(nt, false)?;
-                    if recalc {
-                        let (recalc, fs_) = self.calc_first(nt, true)?;
-                        if recalc {
-                            Err(Error::UnresolvableFirstSet)?
-                        }
-                        fs = fs_;
-                    }
+                    let fs = self.calc_first(nt)?;
                     first_set.extend(fs.iter().filter(|t| **t != EPSILON));
                     if fs.contains(&EPSILON) {
                         should_break = false;
@@ -405,6 +1320,27 @@ impl<'a> Grammar<'a> {
         Ok(first_set)
     }
 
+    /// 非终结符 `nt` 的 first 集, 按照名字排序, 可以推导出空串时包含 [`EPSILON`].
+    ///
+    /// # Errors
+    /// - [`Error::NonTerminalNotFound`]: `nt` 在文法中不存在.
+    pub fn first_of(&self, nt: NonTerminal<'a>) -> Result<BTreeSet<Terminal<'a>>, Error> {
+        self.first_of_seq([nt.into()])
+    }
+
+    /// token 序列 `seq` 的 first 集, 按照名字排序, 见: [`Grammar::first_set`].
+    ///
+    /// 和 [`Grammar::first_set`] 一样, 空序列和整个序列可以推导出空串时结果包含 [`EPSILON`].
+    ///
+    /// # Errors
+    /// 见: [`Grammar::first_set`].
+    pub fn first_of_seq(
+        &self,
+        seq: impl IntoIterator<Item = Token<'a>>,
+    ) -> Result<BTreeSet<Terminal<'a>>, Error> {
+        Ok(self.first_set(seq.into_iter())?.into_iter().collect())
+    }
+
     /// 计算 seq 的 first 集, 如果 seq 的 first 集中有 [`EPSILON`] 或者 first 集为空,
     /// 那么附加 fallthrough 提供的终结符.
     pub fn first_set_with_fallthrough(
@@ -470,10 +1406,10 @@ impl<'a> Grammar<'a> {
 
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeSet, HashMap, HashSet};
 
     use crate::{
-        NonTerminal, Production, Terminal, Token,
+        Family, NonTerminal, Production, Table, Terminal, Token,
         error::{Error, ParseProductionError},
         grammar::Grammar,
         token::{EOF, EPSILON},
@@ -554,6 +1490,351 @@ mod test {
         )
     }
 
+    #[test]
+    fn clone_into() {
+        let base = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &base)
+            .unwrap()
+            .augmented();
+        let used = base.allocated_bytes();
+        let mut scratch = Bump::new();
+        let mut rows = Vec::new();
+        for _ in 0..3 {
+            {
+                let cloned = grammar.clone_into(&scratch);
+                assert_eq!(cloned.to_string(), grammar.to_string());
+                assert_eq!(cloned.declared_index(1), Some(0));
+                let family = Family::from_grammar(&cloned).unwrap();
+                rows.push(Table::build_from(&family).unwrap().rows());
+            }
+            scratch.reset();
+        }
+        assert_eq!(rows, [rows[0]; 3]);
+        // 项集族分配在临时的 arena 中, 原来的 arena 没有增长.
+        assert_eq!(base.allocated_bytes(), used);
+    }
+
+    #[test]
+    fn reserved_symbols() {
+        let bump = Bump::new();
+        let reserved = |symbol: &str, builtin: &str, line| {
+            Err(Error::ParseProductionError {
+                line,
+                cause: ParseProductionError::ReservedSymbol {
+                    symbol: symbol.into(),
+                    builtin: builtin.into(),
+                },
+            })
+        };
+        assert_eq!(
+            Grammar::from_cfg("S -> a E\nE -> b", "S".into(), &bump),
+            reserved("E", "epsilon", 1)
+        );
+        assert_eq!(
+            Grammar::from_cfg("S -> a\n\nS -> a eof", "S".into(), &bump),
+            reserved("eof", "end of input", 2)
+        );
+        assert!(Grammar::from_cfg("S -> a | E", "S".into(), &bump).is_ok());
+    }
+
+    #[test]
+    fn numbering() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b | c\nA -> a | c", "S".into(), &bump).unwrap();
+        let declared: Vec<_> = grammar.prods().iter().map(ToString::to_string).collect();
+        assert_eq!(declared, ["S -> A b", "S -> c", "A -> a", "A -> c"]);
+        assert_eq!(grammar.augmented_production(), None);
+        assert_eq!(grammar.original_start(), NonTerminal::from("S"));
+        let augmented = grammar.augmented();
+        let accept = augmented.augmented_production().unwrap();
+        assert_eq!(augmented.prods()[accept].to_string(), "Sprime -> S");
+        assert_eq!(augmented.original_start(), NonTerminal::from("S"));
+        assert_eq!(augmented.symbol_start(), NonTerminal::from("Sprime"));
+        assert_eq!(augmented.declared_index(0), None);
+        assert_eq!(augmented.declared_index(4), Some(3));
+        assert_eq!(augmented.declared_index(5), None);
+        for (i, text) in declared.iter().enumerate() {
+            let id = augmented.prod_id(i).unwrap();
+            assert_eq!(&augmented.prod(id).unwrap().to_string(), text);
+            assert_eq!(augmented.declared_index(id), Some(i));
+        }
+        assert_eq!(augmented.prod_id(4), None);
+        assert_eq!(augmented.prod(5), None);
+        // 重复声明的产生式使用第一次声明的编号.
+        let grammar = Grammar::from_cfg("S -> a | a", "S".into(), &bump).unwrap();
+        assert_eq!(grammar.index_of_prod(grammar.prods()[1]), Some(0));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b | c\nA -> a | E", "A".into(), &bump).unwrap();
+        let text = grammar.to_string();
+        assert_eq!(text, "# start: A\nS -> A b\nS -> c\nA -> a\nA -> E\n");
+        let start = Grammar::cfg_start(&text).unwrap();
+        assert_eq!(start, "A");
+        assert_eq!(
+            Grammar::from_cfg(&text, start.into(), &bump).unwrap(),
+            grammar
+        );
+        assert_eq!(format!("{grammar:#}").lines().nth(1), Some("   1 S -> c"));
+        assert_eq!(format!("{:#}", grammar.prods()[0]), "S -> A 'b'");
+        assert_eq!(format!("{:#}", grammar.prods()[3]), "A -> E");
+        assert_eq!(Grammar::cfg_start("# comment\nS -> a"), Some("S"));
+    }
+
+    #[test]
+    fn weights() {
+        let bump = Bump::new();
+        let src = "S -> a S | b | c\n# weight: 5 S -> a S | c";
+        let mut grammar = Grammar::from_cfg(src, "S".into(), &bump).unwrap();
+        assert_eq!(
+            (0..4).map(|id| grammar.weight(id)).collect::<Vec<_>>(),
+            [5, 1, 5, 1]
+        );
+        grammar.set_weight(1, 0).unwrap();
+        assert!(grammar.set_weight(3, 2).is_err());
+        let text = grammar.to_string();
+        assert!(text.ends_with("# weight: 5 S -> a S\n# weight: 0 S -> b\n# weight: 5 S -> c\n"));
+        assert_eq!(
+            Grammar::from_cfg(&text, "S".into(), &bump).unwrap(),
+            grammar
+        );
+        // 增广产生式插入到最前面, 权重跟随产生式移动.
+        let augmented = grammar.clone_into(&bump).augmented();
+        assert_eq!(augmented.weight(1), 5);
+        assert_eq!(augmented.weight(2), 0);
+
+        assert!(Grammar::from_cfg("S -> a\n# weight: x S -> a", "S".into(), &bump).is_err());
+        assert!(Grammar::from_cfg("S -> a\n# weight: 2 S -> b", "S".into(), &bump).is_err());
+    }
+
+    #[test]
+    fn metadata() {
+        let bump = Bump::new();
+        let src = "# version: 2.1\n# meta: author = someone\n# meta: url = a=b\nS -> a";
+        let mut grammar = Grammar::from_cfg(src, "S".into(), &bump).unwrap();
+        assert_eq!(grammar.version(), Some("2.1"));
+        assert_eq!(grammar.metadata()["url"], "a=b");
+        grammar.set_metadata("version", "2.2");
+        let text = grammar.to_string();
+        assert_eq!(
+            text,
+            "# start: S\n# meta: author = someone\n# meta: url = a=b\n# version: 2.2\nS -> a\n"
+        );
+        let parsed = Grammar::from_cfg(&text, "S".into(), &bump).unwrap();
+        assert_eq!(parsed.metadata(), grammar.metadata());
+        assert_eq!(
+            grammar.clone_into(&bump).augmented().metadata(),
+            grammar.metadata()
+        );
+        assert!(Grammar::from_cfg("# meta: author\nS -> a", "S".into(), &bump).is_err());
+    }
+
+    #[test]
+    fn usages() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> if C then S | if C then S else S | a\nC -> b",
+            "S".into(),
+            &bump,
+        )
+        .unwrap();
+        assert_eq!(
+            grammar
+                .usages(NonTerminal::from("S").into())
+                .collect::<Vec<_>>(),
+            [(0, 3), (1, 3), (1, 5)]
+        );
+        assert_eq!(
+            grammar
+                .usages(Terminal::from("b").into())
+                .collect::<Vec<_>>(),
+            [(3, 0)]
+        );
+        let grammar = grammar.augmented();
+        assert_eq!(
+            grammar.usages(NonTerminal::from("S").into()).next(),
+            Some((0, 0))
+        );
+        assert_eq!(grammar.usages(Terminal::from("c").into()).count(), 0);
+    }
+
+    #[test]
+    fn recursive_start() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) | a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        assert_eq!(grammar.prods()[0].to_string(), "Sprime -> S");
+        assert_eq!(grammar.producers_of("Sprime".into()), [0]);
+        assert_eq!(grammar.consumers_of(NonTerminal::from("S").into()), [0, 1]);
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let accept = table.goto(0, "S".into()).unwrap().unwrap();
+        assert_eq!(table.action(accept, EOF), Some(&crate::ActionCell::Accept));
+        // 只有从 I_0 读入 `S` 到达的状态接受, 括号内的 `S` 不会接受.
+        let accepts = (0..table.rows())
+            .filter(|&s| table.action(s, EOF) == Some(&crate::ActionCell::Accept))
+            .count();
+        assert_eq!(accepts, 1);
+
+        // 文法中已经有 `Sprime` 时增广产生式的头部改名.
+        let grammar = Grammar::from_cfg("S -> a Sprime\nSprime -> b | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        assert_eq!(grammar.prods()[0].to_string(), "Sprimeprime -> S");
+        assert_eq!(grammar.symbol_start(), NonTerminal::from("Sprimeprime"));
+    }
+
+    #[test]
+    fn producers_and_consumers() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> if C then S | if C then S else S | a\nC -> b | C and C",
+            "S".into(),
+            &bump,
+        )
+        .unwrap();
+        assert_eq!(grammar.producers_of("S".into()), [0, 1, 2]);
+        assert_eq!(grammar.producers_of("C".into()), [3, 4]);
+        assert_eq!(grammar.consumers_of(NonTerminal::from("S").into()), [0, 1]);
+        assert_eq!(
+            grammar.consumers_of(NonTerminal::from("C").into()),
+            [0, 1, 4]
+        );
+        assert_eq!(grammar.consumers_of(Terminal::from("b").into()), [3]);
+        assert!(grammar.consumers_of(Terminal::from("c").into()).is_empty());
+        assert!(grammar.producers_of("X".into()).is_empty());
+        // 增广之后编号加一.
+        let grammar = grammar.augmented();
+        assert_eq!(grammar.producers_of("S".into()), [1, 2, 3]);
+        assert_eq!(grammar.producers_of("Sprime".into()), [0]);
+        assert_eq!(
+            grammar.consumers_of(NonTerminal::from("S").into()),
+            [0, 1, 2]
+        );
+        let copy = grammar.clone_into(&bump);
+        assert_eq!(copy.consumers_of(NonTerminal::from("C").into()), [1, 2, 5]);
+    }
+
+    #[test]
+    fn follow() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> T Sprime\nSprime -> + T Sprime | E\nT -> id | ( S )",
+            "S".into(),
+            &bump,
+        )
+        .unwrap();
+        let follows = grammar.follow_sets().unwrap();
+        let terms = |s: &'static str| {
+            s.split_whitespace()
+                .map(Terminal::from)
+                .collect::<BTreeSet<_>>()
+        };
+        assert_eq!(follows[&NonTerminal::from("S")], terms(") eof"));
+        assert_eq!(follows[&NonTerminal::from("Sprime")], terms(") eof"));
+        assert_eq!(follows[&NonTerminal::from("T")], terms("+ ) eof"));
+
+        let nt = NonTerminal::from;
+        assert_eq!(grammar.follow_of(nt("T")).unwrap(), terms("+ ) eof"));
+        assert_eq!(grammar.first_of(nt("S")).unwrap(), terms("( id"));
+        assert_eq!(grammar.first_of(nt("Sprime")).unwrap(), terms("+ E"));
+        assert_eq!(
+            grammar
+                .first_of_seq([nt("Sprime").into(), Terminal::from(")").into()])
+                .unwrap(),
+            terms("+ )")
+        );
+        assert_eq!(grammar.first_of_seq([]).unwrap(), terms("E"));
+        assert!(matches!(
+            grammar.first_of(nt("X")),
+            Err(Error::NonTerminalNotFound(_))
+        ));
+        assert!(matches!(
+            grammar.follow_of(nt("X")),
+            Err(Error::NonTerminalNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn incremental_first_follow() {
+        let bump = Bump::new();
+        let mut grammar = Grammar::from_cfg(
+            "S -> D A b | B\nA -> a\nB -> c D\nD -> d",
+            "S".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let nt = NonTerminal::from;
+        fn current<'a>(
+            grammar: &Grammar<'a>,
+        ) -> (
+            Vec<HashSet<Terminal<'a>>>,
+            HashMap<NonTerminal<'a>, BTreeSet<Terminal<'a>>>,
+        ) {
+            let firsts = ["S", "A", "B", "D"]
+                .map(|n| {
+                    grammar
+                        .first_set([NonTerminal::from(n).into()].into_iter())
+                        .unwrap()
+                })
+                .into();
+            (firsts, grammar.follow_sets().unwrap())
+        }
+        let fresh = |src: &'static str| {
+            current(
+                &Grammar::from_cfg(src, "S".into(), &bump)
+                    .unwrap()
+                    .augmented(),
+            )
+        };
+        let cached = |grammar: &Grammar<'_>, n| grammar.first_sets[&nt(n)].get().is_some();
+        current(&grammar);
+
+        // A 变为可空: S 和 A 的 first 集, 以及 A 之前的 D 的 follow 集失效, 其他缓存不受影响.
+        let prod = grammar.parse_production("A -> E").unwrap();
+        assert_eq!(grammar.add_production(&prod).unwrap(), 6);
+        assert!(!cached(&grammar, "S") && !cached(&grammar, "A"));
+        assert!(cached(&grammar, "B") && cached(&grammar, "D"));
+        let follows = super::lock(&grammar.follows);
+        assert!(follows[&nt("D")].is_none());
+        assert!(follows[&nt("A")].is_some() && follows[&nt("B")].is_some());
+        drop(follows);
+        assert_eq!(
+            current(&grammar),
+            fresh("S -> D A b | B\nA -> a\nB -> c D\nD -> d\nA -> E")
+        );
+
+        // 新的非终结符, 删除产生式之后编号前移, 不再使用的符号被删除.
+        let prod = grammar.parse_production("D -> F e").unwrap();
+        let prod = Production::new(nt("D"), vec![nt("F").into(), prod.tail()[1]]);
+        grammar.add_production(&prod).unwrap();
+        let prod = grammar.parse_production("F -> f").unwrap();
+        grammar.add_production(&prod).unwrap();
+        assert_eq!(grammar.remove_production(5).unwrap().to_string(), "D -> d");
+        assert_eq!(grammar.get_token("d"), None);
+        assert_eq!(grammar.prods()[5].to_string(), "A -> E");
+        assert_eq!(
+            current(&grammar),
+            fresh("S -> D A b | B\nA -> a\nB -> c D\nA -> E\nD -> F e\nF -> f")
+        );
+
+        assert!(grammar.remove_production(0).is_err());
+        let prod = grammar.parse_production("x -> S").unwrap();
+        assert!(grammar.add_production(&prod).is_ok());
+        let prod = Production::new(nt("b"), vec![]);
+        assert!(matches!(
+            grammar.add_production(&prod),
+            Err(Error::ParseProductionError {
+                cause: ParseProductionError::TokenTypeMisMatch(_),
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn first() {
         let bump = Bump::new();
@@ -582,5 +1863,107 @@ mod test {
                 .unwrap(),
             [brace_l, stmt, EPSILON].into()
         );
+
+        // 相互左递归, 计算 A 的时候 B 还没有缓存, 一起迭代.
+        let grammar = Grammar::from_cfg("A -> B a | c\nB -> A b | E", "A".into(), &bump).unwrap();
+        let first = |nt: &'static str| {
+            grammar
+                .first_set([NonTerminal::from(nt).into()].into_iter())
+                .unwrap()
+        };
+        let (a, b, c) = (
+            Terminal::from("a"),
+            Terminal::from("b"),
+            Terminal::from("c"),
+        );
+        assert_eq!(first("A"), [a, c].into());
+        assert!(grammar.first_sets[&NonTerminal::from("B")].get().is_some());
+        assert_eq!(first("B"), [a, c, EPSILON].into());
+        assert_eq!(
+            grammar
+                .first_set([NonTerminal::from("B").into(), b.into()].into_iter())
+                .unwrap(),
+            [a, b, c].into()
+        );
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<Grammar<'static>>();
+
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "S -> T Sprime\nSprime -> + T Sprime | E\nT -> id | ( S )",
+            "S".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let nt = NonTerminal::from;
+        let results: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = ["S", "Sprime", "T"]
+                .into_iter()
+                .map(|name| {
+                    let grammar = &grammar;
+                    s.spawn(move || {
+                        (
+                            grammar.first_of(nt(name)).unwrap(),
+                            grammar.follow_of(nt(name)).unwrap(),
+                        )
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for (name, (first, follow)) in ["S", "Sprime", "T"].into_iter().zip(results) {
+            assert_eq!(first, grammar.first_of(nt(name)).unwrap());
+            assert_eq!(follow, grammar.follow_of(nt(name)).unwrap());
+        }
+        assert_eq!(
+            grammar.first_of(nt("T")).unwrap(),
+            BTreeSet::from([Terminal::from("("), Terminal::from("id")])
+        );
+
+        // 项集族不使用 arena, 可以在多个线程中同时构建.
+        let expected = Family::from_grammar(&grammar).unwrap();
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| Family::from_grammar(&grammar).unwrap()))
+                .collect();
+            for handle in handles {
+                assert_eq!(handle.join().unwrap().item_sets(), expected.item_sets());
+            }
+        });
+
+        // 向 arena 中加入符号的操作只能在创建文法的线程上进行.
+        let augmented =
+            std::thread::scope(|s| s.spawn(|| grammar.clone().augmented().prods().len()).join());
+        assert!(augmented.is_err());
+    }
+
+    #[test]
+    fn suffix_first_cache() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let prod = grammar.prods()[1];
+        let first = grammar.suffix_first(prod, 0).unwrap();
+        assert_eq!(
+            *first,
+            grammar.first_set(prod.tail().iter().copied()).unwrap()
+        );
+        let again = grammar.suffix_first(prod, 0).unwrap();
+        assert!(std::sync::Arc::ptr_eq(&first, &again));
+        assert_eq!(grammar.suffix_first_stats().hits, 1);
+        assert_eq!(grammar.suffix_first_stats().misses, 1);
+
+        Family::from_grammar(&grammar).unwrap();
+        let stats = grammar.suffix_first_stats();
+        // 每个带点位置最多计算一次.
+        let positions: usize = grammar.prods().iter().map(|p| p.len() + 1).sum();
+        assert!(stats.misses <= positions);
+        assert!(stats.hits > stats.misses);
     }
 }
@@ -0,0 +1,550 @@
+//! 项集族 GOTO 图的分析和导出.
+//!
+//! 使用 Tarjan 算法求 GOTO 图的强连通分量, 非平凡的强连通分量 (多于一个状态, 或者有自环)
+//! 就是自动机中的环, 对应语言中的重复结构, 可以作为恐慌恢复同步点的参考.
+//!
+//! 除了 DOT 之外, GOTO 图还可以导出为 GraphML (Gephi 等) 和 cytoscape.js 格式的 JSON, 方便交互式地查看很大的自动机.
+//! 开启 `petgraph` feature 之后还可以转换为 [`petgraph`] 的图, 直接使用其中的图算法 (支配树, 最短路径等).
+
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::{Family, StateId, Token};
+
+/// GOTO 图中的一个环 (非平凡的强连通分量).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop<'a> {
+    /// 环中的状态, 从小到大排列.
+    pub states: Vec<StateId>,
+    /// 环内部的边上的符号 (去重, 有序), 也就是会重复出现的符号.
+    pub tokens: Vec<Token<'a>>,
+    /// 从环外进入环的边 (起始状态, 符号, 到达状态).
+    pub entries: Vec<(StateId, Token<'a>, StateId)>,
+}
+
+impl Display for Loop<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |it: &mut dyn Iterator<Item = String>| it.collect::<Vec<_>>().join(", ");
+        write!(
+            f,
+            "states [{}] repeat [{}] entered by [{}]",
+            join(&mut self.states.iter().map(|s| format!("I_{s}"))),
+            join(&mut self.tokens.iter().map(|t| t.to_string())),
+            join(
+                &mut self
+                    .entries
+                    .iter()
+                    .map(|(from, tok, to)| format!("I_{from} -- {tok} --> I_{to}"))
+            ),
+        )
+    }
+}
+
+impl<'a> Family<'a> {
+    /// GOTO 图的所有强连通分量, 每个分量中的状态从小到大排列, 分量按照其最小状态排序.
+    #[must_use]
+    pub fn sccs(&self) -> Vec<Vec<StateId>> {
+        let n = self.len();
+        let succ: Vec<Vec<StateId>> = (0..n)
+            .map(|s| {
+                self.gotos_of(s)
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|(_, dests)| dests.iter().copied())
+                    .collect()
+            })
+            .collect();
+        // 迭代版本的 Tarjan 算法, 避免状态很多时递归过深.
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut next = 0;
+        let mut sccs = Vec::new();
+        for root in 0..n {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            // (状态, 下一个要访问的后继下标).
+            let mut call = vec![(root, 0)];
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            while let Some(&(v, i)) = call.last() {
+                if let Some(&w) = succ[v].get(i) {
+                    if let Some(top) = call.last_mut() {
+                        top.1 += 1;
+                    }
+                    if index[w] == usize::MAX {
+                        index[w] = next;
+                        low[w] = next;
+                        next += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        call.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+                call.pop();
+                if let Some(&(parent, _)) = call.last() {
+                    low[parent] = low[parent].min(low[v]);
+                }
+                if low[v] == index[v] {
+                    let mut scc = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        scc.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    scc.sort_unstable();
+                    sccs.push(scc);
+                }
+            }
+        }
+        sccs.sort_unstable_by_key(|scc| scc[0]);
+        sccs
+    }
+
+    /// GOTO 图中的所有环, 也就是多于一个状态或者有自环的强连通分量, 顺序同 [`Family::sccs`].
+    #[must_use]
+    pub fn loops(&self) -> Vec<Loop<'a>> {
+        let sccs = self.sccs();
+        let mut component = vec![0; self.len()];
+        for (i, scc) in sccs.iter().enumerate() {
+            for &s in scc {
+                component[s] = i;
+            }
+        }
+        let mut loops = Vec::new();
+        for (i, scc) in sccs.into_iter().enumerate() {
+            let mut tokens = Vec::new();
+            for (from, tok, to) in self.gotos() {
+                if component[from] == i && component[to] == i && !tokens.contains(&tok) {
+                    tokens.push(tok);
+                }
+            }
+            if tokens.is_empty() {
+                continue;
+            }
+            tokens.sort_unstable();
+            let entries = self
+                .gotos()
+                .filter(|&(from, _, to)| component[from] != i && component[to] == i)
+                .collect();
+            loops.push(Loop {
+                states: scc,
+                tokens,
+                entries,
+            });
+        }
+        loops
+    }
+
+    /// 渲染 GOTO 图为 Graphviz DOT 格式, 节点标签为项集编号.
+    ///
+    /// 如果 `cluster_loops` 为 `true`, 那么每个环 (见: [`Family::loops`]) 中的状态放在同一个 `subgraph cluster_*` 中.
+    #[must_use]
+    pub fn to_dot(&self, cluster_loops: bool) -> String {
+        let mut out =
+            String::from("digraph goto_graph {\n    rankdir=LR;\n    node [shape=circle];\n");
+        let loops = if cluster_loops {
+            self.loops()
+        } else {
+            Vec::new()
+        };
+        for (i, lp) in loops.iter().enumerate() {
+            out += &format!("    subgraph cluster_{i} {{\n        style=dashed;\n");
+            for s in &lp.states {
+                out += &format!("        I{s};\n");
+            }
+            out += "    }\n";
+        }
+        for s in 0..self.len() {
+            let shape = if self.item_sets()[s].reduces().next().is_some() {
+                ", shape=doublecircle"
+            } else {
+                ""
+            };
+            out += &format!("    I{s} [label=\"I{s}\"{shape}];\n");
+        }
+        for (from, tok, to) in self.gotos() {
+            out += &format!("    I{from} -> I{to} [label={:?}];\n", tok.as_str());
+        }
+        out += "}";
+        out
+    }
+}
+
+/// GOTO 图中一个状态附近的子图, 见: [`Family::neighborhood`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighborhood<'a> {
+    /// 中心状态.
+    pub center: StateId,
+    /// 子图中的状态, 从小到大排列.
+    pub states: Vec<StateId>,
+    /// 两端都在子图中的边 (起始状态, 符号, 到达状态), 顺序同 [`Family::gotos`].
+    pub edges: Vec<(StateId, Token<'a>, StateId)>,
+    /// 还有边连接到子图之外的状态, 从小到大排列.
+    pub boundary: Vec<StateId>,
+}
+
+impl Neighborhood<'_> {
+    /// 渲染为 Graphviz DOT 格式, 中心状态加粗, 还有边连接到子图之外的状态使用虚线.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut out = format!(
+            "digraph neighborhood_{} {{\n    rankdir=LR;\n    node [shape=circle];\n",
+            self.center
+        );
+        for &s in &self.states {
+            let mut attrs = vec![format!("label=\"I{s}\"")];
+            if s == self.center {
+                attrs.push("penwidth=3".to_string());
+            }
+            if self.boundary.binary_search(&s).is_ok() {
+                attrs.push("style=dashed".to_string());
+            }
+            out += &format!("    I{s} [{}];\n", attrs.join(", "));
+        }
+        for (from, tok, to) in &self.edges {
+            out += &format!("    I{from} -> I{to} [label={:?}];\n", tok.as_str());
+        }
+        out += "}";
+        out
+    }
+}
+
+impl<'a> Family<'a> {
+    /// 和 `state` 的距离不超过 `radius` 条边 (不考虑边的方向) 的状态组成的子图, 包括子图中状态之间的所有边.
+    ///
+    /// 状态很多时可以只查看和一个冲突状态相关的部分, `state` 不存在时返回 [`None`].
+    #[must_use]
+    pub fn neighborhood(&self, state: StateId, radius: usize) -> Option<Neighborhood<'a>> {
+        if state >= self.len() {
+            return None;
+        }
+        let mut adjacent: Vec<Vec<StateId>> = vec![Vec::new(); self.len()];
+        for (from, _, to) in self.gotos() {
+            adjacent[from].push(to);
+            adjacent[to].push(from);
+        }
+        let mut dist = vec![usize::MAX; self.len()];
+        dist[state] = 0;
+        let mut queue = VecDeque::from([state]);
+        while let Some(s) = queue.pop_front() {
+            if dist[s] == radius {
+                continue;
+            }
+            for &next in &adjacent[s] {
+                if dist[next] == usize::MAX {
+                    dist[next] = dist[s] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        let inside = |s: StateId| dist[s] != usize::MAX;
+        let states: Vec<_> = (0..self.len()).filter(|&s| inside(s)).collect();
+        let boundary = states
+            .iter()
+            .copied()
+            .filter(|&s| adjacent[s].iter().any(|&n| !inside(n)))
+            .collect();
+        let edges = self
+            .gotos()
+            .filter(|&(from, _, to)| inside(from) && inside(to))
+            .collect();
+        Some(Neighborhood {
+            center: state,
+            states,
+            edges,
+            boundary,
+        })
+    }
+}
+
+/// 转换为 JSON 字符串字面量 (包括两侧的引号).
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if c.is_control() => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl Family<'_> {
+    /// 每个状态所在的环在 [`Family::loops`] 中的下标.
+    fn loop_of(&self) -> Vec<Option<usize>> {
+        let mut loop_of = vec![None; self.len()];
+        for (i, lp) in self.loops().iter().enumerate() {
+            for &s in &lp.states {
+                loop_of[s] = Some(i);
+            }
+        }
+        loop_of
+    }
+
+    /// 导出 GOTO 图为 GraphML 格式.
+    ///
+    /// 节点的属性有 `label` (项集编号), `items` (项集中的项, 每行一个), `reduce` (是否有可以归约的项)
+    /// 和 `loop` (所在的环的编号, 不在环中为 -1), 边的属性有 `label` (符号) 和 `terminal` (是否是终结符).
+    #[must_use]
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"items\" for=\"node\" attr.name=\"items\" attr.type=\"string\"/>\n",
+            "  <key id=\"reduce\" for=\"node\" attr.name=\"reduce\" attr.type=\"boolean\"/>\n",
+            "  <key id=\"loop\" for=\"node\" attr.name=\"loop\" attr.type=\"int\"/>\n",
+            "  <key id=\"symbol\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+            "  <key id=\"terminal\" for=\"edge\" attr.name=\"terminal\" attr.type=\"boolean\"/>\n",
+            "  <graph id=\"goto_graph\" edgedefault=\"directed\">\n",
+        ));
+        for (s, loop_idx) in self.loop_of().into_iter().enumerate() {
+            let is = &self.item_sets()[s];
+            let items = is
+                .items()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n");
+            out += &format!(
+                concat!(
+                    "    <node id=\"I{s}\">\n",
+                    "      <data key=\"label\">I{s}</data>\n",
+                    "      <data key=\"items\">{items}</data>\n",
+                    "      <data key=\"reduce\">{reduce}</data>\n",
+                    "      <data key=\"loop\">{loop_idx}</data>\n",
+                    "    </node>\n",
+                ),
+                s = s,
+                items = xml_escape(&items),
+                reduce = is.reduces().next().is_some(),
+                loop_idx = loop_idx.map_or(-1, |i| i as i64),
+            );
+        }
+        for (i, (from, tok, to)) in self.gotos().enumerate() {
+            out += &format!(
+                concat!(
+                    "    <edge id=\"e{i}\" source=\"I{from}\" target=\"I{to}\">\n",
+                    "      <data key=\"symbol\">{symbol}</data>\n",
+                    "      <data key=\"terminal\">{terminal}</data>\n",
+                    "    </edge>\n",
+                ),
+                i = i,
+                from = from,
+                to = to,
+                symbol = xml_escape(tok.as_str()),
+                terminal = tok.is_term(),
+            );
+        }
+        out += "  </graph>\n</graphml>";
+        out
+    }
+
+    /// 导出 GOTO 图为 cytoscape.js 的 JSON 格式 (`{"elements": {"nodes": [...], "edges": [...]}}`),
+    /// 每个元素的 `data` 中的字段和 [`Family::to_graphml`] 中的属性相同, `loop` 不在环中时为 `null`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let nodes = self
+            .loop_of()
+            .into_iter()
+            .enumerate()
+            .map(|(s, loop_idx)| {
+                let is = &self.item_sets()[s];
+                let items = is
+                    .items()
+                    .map(|item| json_str(&item.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "      {{\"data\": {{\"id\": \"I{s}\", \"label\": \"I{s}\", \"items\": [{items}], \"reduce\": {}, \"loop\": {}}}}}",
+                    is.reduces().next().is_some(),
+                    loop_idx.map_or("null".to_string(), |i| i.to_string()),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let edges = self
+            .gotos()
+            .enumerate()
+            .map(|(i, (from, tok, to))| {
+                format!(
+                    "      {{\"data\": {{\"id\": \"e{i}\", \"source\": \"I{from}\", \"target\": \"I{to}\", \"label\": {}, \"terminal\": {}}}}}",
+                    json_str(tok.as_str()),
+                    tok.is_term(),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!(
+            "{{\n  \"elements\": {{\n    \"nodes\": [\n{nodes}\n    ],\n    \"edges\": [\n{edges}\n    ]\n  }}\n}}"
+        )
+    }
+}
+
+#[cfg(feature = "petgraph")]
+impl<'a> Family<'a> {
+    /// 转换 GOTO 图为 [`petgraph::graph::DiGraph`], 节点的权重为状态编号, 边的权重为符号.
+    ///
+    /// 节点按照状态编号加入, 所以 `NodeIndex::new(state)` 就是状态 `state` 对应的节点.
+    #[must_use]
+    pub fn to_petgraph(&self) -> petgraph::graph::DiGraph<StateId, Token<'a>> {
+        let mut graph = petgraph::graph::DiGraph::with_capacity(self.len(), 0);
+        for state in 0..self.len() {
+            graph.add_node(state);
+        }
+        for (from, tok, to) in self.gotos() {
+            graph.add_edge(
+                petgraph::graph::NodeIndex::new(from),
+                petgraph::graph::NodeIndex::new(to),
+                tok,
+            );
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Terminal, Token};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn goto_loops() {
+        let bump = Bump::new();
+        // 右递归 S -> a S 会在 "a" 上形成自环.
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let sccs = family.sccs();
+        assert_eq!(sccs.iter().map(Vec::len).sum::<usize>(), family.len());
+        let loops = family.loops();
+        assert_eq!(loops.len(), 1);
+        let a = Token::from(Terminal::from("a"));
+        assert_eq!(loops[0].tokens, [a]);
+        assert_eq!(loops[0].states.len(), 1);
+        let state = loops[0].states[0];
+        assert_eq!(loops[0].entries, [(0, a, state)]);
+        assert_eq!(
+            loops[0].to_string(),
+            format!("states [I_{state}] repeat [a] entered by [I_0 -- a --> I_{state}]")
+        );
+        let dot = family.to_dot(true);
+        assert!(dot.contains(&format!(
+            "    subgraph cluster_0 {{\n        style=dashed;\n        I{state};\n    }}"
+        )));
+        assert!(dot.contains(&format!("    I0 -> I{state} [label=\"a\"];")));
+    }
+
+    #[test]
+    fn neighborhood() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        assert_eq!(family.neighborhood(family.len(), 1), None);
+        let all = family.neighborhood(0, family.len()).unwrap();
+        assert_eq!(all.states, (0..family.len()).collect::<Vec<_>>());
+        assert_eq!(all.edges, family.gotos().collect::<Vec<_>>());
+        assert!(all.boundary.is_empty());
+
+        let near = family.neighborhood(0, 1).unwrap();
+        let mut expected: Vec<_> = std::iter::once(0)
+            .chain(family.gotos().filter(|e| e.0 == 0).map(|e| e.2))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(near.states, expected);
+        assert!(
+            near.edges
+                .iter()
+                .all(|&(from, _, to)| { near.states.contains(&from) && near.states.contains(&to) })
+        );
+        assert!(!near.boundary.is_empty() && !near.boundary.contains(&0));
+        let dot = near.to_dot();
+        assert!(dot.contains("    I0 [label=\"I0\", penwidth=3];"));
+        assert_eq!(dot.matches(" -> ").count(), near.edges.len());
+        assert_eq!(dot.matches("style=dashed").count(), near.boundary.len());
+    }
+
+    #[test]
+    fn graph_exports() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> < S > | \"", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let edges = family.gotos().count();
+
+        let graphml = family.to_graphml();
+        assert_eq!(graphml.matches("<node id=").count(), family.len());
+        assert_eq!(graphml.matches("<edge id=").count(), edges);
+        assert!(graphml.contains("<data key=\"symbol\">&lt;</data>"));
+        assert!(graphml.contains("S -&gt; &lt; ⋅ S &gt;"));
+
+        let json = family.to_json();
+        assert!(json.starts_with("{\n  \"elements\": {\n    \"nodes\": [\n"));
+        assert_eq!(json.matches("\"source\": ").count(), edges);
+        assert!(json.contains(r#""label": "\"", "terminal": true"#));
+        assert!(
+            json.contains(
+                r#"{"data": {"id": "I0", "label": "I0", "items": ["S -> ⋅ \" 〈eof〉", "#
+            )
+        );
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_conversion() {
+        use petgraph::{algo, graph::NodeIndex};
+
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let graph = family.to_petgraph();
+        assert_eq!(graph.node_count(), family.len());
+        assert_eq!(graph.edge_count(), family.gotos().count());
+        for (from, tok, to) in family.gotos() {
+            let edge = graph
+                .find_edge(NodeIndex::new(from), NodeIndex::new(to))
+                .unwrap();
+            assert_eq!(graph[edge], tok);
+        }
+        assert_eq!(algo::tarjan_scc(&graph).len(), family.sccs().len());
+        // 每个状态都可以从 I_0 到达, 最短路径长度和访问路径相同.
+        let dist = algo::dijkstra(&graph, NodeIndex::new(0), None, |_| 1);
+        for state in 0..family.len() {
+            assert_eq!(
+                dist[&NodeIndex::new(state)],
+                family.access_path(state).unwrap().len()
+            );
+        }
+    }
+}
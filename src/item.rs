@@ -1,12 +1,15 @@
 use std::{
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt::{Debug, Display},
     hash::Hash,
+    sync::Arc,
 };
 
 use crate::{
-    Grammar, Production, Terminal, Token,
+    Grammar, ProdId, Production, Terminal, Token,
+    conflict::ConflictKind,
     error::Error,
+    table::typst_str,
     token::{EOF, EPSILON},
 };
 
@@ -22,8 +25,8 @@ pub struct Item<'a> {
     prod: &'a Production<'a>,
     /// dot 所处的位置, 在 `0..=prod.len()` 范围中, 产生式中的 epsilon 不算长度.
     dot: usize,
-    /// 前瞻字符
-    look_aheads: BTreeSet<Terminal<'a>>,
+    /// 前瞻字符, 内容相同的集合在文法中共享同一份, 见: [`Grammar::intern_look_aheads`].
+    look_aheads: Arc<BTreeSet<Terminal<'a>>>,
 }
 
 impl Debug for Item<'_> {
@@ -54,53 +57,49 @@ impl Debug for Item<'_> {
 
 impl Display for Item<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let tail_s: String = self
-            .prod
-            .tail_without_eps()
-            .enumerate()
-            .map(|(i, t)| format!("{}{} ", if i == self.dot { "⋅ " } else { "" }, t))
-            .collect();
         let look_aheads: String = self.look_aheads.iter().map(|x| format!("{x}, ")).collect();
         f.pad(&format!(
-            "{} -> {} 〈{}〉",
-            self.prod.head(),
-            format!(
-                "{}{}",
-                tail_s.trim_end(),
-                if self.dot == self.prod.len() {
-                    " ⋅"
-                } else {
-                    ""
-                }
-            )
-            .trim(),
+            "{} 〈{}〉",
+            self.core_string(),
             look_aheads.trim_end_matches([',', ' '])
         ))
     }
 }
 
+/// 序列化为产生式, dot 的位置, 有序的前瞻符和项的文本 (同 [`Display`]).
+#[cfg(feature = "serde")]
+impl serde::Serialize for Item<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("Item", 4)?;
+        s.serialize_field("prod", self.prod)?;
+        s.serialize_field("dot", &self.dot)?;
+        s.serialize_field("look_aheads", &*self.look_aheads)?;
+        s.serialize_field("text", &self.to_string())?;
+        s.end()
+    }
+}
+
 impl<'a> Item<'a> {
-    #[allow(dead_code)]
     #[must_use]
     pub(crate) fn new(
         prod: &'a Production<'a>,
         dot: usize,
-        look_aheads: BTreeSet<Terminal<'a>>,
+        look_aheads: impl Into<Arc<BTreeSet<Terminal<'a>>>>,
     ) -> Self {
         Self {
             prod,
             dot,
-            look_aheads,
+            look_aheads: look_aheads.into(),
         }
     }
 
     #[must_use]
-    pub(crate) fn initial(prod: &'a Production<'a>, look_aheads: BTreeSet<Terminal<'a>>) -> Self {
-        Self {
-            prod,
-            dot: 0,
-            look_aheads,
-        }
+    pub(crate) fn initial(
+        prod: &'a Production<'a>,
+        look_aheads: impl Into<Arc<BTreeSet<Terminal<'a>>>>,
+    ) -> Self {
+        Self::new(prod, 0, look_aheads)
     }
 
     #[must_use]
@@ -127,6 +126,17 @@ impl<'a> Item<'a> {
         self.prod.tail_without_eps().skip(self.dot + 1)
     }
 
+    /// dot 之后 (包括 dot 之后第一个) 还没有被识别的 tokens.
+    pub(crate) fn rest(&self) -> impl Iterator<Item = &Token<'a>> {
+        self.prod.tail_without_eps().skip(self.dot)
+    }
+
+    /// dot 所处的位置, 在 `0..=prod.len()` 范围中.
+    #[must_use]
+    pub fn dot(&self) -> usize {
+        self.dot
+    }
+
     /// 获取当前项下一个期望的 [`Token`] (不包括 [`EPSILON`]), 如果处于可以归约的状态, 返回 [`None`]
     #[must_use]
     pub fn expected(&self) -> Option<Token<'a>> {
@@ -155,10 +165,35 @@ impl<'a> Item<'a> {
     }
 
     #[must_use]
-    fn core(&self) -> (&'a Production<'a>, usize) {
+    pub(crate) fn core(&self) -> (&'a Production<'a>, usize) {
         (self.prod, self.dot)
     }
 
+    /// 项的核心 (产生式和 dot 的位置) 的文本, 不带前瞻符, 例如 `S -> a ⋅ S`.
+    #[must_use]
+    pub fn core_string(&self) -> String {
+        let tail_s: String = self
+            .prod
+            .tail_without_eps()
+            .enumerate()
+            .map(|(i, t)| format!("{}{} ", if i == self.dot { "⋅ " } else { "" }, t))
+            .collect();
+        format!(
+            "{} -> {}",
+            self.prod.head(),
+            format!(
+                "{}{}",
+                tail_s.trim_end(),
+                if self.dot == self.prod.len() {
+                    " ⋅"
+                } else {
+                    ""
+                }
+            )
+            .trim()
+        )
+    }
+
     #[must_use]
     pub fn prod(&self) -> &'a Production<'a> {
         self.prod
@@ -184,6 +219,16 @@ impl Debug for ItemSet<'_> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for ItemSet<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("ItemSet", 1)?;
+        s.serialize_field("items", &self.items)?;
+        s.end()
+    }
+}
+
 impl PartialEq for ItemSet<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.items == other.items
@@ -221,28 +266,30 @@ impl<'a> ItemSet<'a> {
         if start_prod.len() != 1 {
             Err(Error::GrammarNotAugmented)?
         }
-        let item = Item::initial(start_prod.into_iter().next().unwrap(), [EOF].into());
-        Ok(Self {
+        let Some(start_prod) = start_prod.into_iter().next() else {
+            Err(Error::GrammarNotAugmented)?
+        };
+        let item = Item::initial(start_prod, grammar.intern_look_aheads([EOF].into()));
+        Self {
             grammar,
             items: [item].into(),
         }
-        .closure())
+        .closure()
     }
 
     /// 合并具有相同核心, 但是不同 [`look_aheads`] 的项
     #[must_use]
     fn merge(self) -> Self {
-        let mut map: HashMap<(&Production<'_>, usize), Vec<_>> = HashMap::new();
+        let mut map: HashMap<(&'a Production<'a>, usize), BTreeSet<Terminal<'a>>> = HashMap::new();
         for item in self.items {
-            map.entry(item.core()).or_default().push(item);
+            map.entry(item.core())
+                .or_default()
+                .extend(item.look_aheads.iter());
         }
         let items = map
-            .into_values()
-            .filter_map(|v| {
-                v.into_iter().reduce(|mut a, b| {
-                    a.look_aheads.extend(b.look_aheads);
-                    a
-                })
+            .into_iter()
+            .map(|((prod, dot), look_aheads)| {
+                Item::new(prod, dot, self.grammar.intern_look_aheads(look_aheads))
             })
             .collect();
         Self {
@@ -252,8 +299,10 @@ impl<'a> ItemSet<'a> {
     }
 
     /// 获取当前项集的闭包项集
-    #[must_use]
-    fn closure(self) -> Self {
+    ///
+    /// # Errors
+    /// 见: [`Grammar::first_set`].
+    fn closure(self) -> Result<Self, Error> {
         let mut items = self.items.clone();
         loop {
             let mut new_items = BTreeSet::new();
@@ -263,14 +312,15 @@ impl<'a> ItemSet<'a> {
                 };
                 let mut look_aheads: BTreeSet<_> = self
                     .grammar
-                    .first_set(item.future_seq().copied())
-                    .unwrap()
-                    .into_iter()
+                    .suffix_first(item.prod, item.dot + 1)?
+                    .iter()
+                    .copied()
                     .collect();
                 if look_aheads.contains(&EPSILON) {
                     look_aheads.remove(&EPSILON);
-                    look_aheads.extend(&item.look_aheads);
+                    look_aheads.extend(item.look_aheads.iter());
                 }
+                let look_aheads = self.grammar.intern_look_aheads(look_aheads);
                 let prods = self.grammar.prods_of(nt);
                 new_items.insert(item.clone());
                 for prod in prods {
@@ -282,26 +332,30 @@ impl<'a> ItemSet<'a> {
             }
             items.extend(new_items);
         }
-        Self {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(kernel = self.items.len(), items = items.len(), "closure");
+        Ok(Self {
             items,
             grammar: self.grammar,
         }
-        .merge()
+        .merge())
     }
 
-    #[must_use]
-    pub fn goto(&self, token: Token<'a>) -> Option<Self> {
+    /// 计算 GOTO(self, token), 如果没有项能够接受 `token`, 那么返回 `Ok(None)`.
+    ///
+    /// # Errors
+    /// 见: [`Grammar::first_set`].
+    pub fn goto(&self, token: Token<'a>) -> Result<Option<Self>, Error> {
         let items: BTreeSet<Item<'a>> = self.items.iter().filter_map(|i| i.goto(token)).collect();
         if items.is_empty() {
-            None
+            Ok(None)
         } else {
-            Some(
-                Self {
-                    grammar: self.grammar,
-                    items,
-                }
-                .closure(),
-            )
+            Self {
+                grammar: self.grammar,
+                items,
+            }
+            .closure()
+            .map(Some)
         }
     }
 
@@ -309,6 +363,26 @@ impl<'a> ItemSet<'a> {
         self.items.iter()
     }
 
+    /// 按照产生式分组的项, 组按照产生式的编号 (也就是文法中的声明顺序) 排列,
+    /// 同一个产生式的项 (dot 位置或者前瞻符不同) 在同一组中.
+    #[must_use]
+    pub fn items_by_production(&self) -> Vec<(ProdId, Vec<&Item<'a>>)> {
+        let mut groups: BTreeMap<ProdId, Vec<&Item<'a>>> = BTreeMap::new();
+        for item in &self.items {
+            let id = self
+                .grammar
+                .index_of_prod(item.prod())
+                .unwrap_or(ProdId::MAX);
+            groups.entry(id).or_default().push(item);
+        }
+        groups.into_iter().collect()
+    }
+
+    #[must_use]
+    pub(crate) fn grammar(&self) -> &'a Grammar<'a> {
+        self.grammar
+    }
+
     pub fn reduces(&self) -> impl Iterator<Item = (&Item<'a>, Terminal<'a>)> {
         self.items
             .iter()
@@ -317,11 +391,16 @@ impl<'a> ItemSet<'a> {
     }
 }
 
+/// 项集状态编号, 即 [`Family::item_sets`] 中的下标, 也是 ACTION / GOTO 表的行号.
+pub type StateId = usize;
+
 #[derive(Debug)]
 pub struct Family<'a> {
-    item_sets: Vec<&'a ItemSet<'a>>,
-    #[allow(dead_code)]
-    item_set_idxes: HashMap<&'a ItemSet<'a>, usize>,
+    /// 构建项集族的文法.
+    grammar: &'a Grammar<'a>,
+    /// 项集由项集族持有, 不分配在文法的 arena 中, 所以构建项集族只需要 `&Grammar`, 可以在多个线程中同时进行.
+    item_sets: Vec<Arc<ItemSet<'a>>>,
+    item_set_idxes: HashMap<Arc<ItemSet<'a>>, usize>,
     /// 描述了 goto 动作.
     ///
     /// # Note
@@ -331,26 +410,37 @@ pub struct Family<'a> {
     gotos: HashMap<usize, BTreeMap<Token<'a>, BTreeSet<usize>>>,
 }
 
+/// 序列化为 [`crate::owned::OwnedFamily`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for Family<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::owned::OwnedFamily::from_family(self).serialize(serializer)
+    }
+}
+
 impl<'a> Family<'a> {
     /// 从 `grammar` 构建规范 LR(1) 项集族.
-    #[must_use]
-    pub fn from_grammar(grammar: &'a Grammar<'a>) -> Self {
-        let bump = grammar.bump();
-        let i0 = &*bump.alloc(ItemSet::initial(grammar).unwrap());
+    ///
+    /// # Errors
+    /// - [`Error::GrammarNotAugmented`] `grammar` 没有增广.
+    /// - 其他见: [`Grammar::first_set`].
+    pub fn from_grammar(grammar: &'a Grammar<'a>) -> Result<Self, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("family", prods = grammar.prods().len()).entered();
+        let i0 = Arc::new(ItemSet::initial(grammar)?);
         #[allow(clippy::mutable_key_type)]
         let mut item_sets_idx = HashMap::new();
         let mut item_sets = Vec::new();
         let mut gotos: HashMap<usize, BTreeMap<Token<'a>, BTreeSet<usize>>> = HashMap::new();
-        item_sets_idx.insert(i0, 0);
+        item_sets_idx.insert(Arc::clone(&i0), 0);
         item_sets.push(i0);
         loop {
             let mut new_item_sets = Vec::new();
             for (from, is) in item_sets.iter().enumerate() {
                 for &tok in grammar.tokens() {
-                    let Some(nis) = is.goto(tok) else {
+                    let Some(nis) = is.goto(tok)? else {
                         continue;
                     };
-                    let nis = &*bump.alloc(nis);
                     if let Some(&to) = item_sets_idx.get(&nis) {
                         gotos
                             .entry(from)
@@ -369,8 +459,10 @@ impl<'a> Family<'a> {
                             .entry(tok)
                             .or_default()
                             .insert(to);
-                        // println!("{:?}, {}, {}", tok, from, to);
-                        new_item_sets.push(nis);
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(from, to, %tok, items = nis.items.len(), "state discovered");
+                        let nis = Arc::new(nis);
+                        new_item_sets.push(Arc::clone(&nis));
                         item_sets_idx.insert(nis, to);
                     }
                 }
@@ -381,30 +473,227 @@ impl<'a> Family<'a> {
             }
             item_sets.extend(new_item_sets);
         }
-        Self {
+        #[cfg(feature = "tracing")]
+        tracing::info!(states = item_sets.len(), "family built");
+        Ok(Self {
+            grammar,
             item_set_idxes: item_sets_idx,
             item_sets,
             gotos,
+        })
+    }
+
+    /// 使用 Pager 的弱相容 (weak compatibility) 合并构建最小 LR(1) 项集族.
+    ///
+    /// 构造过程和 [`Family::from_grammar`] 相同, 但是 GOTO 得到的项集如果和已有的同核心项集弱相容,
+    /// 就合并前瞻符而不是加入新的项集, 前瞻符变多的项集重新计算出边. 两个同核心项集弱相容, 当且仅当对于任意两个不同的内核项
+    /// i, j, 交叉的前瞻符 (L1_i ∩ L2_j) ∪ (L2_i ∩ L1_j) 为空, 或者 L1_i ∩ L1_j, L2_i ∩ L2_j 之一不为空 (冲突在合并前已经存在).
+    ///
+    /// 合并不会引入规范 LR(1) 中没有的冲突, 分析表的行为和规范 LR(1) 相同, 状态数量接近 LALR(1):
+    /// LALR(1) 合并会引入归约/归约冲突的项集保持分开.
+    ///
+    /// # Errors
+    /// 见: [`Family::from_grammar`].
+    pub fn from_grammar_minimal(grammar: &'a Grammar<'a>) -> Result<Self, Error> {
+        type Core<'a> = BTreeSet<(&'a Production<'a>, usize)>;
+        let kernel = |is: &ItemSet<'a>| -> BTreeMap<_, _> {
+            is.kernel()
+                .map(|item| (item.core(), item.look_aheads.clone()))
+                .collect()
+        };
+        let compatible = |a: &ItemSet<'a>, b: &ItemSet<'a>| {
+            let (a, b) = (kernel(a), kernel(b));
+            let la: Vec<_> = a.values().collect();
+            let lb: Vec<_> = b.values().collect();
+            let meets = |x: &BTreeSet<Terminal<'a>>, y: &BTreeSet<Terminal<'a>>| !x.is_disjoint(y);
+            (0..la.len()).all(|i| {
+                (i + 1..la.len()).all(|j| {
+                    !(meets(la[i], lb[j]) || meets(lb[i], la[j]))
+                        || meets(la[i], la[j])
+                        || meets(lb[i], lb[j])
+                })
+            })
+        };
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("minimal_family", prods = grammar.prods().len()).entered();
+        let mut states = vec![ItemSet::initial(grammar)?];
+        let mut by_core: HashMap<Core<'a>, Vec<StateId>> = HashMap::new();
+        by_core.insert(kernel(&states[0]).into_keys().collect(), vec![0]);
+        let mut gotos: Vec<BTreeMap<Token<'a>, StateId>> = vec![BTreeMap::new()];
+        let mut pending = VecDeque::from([0]);
+        while let Some(from) = pending.pop_front() {
+            for &tok in grammar.tokens() {
+                let Some(nis) = states[from].goto(tok)? else {
+                    continue;
+                };
+                let core: Core<'a> = kernel(&nis).into_keys().collect();
+                let same_core = by_core.entry(core).or_default();
+                let found = same_core
+                    .iter()
+                    .copied()
+                    .find(|&to| compatible(&states[to], &nis));
+                let to = if let Some(to) = found {
+                    let merged = ItemSet {
+                        grammar,
+                        items: states[to].items.iter().chain(&nis.items).cloned().collect(),
+                    }
+                    .merge();
+                    if merged != states[to] {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(from, to, %tok, "look-aheads merged");
+                        states[to] = merged;
+                        pending.push_back(to);
+                    }
+                    to
+                } else {
+                    let to = states.len();
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(from, to, %tok, items = nis.items.len(), "state discovered");
+                    same_core.push(to);
+                    states.push(nis);
+                    gotos.push(BTreeMap::new());
+                    pending.push_back(to);
+                    to
+                };
+                gotos[from].insert(tok, to);
+            }
+        }
+
+        // 合并之后重新计算出边可能使一些项集不再可达, 只保留从 I_0 可达的项集, 按照广度优先的顺序编号.
+        let mut new_of: HashMap<StateId, StateId> = HashMap::from([(0, 0)]);
+        let mut order = vec![0];
+        let mut i = 0;
+        while i < order.len() {
+            for &to in gotos[order[i]].values() {
+                let next = order.len();
+                if *new_of.entry(to).or_insert(next) == next {
+                    order.push(to);
+                }
+            }
+            i += 1;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            states = order.len(),
+            unreachable = states.len() - order.len(),
+            "family built"
+        );
+        let item_sets: Vec<Arc<ItemSet<'a>>> = order
+            .iter()
+            .map(|&old| Arc::new(states[old].clone()))
+            .collect();
+        Ok(Self {
+            grammar,
+            item_set_idxes: item_sets
+                .iter()
+                .enumerate()
+                .map(|(state, is)| (Arc::clone(is), state))
+                .collect(),
+            gotos: order
+                .iter()
+                .enumerate()
+                .filter(|(_, old)| !gotos[**old].is_empty())
+                .map(|(new, &old)| {
+                    let edges = gotos[old]
+                        .iter()
+                        .map(|(&tok, to)| (tok, [new_of[to]].into()))
+                        .collect();
+                    (new, edges)
+                })
+                .collect(),
+            item_sets,
+        })
+    }
+
+    /// 按照和构造顺序无关的规范顺序重新编号所有项集, 使快照测试和不同运行之间的输出逐字节稳定.
+    ///
+    /// 每个内核项 (见: [`ItemSet::kernel`]) 表示为 (产生式编号, dot 位置, 前瞻符), 项集按照排序后的内核项列表的字典序编号.
+    /// I_0 的内核项是增广产生式的初始项, 总是排在最前面, 所以 I_0 的编号不变.
+    #[must_use]
+    pub fn canonicalize(self) -> Self {
+        let key = |is: &ItemSet<'a>| {
+            let grammar = is.grammar();
+            let mut kernel: Vec<_> = is
+                .kernel()
+                .map(|item| {
+                    let prod = grammar.index_of_prod(item.prod()).unwrap_or(ProdId::MAX);
+                    (prod, item.dot(), item.look_aheads().clone())
+                })
+                .collect();
+            kernel.sort();
+            kernel
+        };
+        let mut order: Vec<StateId> = (0..self.len()).collect();
+        order.sort_by_cached_key(|&state| key(&self.item_sets[state]));
+        let mut new_of = vec![0; self.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_of[old] = new;
+        }
+        let item_sets: Vec<_> = order
+            .iter()
+            .map(|&old| Arc::clone(&self.item_sets[old]))
+            .collect();
+        Self {
+            grammar: self.grammar,
+            item_set_idxes: item_sets
+                .iter()
+                .enumerate()
+                .map(|(state, is)| (Arc::clone(is), state))
+                .collect(),
+            item_sets,
+            gotos: self
+                .gotos
+                .into_iter()
+                .map(|(from, edges)| {
+                    let edges = edges
+                        .into_iter()
+                        .map(|(tok, dests)| (tok, dests.into_iter().map(|to| new_of[to]).collect()))
+                        .collect();
+                    (new_of[from], edges)
+                })
+                .collect(),
         }
     }
 
+    /// 构建项集族的文法.
+    #[must_use]
+    pub fn grammar(&self) -> &'a Grammar<'a> {
+        self.grammar
+    }
+
     /// 按照 I_i (i = 0, 1, 2, 3...) 顺序获取项集.
     #[must_use]
-    pub fn item_sets(&self) -> &[&'a ItemSet<'a>] {
+    pub fn item_sets(&self) -> &[Arc<ItemSet<'a>>] {
         &self.item_sets
     }
 
+    /// 获取项集 I_`state`, 编号超出范围时返回 [`None`].
     #[must_use]
-    pub fn index_of_item_set(&self, item_set: &ItemSet) -> Option<usize> {
+    pub fn item_set(&self, state: StateId) -> Option<&ItemSet<'a>> {
+        self.item_sets.get(state).map(|is| &**is)
+    }
+
+    /// 项集的状态编号, 项集族中没有这个项集时返回 [`None`].
+    #[must_use]
+    pub fn state_of(&self, item_set: &ItemSet) -> Option<StateId> {
         // 参数只需要满足此函数调用的生命周期即可, 不需要 'a 生命周期.
         let item_set = unsafe { std::mem::transmute::<&ItemSet, &ItemSet<'a>>(item_set) };
         self.item_set_idxes.get(item_set).copied()
     }
 
-    /// 遍历 gotos (起始项集, 转换 Token, 到达项集).
+    /// 同 [`Family::state_of`].
+    #[must_use]
+    pub fn index_of_item_set(&self, item_set: &ItemSet) -> Option<usize> {
+        self.state_of(item_set)
+    }
+
+    /// 遍历 gotos (起始项集, 转换 Token, 到达项集), 按照起始项集编号和 Token 排序.
     pub fn gotos(&self) -> impl Iterator<Item = (usize, Token<'a>, usize)> {
-        self.gotos.iter().flat_map(|(&from, v)| {
-            v.iter()
+        (0..self.len()).flat_map(move |from| {
+            self.gotos_of(from)
+                .into_iter()
+                .flatten()
                 .flat_map(move |(&tok, dests)| dests.iter().map(move |&to| (from, tok, to)))
         })
     }
@@ -417,6 +706,64 @@ impl<'a> Family<'a> {
         self.gotos.get(&item_set)
     }
 
+    /// 从 I_0 到达项集状态 `state` 的最短路径, 每个元素为 (经过的 Token, 到达的项集状态),
+    /// 路径上 Token 组成的序列就是到达这个状态的最短活前缀.
+    ///
+    /// 如果 `state` 不存在, 那么返回 [`None`], I_0 的路径为空.
+    #[must_use]
+    pub fn access_path(&self, state: StateId) -> Option<Vec<(Token<'a>, StateId)>> {
+        if state >= self.len() {
+            return None;
+        }
+        // 广度优先搜索, 记录到达每个状态的前驱.
+        let mut prev: HashMap<StateId, (StateId, Token<'a>)> = HashMap::new();
+        let mut queue = VecDeque::from([0]);
+        while let Some(from) = queue.pop_front() {
+            if from == state {
+                break;
+            }
+            for (&tok, dests) in self.gotos_of(from).into_iter().flatten() {
+                for &to in dests {
+                    if to != 0 && !prev.contains_key(&to) {
+                        prev.insert(to, (from, tok));
+                        queue.push_back(to);
+                    }
+                }
+            }
+        }
+        let mut path = Vec::new();
+        let mut cur = state;
+        while cur != 0 {
+            let &(from, tok) = prev.get(&cur)?;
+            path.push((tok, cur));
+            cur = from;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// 使用 Typst 形式输出所有项集, 每个项集包括其中的项以及出边.
+    #[must_use]
+    pub fn to_typst(&self) -> String {
+        let mut lines = Vec::new();
+        for (from, is) in self.item_sets.iter().enumerate() {
+            lines.push(format!("=== $I_({from})$"));
+            for item in is.items() {
+                lines.push(format!("- #raw({})", typst_str(&item.to_string())));
+            }
+            for (tok, dests) in self.gotos_of(from).into_iter().flatten() {
+                for to in dests {
+                    lines.push(format!(
+                        "- $I_({from})$ #sym.arrow.r.long#super(raw({})) $I_({to})$",
+                        typst_str(tok.as_str())
+                    ));
+                }
+            }
+            lines.push(String::new());
+        }
+        lines.join("\n").trim_end().to_string()
+    }
+
     /// 获取项集族数量
     #[must_use]
     pub fn len(&self) -> usize {
@@ -429,19 +776,346 @@ impl<'a> Family<'a> {
     }
 }
 
+/// LR(0) 项, 没有前瞻符.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lr0Item<'a> {
+    prod: &'a Production<'a>,
+    /// dot 所处的位置, 在 `0..=prod.len()` 范围中.
+    dot: usize,
+}
+
+impl Display for Lr0Item<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut words: Vec<_> = self
+            .prod
+            .tail_without_eps()
+            .map(ToString::to_string)
+            .collect();
+        words.insert(self.dot, "⋅".to_string());
+        f.pad(&format!("{} -> {}", self.prod.head(), words.join(" ")))
+    }
+}
+
+impl<'a> Lr0Item<'a> {
+    #[must_use]
+    pub fn prod(&self) -> &'a Production<'a> {
+        self.prod
+    }
+
+    #[must_use]
+    pub fn dot(&self) -> usize {
+        self.dot
+    }
+
+    /// dot 之后的第一个 [`Token`], 可以归约时返回 [`None`].
+    #[must_use]
+    pub fn expected(&self) -> Option<Token<'a>> {
+        self.prod.tail_without_eps().nth(self.dot).copied()
+    }
+}
+
+/// LR(0) 项集族中一个状态的冲突: 完成项和 dot 之后是终结符的项同时存在 (移入/归约),
+/// 或者有多个完成项 (归约/归约). LR(0) 在所有终结符上归约, 所以冲突和前瞻符无关.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lr0Conflict<'a> {
+    pub state: StateId,
+    pub kind: ConflictKind,
+    /// 可以归约的产生式编号.
+    pub prods: Vec<ProdId>,
+    /// 移入/归约冲突中可以移入的终结符.
+    pub shifts: Vec<Terminal<'a>>,
+}
+
+impl Display for Lr0Conflict<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prods: Vec<_> = self.prods.iter().map(|p| format!("r{p}")).collect();
+        write!(
+            f,
+            "I_{}: {} conflict, {}",
+            self.state,
+            self.kind,
+            prods.join(", ")
+        )?;
+        if !self.shifts.is_empty() {
+            let shifts: Vec<_> = self.shifts.iter().map(Terminal::as_str).collect();
+            write!(f, ", shift {}", shifts.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// LR(0) 项集族 (LR(0) 自动机), 和 [`Family`] 的构造方式相同, 但是项没有前瞻符.
+///
+/// 状态和 LR(1) 项集按照核心合并的结果一一对应 (编号可能不同), 见: [`crate::compare`].
+#[derive(Debug, Clone)]
+pub struct Lr0Family<'a> {
+    grammar: &'a Grammar<'a>,
+    item_sets: Vec<BTreeSet<Lr0Item<'a>>>,
+    /// 每个状态的出边.
+    gotos: Vec<BTreeMap<Token<'a>, StateId>>,
+}
+
+impl<'a> Lr0Family<'a> {
+    /// 从已经增广的 `grammar` 构建 LR(0) 项集族.
+    ///
+    /// # Errors
+    /// - [`Error::GrammarNotAugmented`] `grammar` 没有增广.
+    pub fn from_grammar(grammar: &'a Grammar<'a>) -> Result<Self, Error> {
+        let start = grammar.prods_of(grammar.symbol_start());
+        if start.len() != 1 {
+            Err(Error::GrammarNotAugmented)?
+        }
+        let Some(start) = start.into_iter().next() else {
+            Err(Error::GrammarNotAugmented)?
+        };
+        let closure = |mut items: BTreeSet<Lr0Item<'a>>| {
+            let mut pending: Vec<_> = items.iter().copied().collect();
+            while let Some(item) = pending.pop() {
+                let Some(Token::NonTerminal(nt)) = item.expected() else {
+                    continue;
+                };
+                for prod in grammar.prods_of(nt) {
+                    let new = Lr0Item { prod, dot: 0 };
+                    if items.insert(new) {
+                        pending.push(new);
+                    }
+                }
+            }
+            items
+        };
+        let i0 = closure(
+            [Lr0Item {
+                prod: start,
+                dot: 0,
+            }]
+            .into(),
+        );
+        let mut family = Self {
+            grammar,
+            item_sets: vec![i0.clone()],
+            gotos: Vec::new(),
+        };
+        let mut idxes = HashMap::from([(i0, 0)]);
+        let mut from = 0;
+        while from < family.item_sets.len() {
+            let mut edges = BTreeMap::new();
+            for &tok in grammar.tokens() {
+                let moved: BTreeSet<_> = family.item_sets[from]
+                    .iter()
+                    .filter(|item| item.expected() == Some(tok))
+                    .map(|item| Lr0Item {
+                        prod: item.prod,
+                        dot: item.dot + 1,
+                    })
+                    .collect();
+                if moved.is_empty() {
+                    continue;
+                }
+                let to_set = closure(moved);
+                let next = family.item_sets.len();
+                let to = *idxes.entry(to_set.clone()).or_insert_with(|| {
+                    family.item_sets.push(to_set);
+                    next
+                });
+                edges.insert(tok, to);
+            }
+            family.gotos.push(edges);
+            from += 1;
+        }
+        Ok(family)
+    }
+
+    /// 按照 I_i (i = 0, 1, 2, 3...) 顺序获取项集.
+    #[must_use]
+    pub fn item_sets(&self) -> &[BTreeSet<Lr0Item<'a>>] {
+        &self.item_sets
+    }
+
+    /// 项集 I_`state` 的出边, 编号超出范围时返回 [`None`].
+    #[must_use]
+    pub fn gotos_of(&self, state: StateId) -> Option<&BTreeMap<Token<'a>, StateId>> {
+        self.gotos.get(state)
+    }
+
+    /// 所有状态中的冲突, 按照状态编号排列, 一个状态同时有两种冲突时只报告为移入/归约冲突.
+    ///
+    /// 增广产生式的完成项只在 EOF 上接受, 不算作归约.
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<Lr0Conflict<'a>> {
+        let mut conflicts = Vec::new();
+        for (state, items) in self.item_sets.iter().enumerate() {
+            let prods: Vec<_> = items
+                .iter()
+                .filter(|item| item.expected().is_none())
+                .filter_map(|item| self.grammar.index_of_prod(item.prod))
+                // 增广产生式的完成项是接受, 不是归约.
+                .filter(|&p| self.grammar.declared_index(p).is_some())
+                .collect();
+            let shifts: Vec<_> = self.gotos[state]
+                .keys()
+                .filter_map(|tok| match tok {
+                    Token::Terminal(t) => Some(*t),
+                    Token::NonTerminal(_) => None,
+                })
+                .collect();
+            let kind = match (prods.len(), shifts.is_empty()) {
+                (0, _) | (1, true) => continue,
+                (_, false) => ConflictKind::ShiftReduce,
+                (_, true) => ConflictKind::ReduceReduce,
+            };
+            conflicts.push(Lr0Conflict {
+                state,
+                kind,
+                prods,
+                shifts,
+            });
+        }
+        conflicts
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.item_sets.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.item_sets.is_empty()
+    }
+}
+
+/// 每个项集输出 `I_i:`, 之后每行一个项, 最后每行一条出边 `X => I_j`.
+impl Display for Lr0Family<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (state, items) in self.item_sets.iter().enumerate() {
+            writeln!(f, "I_{state}:")?;
+            for item in items {
+                writeln!(f, "  {item}")?;
+            }
+            for (tok, to) in &self.gotos[state] {
+                writeln!(f, "  {tok} => I_{to}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeSet;
+    use std::{collections::BTreeSet, sync::Arc};
 
     use bumpalo::Bump;
 
     use crate::{
-        Family, Grammar, NonTerminal, Production, Terminal, Token,
-        item::{Item, ItemSet},
+        Family, Grammar, NonTerminal, Parser, Production, Table, Terminal, Token,
+        conflict::ConflictKind,
+        item::{Item, ItemSet, Lr0Family},
         token::{EOF, EPSILON},
     };
     use pretty_assertions::assert_eq;
 
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use std::sync::{Arc, Mutex};
+
+        /// 把日志写入共享的缓冲区.
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let bump = Bump::new();
+            let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+                .unwrap()
+                .augmented();
+            Family::from_grammar_minimal(&grammar).unwrap();
+            let grammar = Grammar::from_cfg("T -> T + T | n", "T".into(), &bump)
+                .unwrap()
+                .augmented();
+            let family = Family::from_grammar(&grammar).unwrap();
+            Table::build_from(&family).unwrap();
+        });
+        let log = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(log.contains("state discovered"));
+        assert!(log.contains("look-aheads merged"));
+        assert!(log.contains("family built"));
+        // 填表时立即报告冲突, 之后报告它有没有被解决.
+        let conflict = log.find(" conflict ").unwrap();
+        let resolution = log.find("conflict resolution").unwrap();
+        assert!(conflict < resolution);
+        assert!(log[conflict..].contains("term=+"));
+        assert!(log.contains("table built"));
+    }
+
+    #[test]
+    fn lr0_family() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Lr0Family::from_grammar(&grammar).unwrap();
+        // LR(1) 项集族有 10 个状态, 按照核心合并之后是 7 个.
+        assert_eq!(family.len(), 7);
+        assert!(family.conflicts().is_empty());
+        let i0: Vec<_> = family.item_sets()[0]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(i0.len(), 4);
+        assert!(i0.contains(&"Sprime -> ⋅ S".to_string()));
+        assert!(i0.contains(&"C -> ⋅ c C".to_string()));
+
+        let grammar = Grammar::from_cfg(
+            "expr -> expr + term | term\nterm -> term * id | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Lr0Family::from_grammar(&grammar).unwrap();
+        // `exprprime -> expr ⋅` 是接受, 和移入 `+` 不冲突.
+        let conflicts = family.conflicts();
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "I_3: shift/reduce conflict, r2, shift *",
+                "I_6: shift/reduce conflict, r1, shift *",
+            ]
+        );
+
+        let grammar = Grammar::from_cfg("S -> A | B\nA -> x\nB -> x", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let conflicts = Lr0Family::from_grammar(&grammar).unwrap().conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::ReduceReduce);
+        assert!(
+            Lr0Family::from_grammar(&Grammar::from_cfg("S -> a | b", "S".into(), &bump).unwrap())
+                .is_err()
+        );
+    }
+
     #[test]
     fn closure() {
         let bump = Bump::new();
@@ -476,12 +1150,12 @@ mod test {
     fn goto_basic_transition() {
         let bump = Bump::new();
         // 原文法:
-        // S -> E
-        // E -> a
+        // S -> A
+        // A -> a
         //
         // 增广后:
         // Sprime -> S
-        let grammar = Grammar::from_cfg("S -> E\nE -> a", "S".into(), &bump)
+        let grammar = Grammar::from_cfg("S -> A\nA -> a", "S".into(), &bump)
             .unwrap()
             .augmented();
 
@@ -492,17 +1166,17 @@ mod test {
         // 手动构造产生式对象以便验证
         let prod_sprime_s =
             Production::new("Sprime".into(), [NonTerminal::from("S").into()].into());
-        let prod_s_e = Production::new("S".into(), [NonTerminal::from("E").into()].into());
-        let prod_e_a = Production::new("E".into(), [Terminal::from("a").into()].into());
+        let prod_s_a = Production::new("S".into(), [NonTerminal::from("A").into()].into());
+        let prod_a_a = Production::new("A".into(), [Terminal::from("a").into()].into());
 
         // 测试 1: 针对原始起始符 S 的 Goto (测试增广产生式的移动)
         // I0 包含:
         // - Sprime -> . S {EOF}
-        // - S -> . E {EOF}
-        // - E -> . a {EOF}
+        // - S -> . A {EOF}
+        // - A -> . a {EOF}
         // 期望结果: Sprime -> S . {EOF}
         let token_s = Token::from(NonTerminal::from("S"));
-        let next_state_s = i0.goto(token_s).expect("Should goto S");
+        let next_state_s = i0.goto(token_s).unwrap().expect("Should goto S");
 
         assert_eq!(
             next_state_s,
@@ -512,31 +1186,31 @@ mod test {
             }
         );
 
-        // 测试 2: 针对非终结符 E 的 Goto
-        // I0 包含: S -> . E {EOF} (来自于 Sprime -> . S 的闭包)
-        // 期望结果: S -> E . {EOF}
-        let token_e = NonTerminal::from("E");
-        let next_state_e = i0.goto(token_e.into()).expect("Should goto E");
+        // 测试 2: 针对非终结符 A 的 Goto
+        // I0 包含: S -> . A {EOF} (来自于 Sprime -> . S 的闭包)
+        // 期望结果: S -> A . {EOF}
+        let token_nt = NonTerminal::from("A");
+        let next_state_nt = i0.goto(token_nt.into()).unwrap().expect("Should goto A");
 
         assert_eq!(
-            next_state_e,
+            next_state_nt,
             ItemSet {
                 grammar: &grammar,
-                items: [Item::new(&prod_s_e, 1, eof_la())].into() // S -> E .
+                items: [Item::new(&prod_s_a, 1, eof_la())].into() // S -> A .
             }
         );
 
         // 测试 3: 针对终结符 a 的 Goto
-        // I0 包含: E -> . a {EOF}
-        // 期望结果: E -> a . {EOF}
+        // I0 包含: A -> . a {EOF}
+        // 期望结果: A -> a . {EOF}
         let token_a = Terminal::from("a");
-        let next_state_a = i0.goto(token_a.into()).expect("Should goto a");
+        let next_state_a = i0.goto(token_a.into()).unwrap().expect("Should goto a");
 
         assert_eq!(
             next_state_a,
             ItemSet {
                 grammar: &grammar,
-                items: [Item::new(&prod_e_a, 1, eof_la())].into() // E -> a .
+                items: [Item::new(&prod_a_a, 1, eof_la())].into() // A -> a .
             }
         );
     }
@@ -569,7 +1243,7 @@ mod test {
 
         // 执行 GOTO(I0, stmt)
         // 移动的是项 3 和 4
-        let i1 = i0.goto(stmt.into()).expect("Should goto stmt");
+        let i1 = i0.goto(stmt.into()).unwrap().expect("Should goto stmt");
 
         let stmts = NonTerminal::from("stmts");
         let eof_la: fn() -> BTreeSet<_> = || [EOF].into();
@@ -631,7 +1305,7 @@ mod test {
 
         // 2. 测试 GOTO(I0, a)
         // 从 A -> . a {b} 移动
-        let i_next = i0.goto(a_term.into()).unwrap();
+        let i_next = i0.goto(a_term.into()).unwrap().unwrap();
 
         // 期望: A -> a . {b}
         // 即使有 Sprime -> S，也不应该影响这里底层的 Lookahead 传递
@@ -658,7 +1332,7 @@ mod test {
         )
         .unwrap()
         .augmented();
-        let family = Family::from_grammar(&grammar);
+        let family = Family::from_grammar(&grammar).unwrap();
         let program = NonTerminal::from("program");
         let programprime = NonTerminal::from("programprime");
         let stmts = NonTerminal::from("stmts");
@@ -730,20 +1404,32 @@ mod test {
                     .into()
                 }
             ]
-            .iter()
-            .collect::<Vec<_>>()
+            .map(Arc::new)
         );
     }
 
     #[test]
     fn epsilon_prod() {
         let prod = Production::new("head".into(), [EPSILON.into()].into());
-        let item = Item::initial(&prod, [EOF].into());
+        let item = Item::initial(&prod, BTreeSet::from([EOF]));
         assert_eq!(item.expected(), None);
         assert_eq!(item.goto(EPSILON.into()), None);
         assert_eq!(format!("{}", item), r#"head -> ⋅ 〈eof〉"#);
     }
 
+    #[test]
+    fn core_string() {
+        // 终结符中含有 ` 〈` 时仍然只输出核心.
+        let prod = Production::new("S".into(), [Terminal::from("a 〈b").into()].into());
+        let item = Item::new(&prod, 1, BTreeSet::from([EOF]));
+        assert_eq!(item.core_string(), "S -> a 〈b ⋅");
+        assert_eq!(item.to_string(), "S -> a 〈b ⋅ 〈eof〉");
+        assert_eq!(
+            Item::initial(&prod, BTreeSet::new()).core_string(),
+            "S -> ⋅ a 〈b"
+        );
+    }
+
     #[test]
     fn family_of_complex_cfg() {
         let bump = Bump::new();
@@ -766,7 +1452,7 @@ simpleexpr -> ID | NUM | ( arithexpr )"#,
             &bump,
         )
         .unwrap();
-        let family = Family::from_grammar(&grammar);
+        let family = Family::from_grammar(&grammar).unwrap();
         assert_eq!(
             family.gotos_of(42),
             Some(
@@ -781,4 +1467,152 @@ simpleexpr -> ID | NUM | ( arithexpr )"#,
             )
         );
     }
+
+    #[test]
+    fn item_set_lookup() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        for (state, is) in family.item_sets().iter().enumerate() {
+            assert_eq!(family.item_set(state), Some(&**is));
+            assert_eq!(family.state_of(is), Some(state));
+        }
+        assert_eq!(family.item_set(family.len()), None);
+        // 不在项集族中的项集.
+        let other = Grammar::from_cfg("S -> c", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        assert_eq!(family.state_of(&ItemSet::initial(&other).unwrap()), None);
+    }
+
+    #[test]
+    fn canonicalize() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let canonical = Family::from_grammar(&grammar).unwrap().canonicalize();
+        assert_eq!(canonical.len(), family.len());
+        assert_eq!(canonical.item_set(0), family.item_set(0));
+        assert!(
+            canonical
+                .item_sets()
+                .iter()
+                .all(|is| family.state_of(is).is_some())
+        );
+        for (from, tok, to) in canonical.gotos() {
+            let is = canonical
+                .item_set(from)
+                .unwrap()
+                .goto(tok)
+                .unwrap()
+                .unwrap();
+            assert_eq!(canonical.state_of(&is), Some(to));
+        }
+        // 再次规范化不改变编号.
+        let texts = |f: &Family| f.to_typst();
+        assert_eq!(
+            texts(&canonical.canonicalize()),
+            texts(&family.canonicalize())
+        );
+    }
+
+    #[test]
+    fn minimal_family() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        assert_eq!(Family::from_grammar(&grammar).unwrap().len(), 10);
+        // 没有冲突时和 LALR(1) 一样合并所有同核心的项集.
+        assert_eq!(Family::from_grammar_minimal(&grammar).unwrap().len(), 7);
+
+        // LALR(1) 会把 `A -> e ⋅` 和 `B -> e ⋅` 的两个项集合并, 引入归约/归约冲突, 这里保持分开.
+        let grammar = Grammar::from_cfg(
+            "S -> a A c | a B d | b A d | b B c\nA -> e\nB -> e",
+            "S".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let canonical = Family::from_grammar(&grammar).unwrap();
+        let minimal = Family::from_grammar_minimal(&grammar).unwrap();
+        assert_eq!(canonical.len(), 14);
+        assert_eq!(minimal.len(), 14);
+        let canonical = Table::build_from(&canonical).unwrap();
+        let minimal = Table::build_from(&minimal).unwrap();
+        assert!(!canonical.conflict() && !minimal.conflict());
+        let run = |table: &Table<'_>, input: &'static str| {
+            let mut reductions = Vec::new();
+            Parser::new(table)
+                .parse_with_buffer(
+                    input.split(' ').map(Terminal::from),
+                    &mut Vec::with_capacity(16),
+                    |p| reductions.push(p),
+                )
+                .map(|_| reductions)
+                .ok()
+        };
+        assert!(run(&minimal, "b e c").is_some());
+        assert!(run(&minimal, "a e e").is_none());
+        for input in ["a e c", "a e d", "b e d", "b e c", "a e e", "b d"] {
+            assert_eq!(run(&minimal, input), run(&canonical, input), "{input}");
+        }
+    }
+
+    #[test]
+    fn items_by_production() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b | c\nA -> a | S a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        for is in family.item_sets() {
+            let groups = is.items_by_production();
+            let ids: Vec<_> = groups.iter().map(|(id, _)| *id).collect();
+            assert!(ids.is_sorted());
+            assert_eq!(
+                groups.iter().map(|(_, items)| items.len()).sum::<usize>(),
+                is.items().count()
+            );
+            for (id, items) in &groups {
+                assert!(items.iter().all(|i| grammar.prod(*id) == Some(i.prod())));
+            }
+        }
+        // I_0: 增广产生式, S -> A b, S -> c, A -> a, A -> S a.
+        let ids: Vec<_> = family.item_sets()[0]
+            .items_by_production()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn shared_look_aheads() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let items: Vec<_> = family
+            .item_sets()
+            .iter()
+            .flat_map(|is| is.items())
+            .collect();
+        // 内容相同的前瞻符集合是同一份.
+        for a in items.iter().take(200) {
+            for b in &items {
+                if a.look_aheads() == b.look_aheads() {
+                    assert!(Arc::ptr_eq(&a.look_aheads, &b.look_aheads));
+                }
+            }
+        }
+        let distinct: BTreeSet<_> = items.iter().map(|i| i.look_aheads()).collect();
+        assert!(grammar.look_ahead_sets() >= distinct.len());
+        assert!(grammar.look_ahead_sets() * 10 < items.len());
+    }
 }
@@ -0,0 +1,868 @@
+//! 词法分析.
+//!
+//! 文法中的终结符默认只匹配和自己完全相同的词素, 终结符类别 ([`TerminalClass`]) 把一类词素绑定到同一个终结符上,
+//! 例如 `NUM` 匹配所有的数字串, `ID` 匹配所有的标识符, 匹配方式可以是正则表达式或者任意的判断函数.
+//! 分类得到的 `(终结符, 词素)` 直接交给 [`crate::Parser`], 词素会一直保留到语法分析树的叶子中.
+//!
+//! 关键字: 文法中原样出现的终结符 (例如 `while`) 默认是保留字, 和终结符类别 (例如 `ID`) 匹配到同样长度的词素时归为关键字,
+//! 见: [`LexerSpec::keywords`].
+//!
+//! 词法模式: 字符串, 注释等内部的词法规则和外部不同, 通过 [`LexerSpec::rule`] 声明某个模式下的规则,
+//! 规则匹配之后可以进入 (压栈) 或者退出 (弹栈) 模式, 例如在 `"` 上进入 `STRING` 模式, 再次遇到 `"` 时退出;
+//! 模式栈使得嵌套的注释也能正确匹配.
+//!
+//! 不需要终结符类别时, [`TermReader`] 从任意的 [`BufRead`] 中逐行读取以空白分隔的终结符,
+//! 不需要把整个输入读入内存.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Debug,
+    io::{self, BufRead},
+};
+
+use regex::Regex;
+
+use crate::{
+    EOF, EPSILON, Grammar, INVALID, Terminal, Token,
+    diagnostic::{Diagnostic, Severity, Span},
+    error::Error,
+    parser::InputTerm,
+};
+
+/// 终结符类别的匹配方式.
+pub enum Matcher {
+    /// 正则表达式.
+    Regex(Regex),
+    /// 判断函数, 需要判断整个词素.
+    Predicate(Box<dyn Fn(&str) -> bool>),
+}
+
+impl Debug for Matcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Regex(re) => f.debug_tuple("Regex").field(&re.as_str()).finish(),
+            Self::Predicate(_) => f.write_str("Predicate(..)"),
+        }
+    }
+}
+
+/// 终结符类别: 一个终结符以及它能够匹配的一类词素.
+#[derive(Debug)]
+pub struct TerminalClass<'a> {
+    term: Terminal<'a>,
+    matcher: Matcher,
+    /// 正则表达式锚定在两端的版本, 用于匹配整个词素.
+    whole: Option<Regex>,
+    /// 正则表达式锚定在开头的版本, 用于扫描.
+    prefix: Option<Regex>,
+}
+
+impl<'a> TerminalClass<'a> {
+    fn new(term: Terminal<'a>, matcher: Matcher) -> Result<Self, Error> {
+        let anchored = |pattern: String| {
+            Regex::new(&pattern).map_err(|e| Error::InvalidPattern {
+                term: term.to_string(),
+                cause: e.to_string(),
+            })
+        };
+        let (whole, prefix) = match &matcher {
+            Matcher::Regex(re) => (
+                Some(anchored(format!("^(?:{})$", re.as_str()))?),
+                Some(anchored(format!("^(?:{})", re.as_str()))?),
+            ),
+            Matcher::Predicate(_) => (None, None),
+        };
+        Ok(Self {
+            term,
+            matcher,
+            whole,
+            prefix,
+        })
+    }
+
+    #[must_use]
+    pub fn term(&self) -> Terminal<'a> {
+        self.term
+    }
+
+    #[must_use]
+    pub fn matcher(&self) -> &Matcher {
+        &self.matcher
+    }
+
+    /// 词素是否属于这个类别.
+    #[must_use]
+    pub fn matches(&self, lexeme: &str) -> bool {
+        match (&self.matcher, &self.whole) {
+            (_, Some(re)) => re.is_match(lexeme),
+            (Matcher::Predicate(f), _) => f(lexeme),
+            (Matcher::Regex(_), None) => false,
+        }
+    }
+
+    /// 从 `rest` 开头能够匹配的词素长度 (字节), 匹配不到或者只能匹配空串时返回 [`None`].
+    ///
+    /// 正则表达式使用其自身的匹配结果 (leftmost-first), 判断函数取不跨越空白的最长的满足条件的前缀.
+    #[must_use]
+    pub fn match_prefix(&self, rest: &str) -> Option<usize> {
+        let len = match (&self.matcher, &self.prefix) {
+            (_, Some(re)) => re.find(rest).map_or(0, |m| m.end()),
+            (Matcher::Predicate(f), _) => {
+                let run = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                rest[..run]
+                    .char_indices()
+                    .map(|(i, c)| i + c.len_utf8())
+                    .rev()
+                    .find(|&end| f(&rest[..end]))
+                    .unwrap_or(0)
+            }
+            (Matcher::Regex(_), None) => 0,
+        };
+        (len > 0).then_some(len)
+    }
+}
+
+/// 默认的词法模式, 字面终结符和终结符类别只在这个模式下生效, 词素之间的空白会被跳过.
+pub const DEFAULT_MODE: &str = "default";
+
+/// 模式规则匹配之后对模式栈的操作.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModeAction {
+    /// 保持当前模式.
+    Stay,
+    /// 进入模式.
+    Push(String),
+    /// 退出当前模式, 回到上一个模式.
+    Pop,
+}
+
+/// 某个词法模式下的一条规则.
+#[derive(Debug)]
+pub struct ModeRule<'a> {
+    /// 锚定在开头的正则表达式.
+    pattern: Regex,
+    /// 匹配得到的终结符, [`None`] 表示丢弃匹配到的文本 (例如注释).
+    term: Option<Terminal<'a>>,
+    action: ModeAction,
+}
+
+impl<'a> ModeRule<'a> {
+    #[must_use]
+    pub fn term(&self) -> Option<Terminal<'a>> {
+        self.term
+    }
+
+    #[must_use]
+    pub fn action(&self) -> &ModeAction {
+        &self.action
+    }
+}
+
+/// 词法规则: 文法中的字面终结符, 各个终结符类别以及各个模式下的规则.
+#[derive(Debug)]
+pub struct LexerSpec<'a> {
+    /// 只匹配自身的终结符, 不包括 [`EPSILON`], [`EOF`] 和已经声明为类别的终结符.
+    literals: Vec<Terminal<'a>>,
+    classes: Vec<TerminalClass<'a>>,
+    modes: BTreeMap<String, Vec<ModeRule<'a>>>,
+    keywords: bool,
+}
+
+impl<'a> LexerSpec<'a> {
+    /// 使用文法中所有的终结符作为字面终结符, 还没有任何终结符类别, 开启关键字.
+    #[must_use]
+    pub fn new(grammar: &Grammar<'a>) -> Self {
+        let literals = grammar
+            .tokens()
+            .iter()
+            .filter_map(|tok| match tok {
+                Token::Terminal(t) if *t != EPSILON && *t != EOF => Some(*t),
+                _ => None,
+            })
+            .collect();
+        Self {
+            literals,
+            classes: Vec::new(),
+            modes: BTreeMap::new(),
+            keywords: true,
+        }
+    }
+
+    #[must_use]
+    pub fn classes(&self) -> &[TerminalClass<'a>] {
+        &self.classes
+    }
+
+    /// 是否把字面终结符作为关键字 (默认开启).
+    ///
+    /// 开启时, 终结符类别匹配到的词素如果和某个字面终结符相同, 那么归为这个字面终结符,
+    /// 例如 `while` 是关键字而不是 `ID`; 但是更长的 `whilex` 仍然是 `ID`.
+    /// 关闭时终结符类别优先, 字面终结符只有在匹配到更长的词素时才会被选中.
+    #[must_use]
+    pub fn keywords(self, keywords: bool) -> Self {
+        Self { keywords, ..self }
+    }
+
+    /// 声明终结符 `term` 匹配正则表达式 `pattern` 的词素.
+    ///
+    /// # Errors
+    /// - [`Error::InvalidPattern`] 正则表达式不合法.
+    /// - 其他见: [`LexerSpec::class`].
+    pub fn regex(self, term: &str, pattern: &str) -> Result<Self, Error> {
+        let re = Regex::new(pattern).map_err(|e| Error::InvalidPattern {
+            term: term.to_string(),
+            cause: e.to_string(),
+        })?;
+        self.class(term, Matcher::Regex(re))
+    }
+
+    /// 声明终结符 `term` 匹配使 `f` 返回 `true` 的词素.
+    ///
+    /// # Errors
+    /// 见: [`LexerSpec::class`].
+    pub fn predicate(self, term: &str, f: impl Fn(&str) -> bool + 'static) -> Result<Self, Error> {
+        self.class(term, Matcher::Predicate(Box::new(f)))
+    }
+
+    /// 声明终结符类别, 终结符不再匹配和自己相同的词素. 先声明的类别优先匹配.
+    ///
+    /// # Errors
+    /// - [`Error::TerminalNotFound`] 文法中没有这个终结符, 或者它已经被声明为类别.
+    /// - [`Error::InvalidPattern`] 正则表达式无法锚定.
+    pub fn class(mut self, term: &str, matcher: Matcher) -> Result<Self, Error> {
+        let idx = self
+            .literals
+            .iter()
+            .position(|t| t.as_str() == term)
+            .ok_or_else(|| Error::TerminalNotFound(term.to_string()))?;
+        let class = TerminalClass::new(self.literals[idx], matcher)?;
+        self.literals.remove(idx);
+        self.classes.push(class);
+        Ok(self)
+    }
+
+    /// 在模式 `mode` 下声明一条规则: 匹配正则表达式 `pattern` 的文本归为终结符 `term`
+    /// (为 [`None`] 时丢弃), 然后对模式栈执行 `action`.
+    ///
+    /// 同一个模式下先声明的规则优先. 在 [`DEFAULT_MODE`] 下, 规则和字面终结符, 终结符类别一起按照最长匹配原则选择,
+    /// 同样长度时规则优先. 其他模式下只使用该模式的规则, 也不会跳过空白.
+    /// 进入的模式可以在之后再声明规则.
+    ///
+    /// # Errors
+    /// - [`Error::InvalidPattern`] 正则表达式不合法.
+    /// - [`Error::TerminalNotFound`] 文法中没有终结符 `term`.
+    pub fn rule(
+        mut self,
+        mode: &str,
+        pattern: &str,
+        term: Option<&str>,
+        action: ModeAction,
+    ) -> Result<Self, Error> {
+        let term = term
+            .map(|name| {
+                self.literals
+                    .iter()
+                    .copied()
+                    .chain(self.classes.iter().map(TerminalClass::term))
+                    .find(|t| t.as_str() == name)
+                    .ok_or_else(|| Error::TerminalNotFound(name.to_string()))
+            })
+            .transpose()?;
+        let pattern =
+            Regex::new(&format!("^(?:{pattern})")).map_err(|e| Error::InvalidPattern {
+                term: term.map_or_else(|| mode.to_string(), |t| t.to_string()),
+                cause: e.to_string(),
+            })?;
+        self.modes
+            .entry(mode.to_string())
+            .or_default()
+            .push(ModeRule {
+                pattern,
+                term,
+                action,
+            });
+        Ok(self)
+    }
+
+    /// 模式 `mode` 下的规则.
+    #[must_use]
+    pub fn rules(&self, mode: &str) -> &[ModeRule<'a>] {
+        self.modes.get(mode).map_or(&[], Vec::as_slice)
+    }
+
+    fn literal(&self, lexeme: &str) -> Option<Terminal<'a>> {
+        self.literals.iter().copied().find(|t| t.as_str() == lexeme)
+    }
+
+    /// 整个词素对应的终结符, 字面终结符和终结符类别的优先级见: [`LexerSpec::keywords`].
+    #[must_use]
+    pub fn classify(&self, lexeme: &str) -> Option<Terminal<'a>> {
+        let class = || {
+            self.classes
+                .iter()
+                .find(|c| c.matches(lexeme))
+                .map(TerminalClass::term)
+        };
+        if self.keywords {
+            self.literal(lexeme).or_else(class)
+        } else {
+            class().or_else(|| self.literal(lexeme))
+        }
+    }
+
+    /// 把以空白分隔的词素序列转换成语法分析的输入, 行号从 1 开始.
+    ///
+    /// # Errors
+    /// - [`Error::UnknownLexeme`] 词素不匹配任何终结符.
+    pub fn tokenize(&self, input: &'a str) -> Result<Vec<InputTerm<'a>>, Error> {
+        let mut terms = Vec::new();
+        for (ln, line) in input.lines().enumerate() {
+            for lexeme in line.split_whitespace() {
+                let term = self.classify(lexeme).ok_or_else(|| Error::UnknownLexeme {
+                    lexeme: lexeme.to_string(),
+                    line: ln + 1,
+                })?;
+                terms.push(InputTerm::new(term, ln + 1).with_text(lexeme));
+            }
+        }
+        Ok(terms)
+    }
+
+    /// 从 `rest` 开头匹配一个词素, 返回词素长度和终结符.
+    ///
+    /// 选择最长的匹配 (最长匹配原则), 同样长度时的优先级见: [`LexerSpec::keywords`].
+    #[must_use]
+    pub fn next_token(&self, rest: &str) -> Option<(usize, Terminal<'a>)> {
+        let literals = self
+            .literals
+            .iter()
+            .filter(|t| rest.starts_with(t.as_str()))
+            .map(|&t| (t.as_str().len(), t));
+        let classes = self
+            .classes
+            .iter()
+            .filter_map(|c| c.match_prefix(rest).map(|len| (len, c.term)));
+        let candidates: Vec<_> = if self.keywords {
+            literals.chain(classes).collect()
+        } else {
+            classes.chain(literals).collect()
+        };
+        // 同样长度时保留先出现的候选.
+        candidates
+            .into_iter()
+            .reduce(|best, c| if c.0 > best.0 { c } else { best })
+    }
+
+    /// 在模式 `mode` 下从 `rest` 开头匹配, 返回文本长度, 终结符和对模式栈的操作.
+    fn step(
+        &self,
+        mode: &str,
+        rest: &str,
+    ) -> Option<(usize, Option<Terminal<'a>>, Option<&ModeAction>)> {
+        let rules = self.rules(mode).iter().filter_map(|r| {
+            let len = r.pattern.find(rest).map_or(0, |m| m.end());
+            (len > 0).then_some((len, r.term, Some(&r.action)))
+        });
+        let token = (mode == DEFAULT_MODE)
+            .then(|| self.next_token(rest))
+            .flatten()
+            .map(|(len, term)| (len, Some(term), None));
+        // 同样长度时保留先出现的候选, 也就是规则优先.
+        rules
+            .chain(token)
+            .reduce(|best, c| if c.0 > best.0 { c } else { best })
+    }
+
+    /// 扫描源代码, 词素之间的空白可以省略, 行号从 1 开始. 从 [`DEFAULT_MODE`] 开始, 模式见: [`LexerSpec::rule`].
+    ///
+    /// # Errors
+    /// 遇到的第一个错误, 见: [`LexerSpec::lex_recover`].
+    pub fn lex(&self, input: &'a str) -> Result<Vec<InputTerm<'a>>, Error> {
+        let output = self.lex_recover(input);
+        match output.errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(output.terms),
+        }
+    }
+
+    /// 扫描源代码, 遇到错误时继续扫描, 见: [`LexerSpec::lex`].
+    ///
+    /// 连续的无法开始任何词素的字符 (在 [`DEFAULT_MODE`] 下不跨越空白) 合并为一个 [`INVALID`] 终结符,
+    /// 交给语法分析器的恐慌恢复处理. 错误及其位置记录在 [`LexOutput::errors`] 中:
+    /// - [`Error::UnknownLexeme`] 无法识别的字符.
+    /// - [`Error::UnterminatedMode`] 输入结束时还没有回到 [`DEFAULT_MODE`], 例如字符串没有闭合.
+    #[must_use]
+    pub fn lex_recover(&self, input: &'a str) -> LexOutput<'a> {
+        let mut output = LexOutput::default();
+        let mut stack: Vec<&str> = Vec::new();
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut pos = 0;
+        while let Some(c) = input[pos..].chars().next() {
+            let mode = stack.last().copied().unwrap_or(DEFAULT_MODE);
+            let default = mode == DEFAULT_MODE;
+            if default && c.is_whitespace() {
+                pos += c.len_utf8();
+                if c == '\n' {
+                    line += 1;
+                    line_start = pos;
+                }
+                continue;
+            }
+            let Some((len, term, action)) = self.step(mode, &input[pos..]) else {
+                let len = input[pos..]
+                    .char_indices()
+                    .skip(1)
+                    .find(|&(i, c)| {
+                        (default && c.is_whitespace())
+                            || self.step(mode, &input[pos + i..]).is_some()
+                    })
+                    .map_or(input.len() - pos, |(i, _)| i);
+                let text = &input[pos..pos + len];
+                let column = input[line_start..pos].chars().count() + 1;
+                output.terms.push(
+                    InputTerm::new(INVALID, line)
+                        .with_text(text)
+                        .with_column(column),
+                );
+                output.errors.push((
+                    Span {
+                        line: line - 1,
+                        start: pos - line_start,
+                        end: pos + len - line_start,
+                    },
+                    Error::UnknownLexeme {
+                        lexeme: text.to_string(),
+                        line,
+                    },
+                ));
+                pos += len;
+                continue;
+            };
+            let text = &input[pos..pos + len];
+            if let Some(term) = term {
+                let column = input[line_start..pos].chars().count() + 1;
+                output.terms.push(
+                    InputTerm::new(term, line)
+                        .with_text(text)
+                        .with_column(column),
+                );
+            }
+            match action {
+                Some(ModeAction::Push(mode)) => stack.push(mode),
+                Some(ModeAction::Pop) => {
+                    stack.pop();
+                }
+                Some(ModeAction::Stay) | None => {}
+            }
+            pos += len;
+            if let Some(nl) = text.rfind('\n') {
+                line += text.matches('\n').count();
+                line_start = pos - len + nl + 1;
+            }
+        }
+        if let Some(mode) = stack.last() {
+            let col = pos - line_start;
+            output.errors.push((
+                Span {
+                    line: line - 1,
+                    start: col,
+                    end: col,
+                },
+                Error::UnterminatedMode {
+                    mode: (*mode).to_string(),
+                    line,
+                },
+            ));
+        }
+        output
+    }
+}
+
+/// 词法分析的结果, 见: [`LexerSpec::lex_recover`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LexOutput<'a> {
+    /// 词素序列, 无法识别的字符为 [`INVALID`].
+    pub terms: Vec<InputTerm<'a>>,
+    /// 错误及其在源代码中的位置 (行号从 0 开始, 见: [`Span`]).
+    pub errors: Vec<(Span, Error)>,
+}
+
+impl LexOutput<'_> {
+    /// 把错误转换为诊断信息.
+    #[must_use]
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|(span, e)| Diagnostic {
+                span: *span,
+                severity: Severity::Error,
+                message: e.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// 从 [`BufRead`] 中逐行读取以空白分隔的终结符, 记录每个终结符所在的行号和列号 (都从 1 开始).
+///
+/// 每个词素都作为终结符本身, 文法中的终结符直接使用文法中的符号, 文法中没有的词素分配在文法的 arena 中,
+/// 交给语法分析器的恐慌恢复处理. 读取出错时迭代结束, 错误可以通过 [`TermReader::take_error`] 取出.
+///
+/// # Panics
+/// 不在创建文法的线程上读到文法中没有的词素, 见: [`Grammar`].
+#[derive(Debug)]
+pub struct TermReader<'g, 'a, R> {
+    grammar: &'g Grammar<'a>,
+    reader: R,
+    /// 文法中的终结符.
+    terms: HashMap<&'a str, Terminal<'a>>,
+    /// 当前行中还没有返回的终结符.
+    pending: VecDeque<InputTerm<'a>>,
+    line: usize,
+    buf: String,
+    error: Option<io::Error>,
+}
+
+impl<'g, 'a, R: BufRead> TermReader<'g, 'a, R> {
+    #[must_use]
+    pub fn new(grammar: &'g Grammar<'a>, reader: R) -> Self {
+        let terms = grammar
+            .tokens()
+            .iter()
+            .filter_map(Token::as_term)
+            .map(|&t| (t.as_str(), t))
+            .collect();
+        Self {
+            grammar,
+            reader,
+            terms,
+            pending: VecDeque::new(),
+            line: 0,
+            buf: String::new(),
+            error: None,
+        }
+    }
+
+    /// 取出读取时发生的错误, 没有错误时返回 [`None`].
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// 读取下一行并切分, 输入结束或者出错时返回 `false`.
+    fn fill(&mut self) -> bool {
+        self.buf.clear();
+        match self.reader.read_line(&mut self.buf) {
+            Ok(0) => return false,
+            Ok(_) => {}
+            Err(e) => {
+                self.error = Some(e);
+                return false;
+            }
+        }
+        self.line += 1;
+        let mut word: Option<(usize, usize)> = None;
+        for (column, (idx, c)) in self.buf.char_indices().enumerate() {
+            match (c.is_whitespace(), word) {
+                (true, Some((start, col))) => {
+                    self.pending.push_back(self.term(&self.buf[start..idx], col));
+                    word = None;
+                }
+                (false, None) => word = Some((idx, column + 1)),
+                _ => {}
+            }
+        }
+        if let Some((start, col)) = word {
+            self.pending.push_back(self.term(&self.buf[start..], col));
+        }
+        true
+    }
+
+    fn term(&self, word: &str, column: usize) -> InputTerm<'a> {
+        let term = match self.terms.get(word) {
+            Some(&term) => term,
+            None => Terminal::from(&*self.grammar.bump().alloc_str(word)),
+        };
+        InputTerm::new(term, self.line).with_column(column)
+    }
+}
+
+impl<'a, R: BufRead> Iterator for TermReader<'_, 'a, R> {
+    type Item = InputTerm<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(term) = self.pending.pop_front() {
+                return Some(term);
+            }
+            if !self.fill() {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, INVALID, Parser, Table, Terminal,
+        diagnostic::Span,
+        error::Error,
+        lexer::{DEFAULT_MODE, LexerSpec, ModeAction, TermReader},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn terminal_classes() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let spec = LexerSpec::new(&grammar)
+            .regex("NUM", "[0-9]+")
+            .unwrap()
+            .predicate("ID", |s| s.chars().all(|c| c.is_ascii_alphabetic()))
+            .unwrap();
+        assert_eq!(spec.classify("42"), Some(Terminal::from("NUM")));
+        assert_eq!(spec.classify("4a"), None);
+        assert_eq!(spec.classify("x"), Some(Terminal::from("ID")));
+        // 字面终结符优先于终结符类别.
+        assert_eq!(spec.classify("while"), Some(Terminal::from("while")));
+        assert_eq!(spec.classify("NUM"), Some(Terminal::from("ID")));
+        assert!(matches!(
+            LexerSpec::new(&grammar).regex("NUM", "("),
+            Err(Error::InvalidPattern { .. })
+        ));
+        assert!(matches!(
+            LexerSpec::new(&grammar).regex("num", "[0-9]+"),
+            Err(Error::TerminalNotFound(_))
+        ));
+        assert_eq!(
+            spec.tokenize("{\nx = 4a ;\n}"),
+            Err(Error::UnknownLexeme {
+                lexeme: "4a".to_string(),
+                line: 2
+            })
+        );
+
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let terms = spec.tokenize("{\nx = 42 ;\n}").unwrap();
+        let output = Parser::new(&table).parse(terms).unwrap();
+        let tree = output.tree(&grammar).unwrap();
+        let leaves: Vec<_> = tree.leaves().map(|t| (t.term.as_str(), t.text)).collect();
+        assert_eq!(
+            leaves,
+            [
+                ("{", "{"),
+                ("ID", "x"),
+                ("=", "="),
+                ("NUM", "42"),
+                (";", ";"),
+                ("}", "}")
+            ]
+        );
+        assert!(tree.to_ascii().contains(r#"NUM("42")"#));
+        // 直接输入 (终结符, 词素) 对.
+        let output = Parser::new(&table)
+            .parse([
+                (Terminal::from("{"), "{"),
+                (Terminal::from("ID"), "y"),
+                (Terminal::from("="), "="),
+                (Terminal::from("NUM"), "7"),
+                (Terminal::from(";"), ";"),
+                (Terminal::from("}"), "}"),
+            ])
+            .unwrap();
+        assert!(output.accepted());
+        assert_eq!(output.terms()[1].text, "y");
+    }
+
+    #[test]
+    fn keywords() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let spec = LexerSpec::new(&grammar)
+            .regex("NUM", "[0-9]+")
+            .unwrap()
+            .regex("ID", "[a-z][a-z0-9]*")
+            .unwrap();
+        fn lexed<'a>(spec: &LexerSpec<'a>, src: &'a str) -> Vec<(&'a str, &'a str, usize)> {
+            spec.lex(src)
+                .unwrap()
+                .into_iter()
+                .map(|t| (t.term.as_str(), t.text, t.line))
+                .collect()
+        }
+        assert_eq!(
+            lexed(&spec, "while(whilex<=10)\n  if1=then;"),
+            [
+                ("while", "while", 1),
+                ("(", "(", 1),
+                ("ID", "whilex", 1),
+                ("<=", "<=", 1),
+                ("NUM", "10", 1),
+                (")", ")", 1),
+                ("ID", "if1", 2),
+                ("=", "=", 2),
+                ("then", "then", 2),
+                (";", ";", 2),
+            ]
+        );
+        assert_eq!(
+            spec.lex("x = @"),
+            Err(Error::UnknownLexeme {
+                lexeme: "@".to_string(),
+                line: 1
+            })
+        );
+        let spec = spec.keywords(false);
+        assert_eq!(spec.classify("while"), Some(Terminal::from("ID")));
+        assert_eq!(lexed(&spec, "while<"), [("ID", "while", 1), ("<", "<", 1)]);
+    }
+
+    #[test]
+    fn modes() {
+        let bump = Bump::new();
+        let cfg = "prog -> stmt prog | E\nstmt -> print value ;\nvalue -> ID | \" chars \"\nchars -> chunk chars | E\nchunk -> TEXT | ESC";
+        let grammar = Grammar::from_cfg(cfg, "prog".into(), &bump)
+            .unwrap()
+            .augmented();
+        let spec = LexerSpec::new(&grammar)
+            .regex("ID", "[a-z]+")
+            .unwrap()
+            .rule(
+                DEFAULT_MODE,
+                "\"",
+                Some("\""),
+                ModeAction::Push("STRING".into()),
+            )
+            .unwrap()
+            .rule("STRING", r#"[^"\\]+"#, Some("TEXT"), ModeAction::Stay)
+            .unwrap()
+            .rule("STRING", r"\\.", Some("ESC"), ModeAction::Stay)
+            .unwrap()
+            .rule("STRING", "\"", Some("\""), ModeAction::Pop)
+            .unwrap()
+            .rule(DEFAULT_MODE, "//[^\n]*", None, ModeAction::Stay)
+            .unwrap()
+            .rule(
+                DEFAULT_MODE,
+                r"/\*",
+                None,
+                ModeAction::Push("COMMENT".into()),
+            )
+            .unwrap()
+            .rule("COMMENT", r"/\*", None, ModeAction::Push("COMMENT".into()))
+            .unwrap()
+            .rule("COMMENT", r"\*/", None, ModeAction::Pop)
+            .unwrap()
+            .rule("COMMENT", "[^*/]+|[*/]", None, ModeAction::Stay)
+            .unwrap();
+        let src = "print \"a /* b\\\" \";\n/* outer /* inner */\n print x; */ // line\nprint x;";
+        let terms = spec.lex(src).unwrap();
+        assert_eq!(
+            terms
+                .iter()
+                .map(|t| (t.term.as_str(), t.text, t.line))
+                .collect::<Vec<_>>(),
+            [
+                ("print", "print", 1),
+                ("\"", "\"", 1),
+                ("TEXT", "a /* b", 1),
+                ("ESC", "\\\"", 1),
+                ("TEXT", " ", 1),
+                ("\"", "\"", 1),
+                (";", ";", 1),
+                ("print", "print", 4),
+                ("ID", "x", 4),
+                (";", ";", 4),
+            ]
+        );
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert!(Parser::new(&table).parse(terms).unwrap().accepted());
+        assert_eq!(
+            spec.lex("print \"abc"),
+            Err(Error::UnterminatedMode {
+                mode: "STRING".to_string(),
+                line: 1
+            })
+        );
+
+        // 无法识别的字符合并为 INVALID, 继续扫描.
+        let output = spec.lex_recover("print x;\nprint @#y; print $ x;");
+        let invalid: Vec<_> = output
+            .terms
+            .iter()
+            .filter(|t| t.term == INVALID)
+            .map(|t| (t.text, t.line))
+            .collect();
+        assert_eq!(invalid, [("@#", 2), ("$", 2)]);
+        assert_eq!(
+            output
+                .errors
+                .iter()
+                .map(|(span, _)| *span)
+                .collect::<Vec<_>>(),
+            [
+                Span {
+                    line: 1,
+                    start: 6,
+                    end: 8
+                },
+                Span {
+                    line: 1,
+                    start: 17,
+                    end: 18
+                }
+            ]
+        );
+        assert_eq!(output.terms.len(), 11);
+        let recovered = Parser::new(&table).parse(output.terms).unwrap();
+        assert!(recovered.accepted());
+        assert_eq!(recovered.report().errors(), 2);
+    }
+
+    #[test]
+    fn term_reader() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( S ) S | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let input = "( )\n  ( x\t)\n\n";
+        let mut reader = TermReader::new(&grammar, input.as_bytes());
+        let terms: Vec<_> = reader
+            .by_ref()
+            .map(|t| (t.text, t.line, t.column))
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                ("(", 1, 1),
+                (")", 1, 3),
+                ("(", 2, 3),
+                ("x", 2, 5),
+                (")", 2, 7)
+            ]
+        );
+        assert!(reader.take_error().is_none());
+
+        let output = Parser::new(&table)
+            .parse(TermReader::new(&grammar, input.as_bytes()))
+            .unwrap();
+        assert!(output.accepted());
+        assert_eq!(output.report().errors(), 1);
+
+        // 列号按照字符计算.
+        let spec = LexerSpec::new(&grammar);
+        let lexed = spec.lex_recover("(\n é )");
+        let columns: Vec<_> = lexed.terms.iter().map(|t| (t.line, t.column)).collect();
+        assert_eq!(columns, [(1, 1), (2, 2), (2, 4)]);
+    }
+}
@@ -1,12 +1,46 @@
+pub mod audit;
+pub mod canonical;
+pub mod batch;
+pub mod codegen;
+pub mod compare;
+pub mod completion;
+pub mod compose;
+pub mod conflict;
+pub mod construction;
+pub mod counterexample;
+pub mod coverage;
+pub mod diagnostic;
+pub mod equiv;
 pub mod error;
+pub mod explain;
+pub mod generate;
+pub mod glr;
 pub mod grammar;
+pub mod graph;
 pub mod item;
+pub mod lexer;
+pub mod lint;
 pub(crate) mod macros;
+pub mod owned;
 pub mod panic;
+pub mod parser;
+pub mod precedence;
+pub mod regular;
+pub mod render;
+pub mod replay;
+#[cfg(feature = "small")]
+pub mod small;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod snippet;
 pub mod table;
 pub mod token;
+pub mod tree;
+pub mod verify;
 
-pub use grammar::{Grammar, Production};
-pub use item::{Family, Item, ItemSet};
-pub use table::{ActionCell, Table};
-pub use token::{EOF, EPSILON, NonTerminal, Terminal, Token};
+pub use coverage::Coverage;
+pub use grammar::{Grammar, ProdId, Production};
+pub use item::{Family, Item, ItemSet, Lr0Conflict, Lr0Family, Lr0Item, StateId};
+pub use parser::Parser;
+pub use table::{ActionCell, CellRef, ReduceInfo, Symbols, Table, TableDefect};
+pub use token::{EOF, EPSILON, INVALID, NonTerminal, Terminal, Token};
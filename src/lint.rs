@@ -0,0 +1,718 @@
+//! 文法的合理性检查.
+//!
+//! 对一个文法文件给出分类的问题报告, 适合批量检查学生提交的文法:
+//!
+//! - 文法解析错误.
+//! - 从开始符号不可达的非终结符, 无法推导出终结符串的 (不可终止的) 非终结符.
+//! - 左递归 (对 LR 分析没有影响, 但是 LL 分析无法处理) 和 `A =>+ A` 的推导环 (文法一定是二义的).
+//! - 规范 LR(1) 分析表中的冲突.
+//! - 可疑的写法: 只出现一次的终结符 (常常是拼错的非终结符, 不检查运算符和括号), 名字和某个非终结符相近,
+//!   只是因为没有定义产生式才成为终结符的符号, 和其他符号混在一起的 [`EPSILON`]
+//!   (例如 `A -> a E b`, 分析时被忽略, 和 `A -> a b` 相同), 分析任何句子时都不会被归约的非终结符.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
+
+use bumpalo::Bump;
+
+use crate::{
+    EPSILON, Family, Grammar, NonTerminal, ProdId, Production, Table, Token,
+    diagnostic::{self, Severity, SourceMap, Span},
+    error::Error,
+};
+
+/// 问题的种类, 报告中按照这里的顺序分组.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum LintCategory {
+    /// 文法解析错误, 或者无法构建项集族和分析表.
+    Parse,
+    Unreachable,
+    Unproductive,
+    /// `A =>+ A`, 只经过可以推导出空串的符号回到自身.
+    EpsilonCycle,
+    LeftRecursion,
+    Conflict,
+    /// 终结符在所有产生式中只出现了一次, 不检查只由标点组成的终结符.
+    SingleUseTerminal,
+    /// 终结符的名字和某个非终结符相近 (编辑距离), 多半是忘记定义 (或者拼错) 的非终结符, 例如定义了 `stmt`
+    /// 却在 `compoundstmt -> { stmts }` 中使用了没有产生式的 `stmts`.
+    UndefinedNonTerminal,
+    /// 产生式尾部的 [`EPSILON`] 和其他符号混在一起, 分析时被忽略.
+    MixedEpsilon,
+    /// 可达并且可终止, 但是只出现在含有不可终止符号的产生式中, 分析任何句子时都不会被归约.
+    NeverReduced,
+    /// 开始符号出现在产生式尾部. 不是问题: 分析之前会自动增广文法, 不需要手动添加 `S' -> S`.
+    StartOnRhs,
+}
+
+impl LintCategory {
+    #[must_use]
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::Parse | Self::Unproductive | Self::EpsilonCycle | Self::Conflict => {
+                Severity::Error
+            }
+            Self::Unreachable
+            | Self::LeftRecursion
+            | Self::SingleUseTerminal
+            | Self::UndefinedNonTerminal
+            | Self::MixedEpsilon
+            | Self::NeverReduced => Severity::Warning,
+            Self::StartOnRhs => Severity::Info,
+        }
+    }
+}
+
+impl Display for LintCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Parse => "parse error",
+            Self::Unreachable => "unreachable",
+            Self::Unproductive => "unproductive",
+            Self::EpsilonCycle => "epsilon cycle",
+            Self::LeftRecursion => "left recursion",
+            Self::Conflict => "conflict",
+            Self::SingleUseTerminal => "terminal used once",
+            Self::UndefinedNonTerminal => "undefined nonterminal",
+            Self::MixedEpsilon => "mixed epsilon",
+            Self::NeverReduced => "never reduced",
+            Self::StartOnRhs => "start symbol on right-hand side",
+        })
+    }
+}
+
+/// 文法中的一个问题.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Lint {
+    pub category: LintCategory,
+    /// 问题所在的产生式或者产生式头部, 见: [`SourceMap`].
+    pub span: Span,
+    pub message: String,
+}
+
+impl Lint {
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.category.severity()
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.line + 1,
+            self.span.start + 1,
+            self.message
+        )
+    }
+}
+
+/// 检查文法文件, 返回按照种类和位置排序的问题.
+///
+/// `start` 为 [`None`] 时使用第一个产生式的头部作为开始符号. 没有任何产生式的文件没有问题.
+#[must_use]
+pub fn lint<'s>(src: &'s str, start: Option<&'s str>) -> Vec<Lint> {
+    if src.trim().is_empty() {
+        return Vec::new();
+    }
+    let bump = Bump::new();
+    let start = start.unwrap_or_else(|| diagnostic::default_start(src));
+    let parse_error = |span, e: Error| Lint {
+        category: LintCategory::Parse,
+        span,
+        message: e.to_string(),
+    };
+    let grammar = match Grammar::from_cfg(src, start.into(), &bump) {
+        Ok(grammar) => grammar.augmented(),
+        Err(e @ Error::ParseProductionError { line, .. }) => {
+            return vec![parse_error(diagnostic::line_span(src, line), e)];
+        }
+        Err(e) => return vec![parse_error(diagnostic::line_span(src, 0), e)],
+    };
+    let map = SourceMap::new(src);
+    let mut lints = Vec::new();
+    let mut push = |category, span: Option<Span>, message| {
+        lints.push(Lint {
+            category,
+            span: span.unwrap_or_else(|| diagnostic::line_span(src, 0)),
+            message,
+        });
+    };
+    // 非终结符第一次定义的位置.
+    let head_span = |nt| {
+        grammar
+            .producers_of(nt)
+            .iter()
+            .find_map(|&p| grammar.declared_index(p))
+            .and_then(|declared| map.heads.get(declared).copied())
+    };
+
+    let unreachable = diagnostic::unreachable(&grammar);
+    for &nt in &unreachable {
+        push(
+            LintCategory::Unreachable,
+            head_span(nt),
+            format!("`{nt}` is unreachable from the start symbol `{start}`"),
+        );
+    }
+    let productive = grammar.min_lens();
+    let heads = heads(&grammar);
+    for &nt in &heads {
+        if !productive.contains_key(&nt) {
+            push(
+                LintCategory::Unproductive,
+                head_span(nt),
+                format!("`{nt}` cannot derive any string of terminals"),
+            );
+        }
+    }
+
+    let nullable = nullable(&grammar);
+    let mut cyclic = HashSet::new();
+    for &nt in &heads {
+        if let Some(cycle) = cycle(&grammar, &nullable, nt, true) {
+            cyclic.insert(nt);
+            push(
+                LintCategory::EpsilonCycle,
+                head_span(nt),
+                format!("`{nt}` derives itself ({cycle}), the grammar is ambiguous"),
+            );
+        }
+    }
+    for &nt in &heads {
+        if cyclic.contains(&nt) {
+            continue;
+        }
+        if let Some(cycle) = cycle(&grammar, &nullable, nt, false) {
+            push(
+                LintCategory::LeftRecursion,
+                head_span(nt),
+                format!("`{nt}` is left recursive ({cycle}), fine for LR but not for LL parsers"),
+            );
+        }
+    }
+
+    let mut uses: BTreeMap<Token<'_>, Vec<usize>> = BTreeMap::new();
+    for (p, prod) in declared_prods(&grammar) {
+        for &tok in prod.tail() {
+            uses.entry(tok).or_default().push(p);
+        }
+    }
+    let mut undefined = HashSet::new();
+    for (tok, prods) in &uses {
+        let Token::Terminal(term) = *tok else {
+            continue;
+        };
+        let similar: Vec<_> = heads
+            .iter()
+            .filter(|nt| looks_like(term.as_str(), nt.as_str()))
+            .map(|nt| format!("`{nt}`"))
+            .collect();
+        if term == EPSILON || similar.is_empty() {
+            continue;
+        }
+        undefined.insert(term);
+        push(
+            LintCategory::UndefinedNonTerminal,
+            word_span(src, &map, prods[0], term.as_str()),
+            format!(
+                "`{term}` is a terminal because no production defines it, did you mean to define `{term}`? \
+                 similar nonterminals: {}",
+                similar.join(", ")
+            ),
+        );
+    }
+    for (tok, prods) in &uses {
+        let (Token::Terminal(term), [p]) = (*tok, prods.as_slice()) else {
+            continue;
+        };
+        // 运算符和括号只出现一次很常见, 只检查像名字的终结符.
+        if term == EPSILON
+            || undefined.contains(&term)
+            || !term.as_str().chars().any(char::is_alphanumeric)
+        {
+            continue;
+        }
+        let similar = heads
+            .iter()
+            .find(|nt| nt.as_str().eq_ignore_ascii_case(term.as_str()));
+        let message = match similar {
+            Some(nt) => format!("terminal `{term}` is used only once, did you mean `{nt}`?"),
+            None => format!("terminal `{term}` is used only once"),
+        };
+        push(LintCategory::SingleUseTerminal, map.prod(*p), message);
+    }
+    for (p, prod) in declared_prods(&grammar) {
+        if prod.tail().len() == 1 || prod.len() == prod.tail().len() {
+            continue;
+        }
+        let normalized: Vec<_> = prod.tail_without_eps().map(ToString::to_string).collect();
+        let normalized = if normalized.is_empty() {
+            EPSILON.to_string()
+        } else {
+            normalized.join(" ")
+        };
+        push(
+            LintCategory::MixedEpsilon,
+            word_span(src, &map, p, EPSILON.as_str()),
+            format!(
+                "`{EPSILON}` is ignored in `{prod}`, write `{} -> {normalized}` instead",
+                prod.head()
+            ),
+        );
+    }
+    let consumers = grammar.consumers_of(NonTerminal::from(start).into());
+    let augmented = grammar.augmented_production().and_then(|p| grammar.prod(p));
+    if let (Some(&p), Some(augmented)) = (
+        consumers
+            .iter()
+            .find(|&&p| grammar.declared_index(p).is_some()),
+        augmented,
+    ) {
+        push(
+            LintCategory::StartOnRhs,
+            word_span(src, &map, p, start),
+            format!(
+                "start symbol `{start}` is used in `{}`, no manual augmentation needed: \
+                 the grammar is augmented with `{}` and I_0 is its closure",
+                grammar.prods()[p],
+                augmented
+            ),
+        );
+    }
+    for nt in never_reduced(&grammar, &productive) {
+        if unreachable.contains(&nt) || !productive.contains_key(&nt) {
+            continue;
+        }
+        push(
+            LintCategory::NeverReduced,
+            head_span(nt),
+            format!(
+                "`{nt}` is never reduced: every production using it also needs an unproductive nonterminal"
+            ),
+        );
+    }
+
+    // 有推导环时 FIRST 集无法收敛, 不再构建分析表.
+    if !cyclic.is_empty() {
+        lints.sort();
+        return lints;
+    }
+    let family = Family::from_grammar(&grammar);
+    match family.as_ref().map_err(Clone::clone).map(Table::build_from) {
+        Ok(Ok(table)) => {
+            for c in crate::conflict::conflicts(&table) {
+                let prods: Vec<_> = c.prods.iter().map(|p| format!("r{p}")).collect();
+                push(
+                    LintCategory::Conflict,
+                    c.prods.first().and_then(|&p| map.prod(p)),
+                    format!(
+                        "{} conflict on `{}` in I_{} involving {}",
+                        c.kind,
+                        c.term,
+                        c.state,
+                        prods.join(", ")
+                    ),
+                );
+            }
+        }
+        Ok(Err(e)) | Err(e) => push(LintCategory::Parse, None, e.to_string()),
+    }
+    lints.sort();
+    lints
+}
+
+/// 产生式 `p` (增广文法中的编号) 尾部中第一个 `word` 的位置, 找不到时为整个产生式尾部.
+fn word_span(src: &str, map: &SourceMap, p: usize, word: &str) -> Option<Span> {
+    let span = map.prod(p)?;
+    let at = diagnostic::words(src.lines().nth(span.line)?)
+        .into_iter()
+        .find(|&(at, w)| at >= span.start && w == word)
+        .map(|(at, _)| at);
+    Some(at.map_or(span, |at| Span {
+        start: at,
+        end: at + word.len(),
+        ..span
+    }))
+}
+
+/// 终结符 `term` 是否像是非终结符 `nt` 的变体: 忽略大小写之后编辑距离不超过名字长度的三分之一 (至少为 1).
+///
+/// 短名字 (少于 3 个字符, 例如 `id`, `a`) 通常就是终结符, 不检查.
+fn looks_like(term: &str, nt: &str) -> bool {
+    let (term, nt) = (term.to_lowercase(), nt.to_lowercase());
+    if term.chars().count() < 3 || !term.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return false;
+    }
+    edit_distance(&term, &nt) <= (term.chars().count() / 3).max(1)
+}
+
+/// 两个字符串之间的 Levenshtein 编辑距离.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let next = (diag + usize::from(ca != cb))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diag = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// 除了增广产生式之外的产生式和它们的编号.
+fn declared_prods<'g, 'a>(
+    grammar: &'g Grammar<'a>,
+) -> impl Iterator<Item = (ProdId, &'g &'a Production<'a>)> {
+    let augmented = grammar.augmented_production();
+    grammar
+        .prods()
+        .iter()
+        .enumerate()
+        .filter(move |&(p, _)| Some(p) != augmented)
+}
+
+/// 所有产生式的头部 (不包括增广产生式), 按照第一次定义的顺序排列.
+fn heads<'a>(grammar: &Grammar<'a>) -> Vec<NonTerminal<'a>> {
+    let mut heads = Vec::new();
+    for (_, prod) in declared_prods(grammar) {
+        if !heads.contains(&prod.head()) {
+            heads.push(prod.head());
+        }
+    }
+    heads
+}
+
+/// 可以推导出空串的非终结符.
+fn nullable<'a>(grammar: &Grammar<'a>) -> HashSet<NonTerminal<'a>> {
+    let mut nullable = HashSet::new();
+    loop {
+        let mut changed = false;
+        for prod in grammar.prods() {
+            if !nullable.contains(&prod.head())
+                && prod.tail_without_eps().all(|tok| match tok {
+                    Token::NonTerminal(nt) => nullable.contains(nt),
+                    Token::Terminal(_) => false,
+                })
+            {
+                nullable.insert(prod.head());
+                changed = true;
+            }
+        }
+        if !changed {
+            return nullable;
+        }
+    }
+}
+
+/// 从 `nt` 出发回到自身的最短推导 `A => B => A`.
+///
+/// `whole` 为 `true` 时 `A -> α B β` 要求 `α` 和 `β` 都可以推导出空串 (推导环), 否则只要求 `α` (左递归).
+fn cycle<'a>(
+    grammar: &Grammar<'a>,
+    nullable: &HashSet<NonTerminal<'a>>,
+    nt: NonTerminal<'a>,
+    whole: bool,
+) -> Option<String> {
+    let is_nullable =
+        |tok: &Token<'a>| matches!(tok, Token::NonTerminal(nt) if nullable.contains(nt));
+    let next = |from: NonTerminal<'a>| {
+        let mut result = Vec::new();
+        for prod in grammar.prods().iter().filter(|p| p.head() == from) {
+            let tail: Vec<_> = prod.tail_without_eps().copied().collect();
+            for (i, tok) in tail.iter().enumerate() {
+                if let Token::NonTerminal(to) = *tok
+                    && (!whole || tail[i + 1..].iter().all(is_nullable))
+                {
+                    result.push(to);
+                }
+                if !is_nullable(tok) {
+                    break;
+                }
+            }
+        }
+        result
+    };
+    let mut parents: HashMap<NonTerminal<'a>, NonTerminal<'a>> = HashMap::new();
+    let mut queue = VecDeque::from([nt]);
+    while let Some(from) = queue.pop_front() {
+        for to in next(from) {
+            if to == nt {
+                let mut path = vec![nt, from];
+                let mut cur = from;
+                while cur != nt {
+                    cur = parents[&cur];
+                    path.push(cur);
+                }
+                path.reverse();
+                let path: Vec<_> = path.iter().map(NonTerminal::as_str).collect();
+                return Some(path.join(" => "));
+            }
+            if to != nt && !parents.contains_key(&to) {
+                parents.insert(to, from);
+                queue.push_back(to);
+            }
+        }
+    }
+    None
+}
+
+/// 只经过右部都可终止的产生式时从开始符号不可达的非终结符, 按照第一次定义的顺序排列.
+fn never_reduced<'a>(
+    grammar: &Grammar<'a>,
+    productive: &HashMap<NonTerminal<'a>, usize>,
+) -> Vec<NonTerminal<'a>> {
+    let complete = |prod: &Production<'a>| {
+        prod.tail().iter().all(|tok| match tok {
+            Token::NonTerminal(nt) => productive.contains_key(nt),
+            Token::Terminal(_) => true,
+        })
+    };
+    let mut reached = HashSet::from([grammar.symbol_start()]);
+    let mut queue = VecDeque::from([grammar.symbol_start()]);
+    while let Some(nt) = queue.pop_front() {
+        for prod in grammar
+            .prods()
+            .iter()
+            .filter(|p| p.head() == nt && complete(p))
+        {
+            for tok in prod.tail() {
+                if let Token::NonTerminal(next) = *tok
+                    && reached.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    heads(grammar)
+        .into_iter()
+        .filter(|nt| !reached.contains(nt))
+        .collect()
+}
+
+/// 按照种类分组渲染问题报告, 每组为种类和数量, 之后每行一个问题. 没有问题时为 `no problems found`.
+#[must_use]
+pub fn report(lints: &[Lint]) -> String {
+    if lints.is_empty() {
+        return "no problems found\n".to_string();
+    }
+    let mut groups: BTreeMap<LintCategory, Vec<&Lint>> = BTreeMap::new();
+    for lint in lints {
+        groups.entry(lint.category).or_default().push(lint);
+    }
+    let mut out = String::new();
+    for (category, lints) in groups {
+        let severity = match category.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        out += &format!("{category} ({severity}, {}):\n", lints.len());
+        for lint in lints {
+            out += &format!("  {lint}\n");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        diagnostic::Severity,
+        lint::{LintCategory, lint, report},
+    };
+    use pretty_assertions::assert_eq;
+
+    /// `src` 中种类为 `category` 的问题.
+    fn of(src: &str, category: LintCategory) -> Vec<String> {
+        lint(src, None)
+            .iter()
+            .filter(|l| l.category == category)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn report_groups() {
+        let src = "S -> L ; | A U | [ C ]\nL -> L , id | id | Id\nA -> a\nU -> U u\nC -> D\nD -> C | E\nN -> id";
+        assert_eq!(
+            report(&lint(src, None)),
+            "unreachable (warning, 1):
+  7:1: `N` is unreachable from the start symbol `S`
+unproductive (error, 1):
+  4:1: `U` cannot derive any string of terminals
+epsilon cycle (error, 2):
+  5:1: `C` derives itself (C => D => C), the grammar is ambiguous
+  6:1: `D` derives itself (D => C => D), the grammar is ambiguous
+left recursion (warning, 2):
+  2:1: `L` is left recursive (L => L), fine for LR but not for LL parsers
+  4:1: `U` is left recursive (U => U), fine for LR but not for LL parsers
+terminal used once (warning, 3):
+  2:20: terminal `Id` is used only once
+  3:6: terminal `a` is used only once, did you mean `A`?
+  4:6: terminal `u` is used only once, did you mean `U`?
+never reduced (warning, 1):
+  3:1: `A` is never reduced: every production using it also needs an unproductive nonterminal
+"
+        );
+        assert_eq!(
+            report(&lint("S -> ( A ) | x x\nA -> x", None)),
+            "no problems found\n"
+        );
+    }
+
+    #[test]
+    fn parse_error() {
+        let lints = lint("S -> a\nT", None);
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].category, LintCategory::Parse);
+        assert_eq!(lints[0].span.line, 1);
+        assert_eq!(lints[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn unreachable() {
+        assert_eq!(
+            of("S -> x x\nN -> x", LintCategory::Unreachable),
+            ["2:1: `N` is unreachable from the start symbol `S`"]
+        );
+    }
+
+    #[test]
+    fn unproductive() {
+        assert_eq!(
+            of("S -> x x | U\nU -> x U", LintCategory::Unproductive),
+            ["2:1: `U` cannot derive any string of terminals"]
+        );
+    }
+
+    #[test]
+    fn epsilon_cycle() {
+        let lints = lint("S -> C x\nC -> x | E | C", None);
+        assert_eq!(
+            lints.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["2:1: `C` derives itself (C => C), the grammar is ambiguous"]
+        );
+        assert_eq!(lints[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn left_recursion() {
+        assert_eq!(
+            of("S -> L\nL -> L , x | x", LintCategory::LeftRecursion),
+            ["2:1: `L` is left recursive (L => L), fine for LR but not for LL parsers"]
+        );
+        // 经过可以推导出空串的符号的左递归.
+        assert_eq!(
+            of(
+                "S -> L\nL -> O L x | x\nO -> o | E",
+                LintCategory::LeftRecursion
+            ),
+            ["2:1: `L` is left recursive (L => L), fine for LR but not for LL parsers"]
+        );
+    }
+
+    #[test]
+    fn conflict() {
+        let lints = lint("S -> if c then S | if c then S else S | if c", None);
+        let conflicts: Vec<_> = lints
+            .iter()
+            .filter(|l| l.category == LintCategory::Conflict)
+            .collect();
+        assert_eq!(
+            conflicts
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["1:6: shift/reduce conflict on `else` in I_11 involving r1, r2"]
+        );
+        assert_eq!(conflicts[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn single_use_terminal() {
+        assert_eq!(
+            of("S -> A foo\nA -> x x", LintCategory::SingleUseTerminal),
+            ["1:6: terminal `foo` is used only once"]
+        );
+        assert_eq!(
+            of("S -> A\nA -> a", LintCategory::SingleUseTerminal),
+            ["2:6: terminal `a` is used only once, did you mean `A`?"]
+        );
+        // 不检查运算符和括号.
+        assert_eq!(
+            of("S -> ( A )\nA -> x x", LintCategory::SingleUseTerminal),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn undefined_nonterminal() {
+        // `stmts` 没有定义, `stmt` 已经定义; `then` 和任何非终结符都不相近.
+        let src = "program -> { stmts }\nstmt -> if c then stmt | x ;\nstmt_list -> stmt";
+        assert_eq!(
+            of(src, LintCategory::UndefinedNonTerminal),
+            [
+                "1:14: `stmts` is a terminal because no production defines it, did you mean to define `stmts`? \
+              similar nonterminals: `stmt`"
+            ]
+        );
+        // 已经报告为未定义的非终结符, 不再报告只出现一次.
+        assert!(
+            of(src, LintCategory::SingleUseTerminal)
+                .iter()
+                .all(|l| !l.contains("stmts"))
+        );
+    }
+
+    #[test]
+    fn mixed_epsilon() {
+        assert_eq!(
+            report(&lint("S -> ( A ) | x\nA -> x E x | E E", None)),
+            "mixed epsilon (warning, 2):
+  2:8: `E` is ignored in `A -> x E x`, write `A -> x x` instead
+  2:14: `E` is ignored in `A -> E E`, write `A -> E` instead
+"
+        );
+    }
+
+    #[test]
+    fn never_reduced() {
+        assert_eq!(
+            of(
+                "S -> x x | A U\nA -> x\nU -> x U",
+                LintCategory::NeverReduced
+            ),
+            [
+                "2:1: `A` is never reduced: every production using it also needs an unproductive nonterminal"
+            ]
+        );
+    }
+
+    #[test]
+    fn start_on_rhs() {
+        let lints = lint("S -> ( S ) | x x", None);
+        assert_eq!(
+            report(&lints),
+            "start symbol on right-hand side (info, 1):
+  1:8: start symbol `S` is used in `S -> ( S )`, no manual augmentation needed: \
+the grammar is augmented with `Sprime -> S` and I_0 is its closure
+"
+        );
+        assert_eq!(lints[0].severity(), Severity::Info);
+    }
+}
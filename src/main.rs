@@ -1,33 +1,1301 @@
-use std::io::{self, Read};
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use bumpalo::Bump;
 use clap::Parser;
-use lr_analysis::*;
+use lr_analysis::{compare::Comparison, generate::Generator, *};
 
 #[derive(clap::Parser)]
 struct AppArgs {
+    /// 文法的开始符号, 默认为第一个产生式的头部.
     #[clap(short, long)]
-    symbol_start: String,
+    symbol_start: Option<String>,
+    /// 项集族和分析表的输出格式.
+    #[clap(short, long, value_enum, default_value_t = Format::Markdown)]
+    format: Format,
+    /// markdown 输出中每个项最多显示的前瞻符数量.
+    #[clap(long)]
+    max_look_aheads: Option<usize>,
+    /// markdown 输出中每个项集最多显示的项数量.
+    #[clap(long)]
+    max_items: Option<usize>,
+    /// markdown 输出中每个分析表最多包含的列数, 超过时拆分为多个表格.
+    #[clap(long)]
+    max_columns: Option<usize>,
+    /// markdown 输出中每个项集只显示内核项, 不显示闭包加入的项.
+    #[clap(long)]
+    kernel_only: bool,
+    /// markdown 输出中每个项集的项按照产生式的声明顺序排列.
+    #[clap(long)]
+    by_production: bool,
+    /// markdown 输出中分析表的样式.
+    #[clap(long, value_enum, default_value_t = TableStyleArg::Markdown)]
+    table_style: TableStyleArg,
+    /// 使用 Pager 弱相容合并构建最小 LR(1) 项集族, 分析行为和规范 LR(1) 相同, 状态数量接近 LALR(1).
+    #[clap(long)]
+    minimal: bool,
+    /// 把构造过程日志 (发现每个项集的边和内核项, 每个项填写的分析表格子, 见 `construction` 模块) 以 JSON 写入这个文件.
+    #[clap(long)]
+    construction_log: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Format {
+    /// 纯文本项集列表和 markdown 表格.
+    Markdown,
+    /// Typst 标记.
+    Typst,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum TableStyleArg {
+    /// markdown 表格.
+    Markdown,
+    /// 对齐的纯文本表格.
+    Ascii,
+    /// 每个状态一行, 只列出非空的格子.
+    Compact,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SnippetFormat {
+    /// markdown 列表.
+    Markdown,
+    /// LaTeX `tabular`.
+    Latex,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// 随机生成符合文法的终结符序列, 每行一个, 可用作模糊测试的语料.
+    Generate {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 生成的句子数量.
+        #[clap(short, long, default_value_t = 1000)]
+        count: usize,
+        /// 每个句子的最大终结符数量.
+        #[clap(short, long, default_value_t = 200)]
+        max_len: usize,
+        /// 随机数种子, 默认使用当前时间.
+        #[clap(long)]
+        seed: Option<u64>,
+        /// 不随机生成, 而是生成覆盖每个产生式 (或者每个分析表单元格) 至少一次的句子集合.
+        #[clap(long, value_enum)]
+        coverage: Option<Coverage>,
+    },
+    /// 使用文法分析输入的终结符序列 (以空白分隔), 输出最右推导过程或者语法分析树.
+    Parse {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 输入文件, 默认从标准输入读取.
+        #[clap(short, long)]
+        input: Option<PathBuf>,
+        /// 输出语法分析树而不是最右推导过程.
+        #[clap(short, long, value_enum)]
+        tree: Option<TreeStyle>,
+        /// 终结符类别 `TERM=REGEX`, 例如 `NUM=[0-9]+`, 可以指定多次, 输入中匹配的词素被归为该终结符.
+        #[clap(short, long)]
+        class: Vec<String>,
+        /// 不把文法中的字面终结符作为关键字, 终结符类别匹配到同样长度的词素时优先.
+        #[clap(long)]
+        no_keywords: bool,
+        /// 把交给语法分析器的终结符流录制到这个文件中, 可以使用 `replay` 子命令重放.
+        #[clap(long)]
+        record: Option<PathBuf>,
+    },
+    /// 使用同一个文法重放 `parse --record` 录制的终结符流, 分析表不同时拒绝重放.
+    Replay {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 录制文件.
+        recording: PathBuf,
+    },
+    /// 分析语料中的每个输入文件 (以空白分隔的终结符), 统计每个产生式的归约次数和每个状态的进入次数.
+    Profile {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 语料文件.
+        #[clap(required = true)]
+        corpus: Vec<PathBuf>,
+    },
+    /// 逐步讲解一个项集的闭包计算过程: 每个项触发加入了哪些项, 以及前瞻符的计算.
+    Explain {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 项集编号.
+        #[clap(long, default_value_t = 0)]
+        state: usize,
+        /// 不讲解闭包, 而是把项集 (项, 归约和出边) 导出为可以嵌入幻灯片的独立片段.
+        #[clap(long, value_enum)]
+        snippet: Option<SnippetFormat>,
+    },
+    /// 分析或者导出项集族的 GOTO 图.
+    Graph {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 输出格式.
+        #[clap(short, long, value_enum, default_value_t = GraphFormat::Loops)]
+        format: GraphFormat,
+        /// 在 DOT 输出中把每个环中的状态聚成一个 cluster.
+        #[clap(long)]
+        cluster: bool,
+        /// 只输出这个状态附近的子图 (DOT 格式), 忽略 `--format`.
+        #[clap(long)]
+        around: Option<usize>,
+        /// `--around` 子图的半径 (不考虑方向的边数).
+        #[clap(long, default_value_t = 2)]
+        radius: usize,
+    },
+    /// 根据文法生成语法分析器的源代码.
+    Codegen {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 目标语言.
+        #[clap(short, long, value_enum, default_value_t = Lang::C)]
+        lang: Lang,
+        /// 输出目录.
+        #[clap(short, long, default_value = ".")]
+        out_dir: PathBuf,
+        /// 导出符号的前缀, 同时也是输出文件名.
+        #[clap(short, long, default_value = "parser")]
+        prefix: String,
+        /// 为每个非终结符生成 AST 枚举和构建语法树的归约动作, 仅用于 Rust.
+        #[clap(long)]
+        ast: bool,
+        /// 生成恐慌模式错误恢复 (恢复表和带有错误恢复的分析函数).
+        #[clap(long)]
+        recovery: bool,
+    },
+    /// 对比文法在 LR(0), SLR(1), LALR(1), LR(1) 下的状态数量和分析表.
+    Compare {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 输出 HTML 而不是 markdown.
+        #[clap(long)]
+        html: bool,
+        /// 只输出 LR(1) 状态按照 LALR(1) 合并的报告: 可以合并的状态, 节省的状态数量以及是否引入冲突.
+        #[clap(long, conflicts_with = "html")]
+        merge: bool,
+    },
+    /// 输出 LR(0) 自动机 (没有前瞻符的项集族) 和其中的冲突, 有冲突时以非零状态退出.
+    Lr0 {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+    },
+    /// 使用 GLR 分析输入的终结符序列 (以空白分隔), 分析表可以有冲突, 输出共享压缩语法分析森林和其中的语法分析树.
+    Glr {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 输入文件, 默认从标准输入读取.
+        #[clap(short, long)]
+        input: Option<PathBuf>,
+        /// 最多输出的语法分析树数量.
+        #[clap(short, long, default_value_t = 8)]
+        limit: usize,
+    },
+    /// 导出编辑器自动补全数据 (JSON): 每个非终结符的 FIRST 集和产生式, 产生式同时渲染为代码片段.
+    Completion {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+    },
+    /// 确定性审计: 多次运行完整的分析, 检查所有输出是否完全相同, 输出不同之处.
+    Audit {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 运行次数.
+        #[clap(short, long, default_value_t = 10)]
+        runs: usize,
+        /// 生成句子使用的随机数种子.
+        #[clap(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// 逐个列出分析表中的冲突: 冲突的项, 到达冲突状态的活前缀以及修改建议.
+    Conflicts {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 一次输出所有冲突, 不在每个冲突之后等待. 标准输入不是终端时总是一次输出.
+        #[clap(long)]
+        all: bool,
+        /// 只输出根据二元运算符冲突推断出的优先级声明 (`%left + -` 等), 见 `precedence` 模块.
+        #[clap(long)]
+        precedence: bool,
+    },
+    /// 从分层的表达式文法中读出运算符表 (优先级, 结合性), 并检查分析表的实际结合方式是否一致.
+    Operators {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 表达式的非终结符, 同时作为开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+    },
+    /// 检查文法的常见问题 (不可达, 不可终止, 左递归, 推导环, 冲突, 只出现一次的终结符等), 按照种类分组输出.
+    Lint {
+        /// 文法文件, 可以指定多个.
+        #[clap(required = true)]
+        grammar: Vec<PathBuf>,
+        /// 文法的开始符号, 默认为每个文法第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+    },
+    /// 并行分析一个目录中的所有文法文件, 每个文件输出一行概要, 可以另外写出汇总的 JSON 报告.
+    Batch {
+        /// 文法文件所在的目录, 不递归进入子目录.
+        dir: PathBuf,
+        /// 文法的开始符号, 默认为每个文法第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 只分析这个扩展名的文件, 例如 `txt`.
+        #[clap(short, long)]
+        ext: Option<String>,
+        /// 线程数量, 默认为可用的 CPU 数量.
+        #[clap(short, long)]
+        jobs: Option<usize>,
+        /// 把汇总的 JSON 报告写入这个文件.
+        #[clap(long)]
+        json: Option<PathBuf>,
+        /// 同时输出每个文件的检查结果 (见 `lint` 子命令).
+        #[clap(short, long)]
+        verbose: bool,
+    },
+    /// 输出文法的规范形式 (非终结符按照结构重新命名, 产生式按照规范的顺序排列), 或者判断两个文法是否同构.
+    Canonical {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 和另一个文法 (开始符号为第一个产生式的头部) 对比, 同构时输出非终结符的对应关系.
+        #[clap(long)]
+        compare: Option<PathBuf>,
+    },
+    /// 把库文法合并进主文法, 输出组合后的文法, 非终结符的映射报告输出到标准错误.
+    Compose {
+        /// 主文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 主文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 库文法文件, 开始符号为第一个产生式的头部.
+        #[clap(short, long)]
+        library: PathBuf,
+        /// 库文法的非终结符加上前缀 `<namespace>::`, 不指定时同名的非终结符合并产生式.
+        #[clap(short, long)]
+        namespace: Option<String>,
+    },
+    /// 检测文法是否为右线性或者左线性的正则文法, 是的话输出等价的 DFA 和正则表达式, 否则以非零状态退出.
+    Regular {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+    },
+    /// 抽查两个文法的语言是否相同: 从每个文法中取出短句子, 检查是否属于另一个文法的语言, 输出最短的区分两者的句子.
+    Equiv {
+        /// 文法文件.
+        #[clap(short, long)]
+        grammar: PathBuf,
+        /// 文法的开始符号, 默认为第一个产生式的头部.
+        #[clap(short, long)]
+        symbol_start: Option<String>,
+        /// 对比的文法文件, 开始符号为第一个产生式的头部.
+        #[clap(short, long)]
+        other: PathBuf,
+        /// 句子的最大终结符数量.
+        #[clap(short, long, default_value_t = 8)]
+        max_len: usize,
+        /// 每个文法枚举的最短句子数量, 以及随机生成的句子数量.
+        #[clap(short, long, default_value_t = 1000)]
+        limit: usize,
+        /// 生成句子使用的随机数种子.
+        #[clap(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum TreeStyle {
+    /// 使用 ASCII 字符缩进.
+    Ascii,
+    /// 使用 Unicode 制表符缩进.
+    Unicode,
+    /// Graphviz DOT 格式.
+    Dot,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GraphFormat {
+    /// GOTO 图中的环 (强连通分量) 报告.
+    Loops,
+    /// Graphviz DOT 格式.
+    Dot,
+    /// GraphML 格式, 可以导入 Gephi 等工具.
+    Graphml,
+    /// cytoscape.js 格式的 JSON.
+    Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Lang {
+    /// C99, 输出 `<prefix>.h` 和 `<prefix>.c`.
+    C,
+    /// Rust, 输出模块文件 `<prefix>.rs`.
+    Rust,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Coverage {
+    /// 覆盖每个产生式.
+    Productions,
+    /// 覆盖分析表中的每个非空单元格.
+    Cells,
+}
+
+fn print_sentence(sentence: &[Terminal]) {
+    println!(
+        "{}",
+        sentence
+            .iter()
+            .map(Terminal::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+}
+
+/// 获取开始符号, 如果没有指定, 那么使用第一个产生式的头部.
+fn symbol_start<'a>(inp: &'a str, symbol_start: Option<&'a str>) -> &'a str {
+    symbol_start.unwrap_or_else(|| Grammar::cfg_start(inp).unwrap_or_default())
+}
+
+fn generate(
+    inp: &str,
+    start: Option<&str>,
+    count: usize,
+    max_len: usize,
+    seed: Option<u64>,
+    coverage: Option<Coverage>,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    if let Some(coverage) = coverage {
+        let grammar = grammar.augmented();
+        let generator = Generator::new(&grammar, seed);
+        match coverage {
+            Coverage::Productions => generator
+                .covering_sentences()
+                .iter()
+                .for_each(|s| print_sentence(s)),
+            Coverage::Cells => {
+                let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+                let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+                generator
+                    .covering_cell_sentences(&table)
+                    .iter()
+                    .for_each(|s| print_sentence(s));
+            }
+        }
+        return Ok(());
+    }
+    let mut generator = Generator::new(&grammar, seed);
+    for _ in 0..count {
+        let sentence = generator.sentence(max_len).ok_or(format!(
+            "cannot generate sentence of {} within {max_len} terminals",
+            grammar.symbol_start()
+        ))?;
+        print_sentence(&sentence);
+    }
+    Ok(())
+}
+
+fn parse(
+    grammar_inp: &str,
+    start: Option<&str>,
+    input: Option<&Path>,
+    tree: Option<TreeStyle>,
+    classes: &[String],
+    keywords: bool,
+    record: Option<&Path>,
+) -> Result<(), String> {
+    let mut reader: Box<dyn BufRead> = match input {
+        Some(path) => Box::new(io::BufReader::new(
+            fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    // 终结符类别需要扫描整个输入, 词素借用输入的内容, 需要比文法活得更久.
+    let mut inp = String::new();
+    if !classes.is_empty() {
+        reader.read_to_string(&mut inp).map_err(|e| e.to_string())?;
+    }
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(grammar_inp, symbol_start(grammar_inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let parser = lr_analysis::Parser::new(&table);
+    let save = |recording: replay::Recording| match record {
+        Some(path) => {
+            fs::write(path, recording.to_text()).map_err(|e| format!("{}: {e}", path.display()))
+        }
+        None => Ok(()),
+    };
+    let output = if classes.is_empty() {
+        // 逐行读取以空白分隔的终结符, 不需要把整个输入读入内存.
+        let mut terms = replay::Recorder::new(lexer::TermReader::new(&grammar, reader));
+        let output = parser.parse(&mut terms).map_err(|e| e.to_string())?;
+        save(terms.recording(&table))?;
+        if let Some(e) = terms.into_inner().take_error() {
+            Err(e.to_string())?
+        }
+        output
+    } else {
+        let mut spec = lexer::LexerSpec::new(&grammar).keywords(keywords);
+        for class in classes {
+            let (term, pattern) = class
+                .split_once('=')
+                .ok_or_else(|| format!("invalid terminal class: {class}, expected TERM=REGEX"))?;
+            spec = spec.regex(term, pattern).map_err(|e| e.to_string())?;
+        }
+        let lexed = spec.lex_recover(&inp);
+        for (span, e) in &lexed.errors {
+            eprintln!("{}:{}: {e}", span.line + 1, span.start + 1);
+        }
+        let mut terms = replay::Recorder::new(lexed.terms.into_iter());
+        let output = parser.parse(&mut terms).map_err(|e| e.to_string())?;
+        save(terms.recording(&table))?;
+        output
+    };
+    print_parse(&grammar, &output, tree)
+}
+
+fn replay(grammar_inp: &str, start: Option<&str>, recording: &str) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(grammar_inp, symbol_start(grammar_inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let recording = replay::Recording::parse(recording, &grammar).map_err(|e| e.to_string())?;
+    let output = recording
+        .replay(&lr_analysis::Parser::new(&table))
+        .map_err(|e| e.to_string())?;
+    print_parse(&grammar, &output, None)
+}
+
+/// 输出分析结果: 恢复报告输出到标准错误, 最右推导过程或者语法分析树输出到标准输出.
+fn print_parse<'a>(
+    grammar: &Grammar<'a>,
+    output: &parser::ParseOutput<'a>,
+    tree: Option<TreeStyle>,
+) -> Result<(), String> {
+    eprintln!("{}", output.report());
+    match tree {
+        None => {
+            for form in output.marked_derivation() {
+                println!("{form}");
+            }
+        }
+        Some(style) => {
+            // 没有被接受时输出已经读取的部分对应的子树.
+            let trees = match output.tree(grammar) {
+                Some(tree) => vec![tree],
+                None => output.partial_tree(grammar),
+            };
+            for tree in &trees {
+                match style {
+                    TreeStyle::Ascii => println!("{}", tree.to_ascii()),
+                    TreeStyle::Unicode => println!("{}", tree.to_unicode()),
+                    TreeStyle::Dot => println!("{}", tree.to_dot()),
+                }
+            }
+            if !output.accepted() {
+                Err("input is not accepted, printed the partial parse tree")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn profile(grammar_inp: &str, start: Option<&str>, corpus: &[PathBuf]) -> Result<(), String> {
+    // 输入的终结符借用文件内容, 需要比文法活得更久.
+    let inputs = corpus
+        .iter()
+        .map(|path| fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(grammar_inp, symbol_start(grammar_inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let parser = lr_analysis::Parser::new(&table);
+    let mut coverage = lr_analysis::Coverage::new(&table);
+    for (path, inp) in corpus.iter().zip(&inputs) {
+        let output = parser
+            .parse_with_coverage(inp.split_whitespace().map(Terminal::from), &mut coverage)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        if output.report().errors() > 0 {
+            eprintln!("{}: {}", path.display(), output.report());
+        }
+    }
+    print!("{}", coverage.profile());
+    Ok(())
+}
+
+fn explain(
+    inp: &str,
+    start: Option<&str>,
+    state: usize,
+    snippet: Option<SnippetFormat>,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let is = family
+        .item_set(state)
+        .ok_or_else(|| lr_analysis::error::Error::StateNotFound(state).to_string())?;
+    match snippet {
+        Some(SnippetFormat::Markdown) => {
+            println!("{}", is.to_markdown(&family));
+            return Ok(());
+        }
+        Some(SnippetFormat::Latex) => {
+            println!("{}", is.to_latex(&family));
+            return Ok(());
+        }
+        None => {}
+    }
+    println!("I_{state}:");
+    println!("{}", is.explain().map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+fn graph(
+    inp: &str,
+    start: Option<&str>,
+    format: GraphFormat,
+    cluster: bool,
+    around: Option<(usize, usize)>,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    if let Some((state, radius)) = around {
+        let neighborhood = family
+            .neighborhood(state, radius)
+            .ok_or_else(|| lr_analysis::error::Error::StateNotFound(state).to_string())?;
+        println!("{}", neighborhood.to_dot());
+        return Ok(());
+    }
+    match format {
+        GraphFormat::Loops => {
+            for lp in family.loops() {
+                println!("{lp}");
+            }
+        }
+        GraphFormat::Dot => println!("{}", family.to_dot(cluster)),
+        GraphFormat::Graphml => println!("{}", family.to_graphml()),
+        GraphFormat::Json => println!("{}", family.to_json()),
+    }
+    Ok(())
+}
+
+fn codegen(
+    inp: &str,
+    start: Option<&str>,
+    lang: Lang,
+    out_dir: &Path,
+    prefix: &str,
+    options: &codegen::Options,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    match lang {
+        Lang::C => {
+            let c = codegen::c::generate(&table, prefix, options).map_err(|e| e.to_string())?;
+            fs::write(out_dir.join(format!("{prefix}.h")), c.header).map_err(|e| e.to_string())?;
+            fs::write(out_dir.join(format!("{prefix}.c")), c.source).map_err(|e| e.to_string())?;
+        }
+        Lang::Rust => {
+            let source = codegen::rust::generate(&table, options).map_err(|e| e.to_string())?;
+            fs::write(out_dir.join(format!("{prefix}.rs")), source).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn compare(inp: &str, start: Option<&str>, html: bool, merge: bool) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let comparison = Comparison::new(&table).map_err(|e| e.to_string())?;
+    if merge {
+        println!("{}", comparison.merge_report());
+    } else if html {
+        println!("{}", comparison.to_html());
+    } else {
+        println!("{}", comparison.to_markdown());
+    }
+    Ok(())
+}
+
+fn lr0(inp: &str, start: Option<&str>) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Lr0Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    print!("{family}");
+    let conflicts = family.conflicts();
+    if conflicts.is_empty() {
+        println!("{} states, no LR(0) conflicts", family.len());
+        return Ok(());
+    }
+    for c in &conflicts {
+        println!("{c}");
+    }
+    Err(format!("{} states with LR(0) conflicts", conflicts.len()))
+}
+
+fn glr(inp: &str, start: Option<&str>, input: Option<&Path>, limit: usize) -> Result<(), String> {
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) => Box::new(io::BufReader::new(
+            fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let mut terms = lexer::TermReader::new(&grammar, reader);
+    let forest = glr::GlrParser::new(&table)
+        .parse(&mut terms)
+        .map_err(|e| e.to_string())?;
+    if let Some(e) = terms.take_error() {
+        Err(e.to_string())?
+    }
+    print!("{forest}");
+    let trees = forest.trees(limit);
+    eprintln!(
+        "{} ambiguous nodes, printing {} trees",
+        forest.ambiguities().count(),
+        trees.len()
+    );
+    for tree in &trees {
+        println!("\n{}", tree.to_unicode());
+    }
+    Ok(())
+}
+
+fn completion(inp: &str, start: Option<&str>) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let completions = completion::completions(&grammar).map_err(|e| e.to_string())?;
+    print!("{}", completion::to_json(&completions));
+    Ok(())
+}
+
+fn audit(inp: &str, start: Option<&str>, runs: usize, seed: u64) -> Result<(), String> {
+    let divergences = lr_analysis::audit::audit(inp, symbol_start(inp, start), runs, seed)
+        .map_err(|e| e.to_string())?;
+    if divergences.is_empty() {
+        println!("{runs} runs, all outputs are identical");
+        return Ok(());
+    }
+    for d in &divergences {
+        println!("{d}");
+    }
+    Err(format!("{} divergences in {runs} runs", divergences.len()))
+}
+
+fn operators(inp: &str, start: Option<&str>) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let root = NonTerminal::from(symbol_start(inp, start));
+    let levels = precedence::extract(&grammar, root);
+    if levels.is_empty() {
+        return Err(format!("no operator levels found below `{root}`"));
+    }
+    print!("{}", precedence::to_table(&levels));
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let disagreements = precedence::verify(&table, &levels);
+    for d in &disagreements {
+        println!("{d}");
+    }
+    if disagreements.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} sentences disagree with the operator table",
+            disagreements.len()
+        ))
+    }
+}
+
+fn conflicts(inp: &str, start: Option<&str>, all: bool, decls: bool) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?
+        .augmented();
+    let family = Family::from_grammar(&grammar).map_err(|e| e.to_string())?;
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+    let found = conflict::conflicts(&table);
+    let levels = precedence::infer(&table, &found);
+    if decls {
+        print!("{}", precedence::declarations(&levels));
+        return Ok(());
+    }
+    if found.is_empty() {
+        println!("no conflicts, the grammar is LR(1)");
+        return Ok(());
+    }
+    let paging = !all && io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    for (i, c) in found.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, found.len(), c.report(&table));
+        if paging && i + 1 < found.len() {
+            eprint!("-- Enter for the next conflict, q to quit -- ");
+            io::stderr().flush().map_err(|e| e.to_string())?;
+            match lines.next() {
+                Some(Ok(line)) if line.trim() != "q" => {}
+                _ => break,
+            }
+        }
+    }
+    if !levels.is_empty() {
+        println!(
+            "inferred operator table:\n{}",
+            precedence::to_table(&levels)
+        );
+        print!(
+            "suggested declarations:\n{}",
+            precedence::declarations(&levels)
+        );
+    }
+    Err(format!("{} conflicts", found.len()))
+}
+
+fn lint(grammars: &[PathBuf], start: Option<&str>) -> Result<(), String> {
+    let mut failed = 0;
+    for (i, path) in grammars.iter().enumerate() {
+        let lints = match fs::read_to_string(path) {
+            Ok(inp) => lr_analysis::lint::lint(&inp, start),
+            Err(e) => Err(format!("{}: {e}", path.display()))?,
+        };
+        if grammars.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("== {} ==", path.display());
+        }
+        print!("{}", lr_analysis::lint::report(&lints));
+        failed += usize::from(
+            lints
+                .iter()
+                .any(|l| l.severity() == diagnostic::Severity::Error),
+        );
+    }
+    if failed > 0 {
+        Err(format!(
+            "{failed} of {} grammars have errors",
+            grammars.len()
+        ))?;
+    }
+    Ok(())
+}
+
+fn batch(
+    dir: &Path,
+    start: Option<&str>,
+    ext: Option<&str>,
+    jobs: Option<usize>,
+    json: Option<&Path>,
+    verbose: bool,
+) -> Result<(), String> {
+    let mut files = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| ext.is_none_or(|ext| path.extension().is_some_and(|e| e == ext)))
+        .collect::<Vec<_>>();
+    files.sort();
+    let jobs = jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from));
+    let summaries = lr_analysis::batch::analyze(&files, start, jobs);
+    for summary in &summaries {
+        println!("{summary}");
+        if verbose && !summary.lints.is_empty() {
+            for line in lr_analysis::lint::report(&summary.lints).lines() {
+                println!("    {line}");
+            }
+        }
+    }
+    if let Some(json) = json {
+        fs::write(json, lr_analysis::batch::to_json(&summaries))
+            .map_err(|e| format!("{}: {e}", json.display()))?;
+    }
+    let failed = summaries.iter().filter(|s| !s.passed()).count();
+    if failed > 0 {
+        Err(format!(
+            "{failed} of {} grammars have errors",
+            summaries.len()
+        ))?;
+    }
+    println!("all {} grammars passed", summaries.len());
+    Ok(())
+}
+
+fn canonical(inp: &str, start: Option<&str>, other: Option<&str>) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let Some(other) = other else {
+        print!("{}", canonical::Canonical::new(&grammar));
+        return Ok(());
+    };
+    let other = Grammar::from_cfg(other, symbol_start(other, None).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let mapping = canonical::isomorphism(&grammar, &other).ok_or("not isomorphic")?;
+    println!("isomorphic");
+    for (a, b) in mapping {
+        println!("{a} = {b}");
+    }
+    Ok(())
+}
+
+fn compose(
+    inp: &str,
+    start: Option<&str>,
+    library: &str,
+    namespace: Option<&str>,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let library = Grammar::from_cfg(library, symbol_start(library, None).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let composition =
+        compose::compose(&grammar, &library, namespace, &bump).map_err(|e| e.to_string())?;
+    print!("{}", composition.grammar);
+    eprint!("{composition}");
+    Ok(())
+}
+
+fn regular(inp: &str, start: Option<&str>) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let dfa = regular::detect(&grammar).map_err(|e| {
+        format!(
+            "not detected as regular: `{}` is not right-linear, `{}` is not left-linear",
+            grammar.prods()[e.not_right],
+            grammar.prods()[e.not_left]
+        )
+    })?;
+    println!(
+        "{} grammar, DFA with {} states:",
+        dfa.linearity,
+        dfa.states()
+    );
+    print!("{dfa}");
+    println!("regex: {}", dfa.to_regex());
+    Ok(())
+}
+
+fn equiv(
+    inp: &str,
+    start: Option<&str>,
+    other: &str,
+    max_len: usize,
+    limit: usize,
+    seed: u64,
+) -> Result<(), String> {
+    let bump = Bump::new();
+    let grammar = Grammar::from_cfg(inp, symbol_start(inp, start).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let other = Grammar::from_cfg(other, symbol_start(other, None).into(), &bump)
+        .map_err(|e| e.to_string())?;
+    let result = equiv::check(&grammar, &other, max_len, limit, seed);
+    match result.difference {
+        None => {
+            println!(
+                "no difference found in {} sentences up to {max_len} terminals",
+                result.checked
+            );
+            Ok(())
+        }
+        Some(diff) => {
+            let (yes, no) = if diff.in_first {
+                ("first", "second")
+            } else {
+                ("second", "first")
+            };
+            println!("{}", diff.sentence.join(" "));
+            Err(format!(
+                "the sentence above is in the {yes} grammar but not in the {no} (checked {} sentences)",
+                result.checked
+            ))
+        }
+    }
 }
 
 fn main() {
     let args = AppArgs::parse();
+    if let Err(e) = run(&args) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// 读取文件的内容, 错误信息带上文件路径.
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn run(args: &AppArgs) -> Result<(), String> {
+    match &args.command {
+        None => {
+            let options = render::RenderOptions {
+                max_look_aheads: args.max_look_aheads,
+                max_items: args.max_items,
+                max_columns: args.max_columns,
+                kernel_only: args.kernel_only,
+                by_production: args.by_production,
+            };
+            let style = render::TableStyle {
+                format: match args.table_style {
+                    TableStyleArg::Markdown => render::TableFormat::Markdown,
+                    TableStyleArg::Ascii => render::TableFormat::Ascii,
+                    TableStyleArg::Compact => render::TableFormat::Compact,
+                },
+                options,
+            };
+            print_analysis(
+                args.symbol_start.as_deref(),
+                args.format,
+                &style,
+                args.minimal,
+                args.construction_log.as_deref(),
+            )
+        }
+        Some(Command::Parse {
+            grammar,
+            symbol_start,
+            input,
+            tree,
+            class,
+            no_keywords,
+            record,
+        }) => {
+            let grammar_inp = read(grammar)?;
+            parse(
+                &grammar_inp,
+                symbol_start.as_deref(),
+                input.as_deref(),
+                *tree,
+                class,
+                !no_keywords,
+                record.as_deref(),
+            )
+        }
+        Some(Command::Replay {
+            grammar,
+            symbol_start,
+            recording,
+        }) => replay(&read(grammar)?, symbol_start.as_deref(), &read(recording)?),
+        Some(Command::Explain {
+            grammar,
+            symbol_start,
+            state,
+            snippet,
+        }) => explain(&read(grammar)?, symbol_start.as_deref(), *state, *snippet),
+        Some(Command::Graph {
+            grammar,
+            symbol_start,
+            format,
+            cluster,
+            around,
+            radius,
+        }) => graph(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            *format,
+            *cluster,
+            around.map(|state| (state, *radius)),
+        ),
+        Some(Command::Codegen {
+            grammar,
+            symbol_start,
+            lang,
+            out_dir,
+            prefix,
+            ast,
+            recovery,
+        }) => codegen(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            *lang,
+            out_dir,
+            prefix,
+            &codegen::Options {
+                recovery: *recovery,
+                ast: *ast,
+            },
+        ),
+        Some(Command::Compare {
+            grammar,
+            symbol_start,
+            html,
+            merge,
+        }) => compare(&read(grammar)?, symbol_start.as_deref(), *html, *merge),
+        Some(Command::Lr0 {
+            grammar,
+            symbol_start,
+        }) => lr0(&read(grammar)?, symbol_start.as_deref()),
+        Some(Command::Glr {
+            grammar,
+            symbol_start,
+            input,
+            limit,
+        }) => glr(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            input.as_deref(),
+            *limit,
+        ),
+        Some(Command::Completion {
+            grammar,
+            symbol_start,
+        }) => completion(&read(grammar)?, symbol_start.as_deref()),
+        Some(Command::Profile {
+            grammar,
+            symbol_start,
+            corpus,
+        }) => profile(&read(grammar)?, symbol_start.as_deref(), corpus),
+        Some(Command::Audit {
+            grammar,
+            symbol_start,
+            runs,
+            seed,
+        }) => audit(&read(grammar)?, symbol_start.as_deref(), *runs, *seed),
+        Some(Command::Conflicts {
+            grammar,
+            symbol_start,
+            all,
+            precedence,
+        }) => conflicts(&read(grammar)?, symbol_start.as_deref(), *all, *precedence),
+        Some(Command::Operators {
+            grammar,
+            symbol_start,
+        }) => operators(&read(grammar)?, symbol_start.as_deref()),
+        Some(Command::Lint {
+            grammar,
+            symbol_start,
+        }) => lint(grammar, symbol_start.as_deref()),
+        Some(Command::Batch {
+            dir,
+            symbol_start,
+            ext,
+            jobs,
+            json,
+            verbose,
+        }) => batch(
+            dir,
+            symbol_start.as_deref(),
+            ext.as_deref(),
+            *jobs,
+            json.as_deref(),
+            *verbose,
+        ),
+        Some(Command::Canonical {
+            grammar,
+            symbol_start,
+            compare,
+        }) => {
+            let other = compare.as_deref().map(read).transpose()?;
+            canonical(&read(grammar)?, symbol_start.as_deref(), other.as_deref())
+        }
+        Some(Command::Compose {
+            grammar,
+            symbol_start,
+            library,
+            namespace,
+        }) => compose(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            &read(library)?,
+            namespace.as_deref(),
+        ),
+        Some(Command::Regular {
+            grammar,
+            symbol_start,
+        }) => regular(&read(grammar)?, symbol_start.as_deref()),
+        Some(Command::Equiv {
+            grammar,
+            symbol_start,
+            other,
+            max_len,
+            limit,
+            seed,
+        }) => equiv(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            &read(other)?,
+            *max_len,
+            *limit,
+            *seed,
+        ),
+        Some(Command::Generate {
+            grammar,
+            symbol_start,
+            count,
+            max_len,
+            seed,
+            coverage,
+        }) => generate(
+            &read(grammar)?,
+            symbol_start.as_deref(),
+            *count,
+            *max_len,
+            *seed,
+            *coverage,
+        ),
+    }
+}
+
+/// 从标准输入读取文法, 输出产生式, 项集族和语法分析表.
+fn print_analysis(
+    symbol_start_opt: Option<&str>,
+    format: Format,
+    style: &render::TableStyle,
+    minimal: bool,
+    construction_log: Option<&Path>,
+) -> Result<(), String> {
     let mut inp = String::new();
-    io::stdin().read_to_string(&mut inp).unwrap();
+    io::stdin()
+        .read_to_string(&mut inp)
+        .map_err(|e| e.to_string())?;
     let bump = Bump::new();
-    let grammar = Grammar::from_cfg(&inp, args.symbol_start.as_str().into(), &bump)
-        .unwrap()
+    let start = symbol_start(&inp, symbol_start_opt);
+    let grammar = Grammar::from_cfg(&inp, start.into(), &bump)
+        .map_err(|e| e.to_string())?
         .augmented();
-    for prod in grammar.prods() {
-        println!("{:>4} {}", grammar.index_of_prod(prod).unwrap(), prod);
+    let family = if minimal {
+        Family::from_grammar_minimal(&grammar)
+    } else {
+        Family::from_grammar(&grammar)
+    }
+    .map_err(|e| e.to_string())?;
+    if let Some(path) = construction_log {
+        let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+        let steps = construction::construction_log(&table);
+        fs::write(path, construction::to_json(&table, &steps))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
     }
-    println!();
-    let family = Family::from_grammar(&grammar);
+    if let Format::Typst = format {
+        for prod in grammar.prods() {
+            let idx = grammar.index_of_prod(prod).unwrap();
+            println!("/ r{idx}: #raw({:?})", prod.to_string());
+        }
+        println!();
+        println!("{}", family.to_typst());
+        println!();
+        let table = Table::build_from(&family).map_err(|e| e.to_string())?;
+        println!("{}", table.to_typst());
+        return Ok(());
+    }
+    println!("{grammar:#}");
     for (from, is) in family.item_sets().iter().enumerate() {
         println!("I_{from}:");
-        for item in is.items() {
-            println!("{}", item);
-        }
+        println!("{}", is.render(&style.options));
         println!("reduces:");
         for (item, term) in is.reduces() {
             let prod_idx = grammar.index_of_prod(item.prod()).unwrap();
@@ -41,6 +1309,8 @@ fn main() {
         }
         println!();
     }
+    let table = Table::build_from(&family).map_err(|e| e.to_string())?;
     println!("--- Table ---");
-    println!("{}", Table::build_from(&family, &grammar).to_markdown());
+    println!("{}", table.render(style));
+    Ok(())
 }
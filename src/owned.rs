@@ -0,0 +1,429 @@
+//! 不引用文法 arena 的项集族, 分析表和分析过程.
+//!
+//! [`Family`] 中的项集都分配在文法的 [`bumpalo::Bump`] 中, 不能离开创建文法的作用域,
+//! 也不能发送到其他线程. [`OwnedFamily`] 把项集复制成拥有所有权的数据 (符号都转换为 [`String`]),
+//! 可以随意移动, 跨线程发送, 并且和文法的 arena 分别释放.
+//!
+//! 开启 `serde` feature 之后这些结构可以序列化为 JSON 导出, 开启 `schema` feature 之后还可以得到
+//! 导出内容的 JSON Schema, 见: [`crate::schema`].
+
+use std::collections::BTreeMap;
+
+use crate::{
+    ActionCell, Family, ProdId, StateId, Table,
+    error::Error,
+    grammar::VERSION_KEY,
+    parser::ParseOutput,
+    replay::fingerprint,
+    table::{TableDefect, validate_cells},
+};
+
+/// 拥有所有权的项, 见: [`crate::Item`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedItem {
+    /// 产生式编号.
+    pub prod: ProdId,
+    /// dot 所处的位置, 产生式中的 epsilon 不算长度.
+    pub dot: usize,
+    /// 前瞻符, 有序.
+    pub look_aheads: Vec<String>,
+    /// 项的文本, 同 [`crate::Item`] 的 [`std::fmt::Display`].
+    pub text: String,
+}
+
+/// 拥有所有权的项集, 项的顺序和 [`crate::ItemSet::items`] 相同.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedItemSet {
+    pub items: Vec<OwnedItem>,
+}
+
+/// 拥有所有项集的项集族, 状态编号和 [`Family`] 相同.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedFamily {
+    /// 按照产生式编号排列的产生式文本.
+    pub prods: Vec<String>,
+    /// 按照状态编号排列的项集.
+    pub item_sets: Vec<OwnedItemSet>,
+    /// 每个状态的 goto 出边, 键为符号, 值为到达的状态.
+    pub gotos: Vec<BTreeMap<String, Vec<StateId>>>,
+}
+
+impl OwnedFamily {
+    /// 复制 `family` 中的所有项集和 goto 出边.
+    #[must_use]
+    pub fn from_family(family: &Family<'_>) -> Self {
+        let grammar = family.item_set(0).map(|is| is.grammar());
+        let prods = grammar
+            .map(|g| g.prods().iter().map(ToString::to_string).collect())
+            .unwrap_or_default();
+        let item_sets = family
+            .item_sets()
+            .iter()
+            .map(|is| OwnedItemSet {
+                items: is
+                    .items()
+                    .map(|item| OwnedItem {
+                        prod: grammar
+                            .and_then(|g| g.index_of_prod(item.prod()))
+                            .unwrap_or(ProdId::MAX),
+                        dot: item.dot(),
+                        look_aheads: item.look_aheads().iter().map(ToString::to_string).collect(),
+                        text: item.to_string(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        let gotos = (0..family.len())
+            .map(|state| {
+                family
+                    .gotos_of(state)
+                    .into_iter()
+                    .flatten()
+                    .map(|(tok, dests)| (tok.to_string(), dests.iter().copied().collect()))
+                    .collect()
+            })
+            .collect();
+        Self {
+            prods,
+            item_sets,
+            gotos,
+        }
+    }
+
+    /// 获取项集 I_`state`, 编号超出范围时返回 [`None`].
+    #[must_use]
+    pub fn item_set(&self, state: StateId) -> Option<&OwnedItemSet> {
+        self.item_sets.get(state)
+    }
+
+    /// 从 `state` 经过符号 `token` 到达的状态, 没有对应的出边时返回 [`None`].
+    #[must_use]
+    pub fn goto(&self, state: StateId, token: &str) -> Option<StateId> {
+        self.gotos.get(state)?.get(token)?.first().copied()
+    }
+
+    /// 获取项集族数量
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.item_sets.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 拥有所有权的语法分析表, 状态编号和 [`Table`] 相同.
+///
+/// 开启 `serde` feature 之后可以从导出的 JSON 中恢复, 使用之前可以用 [`OwnedTable::validate`] 自检.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedTable {
+    /// 按照产生式编号排列的产生式文本.
+    pub prods: Vec<String>,
+    /// 每个产生式的左部, 和 `prods` 一一对应.
+    pub prod_heads: Vec<String>,
+    /// 每个产生式右部的长度, 不包括 [`crate::EPSILON`].
+    pub prod_lens: Vec<usize>,
+    /// ACTION 表的列对应的终结符, 见: [`Table::symbols`].
+    pub terms: Vec<String>,
+    /// GOTO 表的列对应的非终结符, 见: [`Table::symbols`].
+    pub non_terms: Vec<String>,
+    /// 每个状态的 ACTION 表中的非空格子, 键为终结符.
+    pub actions: Vec<BTreeMap<String, ActionCell>>,
+    /// 每个状态的 GOTO 表中的非空格子, 键为非终结符.
+    pub gotos: Vec<BTreeMap<String, StateId>>,
+    /// 分析表中是否有冲突.
+    pub conflict: bool,
+    /// 文法的元数据, 见: [`crate::Grammar::metadata`].
+    pub metadata: BTreeMap<String, String>,
+    /// 分析表的指纹, 见: [`fingerprint`].
+    pub fingerprint: u64,
+}
+
+impl OwnedTable {
+    /// 复制 `table` 中所有非空的格子以及文法的元数据.
+    #[must_use]
+    pub fn from_table(table: &Table<'_>) -> Self {
+        let symbols = table.symbols();
+        let prods = table.grammar().prods();
+        Self {
+            prods: prods.iter().map(ToString::to_string).collect(),
+            prod_heads: prods.iter().map(|p| p.head().to_string()).collect(),
+            prod_lens: prods.iter().map(|p| p.len()).collect(),
+            terms: symbols.terms.iter().map(ToString::to_string).collect(),
+            non_terms: symbols.non_terms.iter().map(ToString::to_string).collect(),
+            actions: (0..table.rows())
+                .map(|state| {
+                    table
+                        .actions(state)
+                        .into_iter()
+                        .flatten()
+                        .map(|(t, a)| (t.to_string(), a.clone()))
+                        .collect()
+                })
+                .collect(),
+            gotos: (0..table.rows())
+                .map(|state| {
+                    table
+                        .gotos(state)
+                        .into_iter()
+                        .flatten()
+                        .map(|(nt, to)| (nt.to_string(), to))
+                        .collect()
+                })
+                .collect(),
+            conflict: table.conflict(),
+            metadata: table.grammar().metadata().clone(),
+            fingerprint: fingerprint(table),
+        }
+    }
+
+    /// 文法的版本号, 见: [`crate::Grammar::version`].
+    #[must_use]
+    pub fn version(&self) -> Option<&str> {
+        self.metadata.get(VERSION_KEY).map(String::as_str)
+    }
+
+    /// 检查分析表的内部一致性, 和 [`Table::validate`] 相同, 用于检查从缓存中反序列化的分析表.
+    ///
+    /// `prod_heads` 和 `prod_lens` 的长度不同时以较短的为准, 超出的产生式编号报告为 [`TableDefect::ProductionOutOfRange`].
+    #[must_use]
+    pub fn validate(&self) -> Vec<TableDefect> {
+        let prods: Vec<_> = self
+            .prod_heads
+            .iter()
+            .zip(&self.prod_lens)
+            .map(|(head, &len)| (head.as_str(), len))
+            .collect();
+        let actions = self.actions.iter().enumerate().flat_map(|(state, row)| {
+            row.iter().flat_map(move |(term, cell)| {
+                cell.flatten().map(move |leaf| (state, term.as_str(), leaf))
+            })
+        });
+        let gotos = self.gotos.iter().enumerate().flat_map(|(state, row)| {
+            row.iter()
+                .map(move |(non_term, &to)| (state, non_term.as_str(), to))
+        });
+        validate_cells(self.actions.len(), &prods, actions, gotos)
+    }
+
+    /// 检查导出的分析表和 `table` 是否相同 (文法和所有格子都相同), 例如检查部署的分析表是否需要重新生成.
+    ///
+    /// # Errors
+    /// - [`Error::TableMismatch`] 两个分析表的指纹不同.
+    pub fn check_compatible(&self, table: &Table<'_>) -> Result<(), Error> {
+        let found = fingerprint(table);
+        if self.fingerprint == found {
+            Ok(())
+        } else {
+            Err(Error::TableMismatch {
+                expected: self.fingerprint,
+                found,
+            })
+        }
+    }
+}
+
+/// 拥有所有权的归约操作, 见: [`crate::parser::Reduction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedReduction {
+    /// 归约的产生式编号.
+    pub prod: ProdId,
+    /// 归约之前的符号栈.
+    pub stack: Vec<String>,
+    /// 归约时还没有读取的输入的起始位置, 是 [`OwnedTrace::terms`] 的下标.
+    pub cursor: usize,
+}
+
+/// 拥有所有权的语法分析过程, 见: [`ParseOutput`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OwnedTrace {
+    /// 恢复之后的输入终结符序列.
+    pub terms: Vec<String>,
+    /// 按照发生顺序记录的归约操作.
+    pub reductions: Vec<OwnedReduction>,
+    /// 按照发生顺序记录的恐慌恢复事件的文本.
+    pub events: Vec<String>,
+    /// 输入是否最终被接受.
+    pub accepted: bool,
+}
+
+impl OwnedTrace {
+    /// 复制 `output` 中的输入, 归约操作和恢复事件.
+    #[must_use]
+    pub fn from_output(output: &ParseOutput<'_>) -> Self {
+        Self {
+            terms: output.terms().iter().map(|t| t.term.to_string()).collect(),
+            reductions: output
+                .reductions()
+                .iter()
+                .map(|r| OwnedReduction {
+                    prod: r.prod,
+                    stack: r.stack.iter().map(ToString::to_string).collect(),
+                    cursor: r.cursor,
+                })
+                .collect(),
+            events: output
+                .report()
+                .events()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            accepted: output.accepted(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        ActionCell, Family, Grammar, Parser, Table, Terminal,
+        owned::{OwnedFamily, OwnedTable, OwnedTrace},
+        parser::InputTerm,
+        table::TableDefect,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn owned_family() {
+        let (owned, texts) = {
+            let bump = Bump::new();
+            let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+                .unwrap()
+                .augmented();
+            let family = Family::from_grammar(&grammar).unwrap();
+            let texts: Vec<Vec<String>> = family
+                .item_sets()
+                .iter()
+                .map(|is| is.items().map(ToString::to_string).collect())
+                .collect();
+            (OwnedFamily::from_family(&family), texts)
+        };
+        // arena 已经释放, 项集族仍然可以发送到其他线程使用.
+        let owned = std::thread::spawn(move || owned).join().unwrap();
+        assert_eq!(owned.len(), 10);
+        assert_eq!(owned.prods[0], "Sprime -> S");
+        for (is, texts) in owned.item_sets.iter().zip(&texts) {
+            let owned_texts: Vec<_> = is.items.iter().map(|i| i.text.clone()).collect();
+            assert_eq!(&owned_texts, texts);
+        }
+        let i0 = owned.item_set(0).unwrap();
+        let augmented = i0.items.iter().find(|i| i.prod == 0).unwrap();
+        assert_eq!(augmented.look_aheads, ["eof"]);
+        let to = owned.goto(0, "C").unwrap();
+        // S -> C ⋅ C 〈eof〉
+        assert!(
+            owned.item_sets[to]
+                .items
+                .iter()
+                .any(|i| i.prod == 1 && i.dot == 1)
+        );
+        assert_eq!(owned.goto(0, "S").map(|s| owned.gotos[s].len()), Some(0));
+        assert_eq!(owned.goto(0, "x"), None);
+    }
+
+    #[test]
+    fn owned_table_and_trace() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let owned = OwnedTable::from_table(&table);
+        assert_eq!(owned.actions.len(), table.rows());
+        assert!(!owned.conflict);
+        assert_eq!(owned.actions[0]["c"], *table.action(0, "c".into()).unwrap());
+        assert_eq!(
+            owned.gotos[0].get("S").copied(),
+            table.goto(0, "S".into()).flatten()
+        );
+        let input = ["c", "d", "d"].map(|t| InputTerm::from(Terminal::from(t)));
+        let output = Parser::new(&table).parse(input).unwrap();
+        let trace = OwnedTrace::from_output(&output);
+        assert!(trace.accepted);
+        assert_eq!(trace.terms, ["c", "d", "d"]);
+        assert_eq!(trace.reductions.len(), output.reductions().len());
+        assert_eq!(trace.reductions[0].stack, ["c", "d"]);
+        assert!(trace.events.is_empty());
+    }
+
+    #[test]
+    fn table_metadata() {
+        let bump = Bump::new();
+        let src = "# version: 1.2.0\n# meta: author = azazo1\nS -> C C\nC -> c C | d";
+        let grammar = Grammar::from_cfg(src, "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let owned = OwnedTable::from_table(&table);
+        assert_eq!(owned.version(), Some("1.2.0"));
+        assert_eq!(owned.metadata["author"], "azazo1");
+        assert_eq!(owned.check_compatible(&table), Ok(()));
+
+        let other = Grammar::from_cfg("S -> C C\nC -> c C | e", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let other_family = Family::from_grammar(&other).unwrap();
+        let other_table = Table::build_from(&other_family).unwrap();
+        assert_eq!(OwnedTable::from_table(&other_table).version(), None);
+        assert!(owned.check_compatible(&other_table).is_err());
+    }
+
+    #[test]
+    fn validate_owned_table() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let mut owned = OwnedTable::from_table(&table);
+        assert_eq!(owned.prod_heads, ["Sprime", "S", "C", "C"]);
+        assert_eq!(owned.prod_lens, [1, 2, 2, 1]);
+        assert_eq!(owned.validate(), []);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&owned).unwrap();
+            let cached: OwnedTable = serde_json::from_str(&json).unwrap();
+            assert_eq!(cached, owned);
+            assert_eq!(cached.validate(), []);
+        }
+
+        let rows = owned.actions.len();
+        owned.actions[0].insert("c".to_string(), ActionCell::Shift(rows));
+        owned.gotos[0].remove("S");
+        let defects = owned.validate();
+        assert_eq!(
+            defects.first(),
+            Some(&TableDefect::ShiftOutOfRange {
+                state: 0,
+                term: "c".to_string(),
+                to: rows
+            })
+        );
+        assert!(
+            defects
+                .iter()
+                .any(|d| matches!(d, TableDefect::MissingGoto { from: 0, .. }))
+        );
+        assert_eq!(defects.last(), Some(&TableDefect::AcceptUnreachable));
+    }
+}
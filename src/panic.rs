@@ -1,9 +1,13 @@
 //! 恐慌恢复
 
-#[allow(unused_imports)]
-use crate::Grammar;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque, hash_map::Entry},
+};
 
-use crate::{Table, Terminal, Token, error::Error};
+use crate::{Grammar, grammar::DEFAULT_WEIGHT};
+
+use crate::{ActionCell, EOF, NonTerminal, StateId, Table, Terminal, Token, error::Error};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PanicAction<'a> {
@@ -22,8 +26,219 @@ impl PanicAction<'_> {
     }
 }
 
+/// 评估候选动作时最多连续进行的强制归约次数, 见: [`Table::panic_candidates`].
+const PANIC_REDUCE_DEPTH: usize = 8;
+
+/// 执行一个恢复动作之后继续语法分析的结果, 见: [`Table::simulate`].
+enum Simulated {
+    /// 能够消耗的输入终结符数量.
+    Consumed(usize),
+    /// 强制归约之后立即出错, 需要在归约之后的状态栈上继续试探恢复动作.
+    Stuck(Vec<StateId>),
+}
+
+/// 带有评分的恐慌恢复候选动作, 见: [`Table::panic_candidates`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicCandidate<'a> {
+    /// 恢复动作, 其中 [`PanicAction::Empty`] 表示跳过当前的终结符.
+    pub action: PanicAction<'a>,
+    /// 执行恢复动作之后, 语法分析能够继续正常消耗的输入终结符数量 (成功接受时额外加一), 越大越好.
+    pub score: usize,
+}
+
+/// 预先计算的恐慌恢复表, 所有查询的时间复杂度都为 O(1), 见: [`Table::panic_table`].
+#[derive(Debug, Clone)]
+pub struct PanicTable<'t, 'a> {
+    table: &'t Table<'a>,
+    /// 每个项集状态在每个终结符下的 [`Table::panic_action`], 列和 ACTION 表一致.
+    actions: Vec<Vec<PanicAction<'a>>>,
+    /// 每个项集状态的 [`Table::cheapest_insertion`].
+    insertions: Vec<Option<Terminal<'a>>>,
+}
+
+impl<'t, 'a> PanicTable<'t, 'a> {
+    /// 查询 [`Table::panic_action`] 的预先计算结果.
+    /// 如果项集状态不存在或者文法中没有这个终结符, 那么返回 [`None`].
+    #[must_use]
+    pub fn action(&self, state: StateId, term: Terminal) -> Option<&PanicAction<'a>> {
+        let term_idx = self.table.term_index(term)?;
+        self.actions.get(state).map(|row| &row[term_idx])
+    }
+
+    /// 获取一个项集状态的整行恐慌恢复动作, 列和 ACTION 表一致.
+    #[must_use]
+    pub fn row(&self, state: StateId) -> Option<&[PanicAction<'a>]> {
+        self.actions.get(state).map(Vec::as_slice)
+    }
+
+    /// 查询 [`Table::cheapest_insertion`] 的预先计算结果, 用于给出 "是否遗漏了 `;`?" 之类的提示.
+    #[must_use]
+    pub fn expected_insertion(&self, state: StateId) -> Option<Terminal<'a>> {
+        self.insertions.get(state).copied().flatten()
+    }
+
+    #[must_use]
+    pub fn table(&self) -> &'t Table<'a> {
+        self.table
+    }
+}
+
 impl<'a> Table<'a> {
-    /// 恐慌模式获取下一个动作.
+    /// 预先计算所有项集状态的恐慌恢复动作和最佳插入终结符.
+    ///
+    /// # Errors
+    /// 见: [`Table::panic_action`].
+    pub fn panic_table(&self) -> Result<PanicTable<'_, 'a>, Error> {
+        let min_lens = self.grammar().min_lens();
+        let mut actions = Vec::with_capacity(self.rows());
+        let mut insertions = Vec::with_capacity(self.rows());
+        for state in 0..self.rows() {
+            actions.push(
+                self.terms()
+                    .iter()
+                    .map(|&term| self.panic_action(state, term))
+                    .collect::<Result<Vec<_>, _>>()?,
+            );
+            insertions.push(self.insertion_with(state, &min_lens));
+        }
+        Ok(PanicTable {
+            table: self,
+            actions,
+            insertions,
+        })
+    }
+
+    /// 计算在项集状态 `state` 出错时, 代价最小的能够使分析继续进行的插入终结符.
+    ///
+    /// 候选的终结符为 `state` 的 ACTION 表中非空的终结符 (不包括 [`EOF`]),
+    /// 从到达 `state` 的最短路径 (见: [`Family::access_path`]) 出发, 插入终结符之后进行必要的归约, 直到移入这个终结符,
+    /// 代价为移入之后到达的项集中完成内核项还需要的最少终结符数量, 代价相同时优先选择需要归约次数少的, 然后按照 ACTION 表的列顺序.
+    ///
+    /// 如果项集状态不存在或者没有可以插入的终结符, 那么返回 [`None`].
+    ///
+    /// [`Family::access_path`]: crate::Family::access_path
+    #[must_use]
+    pub fn cheapest_insertion(&self, state: StateId) -> Option<Terminal<'a>> {
+        self.insertion_with(state, &self.grammar().min_lens())
+    }
+
+    fn insertion_with(
+        &self,
+        state: StateId,
+        min_lens: &HashMap<NonTerminal<'a>, usize>,
+    ) -> Option<Terminal<'a>> {
+        let path = self.family().access_path(state)?;
+        let stack: Vec<StateId> = std::iter::once(0)
+            .chain(path.iter().map(|&(_, to)| to))
+            .collect();
+        self.cheapest_terminal(&stack, min_lens)
+    }
+
+    /// 在状态栈 `stack` 上选择代价最小的能够被移入的终结符 (不包括 [`EOF`]), 代价见: [`Table::cheapest_insertion`].
+    pub(crate) fn cheapest_terminal(
+        &self,
+        stack: &[StateId],
+        min_lens: &HashMap<NonTerminal<'a>, usize>,
+    ) -> Option<Terminal<'a>> {
+        self.actions(*stack.last()?)?
+            .filter(|&(term, _)| term != EOF)
+            .filter_map(|(term, _)| {
+                let mut stack = stack.to_vec();
+                let mut reductions = 0;
+                loop {
+                    match self.action(*stack.last()?, term)? {
+                        ActionCell::Shift(to) => {
+                            stack.push(*to);
+                            break;
+                        }
+                        ActionCell::Reduce(prod) => {
+                            if !self.reduce_stack(&mut stack, *prod) {
+                                return None;
+                            }
+                            reductions += 1;
+                        }
+                        _ => return None,
+                    }
+                }
+                let cost = self.completion_cost(&stack, min_lens)?;
+                Some(((cost, reductions), term))
+            })
+            .min_by_key(|&(key, _)| key)
+            .map(|(_, term)| term)
+    }
+
+    /// 估计状态栈 `stack` 还需要读入多少个终结符才能被接受 (不考虑前瞻符的限制).
+    ///
+    /// 对栈顶项集中的每个内核项, 代价为完成这个项还需要的最少终结符数量,
+    /// 加上归约之后 (弹出 dot 个状态, 压入 GOTO 状态) 的新状态栈的代价, 取其中的最小值.
+    pub(crate) fn completion_cost(
+        &self,
+        stack: &[StateId],
+        min_lens: &HashMap<NonTerminal<'a>, usize>,
+    ) -> Option<usize> {
+        let (&top, prefix) = stack.split_last()?;
+        self.completion_cost_at(prefix, prefix.len(), top, min_lens, &mut HashMap::new())
+    }
+
+    /// 状态栈为 `prefix[..depth]` 加上 `state` 时的 [`Table::completion_cost`], `memo` 中的 [`None`] 表示正在计算或者无法完成.
+    fn completion_cost_at(
+        &self,
+        prefix: &[StateId],
+        depth: usize,
+        state: StateId,
+        min_lens: &HashMap<NonTerminal<'a>, usize>,
+        memo: &mut HashMap<(usize, StateId), Option<usize>>,
+    ) -> Option<usize> {
+        if let Some(&cost) = memo.get(&(depth, state)) {
+            return cost;
+        }
+        memo.insert((depth, state), None);
+        let mut best = None;
+        for item in self.family().item_set(state)?.items() {
+            let is_start =
+                self.grammar().index_of_prod(item.prod()) == self.grammar().augmented_production();
+            if (item.dot() == 0 && !is_start) || item.dot() > depth {
+                continue;
+            }
+            let Some(rest) = Grammar::seq_min_len(min_lens, item.rest()) else {
+                continue;
+            };
+            let cost = if is_start {
+                // 完成增广产生式也就是接受.
+                Some(rest)
+            } else {
+                let below = prefix[depth - item.dot()];
+                self.goto(below, item.prod().head())
+                    .flatten()
+                    .and_then(|to| {
+                        self.completion_cost_at(prefix, depth - item.dot() + 1, to, min_lens, memo)
+                    })
+                    .map(|c| c + rest)
+            };
+            if let Some(cost) = cost {
+                best = Some(best.map_or(cost, |b: usize| b.min(cost)));
+            }
+        }
+        memo.insert((depth, state), best);
+        best
+    }
+
+    /// 恐慌模式获取下一个动作, 也就是 [`Table::panic_actions`] 中的第一个动作.
+    ///
+    /// # Errors
+    /// 见: [`Table::panic_actions`].
+    /// # Note
+    /// 这个实现并不是时间复杂度 O(1) 的, 但是实际上一个文法的 `panic_action` 函数的输出只依赖与 state 和 term 输入,
+    /// 因此可以提前建表以实现 O(1) 时间复杂度查询.
+    pub fn panic_action(&self, state: usize, term: Terminal) -> Result<PanicAction<'a>, Error> {
+        Ok(self
+            .panic_actions(state, term)?
+            .into_iter()
+            .next()
+            .unwrap_or(PanicAction::Empty))
+    }
+
+    /// 恐慌模式获取所有可行的动作, 按照项集中项的顺序排列, 不包含重复的动作.
     ///
     /// 具体操作:
     /// - 项集中不能归约(reduce)的项, 忽略一个期望的终结符, 尝试 reduce, goto.
@@ -32,30 +247,30 @@ impl<'a> Table<'a> {
     /// - [`Error::StateNotFound`] 项集状态不存在.
     /// - [`Error::AmbiguousGrammar`] 文法是二义性的.
     /// - 其他见: [`Grammar::first_set`].
-    /// # Note
-    /// 这个实现并不是时间复杂度 O(1) 的, 但是实际上一个文法的 `panic_action` 函数的输出只依赖与 state 和 term 输入,
-    /// 因此可以提前建表以实现 O(1) 时间复杂度查询.
-    pub fn panic_action(&self, state: usize, term: Terminal) -> Result<PanicAction<'a>, Error> {
+    pub fn panic_actions(
+        &self,
+        state: usize,
+        term: Terminal,
+    ) -> Result<Vec<PanicAction<'a>>, Error> {
         let is = self
             .family()
             .item_sets()
             .get(state)
             .ok_or(Error::StateNotFound(state))?;
+        let mut actions = Vec::new();
         for i in is.items() {
             // 跳过下一个期望终结符, 尝试 reduce / goto.
             // 不考虑期望非终结符的 Item, 因为项集里面肯定有对应的闭包 Item.
-            match i.expected() {
+            let action = match i.expected() {
                 Some(Token::Terminal(raw_expected)) => {
                     let panic_i = i.with_dot_inc();
                     // 到达新的项集状态.
                     let to = self
                         .family()
                         .gotos_of(state)
-                        // unwrap: 这个状态一定在集族中存在, 并且有出边, 因为 i 有 expected != None.
-                        .unwrap()
-                        .get(&raw_expected.into())
-                        // unwrap: 这个状态一定有 raw_expected 为 token 的 goto 出边, 因为 i.expected() == raw_expected.
-                        .unwrap();
+                        .and_then(|gotos| gotos.get(&raw_expected.into()))
+                        // 这个状态一定有 raw_expected 为 token 的 goto 出边, 因为 i.expected() == raw_expected.
+                        .ok_or(Error::StateNotFound(state))?;
                     if to.len() != 1 {
                         // 文法是二义性的, 无法使用 LR(1) 表达.
                         Err(Error::AmbiguousGrammar)?
@@ -64,7 +279,7 @@ impl<'a> Table<'a> {
                     // 尝试 reduce
                     if panic_i.reduces().into_iter().flatten().any(|t| t == term) {
                         // 先移入这个终结符, 然后才能到达归约/接收状态, 后者为恢复之后的 actions.
-                        return Ok(PanicAction::Shift(raw_expected, to));
+                        PanicAction::Shift(raw_expected, to)
                     // 尝试 goto
                     } else if self
                         .grammar()
@@ -74,21 +289,172 @@ impl<'a> Table<'a> {
                         )?
                         .contains(&term)
                     {
-                        return Ok(PanicAction::Shift(raw_expected, to));
+                        PanicAction::Shift(raw_expected, to)
+                    } else {
+                        continue;
                     }
                 }
-                Some(_) => {}
+                Some(_) => continue,
                 None => {
                     // 直接 reduce
-                    let prod = self.grammar().index_of_prod(i.prod()).unwrap();
-                    if prod == 0 {
-                        return Ok(PanicAction::Accept);
+                    let prod = self
+                        .grammar()
+                        .index_of_prod(i.prod())
+                        .ok_or_else(|| Error::ProductionNotFound(i.prod().to_string()))?;
+                    if Some(prod) == self.grammar().augmented_production() {
+                        PanicAction::Accept
                     } else {
-                        return Ok(PanicAction::Reduce(prod));
+                        PanicAction::Reduce(prod)
                     }
                 }
+            };
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+        Ok(actions)
+    }
+
+    /// 在给定的状态栈和剩余输入上试探所有可行的恢复动作 (包括跳过当前终结符), 按照评分从高到低排序,
+    /// 评分相同时归约权重 (见: [`Grammar::weight`]) 大的产生式的动作排在前面, 其他动作的权重视为默认的 1,
+    /// 再相同时保持 [`Table::panic_actions`] 中的顺序, 跳过当前终结符排在最后.
+    ///
+    /// `rest` 为剩余的输入终结符 (第一个就是出错的终结符), 不需要以 [`EOF`] 结尾, 其末尾视为 [`EOF`].
+    /// # Errors
+    /// - [`Error::StateNotFound`] `stack` 为空或者其中的项集状态不存在.
+    /// - 其他见: [`Table::panic_actions`].
+    pub fn panic_candidates(
+        &self,
+        stack: &[StateId],
+        rest: &[Terminal<'a>],
+    ) -> Result<Vec<PanicCandidate<'a>>, Error> {
+        let &state = stack.last().ok_or(Error::StateNotFound(0))?;
+        let term = rest.first().copied().unwrap_or(EOF);
+        let mut actions = self.panic_actions(state, term)?;
+        if term != EOF {
+            actions.push(PanicAction::Empty);
+        }
+        let mut candidates = actions
+            .into_iter()
+            .map(|action| {
+                Ok(PanicCandidate {
+                    score: self.simulate(stack.to_vec(), &action, rest)?,
+                    action,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        // 评分相同时优先通过权重大的产生式强制归约.
+        candidates.sort_by_key(|c| {
+            let weight = match c.action {
+                PanicAction::Reduce(prod) => self.grammar().weight(prod),
+                _ => DEFAULT_WEIGHT,
+            };
+            (Reverse(c.score), Reverse(weight))
+        });
+        Ok(candidates)
+    }
+
+    /// 执行恢复动作 `first` 之后, 在 `rest` 上继续正常的语法分析, 返回能够消耗的输入终结符数量.
+    ///
+    /// 强制归约只是恢复的中间过程, 如果归约之后立即出错, 那么其评分为归约之后最好的恢复动作的评分,
+    /// 最多连续进行 [`PANIC_REDUCE_DEPTH`] 次这样的强制归约.
+    /// 按层广度优先搜索, 同一个状态栈只展开一次, 每个状态的 [`Table::panic_actions`] 也只计算一次.
+    fn simulate(
+        &self,
+        stack: Vec<StateId>,
+        first: &PanicAction<'a>,
+        rest: &[Terminal<'a>],
+    ) -> Result<usize, Error> {
+        let term = rest.first().copied().unwrap_or(EOF);
+        let mut best = 0;
+        let mut visited = HashSet::new();
+        let mut actions_of = HashMap::new();
+        let mut queue = VecDeque::from([(stack, first.clone(), PANIC_REDUCE_DEPTH)]);
+        while let Some((stack, action, depth)) = queue.pop_front() {
+            let stack = match self.run_after(stack, &action, rest) {
+                Simulated::Consumed(consumed) => {
+                    best = best.max(consumed);
+                    continue;
+                }
+                Simulated::Stuck(stack) => stack,
+            };
+            if depth == 0 || !visited.insert(stack.clone()) {
+                continue;
+            }
+            let top = *stack.last().unwrap();
+            let actions = match actions_of.entry(top) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.panic_actions(top, term)?),
+            };
+            for action in actions.iter() {
+                queue.push_back((stack.clone(), action.clone(), depth - 1));
+            }
+        }
+        Ok(best)
+    }
+
+    /// 执行一个恢复动作之后, 在 `rest` 上继续正常的语法分析, 见: [`Table::simulate`].
+    fn run_after(
+        &self,
+        mut stack: Vec<StateId>,
+        action: &PanicAction<'a>,
+        rest: &[Terminal<'a>],
+    ) -> Simulated {
+        let rest = match action {
+            PanicAction::Shift(_, to) => {
+                stack.push(*to);
+                rest
+            }
+            PanicAction::Reduce(prod) => {
+                if !self.reduce_stack(&mut stack, *prod) {
+                    return Simulated::Consumed(0);
+                }
+                rest
+            }
+            PanicAction::Accept => return Simulated::Consumed(usize::from(rest.is_empty())),
+            PanicAction::Empty => rest.get(1..).unwrap_or_default(),
+        };
+        let mut consumed = 0;
+        loop {
+            let Some(&top) = stack.last() else {
+                return Simulated::Consumed(consumed);
+            };
+            let term = rest.get(consumed).copied().unwrap_or(EOF);
+            match self.action(top, term) {
+                Some(ActionCell::Shift(to)) => {
+                    stack.push(*to);
+                    consumed += 1;
+                }
+                Some(ActionCell::Reduce(prod)) => {
+                    if !self.reduce_stack(&mut stack, *prod) {
+                        return Simulated::Consumed(consumed);
+                    }
+                }
+                Some(ActionCell::Accept) => return Simulated::Consumed(consumed + 1),
+                _ if consumed == 0 && matches!(action, PanicAction::Reduce(_)) => {
+                    return Simulated::Stuck(stack);
+                }
+                _ => return Simulated::Consumed(consumed),
+            }
+        }
+    }
+
+    /// 在状态栈上执行归约, 如果状态栈中的状态不足或者没有对应的 GOTO, 那么返回 `false`.
+    pub(crate) fn reduce_stack(&self, stack: &mut Vec<StateId>, prod: usize) -> bool {
+        let Some(production) = self.grammar().prod(prod) else {
+            return false;
+        };
+        let len = production.len();
+        if stack.len() <= len {
+            return false;
+        }
+        stack.truncate(stack.len() - len);
+        match self.goto(*stack.last().unwrap(), production.head()) {
+            Some(Some(to)) => {
+                stack.push(to);
+                true
             }
+            _ => false,
         }
-        Ok(PanicAction::Empty)
     }
 }
@@ -0,0 +1,1314 @@
+//! 语法分析驱动程序.
+//!
+//! 基于 [`Table`] 进行 LR(1) 语法分析, 记录归约过程 (翻转过来就是最右推导),
+//! 并在遇到语法错误时使用 [`Table::panic_candidates`] 中评分最高的动作进行恐慌恢复, 把恢复过程记录到 [`RecoveryReport`] 中.
+//! 参考龙书中文第二版 P160.
+//!
+//! [`Parser::parse_with_actions`] 在分析过程中调用 [`SemanticActions`], 调用者的上下文 (符号表, 字符串池等)
+//! 以 `&mut Ctx` 传入每个回调, 不需要上下文时 [`Parser::parse_with`] 直接接受移入和归约两个闭包.
+//! [`Parser::parse_with_buffer`] 使用调用者提供的状态栈, 分析过程中不分配内存.
+
+use std::fmt::Display;
+
+use crate::{
+    ActionCell, EOF, ProdId, ReduceInfo, StateId, Table, Terminal, Token,
+    coverage::Coverage,
+    error::{Error, ParseContext},
+    lexer::LexerSpec,
+    panic::PanicAction,
+    tree::Node,
+};
+
+/// 输入的终结符, 其对应的词素以及其所在的行号和列号.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputTerm<'a> {
+    pub term: Terminal<'a>,
+    /// 终结符对应的词素, 例如终结符类别 `NUM` 的 `42`, 没有给出词素时就是终结符本身.
+    pub text: &'a str,
+    pub line: usize,
+    /// 词素的第一个字符在行中的列号, 从 1 开始, 未知时为 0.
+    pub column: usize,
+}
+
+impl<'a> InputTerm<'a> {
+    #[must_use]
+    pub fn new(term: Terminal<'a>, line: usize) -> Self {
+        Self {
+            term,
+            text: term.as_str(),
+            line,
+            column: 0,
+        }
+    }
+
+    /// 替换词素.
+    #[must_use]
+    pub fn with_text(self, text: &'a str) -> Self {
+        Self { text, ..self }
+    }
+
+    /// 设置列号.
+    #[must_use]
+    pub fn with_column(self, column: usize) -> Self {
+        Self { column, ..self }
+    }
+}
+
+impl<'a> From<Terminal<'a>> for InputTerm<'a> {
+    fn from(term: Terminal<'a>) -> Self {
+        Self::new(term, 0)
+    }
+}
+
+impl<'a> From<(usize, Terminal<'a>)> for InputTerm<'a> {
+    fn from((line, term): (usize, Terminal<'a>)) -> Self {
+        Self::new(term, line)
+    }
+}
+
+/// `(终结符类别, 词素)`, 见: [`crate::lexer::TerminalClass`].
+impl<'a> From<(Terminal<'a>, &'a str)> for InputTerm<'a> {
+    fn from((term, text): (Terminal<'a>, &'a str)) -> Self {
+        Self::new(term, 0).with_text(text)
+    }
+}
+
+/// 一次归约操作.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reduction<'a> {
+    /// 归约的产生式编号.
+    pub prod: usize,
+    /// 归约之前的符号栈.
+    pub stack: Vec<Token<'a>>,
+    /// 符号栈中每个符号推导出的第一个终结符在 [`ParseOutput::terms`] 中的下标,
+    /// 推导出空串的符号为它之后的下一个终结符的下标.
+    pub starts: Vec<usize>,
+    /// 归约时还没有读取的输入的起始位置, 是 [`ParseOutput::terms`] 的下标,
+    /// 可能大于等于其长度, 也就是说后面没有未被读取的输入.
+    pub cursor: usize,
+}
+
+/// 恐慌恢复过程中发生的事件.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryEvent<'a> {
+    /// 插入了缺少的终结符.
+    Inserted {
+        state: StateId,
+        term: Terminal<'a>,
+        /// 插入时的前瞻终结符, 也就是出错的终结符.
+        found: Terminal<'a>,
+        line: usize,
+        /// 出错时正在分析的结构, 从内到外.
+        context: Vec<ParseContext>,
+    },
+    /// 跳过了无法处理的终结符.
+    Skipped {
+        state: StateId,
+        term: Terminal<'a>,
+        line: usize,
+        /// 出错时正在分析的结构, 从内到外.
+        context: Vec<ParseContext>,
+    },
+    /// 恐慌模式下忽略前瞻符强制进行的归约.
+    Reduced {
+        state: StateId,
+        prod: usize,
+        line: usize,
+    },
+    /// 输入已经结束, 但是无法恢复.
+    Escaped {
+        state: StateId,
+        line: usize,
+        /// 出错时正在分析的结构, 从内到外.
+        context: Vec<ParseContext>,
+    },
+}
+
+impl RecoveryEvent<'_> {
+    /// 事件是否代表一个需要报告的语法错误, 强制归约只是恢复的中间过程, 不单独算作错误.
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Self::Reduced { .. })
+    }
+
+    /// 把语法错误转换为 [`Error::UnexpectedToken`] 或者 [`Error::UnexpectedEof`], 期望的终结符来自 `table`.
+    ///
+    /// 不是语法错误时返回 [`None`].
+    #[must_use]
+    pub fn to_error(&self, table: &Table<'_>) -> Option<Error> {
+        let expected = |state| {
+            table
+                .actions(state)
+                .into_iter()
+                .flatten()
+                .map(|(t, _)| t.to_string())
+                .collect()
+        };
+        match self {
+            &Self::Inserted {
+                found,
+                state,
+                ref context,
+                ..
+            }
+            | &Self::Skipped {
+                term: found,
+                state,
+                ref context,
+                ..
+            } if found != EOF => Some(Error::UnexpectedToken {
+                state,
+                token: found.to_string(),
+                expected: expected(state),
+                context: context.clone(),
+            }),
+            &Self::Inserted {
+                state, ref context, ..
+            }
+            | &Self::Skipped {
+                state, ref context, ..
+            }
+            | &Self::Escaped {
+                state, ref context, ..
+            } => Some(Error::UnexpectedEof {
+                state,
+                expected: expected(state),
+                context: context.clone(),
+            }),
+            Self::Reduced { .. } => None,
+        }
+    }
+}
+
+impl Display for RecoveryEvent<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inserted { term, line, .. } => write!(f, "inserting '{term}' at line {line}"),
+            Self::Skipped { term, line, .. } => write!(f, "skipping '{term}' at line {line}"),
+            Self::Reduced { prod, line, .. } => write!(f, "reducing r{prod} at line {line}"),
+            Self::Escaped { line, .. } => write!(f, "giving up at line {line}"),
+        }
+    }
+}
+
+/// 恐慌恢复报告, 按照发生顺序记录所有恢复事件.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport<'a> {
+    events: Vec<RecoveryEvent<'a>>,
+}
+
+impl<'a> RecoveryReport<'a> {
+    pub fn events(&self) -> &[RecoveryEvent<'a>] {
+        &self.events
+    }
+
+    /// 语法错误的数量, 见: [`RecoveryEvent::is_error`].
+    #[must_use]
+    pub fn errors(&self) -> usize {
+        self.events.iter().filter(|e| e.is_error()).count()
+    }
+
+    pub fn insertions(&self) -> impl Iterator<Item = &RecoveryEvent<'a>> {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, RecoveryEvent::Inserted { .. }))
+    }
+
+    pub fn deletions(&self) -> impl Iterator<Item = &RecoveryEvent<'a>> {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, RecoveryEvent::Skipped { .. }))
+    }
+
+    pub fn forced_reductions(&self) -> impl Iterator<Item = &RecoveryEvent<'a>> {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, RecoveryEvent::Reduced { .. }))
+    }
+
+    /// 是否从所有错误中恢复了过来.
+    #[must_use]
+    pub fn recovered(&self) -> bool {
+        !self
+            .events
+            .iter()
+            .any(|e| matches!(e, RecoveryEvent::Escaped { .. }))
+    }
+
+    fn push(&mut self, event: RecoveryEvent<'a>) {
+        self.events.push(event);
+    }
+
+    /// 记录事件并通知 `hooks`.
+    fn record(&mut self, hooks: &mut dyn Hooks<'a>, event: RecoveryEvent<'a>) {
+        hooks.error(&event);
+        self.push(event);
+    }
+}
+
+/// 输出形如 `3 errors, recovered by inserting ';' at line 4, skipping 'x' at line 5` 的摘要.
+impl Display for RecoveryReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors = self.errors();
+        if errors == 0 {
+            return f.pad("no errors");
+        }
+        let details = self
+            .events
+            .iter()
+            .filter(|e| e.is_error())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let plural = if errors == 1 { "" } else { "s" };
+        if self.recovered() {
+            f.pad(&format!("{errors} error{plural}, recovered by {details}"))
+        } else {
+            f.pad(&format!("{errors} error{plural}, unrecoverable: {details}"))
+        }
+    }
+}
+
+/// 语法分析结果.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOutput<'a> {
+    /// 恢复之后的输入终结符序列 (包括插入的终结符, 不包括被跳过的终结符).
+    terms: Vec<InputTerm<'a>>,
+    /// 按照发生顺序记录的归约操作.
+    reductions: Vec<Reduction<'a>>,
+    report: RecoveryReport<'a>,
+    accepted: bool,
+    /// 恐慌恢复插入的终结符在 `terms` 中的下标.
+    inserted: Vec<usize>,
+    /// 被跳过的终结符, 以及它原本在 `terms` 中的位置 (在这个下标的终结符之前).
+    skipped: Vec<(usize, InputTerm<'a>)>,
+}
+
+impl<'a> ParseOutput<'a> {
+    #[must_use]
+    pub fn terms(&self) -> &[InputTerm<'a>] {
+        &self.terms
+    }
+
+    #[must_use]
+    pub fn reductions(&self) -> &[Reduction<'a>] {
+        &self.reductions
+    }
+
+    #[must_use]
+    pub fn report(&self) -> &RecoveryReport<'a> {
+        &self.report
+    }
+
+    /// 输入是否最终被接受 (可能经过了恐慌恢复).
+    #[must_use]
+    pub fn accepted(&self) -> bool {
+        self.accepted
+    }
+
+    /// 第 `idx` 个终结符是否是恐慌恢复插入的.
+    #[must_use]
+    pub fn is_inserted(&self, idx: usize) -> bool {
+        self.inserted.contains(&idx)
+    }
+
+    /// 同 [`ParseOutput::derivation`], 每个句型渲染为以空格分隔的一行,
+    /// 恐慌恢复插入的终结符标记为 `⟨inserted ;⟩`, 还没有读取的输入中被跳过的终结符标记为 `⟨skipped x⟩`.
+    ///
+    /// 没有进行恢复时和 [`ParseOutput::derivation`] 的符号相同.
+    pub fn marked_derivation(&self) -> impl Iterator<Item = String> + '_ {
+        let skipped_at = move |idx: usize| {
+            self.skipped
+                .iter()
+                .filter(move |(pos, _)| *pos == idx)
+                .map(|(_, t)| format!("⟨skipped {}⟩", t.term))
+        };
+        let term = move |idx: usize| {
+            let term = self.terms[idx].term;
+            if self.is_inserted(idx) {
+                format!("⟨inserted {term}⟩")
+            } else {
+                term.to_string()
+            }
+        };
+        self.reductions.iter().rev().map(move |r| {
+            let stack = r.stack.iter().zip(&r.starts).map(|(tok, &start)| {
+                if tok.is_term() && self.is_inserted(start) {
+                    format!("⟨inserted {tok}⟩")
+                } else {
+                    tok.to_string()
+                }
+            });
+            let rest = (r.cursor..self.terms.len())
+                .flat_map(|idx| skipped_at(idx).chain([term(idx)]))
+                .chain(
+                    self.skipped
+                        .iter()
+                        .filter(|(pos, _)| *pos >= self.terms.len().max(r.cursor))
+                        .map(|(_, t)| format!("⟨skipped {}⟩", t.term)),
+                );
+            stack.chain(rest).collect::<Vec<_>>().join(" ")
+        })
+    }
+
+    /// 最右推导过程中的每一个句型, 从开始符号 (第一行) 到输入串 (最后一行).
+    ///
+    /// 第一行是增广之前的开始符号, 不包括增广产生式 `Sprime -> S` 这一步, 所以推导和用户的文法一致.
+    pub fn derivation(&self) -> impl Iterator<Item = Vec<Token<'a>>> + '_ {
+        self.reductions.iter().rev().map(|r| {
+            r.stack
+                .iter()
+                .copied()
+                .chain(self.terms.iter().skip(r.cursor).map(|t| t.term.into()))
+                .collect()
+        })
+    }
+}
+
+impl<'a> Table<'a> {
+    /// 使用 `spec` 扫描 `input` 并进行语法分析, 返回语法分析树, 不进行错误恢复.
+    ///
+    /// # Errors
+    /// - 词法错误, 见: [`LexerSpec::lex`].
+    /// - 其他见: [`Parser::parse_tree`].
+    pub fn parse_str(&self, input: &'a str, spec: &LexerSpec<'a>) -> Result<Node<'a>, Error> {
+        Parser::new(self).parse_tree(spec.lex(input)?)
+    }
+}
+
+/// 分析过程中符号栈上的符号.
+#[derive(Debug, Clone, Copy)]
+struct StackSymbol<'a> {
+    token: Token<'a>,
+    /// 推导出的第一个终结符所在的行号.
+    line: usize,
+    /// 推导出的第一个终结符在输入中的下标, 见: [`Reduction::starts`].
+    start: usize,
+}
+
+/// 分析过程中的语义动作, 每个回调都会收到调用者在 [`Parser::parse_with_actions`] 中传入的上下文 `ctx`.
+///
+/// 每个符号栈上的符号对应一个值: 移入 (包括恐慌恢复插入) 终结符时由 [`SemanticActions::shift`] 产生,
+/// 归约时由 [`SemanticActions::reduce`] 把尾部符号的值合并为头部的值. 增广产生式不调用 `reduce`,
+/// 接受时开始符号的值作为分析结果.
+pub trait SemanticActions<'a, Ctx: ?Sized> {
+    type Value;
+
+    fn shift(&mut self, ctx: &mut Ctx, term: &InputTerm<'a>) -> Self::Value;
+
+    /// 归约产生式 `prod`, `children` 为尾部符号的值, 按照从左到右的顺序, 不包括 [`crate::EPSILON`].
+    fn reduce(&mut self, ctx: &mut Ctx, prod: ProdId, children: Vec<Self::Value>) -> Self::Value;
+
+    /// 恐慌恢复中发生的事件, 和 [`ParseOutput::report`] 中的事件相同, 默认忽略.
+    fn error(&mut self, ctx: &mut Ctx, event: &RecoveryEvent<'a>) {
+        let _ = (ctx, event);
+    }
+}
+
+/// 由两个闭包组成的语义动作, 见: [`Parser::parse_with`].
+struct Closures<S, R> {
+    shift: S,
+    reduce: R,
+}
+
+impl<'a, T, S, R> SemanticActions<'a, ()> for Closures<S, R>
+where
+    S: FnMut(&InputTerm<'a>) -> T,
+    R: FnMut(ProdId, Vec<T>) -> T,
+{
+    type Value = T;
+
+    fn shift(&mut self, (): &mut (), term: &InputTerm<'a>) -> T {
+        (self.shift)(term)
+    }
+
+    fn reduce(&mut self, (): &mut (), prod: ProdId, children: Vec<T>) -> T {
+        (self.reduce)(prod, children)
+    }
+}
+
+/// 分析循环向外通知的事件, 不需要语义动作的分析使用 `()`.
+trait Hooks<'a> {
+    fn shift(&mut self, term: &InputTerm<'a>);
+    fn reduce(&mut self, prod: ProdId, len: usize);
+    fn error(&mut self, event: &RecoveryEvent<'a>);
+}
+
+impl<'a> Hooks<'a> for () {
+    fn shift(&mut self, _: &InputTerm<'a>) {}
+    fn reduce(&mut self, _: ProdId, _: usize) {}
+    fn error(&mut self, _: &RecoveryEvent<'a>) {}
+}
+
+/// 把 [`Hooks`] 转发给 [`SemanticActions`], 维护和符号栈对应的值栈.
+struct Driver<'x, A, C: ?Sized, V> {
+    actions: &'x mut A,
+    ctx: &'x mut C,
+    values: Vec<V>,
+    /// 增广产生式, 归约它 (接受) 时不调用语义动作.
+    accept: ProdId,
+}
+
+impl<'a, A, C, V> Hooks<'a> for Driver<'_, A, C, V>
+where
+    A: SemanticActions<'a, C, Value = V>,
+    C: ?Sized,
+{
+    fn shift(&mut self, term: &InputTerm<'a>) {
+        let value = self.actions.shift(self.ctx, term);
+        self.values.push(value);
+    }
+
+    fn reduce(&mut self, prod: ProdId, len: usize) {
+        if prod == self.accept {
+            return;
+        }
+        let children = self.values.split_off(self.values.len().saturating_sub(len));
+        let value = self.actions.reduce(self.ctx, prod, children);
+        self.values.push(value);
+    }
+
+    fn error(&mut self, event: &RecoveryEvent<'a>) {
+        self.actions.error(self.ctx, event);
+    }
+}
+
+/// [`Parser::parse_with_buffer`] 的分析错误, 不携带需要分配内存的信息.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferError<'a> {
+    #[error("Unexpected token {term} on I_{state} at position {position}.")]
+    Syntax {
+        state: StateId,
+        term: Terminal<'a>,
+        /// 出错的终结符在输入中的下标, 输入结束时为输入的长度.
+        position: usize,
+    },
+    #[error("Conflict on {term} in I_{state} at position {position}.")]
+    Conflict {
+        state: StateId,
+        term: Terminal<'a>,
+        position: usize,
+    },
+    #[error("State stack is full (capacity {capacity}) at position {position}.")]
+    StackFull { capacity: usize, position: usize },
+}
+
+/// 恐慌恢复时用于评估候选动作的最大输入终结符数量.
+const PANIC_WINDOW: usize = 16;
+
+/// LR(1) 语法分析驱动程序.
+#[derive(Debug, Clone, Copy)]
+pub struct Parser<'t, 'a> {
+    table: &'t Table<'a>,
+}
+
+impl<'t, 'a> Parser<'t, 'a> {
+    #[must_use]
+    pub fn new(table: &'t Table<'a>) -> Self {
+        Self { table }
+    }
+
+    /// 接受时归约的增广产生式, 分析表总是由增广文法构建.
+    fn accept_prod(&self) -> ProdId {
+        self.table
+            .grammar()
+            .augmented_production()
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn table(&self) -> &'t Table<'a> {
+        self.table
+    }
+
+    /// 分析输入的终结符序列, 输入不需要以 [`EOF`] 结尾.
+    ///
+    /// 遇到语法错误时进行恐慌恢复, 恢复过程记录在 [`ParseOutput::report`] 中.
+    /// # Errors
+    /// - [`Error::AmbiguousGrammar`] 分析过程中遇到了冲突的表项.
+    /// - 其他见: [`Table::panic_candidates`].
+    pub fn parse<I>(&self, input: I) -> Result<ParseOutput<'a>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        self.run(input, None, &mut ())
+    }
+
+    /// 分析输入的终结符序列, 返回具体语法树 (见: [`Node`]), 不进行错误恢复.
+    ///
+    /// 每次归约 (除了增广产生式) 对应一个内部节点, 子节点按照产生式右部的顺序排列, 叶子是输入的终结符.
+    /// # Errors
+    /// - [`Error::UnexpectedToken`] 遇到的第一个语法错误.
+    /// - [`Error::UnexpectedEof`] 输入提前结束.
+    /// - 其他见: [`Parser::parse`].
+    pub fn parse_tree<I>(&self, input: I) -> Result<Node<'a>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        let output = self.parse(input)?;
+        if let Some(e) = output
+            .report()
+            .events()
+            .iter()
+            .find_map(|e| e.to_error(self.table))
+        {
+            Err(e)?
+        }
+        // 没有语法错误时一定被接受, 也就能重建语法分析树.
+        output
+            .tree(self.table.grammar())
+            .ok_or_else(|| Error::UnexpectedEof {
+                state: 0,
+                expected: Vec::new(),
+                context: Vec::new(),
+            })
+    }
+
+    /// 同 [`Parser::parse`], 同时调用语义动作 `actions`, `ctx` 传入每个回调.
+    ///
+    /// 输入被接受时 (可能经过了恐慌恢复) 返回开始符号的值.
+    /// # Errors
+    /// 见: [`Parser::parse`].
+    pub fn parse_with_actions<I, A, C>(
+        &self,
+        input: I,
+        actions: &mut A,
+        ctx: &mut C,
+    ) -> Result<(ParseOutput<'a>, Option<A::Value>), Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+        A: SemanticActions<'a, C>,
+        C: ?Sized,
+    {
+        let mut driver = Driver {
+            actions,
+            ctx,
+            values: Vec::new(),
+            accept: self.accept_prod(),
+        };
+        let output = self.run(input, None, &mut driver)?;
+        let value = driver.values.pop().filter(|_| output.accepted);
+        Ok((output, value))
+    }
+
+    /// 同 [`Parser::parse_with_actions`], 语义动作由两个闭包给出, 不需要上下文:
+    /// 移入终结符时调用 `shift` 产生它的值, 归约产生式 (编号见: [`crate::Grammar::index_of_prod`]) 时调用
+    /// `reduce(prod, children)`, `children` 为尾部符号的值, 按照从左到右的顺序.
+    ///
+    /// 输入被接受时 (可能经过了恐慌恢复) 返回开始符号的值.
+    /// # Errors
+    /// 见: [`Parser::parse`].
+    pub fn parse_with<I, T>(
+        &self,
+        input: I,
+        shift: impl FnMut(&InputTerm<'a>) -> T,
+        reduce: impl FnMut(ProdId, Vec<T>) -> T,
+    ) -> Result<(ParseOutput<'a>, Option<T>), Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        self.parse_with_actions(input, &mut Closures { shift, reduce }, &mut ())
+    }
+
+    /// 同 [`Parser::parse`], 同时在 `coverage` 中记录用到的 ACTION/GOTO 表格子, 被归约的产生式和进入的状态.
+    ///
+    /// `coverage` 应该基于这个分析器的语法分析表创建. 恐慌恢复中插入终结符和强制归约不会标记 ACTION 表格子.
+    /// # Errors
+    /// 见: [`Parser::parse`].
+    pub fn parse_with_coverage<I>(
+        &self,
+        input: I,
+        coverage: &mut Coverage<'_, 'a>,
+    ) -> Result<ParseOutput<'a>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        let output = self.run(input, Some(&mut *coverage), &mut ())?;
+        coverage.finish_parse();
+        Ok(output)
+    }
+
+    fn run<I>(
+        &self,
+        input: I,
+        mut coverage: Option<&mut Coverage<'_, 'a>>,
+        hooks: &mut dyn Hooks<'a>,
+    ) -> Result<ParseOutput<'a>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<InputTerm<'a>>,
+    {
+        let mut terms: Vec<InputTerm<'a>> = input.into_iter().map(Into::into).collect();
+        let mut stack: Vec<StateId> = vec![0];
+        if let Some(coverage) = coverage.as_deref_mut() {
+            coverage.mark_state(0);
+        }
+        let mut step: Vec<StackSymbol<'a>> = Vec::new();
+        // 恐慌恢复插入的终结符在 terms 中的下标.
+        let mut inserted = Vec::new();
+        // 被跳过的终结符以及跳过时它在 terms 中的位置.
+        let mut skipped = Vec::new();
+        let mut reductions = Vec::new();
+        let mut report = RecoveryReport::default();
+        let mut cursor = 0;
+        // 连续的不消耗输入的恢复动作数量, 防止恢复过程陷入死循环.
+        let mut stall = 0;
+        let stall_limit = self.table.rows() * 2 + 16;
+        let accept = self.accept_prod();
+        let accepted = loop {
+            // 栈不会为空, 因为 pop 之前一定要有对应数量的状态被压入.
+            let top = *stack.last().unwrap();
+            let (term, line) = match terms.get(cursor) {
+                Some(t) => (t.term, t.line),
+                None => (EOF, terms.last().map_or(0, |t| t.line)),
+            };
+            let action = self.table.action(top, term).unwrap_or(&ActionCell::Empty);
+            if let Some(coverage) = coverage.as_deref_mut()
+                && !action.is_empty()
+            {
+                coverage.mark_cell(top, term);
+            }
+            match action {
+                ActionCell::Shift(to) => {
+                    if let Some(coverage) = coverage.as_deref_mut() {
+                        coverage.mark_state(*to);
+                    }
+                    stack.push(*to);
+                    step.push(StackSymbol {
+                        token: term.into(),
+                        line,
+                        start: cursor,
+                    });
+                    hooks.shift(&terms[cursor]);
+                    cursor += 1;
+                    stall = 0;
+                }
+                ActionCell::Reduce(prod) => {
+                    let info = self.reduce(
+                        *prod,
+                        cursor,
+                        &mut stack,
+                        &mut step,
+                        &mut reductions,
+                        coverage.as_deref_mut(),
+                    )?;
+                    hooks.reduce(*prod, info.len);
+                }
+                ActionCell::Accept => {
+                    let info = self.reduce(
+                        accept,
+                        cursor,
+                        &mut stack,
+                        &mut step,
+                        &mut reductions,
+                        coverage.as_deref_mut(),
+                    )?;
+                    hooks.reduce(accept, info.len);
+                    break true;
+                }
+                ActionCell::Conflict(_, _) => Err(Error::AmbiguousGrammar)?,
+                ActionCell::Empty => {
+                    stall += 1;
+                    let panic_action = if stall > stall_limit {
+                        PanicAction::Empty
+                    } else {
+                        let rest: Vec<_> = terms[cursor..]
+                            .iter()
+                            .take(PANIC_WINDOW)
+                            .map(|t| t.term)
+                            .collect();
+                        self.table
+                            .panic_candidates(&stack, &rest)?
+                            .into_iter()
+                            .next()
+                            .map_or(PanicAction::Empty, |c| c.action)
+                    };
+                    match panic_action {
+                        PanicAction::Reduce(prod) => {
+                            report.record(
+                                hooks,
+                                RecoveryEvent::Reduced {
+                                    state: top,
+                                    prod,
+                                    line,
+                                },
+                            );
+                            let info = self.reduce(
+                                prod,
+                                cursor,
+                                &mut stack,
+                                &mut step,
+                                &mut reductions,
+                                coverage.as_deref_mut(),
+                            )?;
+                            hooks.reduce(prod, info.len);
+                        }
+                        PanicAction::Shift(skipped, to) => {
+                            report.record(
+                                hooks,
+                                RecoveryEvent::Inserted {
+                                    state: top,
+                                    term: skipped,
+                                    found: term,
+                                    line,
+                                    context: self.context(&stack, &step),
+                                },
+                            );
+                            // 相当于把程序当成原本就是被修正过的版本.
+                            terms.insert(cursor, InputTerm::new(skipped, line));
+                            inserted.push(cursor);
+                            if let Some(coverage) = coverage.as_deref_mut() {
+                                coverage.mark_state(to);
+                            }
+                            stack.push(to);
+                            step.push(StackSymbol {
+                                token: skipped.into(),
+                                line,
+                                start: cursor,
+                            });
+                            hooks.shift(&terms[cursor]);
+                            cursor += 1;
+                        }
+                        PanicAction::Accept => {
+                            // 提前接受, 剩余的输入都被跳过.
+                            while let Some(rest) = terms.get(cursor).filter(|t| t.term != EOF) {
+                                report.record(
+                                    hooks,
+                                    RecoveryEvent::Skipped {
+                                        state: top,
+                                        term: rest.term,
+                                        line: rest.line,
+                                        context: self.context(&stack, &step),
+                                    },
+                                );
+                                skipped.push((cursor, terms.remove(cursor)));
+                            }
+                            let info = self.reduce(
+                                accept,
+                                cursor,
+                                &mut stack,
+                                &mut step,
+                                &mut reductions,
+                                coverage.as_deref_mut(),
+                            )?;
+                            hooks.reduce(accept, info.len);
+                            break true;
+                        }
+                        PanicAction::Empty if term == EOF => {
+                            report.record(
+                                hooks,
+                                RecoveryEvent::Escaped {
+                                    state: top,
+                                    line,
+                                    context: self.context(&stack, &step),
+                                },
+                            );
+                            break false;
+                        }
+                        PanicAction::Empty => {
+                            // 无法从恐慌状态下恢复, 跳过这个终结符.
+                            report.record(
+                                hooks,
+                                RecoveryEvent::Skipped {
+                                    state: top,
+                                    term,
+                                    line,
+                                    context: self.context(&stack, &step),
+                                },
+                            );
+                            skipped.push((cursor, terms.remove(cursor)));
+                            stall = 0;
+                        }
+                    }
+                }
+            }
+        };
+        Ok(ParseOutput {
+            terms,
+            reductions,
+            report,
+            accepted,
+            inserted,
+            skipped,
+        })
+    }
+
+    /// 使用调用者提供的状态栈 `stack` 分析输入的终结符序列 (不需要以 [`EOF`] 结尾), 每次归约调用 `on_reduce`,
+    /// 增广产生式除外. 分析过程中不分配内存, 也不进行恐慌恢复, 适合对延迟敏感的场合:
+    /// 状态栈不会扩容, 深度超过 `stack.capacity()` 时报错. 分析之前清空 `stack`, 同一个缓冲区可以在多次分析之间复用,
+    /// 不同的缓冲区可以在多个线程中同时使用同一个分析器.
+    ///
+    /// 返回分析过程中状态栈的最大深度, 可以据此调整缓冲区的容量.
+    /// # Errors
+    /// - [`BufferError::Syntax`] 输入不符合文法.
+    /// - [`BufferError::Conflict`] 遇到了冲突的表项.
+    /// - [`BufferError::StackFull`] 状态栈的深度超过了 `stack` 的容量.
+    pub fn parse_with_buffer<I>(
+        &self,
+        input: I,
+        stack: &mut Vec<StateId>,
+        mut on_reduce: impl FnMut(ProdId),
+    ) -> Result<usize, BufferError<'a>>
+    where
+        I: IntoIterator<Item = Terminal<'a>>,
+    {
+        let mut input = input.into_iter();
+        let capacity = stack.capacity();
+        let mut position = 0;
+        let mut term = input.next().unwrap_or(EOF);
+        let push = |stack: &mut Vec<StateId>, state, position| {
+            if stack.len() == capacity {
+                return Err(BufferError::StackFull { capacity, position });
+            }
+            stack.push(state);
+            Ok(())
+        };
+        stack.clear();
+        push(stack, 0, position)?;
+        let mut depth = 1;
+        loop {
+            let state = *stack.last().unwrap_or(&0);
+            match self.table.action(state, term).unwrap_or(&ActionCell::Empty) {
+                ActionCell::Shift(to) => {
+                    push(stack, *to, position)?;
+                    position += 1;
+                    term = input.next().unwrap_or(EOF);
+                }
+                ActionCell::Reduce(prod) => {
+                    let syntax = BufferError::Syntax {
+                        state,
+                        term,
+                        position,
+                    };
+                    let info = self.table.reduce_info(*prod).ok_or(syntax)?;
+                    stack.truncate(stack.len().saturating_sub(info.len));
+                    let top = *stack.last().unwrap_or(&0);
+                    let to = self.table.goto_at(top, info.head).ok_or(syntax)?;
+                    push(stack, to, position)?;
+                    on_reduce(*prod);
+                }
+                ActionCell::Accept => return Ok(depth),
+                ActionCell::Conflict(_, _) => Err(BufferError::Conflict {
+                    state,
+                    term,
+                    position,
+                })?,
+                ActionCell::Empty => Err(BufferError::Syntax {
+                    state,
+                    term,
+                    position,
+                })?,
+            }
+            depth = depth.max(stack.len());
+        }
+    }
+
+    /// 分析栈上正在分析的结构, 从内到外.
+    ///
+    /// 从栈顶开始, 取状态的内核项中 dot 在右部中间且 dot 最小的项 (最内层的结构), 它的右部已经识别的部分
+    /// 占据了符号栈顶部的 dot 个符号, 跳过这些符号之后继续向外查找. 连续的同一个非终结符 (例如右递归的列表)
+    /// 合并为一个, 行号为最外层的.
+    fn context(&self, stack: &[StateId], step: &[StackSymbol<'a>]) -> Vec<ParseContext> {
+        let family = self.table.family();
+        let grammar = self.table.grammar();
+        let augmented = grammar.augmented_production();
+        let mut context: Vec<ParseContext> = Vec::new();
+        let mut depth = step.len();
+        while depth > 0 {
+            let Some(is) = stack.get(depth).and_then(|&s| family.item_set(s)) else {
+                break;
+            };
+            let Some(item) = is
+                .kernel()
+                .filter(|i| {
+                    i.dot() > 0 && i.dot() <= depth && grammar.index_of_prod(i.prod()) != augmented
+                })
+                .min_by_key(|i| (i.dot() == i.prod().len(), i.dot()))
+            else {
+                break;
+            };
+            depth -= item.dot();
+            if item.dot() == item.prod().len() {
+                // 已经识别完整的结构, 不算正在分析.
+                continue;
+            }
+            let head = item.prod().head().to_string();
+            let line = step[depth].line;
+            match context.last_mut() {
+                Some(last) if last.head == head => last.line = line,
+                _ => context.push(ParseContext { head, line }),
+            }
+        }
+        context
+    }
+
+    fn reduce(
+        &self,
+        prod: usize,
+        cursor: usize,
+        stack: &mut Vec<StateId>,
+        step: &mut Vec<StackSymbol<'a>>,
+        reductions: &mut Vec<Reduction<'a>>,
+        coverage: Option<&mut Coverage<'_, 'a>>,
+    ) -> Result<ReduceInfo, Error> {
+        let info = self
+            .table
+            .reduce_info(prod)
+            .ok_or_else(|| Error::ProductionNotFound(format!("r{prod}")))?;
+        let head = self.table.non_terms()[info.head];
+        reductions.push(Reduction {
+            prod,
+            stack: step.iter().map(|sym| sym.token).collect(),
+            starts: step.iter().map(|sym| sym.start).collect(),
+            cursor,
+        });
+        // 空产生式从前一个符号所在的行, 下一个输入终结符开始.
+        let mut line = step.last().map_or(0, |sym| sym.line);
+        let mut start = cursor;
+        for _ in 0..info.len {
+            if let Some(sym) = step.pop() {
+                (line, start) = (sym.line, sym.start);
+            }
+            stack.pop();
+        }
+        step.push(StackSymbol {
+            token: head.into(),
+            line,
+            start,
+        });
+        let top = *stack.last().unwrap_or(&0);
+        let to = self.table.goto_at(top, info.head);
+        if let Some(to) = to {
+            stack.push(to);
+        }
+        if let Some(coverage) = coverage {
+            coverage.mark_prod(prod);
+            if let Some(to) = to {
+                coverage.mark_state(to);
+            }
+            if prod != self.accept_prod() {
+                coverage.mark_cell(top, head);
+            }
+        }
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        EOF, Family, Grammar, NonTerminal, Table, Terminal,
+        error::Error,
+        lexer::LexerSpec,
+        parser::{BufferError, InputTerm, Parser, RecoveryEvent, SemanticActions},
+    };
+    use pretty_assertions::assert_eq;
+
+    fn terms(input: &str) -> Vec<InputTerm<'_>> {
+        input
+            .lines()
+            .enumerate()
+            .flat_map(|(ln, s)| {
+                s.split_whitespace()
+                    .map(move |part| InputTerm::new(Terminal::from(part), ln))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recovery_report() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+
+        let ok = parser.parse(terms("{\nID = NUM ;\n}")).unwrap();
+        assert!(ok.accepted());
+        assert_eq!(ok.report().errors(), 0);
+        assert_eq!(ok.report().to_string(), "no errors");
+        assert_eq!(
+            ok.derivation().next().unwrap(),
+            [NonTerminal::from("program").into()]
+        );
+
+        let output = parser
+            .parse(terms("{\nwhile ( ID == NUM )\n{\nID = NUM\n}\n}"))
+            .unwrap();
+        assert!(output.accepted());
+        assert_eq!(output.report().errors(), 1);
+        assert!(matches!(
+            output.report().insertions().collect::<Vec<_>>()[..],
+            [RecoveryEvent::Inserted { term, line: 4, .. }] if term.as_str() == ";"
+        ));
+        assert_eq!(
+            output.report().to_string(),
+            "1 error, recovered by inserting ';' at line 4"
+        );
+        let forms: Vec<_> = output.marked_derivation().collect();
+        assert_eq!(forms.len(), output.derivation().count());
+        assert_eq!(
+            forms.last().unwrap(),
+            "{ while ( ID == NUM ) { ID = NUM ⟨inserted ;⟩ } }"
+        );
+        assert!(
+            forms
+                .iter()
+                .any(|f| f.ends_with("ID = arithexpr ⟨inserted ;⟩ } }"))
+        );
+        assert_eq!(forms[0], "program");
+
+        let output = parser.parse(terms("{\nID = NUM ; )\n}")).unwrap();
+        let forms: Vec<_> = output.marked_derivation().collect();
+        assert_eq!(forms[3], "{ stmt stmts ⟨skipped )⟩ }");
+        assert_eq!(forms.last().unwrap(), "{ ID = NUM ; ⟨skipped )⟩ }");
+    }
+
+    #[test]
+    fn parse_str() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let spec = LexerSpec::new(&grammar)
+            .regex("NUM", "[0-9]+")
+            .unwrap()
+            .regex("ID", "[a-z]+")
+            .unwrap();
+
+        let tree = table.parse_str("{ x = 42; }", &spec).unwrap();
+        assert_eq!(tree.token(), NonTerminal::from("program").into());
+        assert_eq!(
+            tree.leaves().map(|t| t.text).collect::<Vec<_>>(),
+            ["{", "x", "=", "42", ";", "}"]
+        );
+        assert!(matches!(
+            table.parse_str("{ x = 42 }", &spec),
+            Err(Error::UnexpectedToken { token, .. }) if token == "}"
+        ));
+        assert!(matches!(
+            table.parse_str("{ x = 42;", &spec),
+            Err(Error::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            table.parse_str("{ x = 4@2; }", &spec),
+            Err(Error::UnknownLexeme { .. })
+        ));
+    }
+
+    #[test]
+    fn error_context() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let spec = LexerSpec::new(&grammar)
+            .regex("NUM", "[0-9]+")
+            .unwrap()
+            .regex("ID", "[a-z]+")
+            .unwrap();
+        let src = "{\nwhile ( x == 1 )\n{\nx = 2;\nx = 3;\nx = ( 4 +\n}\n}";
+        let Err(Error::UnexpectedToken { context, .. }) = table.parse_str(src, &spec) else {
+            panic!()
+        };
+        let frames: Vec<_> = context.iter().map(|c| (c.head.as_str(), c.line)).collect();
+        assert_eq!(
+            frames,
+            [
+                ("arithexprprime", 6),
+                ("arithexpr", 6),
+                ("simpleexpr", 6),
+                ("assgstmt", 6),
+                ("stmts", 4),
+                ("compoundstmt", 3),
+                ("whilestmt", 2),
+                ("compoundstmt", 1),
+            ]
+        );
+        let message = table.parse_str(src, &spec).unwrap_err().to_string();
+        assert!(message.ends_with(
+            "\n  while parsing `whilestmt` started at line 2\n  while parsing `compoundstmt` started at line 1"
+        ));
+    }
+
+    /// 把标识符放入字符串池, 列表的值为标识符在池中的下标.
+    struct Interner;
+
+    #[derive(Default)]
+    struct Pool<'a> {
+        names: Vec<&'a str>,
+        errors: usize,
+    }
+
+    impl<'a> SemanticActions<'a, Pool<'a>> for Interner {
+        type Value = Vec<usize>;
+
+        fn shift(&mut self, pool: &mut Pool<'a>, term: &InputTerm<'a>) -> Vec<usize> {
+            if term.term != Terminal::from("id") {
+                return Vec::new();
+            }
+            let idx = pool.names.iter().position(|&n| n == term.text);
+            vec![idx.unwrap_or_else(|| {
+                pool.names.push(term.text);
+                pool.names.len() - 1
+            })]
+        }
+
+        fn reduce(&mut self, _: &mut Pool<'a>, _: usize, children: Vec<Vec<usize>>) -> Vec<usize> {
+            children.concat()
+        }
+
+        fn error(&mut self, pool: &mut Pool<'a>, event: &RecoveryEvent<'a>) {
+            pool.errors += usize::from(event.is_error());
+        }
+    }
+
+    #[test]
+    fn semantic_actions() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( L )\nL -> L , id | id", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let input = |src: &'static str| -> Vec<InputTerm<'static>> {
+            src.split(' ')
+                .map(|word| match word {
+                    "(" | ")" | "," => Terminal::from(word).into(),
+                    _ => (Terminal::from("id"), word).into(),
+                })
+                .collect()
+        };
+
+        let mut pool = Pool::default();
+        let (output, value) = parser
+            .parse_with_actions(input("( a , b , a )"), &mut Interner, &mut pool)
+            .unwrap();
+        assert!(output.accepted());
+        assert_eq!(value, Some(vec![0, 1, 0]));
+        assert_eq!(pool.names, ["a", "b"]);
+        assert_eq!(pool.errors, 0);
+
+        // 同一个上下文可以用于多次分析, 恐慌恢复的事件也会传给语义动作.
+        let (output, value) = parser
+            .parse_with_actions(input("( c , , b"), &mut Interner, &mut pool)
+            .unwrap();
+        // 恐慌恢复插入的终结符也会移入, 词素为终结符本身.
+        assert_eq!(pool.names, ["a", "b", "c", "id"]);
+        assert_eq!(pool.errors, output.report().errors());
+        assert!(pool.errors > 0);
+        assert_eq!(value.is_some(), output.accepted());
+    }
+
+    #[test]
+    fn closure_actions() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "e -> e + t | t\nt -> t * f | f\nf -> ( e ) | NUM",
+            "e".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let input: Vec<InputTerm> = "2 * ( 3 + 4 ) + 1"
+            .split(' ')
+            .map(|word| match word.parse::<i64>() {
+                Ok(_) => (Terminal::from("NUM"), word).into(),
+                Err(_) => Terminal::from(word).into(),
+            })
+            .collect();
+        let mut reduced = Vec::new();
+        let (output, value) = Parser::new(&table)
+            .parse_with(
+                input,
+                |term| term.text.parse::<i64>().unwrap_or(0),
+                |prod, children| {
+                    reduced.push(prod);
+                    match prod {
+                        1 => children[0] + children[2],
+                        3 => children[0] * children[2],
+                        5 => children[1],
+                        _ => children[0],
+                    }
+                },
+            )
+            .unwrap();
+        assert!(output.accepted());
+        assert_eq!(value, Some(15));
+        // 增广产生式不调用 `reduce`.
+        assert_eq!(
+            reduced,
+            output
+                .reductions()
+                .iter()
+                .map(|r| r.prod)
+                .filter(|&p| p != 0)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn buffered_parse() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> ( L )\nL -> L , id | id", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let input: Vec<_> = "( id , id , id )".split(' ').map(Terminal::from).collect();
+
+        let mut stack = Vec::with_capacity(8);
+        let capacity = stack.capacity();
+        let mut reductions = Vec::new();
+        let depth = parser
+            .parse_with_buffer(input.iter().copied(), &mut stack, |p| reductions.push(p))
+            .unwrap();
+        let expected: Vec<_> = parser
+            .parse(input.iter().copied())
+            .unwrap()
+            .reductions()
+            .iter()
+            .map(|r| r.prod)
+            .filter(|&p| p != 0)
+            .collect();
+        assert_eq!(reductions, expected);
+        assert_eq!(depth, 5);
+        // 缓冲区复用, 不会扩容.
+        parser
+            .parse_with_buffer(input.iter().copied(), &mut stack, |_| {})
+            .unwrap();
+        assert_eq!(stack.capacity(), capacity);
+
+        assert_eq!(
+            parser.parse_with_buffer(input[..3].iter().copied(), &mut stack, |_| {}),
+            Err(BufferError::Syntax {
+                state: stack[stack.len() - 1],
+                term: EOF,
+                position: 3
+            })
+        );
+        let mut small = Vec::with_capacity(2);
+        let capacity = small.capacity();
+        assert!(matches!(
+            parser.parse_with_buffer(input.iter().copied(), &mut small, |_| {}),
+            Err(BufferError::StackFull { capacity: c, .. }) if c == capacity
+        ));
+    }
+}
@@ -0,0 +1,530 @@
+//! 表达式文法的运算符优先级推断.
+//!
+//! `expr -> expr + expr | expr * expr` 这样的二元运算符产生式是二义的, 在分析表中表现为移入/归约冲突:
+//! 归约 `expr -> expr op1 expr` 还是移入 `op2` 取决于两个运算符之间的优先级和结合性.
+//! 这里从冲突中找出这类产生式涉及的运算符, 按照常见的约定 (赋值 < 逻辑 < 比较 < 加减 < 乘除 < 乘方)
+//! 推断运算符表, 并生成可以解决这些冲突的 `%left`/`%right`/`%nonassoc` 声明.
+//!
+//! 不认识的运算符按照在文法中出现的顺序排在已知运算符之后, 先出现的优先级较低, 结合性为左结合.
+//!
+//! 已经按照优先级分层的无二义表达式文法 (`expr -> expr + term | term`, `term -> term * factor | factor` ...)
+//! 可以用 [`extract`] 直接读出运算符表, 用 [`verify`] 在分析表上分析 `a op1 a op2 a` 形式的句子,
+//! 检查实际的结合顺序是否和运算符表一致, 适合为文法生成文档.
+//!
+//! 文法文件中的 `%left`/`%right`/`%nonassoc` 声明 (见: [`Grammar::precedence`]) 在构建分析表时按照 [`resolve`] 解决冲突,
+//! 二义的表达式文法不需要改写成分层的形式.
+
+use std::{cmp::Ordering, fmt::Display};
+
+use crate::{
+    ActionCell, Grammar, NonTerminal, ProdId, Table, Terminal, Token,
+    conflict::{Conflict, Suggestion},
+    generate::Generator,
+    parser::Parser,
+};
+
+/// 运算符的结合性.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Assoc {
+    Left,
+    Right,
+    NonAssoc,
+}
+
+impl Display for Assoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::NonAssoc => "nonassoc",
+        })
+    }
+}
+
+/// 运算符表中的一个优先级.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OperatorLevel<'a> {
+    /// 优先级, 从 1 开始, 越大结合得越紧.
+    pub level: usize,
+    pub assoc: Assoc,
+    /// 这一级中的运算符, 按照在文法中出现的顺序排列.
+    pub operators: Vec<Terminal<'a>>,
+    /// 是否是根据出现顺序猜测的 (运算符不在常见的约定中).
+    pub guessed: bool,
+}
+
+/// 常见运算符的约定优先级 (从低到高) 和结合性.
+const CONVENTIONS: &[(&[&str], Assoc)] = &[
+    (&["=", "+=", "-=", "*=", "/=", ":="], Assoc::Right),
+    (&["||", "or"], Assoc::Left),
+    (&["&&", "and"], Assoc::Left),
+    (&["==", "!=", "<", ">", "<=", ">="], Assoc::NonAssoc),
+    (&["+", "-"], Assoc::Left),
+    (&["*", "/", "%"], Assoc::Left),
+    (&["^", "**"], Assoc::Right),
+];
+
+/// 产生式是否为 `expr -> expr op expr` 形式的二元运算符产生式, 是则返回运算符.
+fn binary_operator<'a>(table: &Table<'a>, prod: ProdId) -> Option<Terminal<'a>> {
+    let prod = table.grammar().prod(prod)?;
+    let head = Token::from(prod.head());
+    match prod.tail_without_eps().copied().collect::<Vec<_>>()[..] {
+        [l, Token::Terminal(op), r] if l == head && r == head => Some(op),
+        _ => None,
+    }
+}
+
+/// 从 `root` 开始沿着分层的表达式文法读出运算符表, 从低到高排列.
+///
+/// 每一层是一个非终结符 `A`, 它的二元运算符产生式决定这一层的运算符和结合性: `A -> A op B` 为左结合,
+/// `A -> B op A` 为右结合, `A -> B op B` 为不结合, 其中 `B` 是下一层; 只有单个非终结符的产生式 `A -> B`
+/// 也指向下一层, 其他产生式 (括号, 前缀运算符等) 被忽略. 没有下一层或者回到已经访问过的层时结束.
+/// 一层中的运算符结合性不同时取第一个运算符的结合性, 由 [`verify`] 报告不一致的地方.
+#[must_use]
+pub fn extract<'a>(grammar: &Grammar<'a>, root: NonTerminal<'a>) -> Vec<OperatorLevel<'a>> {
+    let mut levels: Vec<OperatorLevel<'a>> = Vec::new();
+    let mut visited = Vec::new();
+    let mut at = Some(root);
+    while let Some(nt) = at.filter(|nt| !visited.contains(nt)) {
+        visited.push(nt);
+        let head = Token::from(nt);
+        let mut operators = Vec::new();
+        let mut assoc = None;
+        let mut next = None;
+        let mut units = Vec::new();
+        for &p in grammar.producers_of(nt) {
+            let tail: Vec<_> = grammar.prods()[p].tail_without_eps().copied().collect();
+            let (op, this, lower) = match tail[..] {
+                [Token::NonTerminal(b)] => {
+                    units.push(b);
+                    continue;
+                }
+                [l, Token::Terminal(op), r] if l == head && r != head => (op, Assoc::Left, r),
+                [l, Token::Terminal(op), r] if r == head && l != head => (op, Assoc::Right, l),
+                [l, Token::Terminal(op), r] if l == r && l != head => (op, Assoc::NonAssoc, l),
+                _ => continue,
+            };
+            let Token::NonTerminal(lower) = lower else {
+                continue;
+            };
+            operators.push(op);
+            assoc.get_or_insert(this);
+            next.get_or_insert(lower);
+        }
+        if let Some(assoc) = assoc {
+            levels.push(OperatorLevel {
+                level: levels.len() + 1,
+                assoc,
+                operators,
+                guessed: false,
+            });
+            at = next;
+        } else {
+            at = match units[..] {
+                [b] => Some(b),
+                _ => None,
+            };
+        }
+    }
+    levels
+}
+
+/// 运算符表和分析表的实际行为不一致的句子.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement<'a> {
+    /// `a op1 a op2 a`, `a` 为最高一层运算符的操作数能推导出的最短终结符串.
+    pub sentence: Vec<Terminal<'a>>,
+    /// 按照运算符表应该先归约的运算符在句子中的下标, [`None`] 表示句子应该被拒绝 (同一层的不结合运算符).
+    pub expected: Option<usize>,
+    /// 分析表实际先归约的运算符在句子中的下标, [`None`] 表示句子被拒绝.
+    pub found: Option<usize>,
+}
+
+impl Disagreement<'_> {
+    /// 先归约 `first` 处的运算符时句子的结合方式, 例如 `(a - a) - a`.
+    fn grouping(&self, first: Option<usize>) -> String {
+        let Some(first) = first else {
+            return "rejected".to_string();
+        };
+        let words: Vec<_> = self.sentence.iter().map(Terminal::as_str).collect();
+        // 两个运算符之间的操作数和第一个操作数等长.
+        let (a, b) = (words.len() / 3, words.len() - words.len() / 3 - 1);
+        let (open, close) = if first == a {
+            (0, b)
+        } else {
+            (a + 1, words.len())
+        };
+        let mut out = Vec::new();
+        for (i, w) in words.iter().enumerate() {
+            if i == open {
+                out.push(format!("({w}"));
+            } else if i + 1 == close {
+                out.push(format!("{w})"));
+            } else {
+                out.push((*w).to_string());
+            }
+        }
+        out.join(" ")
+    }
+}
+
+impl Display for Disagreement<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected `{}`, found `{}`",
+            self.grouping(self.expected),
+            self.grouping(self.found)
+        )
+    }
+}
+
+/// 对运算符表中的每一对运算符分析句子 `a op1 a op2 a`, 返回实际先归约的运算符和运算符表不一致的句子.
+///
+/// `table` 的开始符号应该是运算符表的根 (见: [`extract`]), 否则句子会被拒绝.
+#[must_use]
+pub fn verify<'a>(table: &Table<'a>, levels: &[OperatorLevel<'a>]) -> Vec<Disagreement<'a>> {
+    let grammar = table.grammar();
+    let binary = |p: ProdId| {
+        let prod = grammar.prod(p)?;
+        match prod.tail_without_eps().copied().collect::<Vec<_>>()[..] {
+            [
+                Token::NonTerminal(_),
+                Token::Terminal(op),
+                Token::NonTerminal(_),
+            ] => Some(op),
+            _ => None,
+        }
+    };
+    // 最高一层的操作数可以从每一层推导出来.
+    let operand = levels.last().and_then(|level| {
+        (0..grammar.prods().len()).find_map(|p| {
+            let prod = grammar.prod(p)?;
+            let tail: Vec<_> = prod.tail_without_eps().copied().collect();
+            match tail[..] {
+                [l, Token::Terminal(op), r] if op == level.operators[0] => {
+                    [l, r].into_iter().find_map(|tok| match tok {
+                        Token::NonTerminal(nt) if nt != prod.head() || l == r => Some(nt),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        })
+    });
+    let Some(operand) = operand else {
+        return Vec::new();
+    };
+    let mut generator = Generator::new(grammar, 0);
+    let Some(atom) = generator
+        .min_len(operand)
+        .and_then(|len| generator.sentence_from(operand, len))
+    else {
+        return Vec::new();
+    };
+
+    let parser = Parser::new(table);
+    let ops: Vec<_> = levels
+        .iter()
+        .flat_map(|level| level.operators.iter().map(move |&op| (op, level)))
+        .collect();
+    // 两个运算符在句子中的下标.
+    let (at1, at2) = (atom.len(), 2 * atom.len() + 1);
+    let mut disagreements = Vec::new();
+    for &(op1, l1) in &ops {
+        for &(op2, l2) in &ops {
+            let expected = match l1.level.cmp(&l2.level) {
+                std::cmp::Ordering::Greater => Some(at1),
+                std::cmp::Ordering::Less => Some(at2),
+                std::cmp::Ordering::Equal => match l1.assoc {
+                    Assoc::Left => Some(at1),
+                    Assoc::Right => Some(at2),
+                    Assoc::NonAssoc => None,
+                },
+            };
+            let sentence: Vec<_> = [&atom[..], &[op1], &atom[..], &[op2], &atom[..]].concat();
+            // 第一个二元运算符产生式归约时还没有读入第二个运算符, 说明先归约第一个运算符.
+            let found = parser
+                .parse(sentence.iter().copied())
+                .ok()
+                // 恐慌恢复中的强制归约不算作错误, 但是说明句子不能被直接接受 (例如不结合的运算符).
+                .filter(|output| output.accepted() && output.report().events().is_empty())
+                .and_then(|output| {
+                    output
+                        .reductions()
+                        .iter()
+                        .find(|r| binary(r.prod).is_some())
+                        .map(|r| if r.cursor <= at2 { at1 } else { at2 })
+                });
+            if found != expected {
+                disagreements.push(Disagreement {
+                    sentence,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    disagreements
+}
+
+/// 根据 `conflicts` 中的二元运算符移入/归约冲突推断运算符表, 从低到高排列,
+/// 没有这类冲突时返回空表.
+#[must_use]
+pub fn infer<'a>(table: &Table<'a>, conflicts: &[Conflict<'a>]) -> Vec<OperatorLevel<'a>> {
+    // 所有二元运算符产生式的运算符, 按照产生式编号 (出现顺序) 排列.
+    let binary: Vec<Terminal<'a>> = (0..table.grammar().prods().len())
+        .filter_map(|p| binary_operator(table, p))
+        .collect();
+    let mut operators = Vec::new();
+    for conflict in conflicts {
+        let Suggestion::Precedence { prod, term } = conflict.suggestion else {
+            continue;
+        };
+        let Some(op) = binary_operator(table, prod) else {
+            continue;
+        };
+        if !binary.contains(&term) {
+            continue;
+        }
+        for t in [op, term] {
+            if !operators.contains(&t) {
+                operators.push(t);
+            }
+        }
+    }
+    operators.sort_by_key(|t| binary.iter().position(|op| op == t));
+
+    let mut levels: Vec<OperatorLevel<'a>> = Vec::new();
+    for (rank, &(names, assoc)) in CONVENTIONS.iter().enumerate() {
+        let ops: Vec<_> = operators
+            .iter()
+            .copied()
+            .filter(|t| names.contains(&t.as_str()))
+            .collect();
+        if !ops.is_empty() {
+            levels.push(OperatorLevel {
+                level: rank,
+                assoc,
+                operators: ops,
+                guessed: false,
+            });
+        }
+    }
+    for &op in &operators {
+        if !CONVENTIONS
+            .iter()
+            .any(|(names, _)| names.contains(&op.as_str()))
+        {
+            levels.push(OperatorLevel {
+                level: 0,
+                assoc: Assoc::Left,
+                operators: vec![op],
+                guessed: true,
+            });
+        }
+    }
+    for (i, level) in levels.iter_mut().enumerate() {
+        level.level = i + 1;
+    }
+    levels
+}
+
+/// 按照文法的优先级声明解决移入/归约冲突, 同 yacc: 产生式的优先级是尾部中最后一个声明了优先级的终结符的优先级,
+/// 和向前看的终结符 `term` 比较, 产生式的优先级高时归约, 低时移入; 相同时左结合归约, 右结合移入,
+/// 不结合报告语法错误 ([`ActionCell::Empty`]).
+///
+/// 只处理一个移入和一个归约的格子, 归约/归约冲突和没有声明优先级的格子返回 [`None`].
+#[must_use]
+pub fn resolve(grammar: &Grammar<'_>, term: Terminal<'_>, cell: &ActionCell) -> Option<ActionCell> {
+    let leaves: Vec<_> = cell.flatten().collect();
+    let (shift, prod) = match leaves[..] {
+        [shift @ ActionCell::Shift(_), &ActionCell::Reduce(prod)]
+        | [&ActionCell::Reduce(prod), shift @ ActionCell::Shift(_)] => (shift, prod),
+        _ => return None,
+    };
+    let (term_level, assoc) = grammar.precedence(term)?;
+    let (prod_level, _) = grammar
+        .prod(prod)?
+        .tail()
+        .iter()
+        .rev()
+        .find_map(|tok| grammar.precedence(*tok.as_term()?))?;
+    Some(match (prod_level.cmp(&term_level), assoc) {
+        (Ordering::Greater, _) | (Ordering::Equal, Assoc::Left) => ActionCell::Reduce(prod),
+        (Ordering::Less, _) | (Ordering::Equal, Assoc::Right) => shift.clone(),
+        (Ordering::Equal, Assoc::NonAssoc) => ActionCell::Empty,
+    })
+}
+
+/// 生成 yacc 风格的优先级声明, 每级一行, 先声明的优先级较低, 例如:
+///
+/// ```text
+/// %left + -
+/// %left * /
+/// ```
+#[must_use]
+pub fn declarations(levels: &[OperatorLevel<'_>]) -> String {
+    levels
+        .iter()
+        .map(|level| {
+            let ops: Vec<_> = level.operators.iter().map(Terminal::as_str).collect();
+            format!("%{} {}\n", level.assoc, ops.join(" "))
+        })
+        .collect()
+}
+
+/// 渲染运算符表, 每行为优先级, 结合性和运算符, 猜测的优先级带有 `(guessed)` 标记.
+#[must_use]
+pub fn to_table(levels: &[OperatorLevel<'_>]) -> String {
+    let mut out = format!("{:<6} {:<9} operators\n", "level", "assoc");
+    for level in levels {
+        let ops: Vec<_> = level.operators.iter().map(Terminal::as_str).collect();
+        out += &format!("{:<6} {:<9} {}", level.level, level.assoc, ops.join(" "));
+        if level.guessed {
+            out += " (guessed)";
+        }
+        out += "\n";
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Parser, Table, Terminal,
+        conflict::conflicts,
+        precedence::{Assoc, declarations, extract, infer, to_table, verify},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn infer_operator_table() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "expr -> expr * expr | expr + expr | expr ^ expr | expr @ expr | expr - expr | ( expr ) | id",
+            "expr".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let levels = infer(&table, &conflicts(&table));
+        let ops: Vec<Vec<_>> = levels
+            .iter()
+            .map(|l| l.operators.iter().map(Terminal::as_str).collect())
+            .collect();
+        assert_eq!(ops, [vec!["+", "-"], vec!["*"], vec!["^"], vec!["@"]]);
+        assert_eq!(levels[2].assoc, Assoc::Right);
+        assert!(levels[3].guessed && !levels[0].guessed);
+        assert_eq!(
+            declarations(&levels),
+            "%left + -\n%left *\n%right ^\n%left @\n"
+        );
+        assert_eq!(
+            to_table(&levels).lines().nth(4),
+            Some("4      left      @ (guessed)")
+        );
+
+        // 没有二元运算符冲突的文法.
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert_eq!(infer(&table, &conflicts(&table)), []);
+    }
+
+    #[test]
+    fn extract_operator_table() {
+        let bump = Bump::new();
+        let src = "expr -> expr + term | expr - term | term
+term -> term * factor | term / factor | factor
+factor -> unary ^ factor | unary
+unary -> - unary | atom
+atom -> ( expr ) | id";
+        let grammar = Grammar::from_cfg(src, "expr".into(), &bump)
+            .unwrap()
+            .augmented();
+        let levels = extract(&grammar, "expr".into());
+        assert_eq!(
+            to_table(&levels),
+            "level  assoc     operators
+1      left      + -
+2      left      * /
+3      right     ^
+"
+        );
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert_eq!(verify(&table, &levels), []);
+
+        // 运算符表和分析表不一致: 声称 `^` 左结合.
+        let mut wrong = levels.clone();
+        wrong[2].assoc = Assoc::Left;
+        let disagreements = verify(&table, &wrong);
+        assert_eq!(disagreements.len(), 1);
+        assert_eq!(
+            disagreements[0].to_string(),
+            "expected `(id ^ id) ^ id`, found `id ^ (id ^ id)`"
+        );
+    }
+
+    #[test]
+    fn precedence_declarations() {
+        let bump = Bump::new();
+        let src = "%nonassoc <
+%left + -
+%left * /
+%right ^
+expr -> expr < expr | expr + expr | expr - expr | expr * expr | expr / expr | expr ^ expr | ( expr ) | id";
+        let grammar = Grammar::from_cfg(src, "expr".into(), &bump)
+            .unwrap()
+            .augmented();
+        assert_eq!(grammar.precedence("*".into()), Some((3, Assoc::Left)));
+        assert_eq!(grammar.precedence("(".into()), None);
+        assert!(grammar.to_string().contains("%nonassoc <\n%left + -\n"));
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert!(!table.conflict());
+        assert!(table.resolved() > 0);
+        assert_eq!(verify(&table, grammar.precedence_levels()), []);
+        let accepts = |input: &'static str| {
+            Parser::new(&table)
+                .parse_with_buffer(
+                    input.split(' ').map(Terminal::from),
+                    &mut Vec::with_capacity(16),
+                    |_| {},
+                )
+                .is_ok()
+        };
+        assert!(accepts("id < id + id * id"));
+        // 不结合的运算符不能连用.
+        assert!(!accepts("id < id < id"));
+
+        // 使用推断出的运算符表.
+        let src = "expr -> expr * expr | expr + expr | id";
+        let ambiguous = Grammar::from_cfg(src, "expr".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&ambiguous).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let levels = infer(&table, &conflicts(&table));
+        let mut grammar = Grammar::from_cfg(src, "expr".into(), &bump)
+            .unwrap()
+            .augmented();
+        grammar.set_precedence(&levels).unwrap();
+        let family = Family::from_grammar(&grammar).unwrap();
+        assert!(!Table::build_from(&family).unwrap().conflict());
+
+        assert!(Grammar::from_cfg("%lift +\nexpr -> expr + expr", "expr".into(), &bump).is_err());
+        assert!(
+            Grammar::from_cfg("%left expr\nexpr -> expr + expr", "expr".into(), &bump).is_err()
+        );
+    }
+}
@@ -0,0 +1,439 @@
+//! 正则文法检测.
+//!
+//! 所有产生式都是右线性的 (`A -> w B` 或者 `A -> w`, `w` 是终结符串) 或者都是左线性的 (`A -> B w` 或者 `A -> w`)
+//! 时文法生成正则语言, 不需要完整的 LR 分析器, 一个 DFA (或者正则表达式) 就足够了.
+//! 这是充分条件: 不满足的文法也可能生成正则语言 (例如 `S -> S S | a`), 这里不做判断.
+//!
+//! 检测到正则文法时先构造 NFA (非终结符为状态, 单个非终结符的产生式和空产生式为 ε 边),
+//! 再用子集构造得到 DFA, 用状态消除法得到等价的正则表达式.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use crate::{EOF, EPSILON, Grammar, NonTerminal, ProdId, Terminal, Token};
+
+/// 正则文法的种类.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linearity {
+    Right,
+    Left,
+}
+
+impl Display for Linearity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Right => "right-linear",
+            Self::Left => "left-linear",
+        })
+    }
+}
+
+/// 文法既不是右线性的也不是左线性的, 分别给出第一个反例产生式的编号.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotLinear {
+    pub not_right: ProdId,
+    pub not_left: ProdId,
+}
+
+/// 和文法等价的 DFA, 开始状态为 0, 不存在的转换进入 (省略的) 死状态.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa<'a> {
+    pub linearity: Linearity,
+    /// 字母表, 为文法中的终结符, 不包括 [`EPSILON`] 和 [`EOF`].
+    pub terms: Vec<Terminal<'a>>,
+    /// `transitions[state][col]`, `col` 为终结符在 [`Dfa::terms`] 中的下标.
+    pub transitions: Vec<Vec<Option<usize>>>,
+    pub accepting: Vec<bool>,
+}
+
+/// 单个非终结符的位置: 右线性时必须在最后, 左线性时必须在最前.
+fn is_linear(tail: &[Token<'_>], linearity: Linearity) -> bool {
+    let non_terms: Vec<_> = tail
+        .iter()
+        .enumerate()
+        .filter(|(_, tok)| matches!(tok, Token::NonTerminal(_)))
+        .map(|(idx, _)| idx)
+        .collect();
+    match (non_terms.as_slice(), linearity) {
+        ([], _) => true,
+        ([idx], Linearity::Right) => idx + 1 == tail.len(),
+        ([idx], Linearity::Left) => *idx == 0,
+        _ => false,
+    }
+}
+
+/// 检测文法是否为右线性或者左线性 (都满足时为右线性) 的正则文法, 是的话构造等价的 DFA.
+///
+/// # Errors
+/// 文法既不是右线性的也不是左线性的, 见: [`NotLinear`].
+pub fn detect<'a>(grammar: &Grammar<'a>) -> Result<Dfa<'a>, NotLinear> {
+    let tails: Vec<Vec<Token<'a>>> = grammar
+        .prods()
+        .iter()
+        .map(|p| p.tail_without_eps().copied().collect())
+        .collect();
+    let counterexample = |linearity| tails.iter().position(|t| !is_linear(t, linearity));
+    let linearity = match (
+        counterexample(Linearity::Right),
+        counterexample(Linearity::Left),
+    ) {
+        (None, _) => Linearity::Right,
+        (_, None) => Linearity::Left,
+        (Some(not_right), Some(not_left)) => Err(NotLinear {
+            not_right,
+            not_left,
+        })?,
+    };
+    let nfa = Nfa::new(grammar, &tails, linearity);
+    Ok(nfa.determinize(grammar, linearity))
+}
+
+/// NFA, 边上的 [`None`] 为 ε.
+struct Nfa<'a> {
+    edges: Vec<Vec<(Option<Terminal<'a>>, usize)>>,
+    start: usize,
+    accept: usize,
+}
+
+impl<'a> Nfa<'a> {
+    /// 每个非终结符一个状态, 右线性时额外的状态为接受状态, 左线性时额外的状态为开始状态;
+    /// 多个终结符的产生式在中间插入新的状态.
+    fn new(grammar: &Grammar<'a>, tails: &[Vec<Token<'a>>], linearity: Linearity) -> Self {
+        let mut states: BTreeMap<NonTerminal<'a>, usize> = BTreeMap::new();
+        for prod in grammar.prods() {
+            let next = states.len();
+            states.entry(prod.head()).or_insert(next);
+        }
+        let extra = states.len();
+        let mut nfa = Self {
+            edges: vec![Vec::new(); extra + 1],
+            start: extra,
+            accept: extra,
+        };
+        let start = states.get(&grammar.symbol_start()).copied();
+        match linearity {
+            Linearity::Right => nfa.start = start.unwrap_or(extra),
+            Linearity::Left => nfa.accept = start.unwrap_or(extra),
+        }
+        for (prod, tail) in grammar.prods().iter().zip(tails) {
+            let head = states[&prod.head()];
+            let other = tail.iter().find_map(|tok| match tok {
+                Token::NonTerminal(nt) => Some(*nt),
+                Token::Terminal(_) => None,
+            });
+            let other = match other {
+                // 没有产生式的非终结符不推导出任何串, 这个产生式不产生边.
+                Some(nt) => match states.get(&nt) {
+                    Some(&state) => state,
+                    None => continue,
+                },
+                None => extra,
+            };
+            let (from, to) = match linearity {
+                Linearity::Right => (head, other),
+                Linearity::Left => (other, head),
+            };
+            let terms: Vec<_> = tail
+                .iter()
+                .filter_map(|tok| match tok {
+                    Token::Terminal(t) => Some(*t),
+                    Token::NonTerminal(_) => None,
+                })
+                .collect();
+            nfa.chain(from, &terms, to);
+        }
+        nfa
+    }
+
+    /// 从 `from` 依次读入 `terms` 到达 `to`.
+    fn chain(&mut self, from: usize, terms: &[Terminal<'a>], to: usize) {
+        let Some((last, init)) = terms.split_last() else {
+            self.edges[from].push((None, to));
+            return;
+        };
+        let mut at = from;
+        for &term in init {
+            self.edges.push(Vec::new());
+            let next = self.edges.len() - 1;
+            self.edges[at].push((Some(term), next));
+            at = next;
+        }
+        self.edges[at].push((Some(*last), to));
+    }
+
+    fn closure(&self, mut set: BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut stack: Vec<_> = set.iter().copied().collect();
+        while let Some(state) = stack.pop() {
+            for &(label, to) in &self.edges[state] {
+                if label.is_none() && set.insert(to) {
+                    stack.push(to);
+                }
+            }
+        }
+        set
+    }
+
+    /// 子集构造.
+    fn determinize(&self, grammar: &Grammar<'a>, linearity: Linearity) -> Dfa<'a> {
+        let terms: Vec<_> = grammar
+            .tokens()
+            .iter()
+            .filter_map(|tok| match *tok {
+                Token::Terminal(t) if t != EPSILON && t != EOF => Some(t),
+                _ => None,
+            })
+            .collect();
+        let mut dfa = Dfa {
+            linearity,
+            terms,
+            transitions: Vec::new(),
+            accepting: Vec::new(),
+        };
+        let start = self.closure(BTreeSet::from([self.start]));
+        let mut ids = BTreeMap::from([(start.clone(), 0)]);
+        let mut sets = vec![start];
+        let mut idx = 0;
+        while let Some(set) = sets.get(idx).cloned() {
+            let row = dfa
+                .terms
+                .iter()
+                .map(|&term| {
+                    let moved: BTreeSet<_> = set
+                        .iter()
+                        .flat_map(|&s| &self.edges[s])
+                        .filter(|(label, _)| *label == Some(term))
+                        .map(|&(_, to)| to)
+                        .collect();
+                    if moved.is_empty() {
+                        return None;
+                    }
+                    let moved = self.closure(moved);
+                    let next = ids.len();
+                    Some(*ids.entry(moved.clone()).or_insert_with(|| {
+                        sets.push(moved);
+                        next
+                    }))
+                })
+                .collect();
+            dfa.transitions.push(row);
+            dfa.accepting.push(set.contains(&self.accept));
+            idx += 1;
+        }
+        dfa
+    }
+}
+
+/// 状态消除法使用的正则表达式.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Re {
+    Eps,
+    Sym(String),
+    Cat(Vec<Re>),
+    Alt(Vec<Re>),
+    Star(Box<Re>),
+}
+
+impl Re {
+    fn cat(parts: impl IntoIterator<Item = Re>) -> Self {
+        let mut flat = Vec::new();
+        for part in parts {
+            match part {
+                Self::Eps => {}
+                Self::Cat(inner) => flat.extend(inner),
+                other => flat.push(other),
+            }
+        }
+        match flat.len() {
+            0 => Self::Eps,
+            1 => flat.pop().unwrap_or(Self::Eps),
+            _ => Self::Cat(flat),
+        }
+    }
+
+    fn alt(a: Self, b: Self) -> Self {
+        let mut flat = Vec::new();
+        for part in [a, b] {
+            let parts = match part {
+                Self::Alt(inner) => inner,
+                other => vec![other],
+            };
+            for part in parts {
+                if !flat.contains(&part) {
+                    flat.push(part);
+                }
+            }
+        }
+        if flat.len() == 1 {
+            flat.pop().unwrap_or(Self::Eps)
+        } else {
+            Self::Alt(flat)
+        }
+    }
+
+    fn star(self) -> Self {
+        match self {
+            Self::Eps => Self::Eps,
+            star @ Self::Star(_) => star,
+            other => Self::Star(Box::new(other)),
+        }
+    }
+
+    fn atom(&self) -> String {
+        match self {
+            Self::Cat(_) | Self::Alt(_) => format!("({self})"),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl Display for Re {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Eps => write!(f, "ε"),
+            Self::Sym(s) => write!(f, "{s}"),
+            Self::Cat(parts) => {
+                let parts: Vec<_> = parts
+                    .iter()
+                    .map(|p| match p {
+                        Self::Alt(_) => p.atom(),
+                        _ => p.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" "))
+            }
+            Self::Alt(parts) => {
+                let parts: Vec<_> = parts.iter().map(ToString::to_string).collect();
+                write!(f, "{}", parts.join(" | "))
+            }
+            Self::Star(inner) => write!(f, "{}*", inner.atom()),
+        }
+    }
+}
+
+impl Dfa<'_> {
+    #[must_use]
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// 判断 `input` (终结符的名字) 是否属于 DFA 的语言.
+    #[must_use]
+    pub fn accepts(&self, input: &[&str]) -> bool {
+        let mut state = 0;
+        for word in input {
+            let Some(col) = self.terms.iter().position(|t| t.as_str() == *word) else {
+                return false;
+            };
+            match self.transitions[state][col] {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        self.accepting[state]
+    }
+
+    /// 使用状态消除法得到等价的正则表达式, 终结符之间以空格分隔, `ε` 为空串, 语言为空时为 `∅`.
+    #[must_use]
+    pub fn to_regex(&self) -> String {
+        // 0..n 为 DFA 的状态, n 为新的开始状态, n + 1 为新的接受状态.
+        let n = self.states();
+        let mut edges: Vec<Vec<Option<Re>>> = vec![vec![None; n + 2]; n + 2];
+        let add = |edges: &mut Vec<Vec<Option<Re>>>, from: usize, to: usize, re: Re| {
+            let cell = &mut edges[from][to];
+            *cell = Some(match cell.take() {
+                Some(old) => Re::alt(old, re),
+                None => re,
+            });
+        };
+        add(&mut edges, n, 0, Re::Eps);
+        for (state, row) in self.transitions.iter().enumerate() {
+            for (col, to) in row.iter().enumerate() {
+                if let Some(to) = to {
+                    add(&mut edges, state, *to, Re::Sym(self.terms[col].to_string()));
+                }
+            }
+            if self.accepting[state] {
+                add(&mut edges, state, n + 1, Re::Eps);
+            }
+        }
+        for k in 0..n {
+            let loop_re = edges[k][k].take().map_or(Re::Eps, Re::star);
+            let ins: Vec<_> = (0..n + 2)
+                .filter_map(|i| edges[i][k].take().map(|re| (i, re)))
+                .collect();
+            let outs: Vec<_> = (0..n + 2)
+                .filter_map(|j| edges[k][j].take().map(|re| (j, re)))
+                .collect();
+            for (i, into) in &ins {
+                for (j, out) in &outs {
+                    let re = Re::cat([into.clone(), loop_re.clone(), out.clone()]);
+                    add(&mut edges, *i, *j, re);
+                }
+            }
+        }
+        edges[n][n + 1]
+            .as_ref()
+            .map_or_else(|| "∅".to_string(), ToString::to_string)
+    }
+}
+
+/// 每行一个状态: `*` 标记接受状态, 之后为各个终结符上的转换.
+impl Display for Dfa<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (state, row) in self.transitions.iter().enumerate() {
+            let mark = if self.accepting[state] { "*" } else { " " };
+            let moves: Vec<_> = row
+                .iter()
+                .zip(&self.terms)
+                .filter_map(|(to, term)| to.map(|to| format!("{term} -> {to}")))
+                .collect();
+            writeln!(f, "{mark}{state:>3}: {}", moves.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Grammar,
+        regular::{Linearity, NotLinear, detect},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn regular_grammars() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b A\nA -> c A | E", "S".into(), &bump).unwrap();
+        let dfa = detect(&grammar).unwrap();
+        assert_eq!(dfa.linearity, Linearity::Right);
+        assert!(dfa.accepts(&["b"]));
+        assert!(dfa.accepts(&["a", "a", "b", "c", "c"]));
+        assert!(!dfa.accepts(&["a"]));
+        assert!(!dfa.accepts(&["b", "a"]));
+        assert_eq!(dfa.to_regex(), "a* b c*");
+
+        let grammar = Grammar::from_cfg("S -> S a b | c | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let dfa = detect(&grammar).unwrap();
+        assert_eq!(dfa.linearity, Linearity::Left);
+        assert!(dfa.accepts(&["c", "a", "b", "a", "b"]));
+        assert!(dfa.accepts(&["a", "b"]));
+        assert!(!dfa.accepts(&["c", "a"]));
+        assert_eq!(dfa.to_regex(), "ε | (c | a b) (a b)*");
+
+        let grammar = Grammar::from_cfg("S -> ( S ) | a", "S".into(), &bump).unwrap();
+        assert_eq!(
+            detect(&grammar),
+            Err(NotLinear {
+                not_right: 0,
+                not_left: 0
+            })
+        );
+    }
+}
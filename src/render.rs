@@ -0,0 +1,380 @@
+//! 项集和语法分析表的文本渲染选项.
+//!
+//! 大文法 (几百个终结符) 的项集前瞻符列表和分析表非常宽, 通过 [`RenderOptions`] 截断前瞻符列表,
+//! 限制每个项集显示的项数, 并把分析表按列拆分成多个 markdown 表格. 默认不做任何限制.
+//! 也可以像龙书中的插图一样只显示每个项集的内核项.
+
+use std::fmt::Display;
+
+use crate::{Item, ItemSet, StateId, Table, Token};
+
+/// 渲染选项, 为 [`None`] 的选项不做限制.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// 每个项最多显示的前瞻符数量, 其余的折叠为 `… (+n)`.
+    pub max_look_aheads: Option<usize>,
+    /// 每个项集最多显示的项数量, 其余的折叠为一行 `… (+n items)`.
+    pub max_items: Option<usize>,
+    /// 分析表每个 markdown 表格最多包含的列数 (不包括状态列), 超过时拆分为多个表格.
+    pub max_columns: Option<usize>,
+    /// 只显示项集的内核项 (见: [`ItemSet::kernel`]), 不显示闭包加入的项.
+    pub kernel_only: bool,
+    /// 按照产生式的声明顺序排列项, 见: [`ItemSet::items_by_production`].
+    pub by_production: bool,
+}
+
+impl Item<'_> {
+    /// 按照 `options` 截断前瞻符列表, 不截断时和 [`std::fmt::Display`] 的输出相同.
+    #[must_use]
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let limit = options.max_look_aheads.unwrap_or(usize::MAX);
+        let look_aheads = self.look_aheads();
+        if look_aheads.len() <= limit {
+            return self.to_string();
+        }
+        let shown = look_aheads
+            .iter()
+            .take(limit)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sep = if shown.is_empty() { "" } else { ", " };
+        let hidden = look_aheads.len() - limit;
+        format!("{} 〈{shown}{sep}… (+{hidden})〉", self.core_string())
+    }
+}
+
+impl ItemSet<'_> {
+    /// 每行一个项, 按照 `options` 截断.
+    #[must_use]
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let mut items: Vec<_> = if options.by_production {
+            self.items_by_production()
+                .into_iter()
+                .flat_map(|(_, items)| items)
+                .collect()
+        } else {
+            self.items().collect()
+        };
+        if options.kernel_only {
+            let kernel: Vec<_> = self.kernel().collect();
+            items.retain(|item| kernel.contains(item));
+        }
+        let limit = options.max_items.unwrap_or(usize::MAX);
+        let mut lines: Vec<_> = items
+            .iter()
+            .take(limit)
+            .map(|item| item.render(options))
+            .collect();
+        if items.len() > limit {
+            lines.push(format!("… (+{} items)", items.len() - limit));
+        }
+        lines.join("\n")
+    }
+}
+
+/// 分析表的文本格式.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    /// markdown 表格.
+    #[default]
+    Markdown,
+    /// 使用 `+`, `-`, `|` 绘制边框并对齐列的纯文本表格.
+    Ascii,
+    /// 每个状态一行, 只列出非空的格子, 形如 `I_0: {=s1, program=3`.
+    Compact,
+}
+
+/// 分析表的渲染样式, 见: [`Table::render`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableStyle {
+    pub format: TableFormat,
+    /// 其中的 [`RenderOptions::max_columns`] 用于拆分宽表格, [`TableFormat::Compact`] 不拆分.
+    pub options: RenderOptions,
+}
+
+impl TableStyle {
+    #[must_use]
+    pub fn new(format: TableFormat) -> Self {
+        Self {
+            format,
+            options: RenderOptions::default(),
+        }
+    }
+}
+
+/// 使用指定样式输出的分析表, 见: [`Table::styled`].
+#[derive(Debug, Clone, Copy)]
+pub struct StyledTable<'t, 'a> {
+    table: &'t Table<'a>,
+    style: TableStyle,
+}
+
+impl Display for StyledTable<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.table.render(&self.style))
+    }
+}
+
+/// 默认输出 markdown 表格, 使用 `{:#}` 时输出 [`TableFormat::Ascii`] 表格.
+impl Display for Table<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = if f.alternate() {
+            TableFormat::Ascii
+        } else {
+            TableFormat::Markdown
+        };
+        f.write_str(&self.render(&TableStyle::new(format)))
+    }
+}
+
+impl<'a> Table<'a> {
+    /// ACTION 表的所有列和 GOTO 表中不是整列为空的列.
+    fn columns(&self) -> Vec<Token<'a>> {
+        self.terms()
+            .iter()
+            .map(|&t| t.into())
+            .chain(self.goto_columns().into_iter().map(Token::from))
+            .collect()
+    }
+
+    /// 格子的文本, 空格子为空串.
+    fn cell_text(&self, state: StateId, col: Token<'a>) -> String {
+        match col {
+            Token::Terminal(t) => self.action(state, t).map(ToString::to_string),
+            Token::NonTerminal(nt) => self.goto(state, nt).flatten().map(|to| to.to_string()),
+        }
+        .unwrap_or_default()
+    }
+
+    /// 按照 `style` 输出表格.
+    #[must_use]
+    pub fn render(&self, style: &TableStyle) -> String {
+        match style.format {
+            TableFormat::Markdown => self.render_markdown(&style.options),
+            TableFormat::Ascii => self.render_ascii(&style.options),
+            TableFormat::Compact => self.render_compact(),
+        }
+    }
+
+    /// 用于 `println!("{}", table.styled(style))`.
+    #[must_use]
+    pub fn styled(&self, style: TableStyle) -> StyledTable<'_, 'a> {
+        StyledTable { table: self, style }
+    }
+
+    /// 使用 markdown 形式输出表格, 按照 [`RenderOptions::max_columns`] 拆分为多个以空行分隔的表格,
+    /// 每个表格都包含状态列.
+    #[must_use]
+    pub fn render_markdown(&self, options: &RenderOptions) -> String {
+        let cols = self.columns();
+        let chunk = options.max_columns.unwrap_or(usize::MAX).max(1);
+        let mut tables = Vec::new();
+        for cols in cols.chunks(chunk) {
+            let header: String = cols
+                .iter()
+                .map(|c| format!(" `{}` |", c.as_str()))
+                .collect();
+            let sep = " - |".repeat(cols.len());
+            let mut lines = vec![format!("| |{header}"), format!("| - |{sep}")];
+            for state in 0..self.rows() {
+                let cells: String = cols
+                    .iter()
+                    .map(|&col| format!(" {} |", self.cell_text(state, col)))
+                    .collect();
+                lines.push(format!("| $I_{{{state}}}$ |{cells}"));
+            }
+            tables.push(lines.join("\n"));
+        }
+        tables.join("\n\n")
+    }
+
+    /// 输出对齐的纯文本表格, 拆分规则同 [`Table::render_markdown`].
+    #[must_use]
+    pub fn render_ascii(&self, options: &RenderOptions) -> String {
+        let cols = self.columns();
+        let chunk = options.max_columns.unwrap_or(usize::MAX).max(1);
+        let mut tables = Vec::new();
+        for cols in cols.chunks(chunk) {
+            let mut rows = vec![
+                std::iter::once(String::new())
+                    .chain(cols.iter().map(|c| c.as_str().to_string()))
+                    .collect::<Vec<_>>(),
+            ];
+            for state in 0..self.rows() {
+                rows.push(
+                    std::iter::once(format!("I_{state}"))
+                        .chain(cols.iter().map(|&col| self.cell_text(state, col)))
+                        .collect(),
+                );
+            }
+            let widths: Vec<_> = (0..=cols.len())
+                .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+                .collect();
+            let border: String = widths
+                .iter()
+                .map(|w| format!("+{}", "-".repeat(w + 2)))
+                .collect::<String>()
+                + "+";
+            let mut lines = vec![border.clone()];
+            for (i, row) in rows.iter().enumerate() {
+                let line: String = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, &w)| format!("| {cell:<w$} "))
+                    .collect();
+                lines.push(line + "|");
+                if i == 0 {
+                    lines.push(border.clone());
+                }
+            }
+            lines.push(border);
+            tables.push(lines.join("\n"));
+        }
+        tables.join("\n\n")
+    }
+
+    /// 每个状态一行, 只列出非空的格子.
+    #[must_use]
+    pub fn render_compact(&self) -> String {
+        let cols = self.columns();
+        (0..self.rows())
+            .map(|state| {
+                let cells = cols
+                    .iter()
+                    .filter_map(|&col| {
+                        let text = self.cell_text(state, col);
+                        (!text.is_empty()).then(|| format!("{col}={text}"))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("I_{state}: {cells}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Table,
+        render::{RenderOptions, TableFormat, TableStyle},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn truncation() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let options = RenderOptions {
+            max_look_aheads: Some(2),
+            max_items: Some(3),
+            max_columns: Some(10),
+            kernel_only: false,
+            by_production: false,
+        };
+        let is = family
+            .item_sets()
+            .iter()
+            .max_by_key(|is| is.items().count())
+            .unwrap();
+        let unlimited = is.render(&RenderOptions::default());
+        assert_eq!(
+            unlimited,
+            is.items()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        let rendered = is.render(&options);
+        let lines: Vec<_> = rendered.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[3], format!("… (+{} items)", is.items().count() - 3));
+        // 前瞻符多于 2 个的项被截断.
+        let mut truncated = 0;
+        for (line, item) in lines[..3].iter().zip(is.items()) {
+            if item.look_aheads().len() > 2 {
+                truncated += 1;
+                assert!(line.ends_with(&format!("… (+{})〉", item.look_aheads().len() - 2)));
+            } else {
+                assert_eq!(*line, item.to_string());
+            }
+        }
+        assert!(truncated > 0);
+
+        let kernel_only = RenderOptions {
+            kernel_only: true,
+            ..RenderOptions::default()
+        };
+        // I_0 的内核项只有增广产生式的初始项.
+        assert_eq!(
+            family.item_sets()[0].render(&kernel_only),
+            "programprime -> ⋅ program 〈eof〉"
+        );
+        assert_eq!(is.render(&kernel_only).lines().count(), is.kernel().count());
+        assert!(is.kernel().count() < is.items().count());
+
+        let by_production = RenderOptions {
+            by_production: true,
+            ..RenderOptions::default()
+        };
+        let expected: Vec<_> = is
+            .items_by_production()
+            .into_iter()
+            .flat_map(|(_, items)| items)
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(is.render(&by_production), expected.join("\n"));
+
+        let markdown = table.render_markdown(&options);
+        let tables: Vec<_> = markdown.split("\n\n").collect();
+        let cols = table.action_cols() + table.goto_columns().len();
+        assert_eq!(tables.len(), cols.div_ceil(10));
+        for t in &tables {
+            assert_eq!(t.lines().count(), table.rows() + 2);
+            assert!(t.lines().next().unwrap().matches('`').count() <= 20);
+        }
+    }
+
+    #[test]
+    fn table_styles() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert_eq!(table.to_string(), table.to_markdown());
+        assert_eq!(
+            format!("{table:#}"),
+            [
+                "+-----+----+----+---+-----+---+",
+                "|     | a  | b  | E | eof | S |",
+                "+-----+----+----+---+-----+---+",
+                "| I_0 | s1 | s2 |   |     | 3 |",
+                "| I_1 | s1 | s2 |   |     | 4 |",
+                "| I_2 |    |    |   | r2  |   |",
+                "| I_3 |    |    |   | acc |   |",
+                "| I_4 |    |    |   | r1  |   |",
+                "+-----+----+----+---+-----+---+",
+            ]
+            .join("\n")
+        );
+        // 整列为空的 GOTO 列只是不渲染, 仍然可以查询.
+        assert_eq!(table.goto_cols(), 2);
+        assert_eq!(table.goto(0, "Sprime".into()), Some(None));
+        assert_eq!(
+            table
+                .styled(TableStyle::new(TableFormat::Compact))
+                .to_string(),
+            "I_0: a=s1, b=s2, S=3\nI_1: a=s1, b=s2, S=4\nI_2: eof=r2\nI_3: eof=acc\nI_4: eof=r1"
+        );
+    }
+}
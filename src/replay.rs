@@ -0,0 +1,275 @@
+//! 终结符流的录制和重放.
+//!
+//! [`Recorder`] 包装任意的终结符迭代器, 记录交给语法分析器的每个终结符 (包括词素, 行号和列号),
+//! 得到的 [`Recording`] 可以保存为文本文件, 附在错误报告中. 重放时对同一个分析表重新分析完全相同的输入,
+//! 分析表的指纹 (见: [`fingerprint`]) 不同时拒绝重放, 保证结果是确定的.
+//!
+//! 文件格式: 第一行为版本注释, 第二行为分析表指纹, 之后每行一个终结符 `line:column "term" "text"`,
+//! 终结符和词素使用 JSON 字符串字面量.
+
+use crate::{
+    Grammar, Parser, Table, Terminal, Token,
+    error::Error,
+    graph::json_str,
+    parser::{InputTerm, ParseOutput},
+};
+
+/// 录制文件第一行的版本注释.
+const HEADER: &str = "# lr-analysis recording v1";
+
+/// 分析表的指纹: 文法和紧凑格式分析表的 FNV-1a 哈希, 不依赖运行环境, 跨版本稳定.
+#[must_use]
+pub fn fingerprint(table: &Table<'_>) -> u64 {
+    let text = format!("{}\n{}", table.grammar(), table.render_compact());
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 记录下来的终结符流.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording<'a> {
+    pub terms: Vec<InputTerm<'a>>,
+    /// 录制时的分析表指纹, 未知时为 [`None`], 此时重放不检查分析表.
+    pub table: Option<u64>,
+}
+
+impl<'a> Recording<'a> {
+    /// 渲染为录制文件, 见模块文档.
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{HEADER}\n");
+        if let Some(table) = self.table {
+            out += &format!("# table: {table:016x}\n");
+        }
+        for term in &self.terms {
+            out += &format!(
+                "{}:{} {} {}\n",
+                term.line,
+                term.column,
+                json_str(term.term.as_str()),
+                json_str(term.text)
+            );
+        }
+        out
+    }
+
+    /// 解析录制文件, 文法中没有的终结符和词素分配在文法的 arena 中.
+    ///
+    /// # Errors
+    /// - [`Error::InvalidRecording`] 版本注释不匹配, 或者某一行的格式不正确.
+    ///
+    /// # Panics
+    /// 不在创建文法的线程上调用, 并且录制文件中有文法中没有的终结符或者词素, 见: [`Grammar`].
+    pub fn parse(src: &str, grammar: &Grammar<'a>) -> Result<Self, Error> {
+        let invalid = |line: usize, cause: &str| Error::InvalidRecording {
+            line: line + 1,
+            cause: cause.to_string(),
+        };
+        let mut lines = src.lines().enumerate();
+        if lines.next().map(|(_, l)| l.trim()) != Some(HEADER) {
+            Err(invalid(0, "missing version header"))?;
+        }
+        let alloc = |s: String| -> &'a str {
+            match grammar.get_token(&s) {
+                Some(Token::Terminal(t)) => t.as_str(),
+                _ => grammar.bump().alloc_str(&s),
+            }
+        };
+        let mut recording = Self {
+            terms: Vec::new(),
+            table: None,
+        };
+        for (idx, line) in lines {
+            let line = line.trim();
+            if let Some(table) = line.strip_prefix("# table:") {
+                let table = u64::from_str_radix(table.trim(), 16)
+                    .map_err(|_| invalid(idx, "invalid table fingerprint"))?;
+                recording.table = Some(table);
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pos, rest) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid(idx, "expected `line:column \"term\" \"text\"`"))?;
+            let (l, c) = pos
+                .split_once(':')
+                .and_then(|(l, c)| Some((l.parse().ok()?, c.parse().ok()?)))
+                .ok_or_else(|| invalid(idx, "invalid position"))?;
+            let (term, rest) = unquote(rest).ok_or_else(|| invalid(idx, "invalid terminal"))?;
+            let (text, rest) =
+                unquote(rest.trim_start()).ok_or_else(|| invalid(idx, "invalid lexeme"))?;
+            if !rest.trim().is_empty() {
+                Err(invalid(idx, "trailing characters"))?;
+            }
+            let term = Terminal::from(alloc(term));
+            let text = if text == term.as_str() {
+                term.as_str()
+            } else {
+                alloc(text)
+            };
+            recording
+                .terms
+                .push(InputTerm::new(term, l).with_text(text).with_column(c));
+        }
+        Ok(recording)
+    }
+
+    /// 使用 `parser` 重新分析记录的终结符流.
+    ///
+    /// # Errors
+    /// - [`Error::TableMismatch`] 录制时的分析表和 `parser` 的分析表不同.
+    /// - 其他见: [`Parser::parse`].
+    pub fn replay(&self, parser: &Parser<'_, 'a>) -> Result<ParseOutput<'a>, Error> {
+        let found = fingerprint(parser.table());
+        if let Some(expected) = self.table
+            && expected != found
+        {
+            Err(Error::TableMismatch { expected, found })?;
+        }
+        parser.parse(self.terms.iter().copied())
+    }
+}
+
+/// 解析开头的一个 JSON 字符串字面量 (只支持 [`json_str`] 产生的转义), 返回内容和剩余部分.
+fn unquote(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut out = String::new();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[idx + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = (0..4)
+                        .filter_map(|_| chars.next().map(|(_, c)| c))
+                        .collect();
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+/// 包装终结符迭代器, 记录经过的每个终结符, 例如 `parser.parse(&mut recorder)`.
+#[derive(Debug)]
+pub struct Recorder<'a, I> {
+    inner: I,
+    terms: Vec<InputTerm<'a>>,
+}
+
+impl<'a, I> Recorder<'a, I>
+where
+    I: Iterator,
+    I::Item: Into<InputTerm<'a>>,
+{
+    #[must_use]
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            terms: Vec::new(),
+        }
+    }
+
+    /// 已经记录的终结符流, `table` 为分析时使用的分析表.
+    #[must_use]
+    pub fn recording(&self, table: &Table<'a>) -> Recording<'a> {
+        Recording {
+            terms: self.terms.clone(),
+            table: Some(fingerprint(table)),
+        }
+    }
+
+    /// 取回被包装的迭代器.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<'a, I> Iterator for Recorder<'a, I>
+where
+    I: Iterator,
+    I::Item: Into<InputTerm<'a>>,
+{
+    type Item = InputTerm<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let term = self.inner.next()?.into();
+        self.terms.push(term);
+        Some(term)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Parser, Table,
+        error::Error,
+        lexer::TermReader,
+        replay::{Recorder, Recording, fingerprint},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn record_and_replay() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let input = "{\n  ID = NUM ;\n  ID = = NUM\n}";
+        let mut recorder = Recorder::new(TermReader::new(&grammar, Cursor::new(input)));
+        let output = parser.parse(&mut recorder).unwrap();
+        let recording = recorder.recording(&table);
+        assert_eq!(recording.terms.len(), 10);
+
+        let text = recording.to_text();
+        assert!(text.contains("\n3:3 \"ID\" \"ID\"\n3:6 \"=\" \"=\"\n"));
+        let parsed = Recording::parse(&text, &grammar).unwrap();
+        assert_eq!(parsed, recording);
+        let replayed = parsed.replay(&parser).unwrap();
+        assert_eq!(replayed.terms(), output.terms());
+        assert_eq!(replayed.reductions(), output.reductions());
+        assert_eq!(replayed.report().to_string(), output.report().to_string());
+
+        // 词素中的特殊字符.
+        let mut escaped = recording.clone();
+        escaped.terms[0] = escaped.terms[0].with_text("a \"b\"\n");
+        assert_eq!(
+            Recording::parse(&escaped.to_text(), &grammar).unwrap(),
+            escaped
+        );
+
+        // 不同的分析表.
+        let other = Grammar::from_cfg("S -> a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let other_family = Family::from_grammar(&other).unwrap();
+        let other_table = Table::build_from(&other_family).unwrap();
+        let found = fingerprint(&other_table);
+        assert_eq!(
+            recording.replay(&Parser::new(&other_table)),
+            Err(Error::TableMismatch {
+                expected: fingerprint(&table),
+                found
+            })
+        );
+        assert!(matches!(
+            Recording::parse("1:1 \"ID\" \"ID\"", &grammar),
+            Err(Error::InvalidRecording { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,46 @@
+//! 导出内容的 JSON Schema, 需要开启 `schema` feature.
+//!
+//! 导出的内容包括项集族 ([`OwnedFamily`]), 分析表 ([`OwnedTable`]), 分析过程 ([`OwnedTrace`])
+//! 和诊断信息 ([`Diagnostic`] 的列表), 使用 `serde_json` 序列化.
+//! 仓库的 `schema/` 目录中发布了由 [`schemas`] 生成的文件, 网页等下游可以据此生成类型化的客户端.
+
+use schemars::{Schema, schema_for};
+
+use crate::{
+    diagnostic::Diagnostic,
+    owned::{OwnedFamily, OwnedTable, OwnedTrace},
+};
+
+/// 所有导出内容的 (名字, JSON Schema), 名字也是 `schema/` 目录中的文件名 (`<name>.json`).
+#[must_use]
+pub fn schemas() -> Vec<(&'static str, Schema)> {
+    vec![
+        ("family", schema_for!(OwnedFamily)),
+        ("table", schema_for!(OwnedTable)),
+        ("trace", schema_for!(OwnedTrace)),
+        ("diagnostics", schema_for!(Vec<Diagnostic>)),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::schema::schemas;
+    use pretty_assertions::assert_eq;
+
+    /// `schema/` 目录中的文件需要和代码保持一致, 设置环境变量 `UPDATE_SCHEMA` 运行测试可以重新生成.
+    #[test]
+    fn published_schemas() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+        for (name, schema) in schemas() {
+            let path = dir.join(format!("{name}.json"));
+            let text = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+            if std::env::var_os("UPDATE_SCHEMA").is_some() {
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(&path, &text).unwrap();
+            }
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), text, "{name}");
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! 小文法的定长分析表.
+//!
+//! 状态和符号都很少的文法 (例如单片机上的命令解析) 可以把分析表展开为定长的二维数组:
+//! 不去重行, 不需要行号表, 分析循环直接按照 (状态, 列) 访问格子, 状态栈也是定长数组, 分析时不分配内存.
+//! 各个维度是常量泛型参数, 可以通过 [`shape`] 查询.
+//!
+//! 格子的编码和 [`CompressedTable`] 相同, GOTO 表的列为 [`CompressedTable::goto_non_terms`].
+
+use crate::{ProdId, StateId, Table, codegen::CompressedTable, error::Error};
+
+/// 定长分析表的维度: (状态数量, ACTION 表的列数, GOTO 表的列数, 产生式数量).
+pub type Shape = (usize, usize, usize, usize);
+
+/// 分析表展开为定长数组之后的维度, 作为 [`SmallTable`] 的常量泛型参数.
+///
+/// # Errors
+/// 见: [`CompressedTable::from_table`].
+pub fn shape(table: &Table<'_>) -> Result<Shape, Error> {
+    let compressed = CompressedTable::from_table(table)?;
+    Ok((
+        compressed.states(),
+        compressed.terms.len(),
+        compressed.goto_non_terms.len(),
+        compressed.prod_lens.len(),
+    ))
+}
+
+/// 定长分析表的分析错误, 不携带需要分配内存的信息.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmallError {
+    #[error("Unexpected column {column} on I_{state} at position {position}.")]
+    Syntax {
+        state: StateId,
+        /// 终结符的列号, 见: [`SmallTable::parse`].
+        column: usize,
+        /// 出错的终结符在输入中的下标, 输入结束时为输入的长度.
+        position: usize,
+    },
+    #[error("State stack overflows at position {position}.")]
+    StackOverflow { position: usize },
+}
+
+/// 展开为定长数组的语法分析表, 维度见: [`shape`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmallTable<
+    const STATES: usize,
+    const TERMS: usize,
+    const GOTOS: usize,
+    const PRODS: usize,
+> {
+    /// ACTION 表, 列和 [`CompressedTable::terms`] 一致, [`crate::EOF`] 在最后一列.
+    pub action: [[i16; TERMS]; STATES],
+    /// GOTO 表, `-1` 表示没有转换.
+    pub goto: [[i16; GOTOS]; STATES],
+    /// 每个产生式尾部的长度.
+    pub prod_lens: [u8; PRODS],
+    /// 每个产生式头部在 GOTO 表中的列.
+    pub prod_gotos: [u8; PRODS],
+    /// 增广产生式的编号, 使用它归约就是接受, 见: [`CompressedTable::accept_prod`].
+    pub accept: ProdId,
+}
+
+impl<const STATES: usize, const TERMS: usize, const GOTOS: usize, const PRODS: usize>
+    SmallTable<STATES, TERMS, GOTOS, PRODS>
+{
+    /// 把分析表展开为定长数组.
+    ///
+    /// # Errors
+    /// - [`Error::TableSize`] 分析表的维度和常量泛型参数不同, 或者格子超出了 `i16` 的范围.
+    /// - 其他见: [`CompressedTable::from_table`].
+    pub fn from_table(table: &Table<'_>) -> Result<Self, Error> {
+        let compressed = CompressedTable::from_table(table)?;
+        let check = |what: &str, expected: usize, found: usize| {
+            if expected == found {
+                Ok(())
+            } else {
+                Err(Error::TableSize {
+                    what: what.to_string(),
+                    expected,
+                    found,
+                })
+            }
+        };
+        check("states", STATES, compressed.states())?;
+        check("terminal columns", TERMS, compressed.terms.len())?;
+        check("goto columns", GOTOS, compressed.goto_non_terms.len())?;
+        check("productions", PRODS, compressed.prod_lens.len())?;
+        let max = i16::MAX as usize;
+        if compressed.max_value() as usize > max {
+            check("max cell", max, compressed.max_value() as usize)?;
+        }
+        if let Some(&len) = compressed.prod_lens.iter().find(|&&l| l > u8::MAX.into()) {
+            check("production length", u8::MAX.into(), len)?;
+        }
+        let mut small = Self {
+            action: [[0; TERMS]; STATES],
+            goto: [[-1; GOTOS]; STATES],
+            prod_lens: [0; PRODS],
+            prod_gotos: [0; PRODS],
+            accept: compressed.accept_prod,
+        };
+        for state in 0..STATES {
+            let action = &compressed.action_rows[compressed.action_row_of[state]];
+            let goto = &compressed.goto_rows[compressed.goto_row_of[state]];
+            for (cell, &v) in small.action[state].iter_mut().zip(action) {
+                *cell = v as i16;
+            }
+            for (cell, &v) in small.goto[state].iter_mut().zip(goto) {
+                *cell = v as i16;
+            }
+        }
+        for p in 0..PRODS {
+            small.prod_lens[p] = compressed.prod_lens[p] as u8;
+            small.prod_gotos[p] = compressed.prod_gotos[p] as u8;
+        }
+        Ok(small)
+    }
+
+    /// 分析终结符的列号序列 (不包括最后的 [`crate::EOF`], 由分析函数补上), 每次归约调用 `on_reduce`,
+    /// 接受时不调用. 状态栈最多 `DEPTH` 层, 分析过程不分配内存.
+    ///
+    /// # Errors
+    /// - [`SmallError::Syntax`] 输入不符合文法, 或者列号超出范围.
+    /// - [`SmallError::StackOverflow`] 状态栈超过 `DEPTH` 层.
+    pub fn parse<const DEPTH: usize>(
+        &self,
+        input: impl IntoIterator<Item = usize>,
+        mut on_reduce: impl FnMut(ProdId),
+    ) -> Result<(), SmallError> {
+        let mut input = input.into_iter();
+        let mut stack = [0 as StateId; DEPTH];
+        let mut top = 1;
+        let mut position = 0;
+        let mut column = input.next().unwrap_or(TERMS - 1);
+        if DEPTH == 0 {
+            Err(SmallError::StackOverflow { position })?;
+        }
+        loop {
+            let state = stack[top - 1];
+            let syntax = SmallError::Syntax {
+                state,
+                column,
+                position,
+            };
+            let cell = self.action[state].get(column).copied().unwrap_or(0);
+            match cell {
+                0 => Err(syntax)?,
+                accept if (-accept - 1) as ProdId == self.accept => return Ok(()),
+                to if to > 0 => {
+                    if top == DEPTH {
+                        Err(SmallError::StackOverflow { position })?;
+                    }
+                    stack[top] = (to - 1) as StateId;
+                    top += 1;
+                    position += 1;
+                    column = input.next().unwrap_or(TERMS - 1);
+                }
+                reduce => {
+                    let prod = (-reduce - 1) as ProdId;
+                    top -= usize::from(self.prod_lens[prod]);
+                    let to = self.goto[stack[top - 1]][usize::from(self.prod_gotos[prod])];
+                    if to < 0 {
+                        Err(syntax)?;
+                    }
+                    stack[top] = to as StateId;
+                    top += 1;
+                    on_reduce(prod);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Parser, Table,
+        codegen::CompressedTable,
+        error::Error,
+        small::{SmallError, SmallTable, shape},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn small_table() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert_eq!(shape(&table), Ok((10, 3, 2, 4)));
+        let small = SmallTable::<10, 3, 2, 4>::from_table(&table).unwrap();
+        assert_eq!(
+            SmallTable::<10, 4, 2, 4>::from_table(&table),
+            Err(Error::TableSize {
+                what: "terminal columns".into(),
+                expected: 4,
+                found: 3
+            })
+        );
+
+        let compressed = CompressedTable::from_table(&table).unwrap();
+        let column = |t: &str| compressed.terms.iter().position(|c| c.as_str() == t);
+        let input: Vec<_> = "c d c c d".split(' ').map(|t| column(t).unwrap()).collect();
+        let mut reductions = Vec::new();
+        small
+            .parse::<16>(input.iter().copied(), |p| reductions.push(p))
+            .unwrap();
+        let output = Parser::new(&table)
+            .parse(input.iter().map(|&col| compressed.terms[col]))
+            .unwrap();
+        let expected: Vec<_> = output
+            .reductions()
+            .iter()
+            .map(|r| r.prod)
+            .filter(|&p| Some(p) != grammar.augmented_production())
+            .collect();
+        assert_eq!(reductions, expected);
+
+        assert!(matches!(
+            small.parse::<16>(input[..2].iter().copied(), |_| {}),
+            Err(SmallError::Syntax { position: 2, .. })
+        ));
+        assert_eq!(
+            small.parse::<3>(input.iter().copied(), |_| {}),
+            Err(SmallError::StackOverflow { position: 3 })
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! 单个项集的独立片段.
+//!
+//! 把一个项集 (其中的项, 可以进行的归约和出边) 导出为 markdown 或者 LaTeX 片段,
+//! 可以直接嵌入幻灯片或者讲义, 不需要输出整个项集族.
+
+use std::collections::BTreeMap;
+
+use crate::{Family, ItemSet, ProdId, Terminal, Token};
+
+/// 一个项集可以进行的归约: (产生式编号, 前瞻符), 按照产生式编号排列.
+/// 增广产生式的归约即为接受.
+fn reductions<'a>(is: &ItemSet<'a>) -> BTreeMap<ProdId, Vec<Terminal<'a>>> {
+    let mut reductions: BTreeMap<ProdId, Vec<Terminal<'a>>> = BTreeMap::new();
+    for (item, term) in is.reduces() {
+        let id = is
+            .grammar()
+            .index_of_prod(item.prod())
+            .unwrap_or(ProdId::MAX);
+        reductions.entry(id).or_default().push(term);
+    }
+    reductions
+}
+
+/// 项集的出边 (转换 Token, 到达项集), 项集不在 `family` 中时为空.
+fn transitions<'a>(is: &ItemSet<'a>, family: &Family<'a>) -> Vec<(Token<'a>, usize)> {
+    family
+        .state_of(is)
+        .and_then(|state| family.gotos_of(state))
+        .into_iter()
+        .flatten()
+        .flat_map(|(&tok, dests)| dests.iter().map(move |&to| (tok, to)))
+        .collect()
+}
+
+/// markdown 行内代码, 内容中有反引号时使用两个反引号包裹.
+fn md_code(s: &str) -> String {
+    if s.contains('`') {
+        format!("`` {s} ``")
+    } else {
+        format!("`{s}`")
+    }
+}
+
+/// 转义 LaTeX 文本模式中的特殊字符.
+fn latex_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => out += r"\textbackslash{}",
+            '~' => out += r"\textasciitilde{}",
+            '^' => out += r"\textasciicircum{}",
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 等宽字体的 LaTeX 符号.
+fn latex_tt(s: &str) -> String {
+    format!(r"\texttt{{{}}}", latex_escape(s))
+}
+
+impl<'a> ItemSet<'a> {
+    /// 项集的编号, 项集不在 `family` 中时为 `?`.
+    fn state_name(&self, family: &Family<'a>) -> String {
+        family
+            .state_of(self)
+            .map_or("?".to_string(), |s| s.to_string())
+    }
+
+    /// 把项集导出为独立的 markdown 片段: 标题, 项列表, 归约列表和出边列表 (编号查找自 `family`).
+    ///
+    /// 增广产生式的归约显示为 `accept`, 没有归约或者出边时省略对应的部分.
+    #[must_use]
+    pub fn to_markdown(&self, family: &Family<'a>) -> String {
+        let grammar = self.grammar();
+        let mut lines = vec![
+            format!("**$I_{{{}}}$**", self.state_name(family)),
+            String::new(),
+        ];
+        for item in self.items() {
+            lines.push(format!("- {}", md_code(&item.to_string())));
+        }
+        let reductions = reductions(self);
+        if !reductions.is_empty() {
+            lines.extend([String::new(), "reduce:".to_string(), String::new()]);
+            for (id, terms) in reductions {
+                let on = terms
+                    .iter()
+                    .map(|t| md_code(t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match grammar.prod(id) {
+                    Some(_) if grammar.declared_index(id).is_none() => {
+                        lines.push(format!("- accept on {on}"));
+                    }
+                    Some(prod) => {
+                        lines.push(format!("- r{id} {} on {on}", md_code(&prod.to_string())));
+                    }
+                    None => {}
+                }
+            }
+        }
+        let transitions = transitions(self, family);
+        if !transitions.is_empty() {
+            lines.extend([String::new(), "goto:".to_string(), String::new()]);
+            for (tok, to) in transitions {
+                lines.push(format!("- {} → $I_{{{to}}}$", md_code(tok.as_str())));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// 把项集导出为独立的 LaTeX `tabular` 片段, 内容同 [`ItemSet::to_markdown`].
+    ///
+    /// 符号使用 `\texttt` 并转义特殊字符, 只依赖 LaTeX 的基本命令.
+    #[must_use]
+    pub fn to_latex(&self, family: &Family<'a>) -> String {
+        let grammar = self.grammar();
+        let prod_latex = |id: ProdId, dot: Option<usize>| -> String {
+            let Some(prod) = grammar.prod(id) else {
+                return String::new();
+            };
+            let mut syms: Vec<String> = prod
+                .tail_without_eps()
+                .map(|t| latex_tt(t.as_str()))
+                .collect();
+            if let Some(dot) = dot {
+                syms.insert(dot, r"$\cdot$".to_string());
+            }
+            if syms.is_empty() {
+                syms.push(r"$\varepsilon$".to_string());
+            }
+            format!(
+                r"{} $\to$ {}",
+                latex_tt(prod.head().as_str()),
+                syms.join(" ")
+            )
+        };
+        let mut lines = vec![
+            r"\begin{tabular}{|l|}".to_string(),
+            r"\hline".to_string(),
+            format!(r"$I_{{{}}}$ \\", self.state_name(family)),
+            r"\hline".to_string(),
+        ];
+        for item in self.items() {
+            let id = grammar.index_of_prod(item.prod()).unwrap_or(ProdId::MAX);
+            let look_aheads = item
+                .look_aheads()
+                .iter()
+                .map(|t| latex_tt(t.as_str()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!(
+                r"{}, {} \\",
+                prod_latex(id, Some(item.dot())),
+                look_aheads
+            ));
+        }
+        let reductions = reductions(self);
+        if !reductions.is_empty() {
+            lines.push(r"\hline".to_string());
+            for (id, terms) in reductions {
+                let on = terms
+                    .iter()
+                    .map(|t| latex_tt(t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if grammar.declared_index(id).is_none() {
+                    lines.push(format!(r"accept on {on} \\"));
+                } else {
+                    lines.push(format!(
+                        r"reduce r{id}: {} on {on} \\",
+                        prod_latex(id, None)
+                    ));
+                }
+            }
+        }
+        let transitions = transitions(self, family);
+        if !transitions.is_empty() {
+            lines.push(r"\hline".to_string());
+            for (tok, to) in transitions {
+                lines.push(format!(
+                    r"goto on {}: $I_{{{to}}}$ \\",
+                    latex_tt(tok.as_str())
+                ));
+            }
+        }
+        lines.push(r"\hline".to_string());
+        lines.push(r"\end{tabular}".to_string());
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Token, snippet::latex_escape};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn state_snippets() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> C C\nC -> c C | d", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let is = &family.item_sets()[0];
+        assert_eq!(
+            is.to_markdown(&family),
+            format!(
+                "**$I_{{0}}$**
+
+{}
+
+goto:
+
+{}",
+                is.items()
+                    .map(|i| format!("- `{i}`"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                family
+                    .gotos_of(0)
+                    .unwrap()
+                    .iter()
+                    .map(|(tok, to)| format!("- `{tok}` → $I_{{{}}}$", to.first().unwrap()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        );
+        // 接受状态和归约状态.
+        let accept = family.gotos_of(0).unwrap()[&Token::NonTerminal("S".into())]
+            .first()
+            .copied()
+            .unwrap();
+        assert_eq!(
+            family.item_sets()[accept].to_markdown(&family),
+            format!(
+                "**$I_{{{accept}}}$**\n\n- `Sprime -> S ⋅ 〈eof〉`\n\nreduce:\n\n- accept on `eof`"
+            )
+        );
+        let d = family.gotos_of(0).unwrap()[&Token::Terminal("d".into())]
+            .first()
+            .copied()
+            .unwrap();
+        let latex = family.item_sets()[d].to_latex(&family);
+        assert_eq!(
+            latex,
+            format!(
+                r"\begin{{tabular}}{{|l|}}
+\hline
+$I_{{{d}}}$ \\
+\hline
+\texttt{{C}} $\to$ \texttt{{d}} $\cdot$, \texttt{{c}}, \texttt{{d}} \\
+\hline
+reduce r3: \texttt{{C}} $\to$ \texttt{{d}} on \texttt{{c}}, \texttt{{d}} \\
+\hline
+\end{{tabular}}"
+            )
+        );
+        assert_eq!(latex_escape(r"a_b{#}\"), r"a\_b\{\#\}\textbackslash{}");
+    }
+}
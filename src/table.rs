@@ -1,8 +1,25 @@
-use std::{collections::HashMap, fmt::Display, mem::swap};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    mem::swap,
+};
 
-use crate::{Family, Grammar, NonTerminal, Terminal, Token};
+use crate::{
+    EOF, Family, Grammar, NonTerminal, ProdId, StateId, Terminal, Token,
+    conflict::{Conflict, ConflictCell, ConflictPolicy, ConflictResolver},
+    error::Error,
+    precedence,
+    render::RenderOptions,
+};
+
+/// 转换为 Typst 字符串字面量 (包括两侧的引号).
+pub(crate) fn typst_str(s: &str) -> String {
+    format!("{s:?}")
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ActionCell {
     /// 移入项集状态编号.
     Shift(usize),
@@ -38,7 +55,7 @@ impl ActionCell {
     }
 
     /// 放入新的 cell 内容, 返回是否冲突
-    fn update(&mut self, cell: ActionCell) -> bool {
+    pub(crate) fn update(&mut self, cell: ActionCell) -> bool {
         let mut conflict = false;
         let mut this = ActionCell::Empty;
         swap(&mut this, self);
@@ -53,7 +70,10 @@ impl ActionCell {
                 *self = Self::Conflict(Box::new(this), Box::new(Self::Conflict(ca, cb)));
                 conflict = true;
             }
-            (a, b) => *self = Self::Conflict(Box::new(a), Box::new(b)),
+            (a, b) => {
+                *self = Self::Conflict(Box::new(a), Box::new(b));
+                conflict = true;
+            }
         }
         conflict
     }
@@ -68,7 +88,208 @@ impl ActionCell {
     }
 }
 
-#[allow(dead_code)]
+/// 语法分析表中一个非空格子的引用, 见: [`Table::cells`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellRef<'t> {
+    /// ACTION 表中的格子, 不会是 [`ActionCell::Empty`].
+    Action(&'t ActionCell),
+    /// GOTO 表中的格子, 值为到达的项集状态编号.
+    Goto(StateId),
+}
+
+/// 归约一个产生式需要的信息, 不需要再查询文法, 见: [`Table::reduce_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReduceInfo {
+    /// 产生式尾部的长度, 不包括 [`crate::EPSILON`], 即归约时弹出的状态数量.
+    pub len: usize,
+    /// 产生式头部在 GOTO 表中的列.
+    pub head: usize,
+}
+
+/// 分析表的列对应的符号, 见: [`Table::symbols`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbols<'t, 'a> {
+    /// ACTION 表的列对应的终结符, 下标即为列号, [`EOF`] 在最后.
+    /// 文法中有空产生式时包括 [`crate::EPSILON`], 这一列总是为空.
+    pub terms: &'t [Terminal<'a>],
+    /// GOTO 表的列对应的非终结符, 下标即为列号, 见: [`ReduceInfo::head`].
+    pub non_terms: &'t [NonTerminal<'a>],
+}
+
+impl Symbols<'_, '_> {
+    /// 终结符在 ACTION 表中的列号.
+    #[must_use]
+    pub fn term_column(&self, term: Terminal) -> Option<usize> {
+        self.terms.iter().position(|&t| t == term)
+    }
+
+    /// 非终结符在 GOTO 表中的列号.
+    #[must_use]
+    pub fn non_term_column(&self, non_term: NonTerminal) -> Option<usize> {
+        self.non_terms.iter().position(|&nt| nt == non_term)
+    }
+}
+
+/// [`Table::validate`] 发现的分析表内部不一致.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableDefect {
+    /// 移入的目标状态不存在.
+    ShiftOutOfRange {
+        state: StateId,
+        term: String,
+        to: StateId,
+    },
+    /// GOTO 的目标状态不存在.
+    GotoOutOfRange {
+        state: StateId,
+        non_term: String,
+        to: StateId,
+    },
+    /// 归约的产生式编号超出范围.
+    ProductionOutOfRange {
+        state: StateId,
+        term: String,
+        prod: usize,
+    },
+    /// 在 `state` 归约 `prod` 之后回到状态 `from`, 但是 `from` 没有产生式左部的 GOTO 表项.
+    MissingGoto {
+        state: StateId,
+        prod: usize,
+        from: StateId,
+    },
+    /// 从初始状态出发无法到达接受动作.
+    AcceptUnreachable,
+}
+
+/// 在格子中填入 `action`, 返回是否冲突, 开启 `tracing` feature 时产生冲突的同时发出 `conflict` 事件.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn fill(cell: &mut ActionCell, action: ActionCell, state: StateId, term: Terminal<'_>) -> bool {
+    let conflict = cell.update(action);
+    #[cfg(feature = "tracing")]
+    if conflict {
+        tracing::debug!(state, %term, actions = %leaves(cell), "conflict");
+    }
+    conflict
+}
+
+/// 格子中的所有动作, 以空格分隔, 例如 `s3 r2`.
+#[cfg(feature = "tracing")]
+fn leaves(cell: &ActionCell) -> String {
+    cell.flatten()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// [`Table::validate`] 和 [`crate::owned::OwnedTable::validate`] 共用的检查.
+///
+/// `prods` 为每个产生式的 (左部, 右部长度), `actions` 和 `gotos` 为所有的 (状态, 列符号, 动作或者目标状态),
+/// 冲突的格子按照其中的每个动作给出.
+pub(crate) fn validate_cells<'s>(
+    rows: usize,
+    prods: &[(&str, usize)],
+    actions: impl IntoIterator<Item = (StateId, &'s str, &'s ActionCell)>,
+    gotos: impl IntoIterator<Item = (StateId, &'s str, StateId)>,
+) -> Vec<TableDefect> {
+    let mut defects = Vec::new();
+    // 每个状态的前驱状态和后继状态, 只包括目标状态存在的边.
+    let mut preds = vec![Vec::new(); rows];
+    let mut succs = vec![Vec::new(); rows];
+    let mut goto_of = HashSet::new();
+    let mut reduces = Vec::new();
+    let mut accept = vec![false; rows];
+    for (state, term, action) in actions {
+        match *action {
+            ActionCell::Shift(to) if to >= rows => {
+                defects.push(TableDefect::ShiftOutOfRange {
+                    state,
+                    term: term.to_string(),
+                    to,
+                });
+            }
+            ActionCell::Shift(to) => {
+                preds[to].push(state);
+                succs[state].push(to);
+            }
+            ActionCell::Reduce(prod) if prod >= prods.len() => {
+                defects.push(TableDefect::ProductionOutOfRange {
+                    state,
+                    term: term.to_string(),
+                    prod,
+                });
+            }
+            ActionCell::Reduce(prod) => reduces.push((state, prod)),
+            ActionCell::Accept => accept[state] = true,
+            _ => {}
+        }
+    }
+    for (state, non_term, to) in gotos {
+        if to >= rows {
+            defects.push(TableDefect::GotoOutOfRange {
+                state,
+                non_term: non_term.to_string(),
+                to,
+            });
+        } else {
+            preds[to].push(state);
+            succs[state].push(to);
+            goto_of.insert((state, non_term));
+        }
+    }
+    reduces.sort_unstable();
+    reduces.dedup();
+    for (state, prod) in reduces {
+        let (head, len) = prods[prod];
+        let mut origins = vec![state];
+        for _ in 0..len {
+            origins = origins.iter().flat_map(|&s| preds[s].clone()).collect();
+            origins.sort_unstable();
+            origins.dedup();
+        }
+        for from in origins {
+            if !goto_of.contains(&(from, head)) {
+                defects.push(TableDefect::MissingGoto { state, prod, from });
+            }
+        }
+    }
+    // 从初始状态出发的可达状态.
+    let mut reachable = vec![false; rows];
+    let mut queue = vec![0];
+    while let Some(state) = queue.pop() {
+        if state >= rows || std::mem::replace(&mut reachable[state], true) {
+            continue;
+        }
+        queue.extend(succs[state].iter().filter(|&&to| !reachable[to]));
+    }
+    if !(0..rows).any(|s| reachable[s] && accept[s]) {
+        defects.push(TableDefect::AcceptUnreachable);
+    }
+    defects
+}
+
+impl Display for TableDefect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShiftOutOfRange { state, term, to } => {
+                write!(f, "I_{state} shifts `{term}` to missing state {to}")
+            }
+            Self::GotoOutOfRange {
+                state,
+                non_term,
+                to,
+            } => write!(f, "I_{state} goes to missing state {to} on `{non_term}`"),
+            Self::ProductionOutOfRange { state, term, prod } => {
+                write!(f, "I_{state} reduces missing production r{prod} on `{term}`")
+            }
+            Self::MissingGoto { state, prod, from } => write!(
+                f,
+                "reducing r{prod} in I_{state} returns to I_{from}, which has no GOTO for its head"
+            ),
+            Self::AcceptUnreachable => write!(f, "accept is not reachable from I_0"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Table<'a> {
     /// ACTION 表
@@ -84,26 +305,83 @@ pub struct Table<'a> {
     non_terms: Vec<NonTerminal<'a>>,
     term_idxes: HashMap<Terminal<'a>, usize>,
     non_term_idxes: HashMap<NonTerminal<'a>, usize>,
-    /// 文法在规范 LR(1) 分析中是否是冲突的.
+    /// 文法在规范 LR(1) 分析中是否是冲突的, 冲突被 [`ConflictPolicy::Allow`] 解决之后为 `false`.
     conflict: bool,
+    /// 被 [`ConflictResolver`], 优先级声明或者 [`ConflictPolicy::Allow`] 解决的冲突格子数量.
+    resolved: usize,
+    /// 按照产生式编号排列的归约信息.
+    reduces: Vec<ReduceInfo>,
+}
+
+/// 序列化为 [`crate::owned::OwnedTable`], 见: [`Table::to_json`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for Table<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::owned::OwnedTable::from_table(self).serialize(serializer)
+    }
 }
 
 impl<'a> Table<'a> {
-    #[must_use]
-    pub fn build_from(family: &'a Family<'a>, grammar: &'a Grammar<'a>) -> Self {
+    /// 根据项集族和构建它的文法 (见: [`Family::grammar`]) 构建语法分析表,
+    /// 冲突的格子保留为 [`ActionCell::Conflict`], 即 [`ConflictPolicy::Warn`].
+    /// 文法中声明了优先级的移入/归约冲突会被解决, 见: [`Grammar::precedence_levels`].
+    ///
+    /// # Errors
+    /// 见: [`Table::build_with_policy`].
+    pub fn build_from(family: &'a Family<'a>) -> Result<Self, Error> {
+        Self::build_with_policy(family, ConflictPolicy::Warn)
+    }
+
+    /// 根据项集族和文法构建语法分析表, 按照 `policy` 处理冲突.
+    ///
+    /// # Errors
+    /// - [`Error::ConflictingTable`] `policy` 为 [`ConflictPolicy::Error`] 并且分析表中有冲突.
+    /// - [`Error::TerminalNotFound`] / [`Error::NonTerminalNotFound`] 项集族中出现了文法中没有的符号.
+    /// - [`Error::ProductionNotFound`] 项集族中的项对应的产生式在文法中不存在.
+    pub fn build_with_policy(family: &'a Family<'a>, policy: ConflictPolicy) -> Result<Self, Error> {
+        Self::build_with_resolver(family, policy, &mut |_: &ConflictCell<'a>| None)
+    }
+
+    /// 根据项集族和文法构建语法分析表, 每个冲突格子先交给 `resolver` 处理,
+    /// `resolver` 返回 [`None`] (推迟) 的格子按照文法的优先级声明处理 (见: [`crate::precedence::resolve`]),
+    /// 仍然没有解决的格子再按照 `policy` 处理.
+    ///
+    /// # Errors
+    /// 见: [`Table::build_with_policy`].
+    pub fn build_with_resolver(
+        family: &'a Family<'a>,
+        policy: ConflictPolicy,
+        resolver: &mut dyn ConflictResolver<'a>,
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("table", states = family.len()).entered();
+        let grammar = family.grammar();
         let tokens = grammar.tokens().iter();
         // 这里要求终结符一定要在非终结符排序的前面.
         let terms: Vec<_> = tokens.clone().map_while(|t| t.as_term()).copied().collect();
         let non_terms: Vec<_> = tokens
             .clone()
             .skip_while(|t| t.is_term())
-            .map(|t| t.as_non_term().unwrap())
+            .filter_map(|t| t.as_non_term())
             .copied()
             .collect();
         let term_idxes: HashMap<Terminal<'a>, usize> =
             terms.iter().enumerate().map(|(a, b)| (*b, a)).collect();
         let non_term_idxes: HashMap<NonTerminal<'a>, usize> =
             non_terms.iter().enumerate().map(|(a, b)| (*b, a)).collect();
+        let reduces = grammar
+            .prods()
+            .iter()
+            .map(|prod| {
+                let head = *non_term_idxes
+                    .get(&prod.head())
+                    .ok_or_else(|| Error::NonTerminalNotFound(prod.head().to_string()))?;
+                Ok(ReduceInfo {
+                    len: prod.len(),
+                    head,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
         let action_cols = terms.len();
         let goto_cols = non_terms.len();
         let rows = family.len();
@@ -119,28 +397,98 @@ impl<'a> Table<'a> {
             {
                 match tok {
                     Token::Terminal(t) => {
-                        let term_idx = *term_idxes.get(t).unwrap();
-                        conflict |= action[row][term_idx].update(ActionCell::Shift(to));
+                        let term_idx = *term_idxes
+                            .get(t)
+                            .ok_or_else(|| Error::TerminalNotFound(t.to_string()))?;
+                        conflict |=
+                            fill(&mut action[row][term_idx], ActionCell::Shift(to), row, *t);
                     }
                     Token::NonTerminal(nt) => {
-                        let non_term_idx = *non_term_idxes.get(nt).unwrap();
+                        let non_term_idx = *non_term_idxes
+                            .get(nt)
+                            .ok_or_else(|| Error::NonTerminalNotFound(nt.to_string()))?;
                         goto[row][non_term_idx] = Some(to);
                     }
                 }
             }
             for (item, t) in is.reduces() {
-                let prod_idx = grammar.index_of_prod(item.prod()).unwrap();
-                let term_idx = *term_idxes.get(&t).unwrap();
-                if prod_idx == 0 && term_idx == terms.len() - 1 {
+                let prod_idx = grammar
+                    .index_of_prod(item.prod())
+                    .ok_or_else(|| Error::ProductionNotFound(item.prod().to_string()))?;
+                let term_idx = *term_idxes
+                    .get(&t)
+                    .ok_or_else(|| Error::TerminalNotFound(t.to_string()))?;
+                if Some(prod_idx) == grammar.augmented_production() && term_idx == terms.len() - 1 {
                     // 根据排序 EOF 是最后一个终结符.
-                    // startprime -> start dot, EOF 也就是 acc 状态.
-                    conflict |= action[row][term_idx].update(ActionCell::Accept);
+                    // 增广产生式 startprime -> start dot, EOF 也就是 acc 状态.
+                    conflict |= fill(&mut action[row][term_idx], ActionCell::Accept, row, t);
                 } else {
-                    conflict |= action[row][term_idx].update(ActionCell::Reduce(prod_idx));
+                    conflict |= fill(
+                        &mut action[row][term_idx],
+                        ActionCell::Reduce(prod_idx),
+                        row,
+                        t,
+                    );
                 }
             }
         }
-        Self {
+        let mut resolved = 0;
+        if conflict {
+            for (row, cells) in action.iter_mut().enumerate() {
+                for (col, cell) in cells.iter_mut().enumerate() {
+                    if !cell.is_conflict() {
+                        continue;
+                    }
+                    let candidate =
+                        ConflictCell::new(grammar, &family.item_sets()[row], row, terms[col], cell);
+                    let chosen = resolver
+                        .resolve(&candidate)
+                        .or_else(|| precedence::resolve(grammar, terms[col], cell));
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        state = row,
+                        term = %terms[col],
+                        actions = %leaves(cell),
+                        resolved = chosen.is_some(),
+                        "conflict resolution"
+                    );
+                    if let Some(chosen) = chosen {
+                        *cell = chosen;
+                        resolved += 1;
+                    }
+                }
+            }
+            conflict = action.iter().flatten().any(ActionCell::is_conflict);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(rows, resolved, conflict, "table built");
+        match policy {
+            ConflictPolicy::Warn => {}
+            ConflictPolicy::Error => {
+                let mut cells = action.iter().enumerate().flat_map(|(row, cells)| {
+                    cells
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, cell)| cell.is_conflict())
+                        .map(move |(col, _)| (row, col))
+                });
+                if let Some((state, col)) = cells.next() {
+                    Err(Error::ConflictingTable {
+                        count: cells.count() + 1,
+                        state,
+                        term: terms[col].to_string(),
+                    })?
+                }
+            }
+            ConflictPolicy::Allow(resolution) => {
+                for cell in action.iter_mut().flatten().filter(|c| c.is_conflict()) {
+                    *cell = resolution.resolve(cell);
+                    resolved += 1;
+                }
+                conflict = false;
+            }
+        }
+        Ok(Self {
             action,
             goto,
             non_term_idxes,
@@ -150,7 +498,9 @@ impl<'a> Table<'a> {
             non_terms,
             term_idxes,
             conflict,
-        }
+            resolved,
+            reduces,
+        })
     }
 
     #[must_use]
@@ -173,41 +523,72 @@ impl<'a> Table<'a> {
         self.conflict
     }
 
-    /// 使用 markdown 形式输出表格.
+    /// 分析表中所有的冲突格子: 状态, 终结符, 每个候选动作和产生它的项 ([`Conflict::actions`]),
+    /// 每个冲突可以通过 [`Conflict::counterexamples`] 得到反例推导, 见: [`crate::conflict::conflicts`].
+    pub fn conflicts(&self) -> impl Iterator<Item = Conflict<'a>> + use<'a> {
+        crate::conflict::conflicts(self).into_iter()
+    }
+
+    /// 构建时被 [`ConflictResolver`], 优先级声明或者 [`ConflictPolicy::Allow`] 解决的冲突格子数量.
+    #[must_use]
+    pub fn resolved(&self) -> usize {
+        self.resolved
+    }
+
+    /// 使用 markdown 形式输出表格, 拆分宽表格等选项见: [`Table::render_markdown`].
+    ///
+    /// 渲染的表格都不包括整列为空的 GOTO 列.
     #[must_use]
     pub fn to_markdown(&self) -> String {
-        let mut header_line = "| |".to_string();
-        header_line += &self
-            .terms
-            .iter()
-            .map(|t| format!(" `{}` |", t.as_str()))
+        self.render_markdown(&RenderOptions::default())
+    }
+
+    /// 使用 JSON 形式输出分析表 (缩进格式), 结构同 [`crate::owned::OwnedTable`], 需要开启 `serde` feature.
+    #[cfg(feature = "serde")]
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        // 键都是字符串, 序列化不会失败.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// 使用 Typst 形式输出表格, 表头和单元格内容使用 `raw` 包裹, 不需要担心特殊字符的转义.
+    #[must_use]
+    pub fn to_typst(&self) -> String {
+        let goto_columns = self.goto_column_indexes();
+        let header = std::iter::once("[]".to_string())
             .chain(
-                self.non_terms
+                self.terms
                     .iter()
-                    .map(|nt| format!(" `{}` |", nt.as_str())),
+                    .map(|t| format!("raw({})", typst_str(t.as_str()))),
             )
-            .collect::<String>();
-        let sep_line: String = String::from("| - |")
-            + &std::iter::repeat_n(" - |", self.terms.len() + self.non_terms.len())
-                .collect::<String>();
-        let mut data_lines = String::new();
-        for (i, (action_row, goto_row)) in self.action.iter().zip(self.goto.iter()).enumerate() {
-            let line = format!("| $I_{{{i}}}$ |")
-                + &action_row
+            .chain(
+                goto_columns
                     .iter()
-                    .map(|act| format!(" {act} |"))
-                    .chain(goto_row.iter().map(|to| {
-                        if let Some(to) = to {
-                            format!(" {to} |")
-                        } else {
-                            "  |".to_string()
-                        }
-                    }))
-                    .collect::<String>();
-            data_lines += &line;
-            data_lines += "\n";
+                    .map(|&col| format!("raw({})", typst_str(self.non_terms[col].as_str()))),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut lines = vec![
+            "#table(".to_string(),
+            format!(
+                "  columns: {},",
+                self.terms.len() + goto_columns.len() + 1
+            ),
+            format!("  table.header({header}),"),
+        ];
+        for (i, action_row) in self.action.iter().enumerate() {
+            let row = std::iter::once(format!("[$I_({i})$]"))
+                .chain(action_row.iter().map(|act| format!("[{act}]")))
+                .chain(goto_columns.iter().map(|&col| match self.goto[i][col] {
+                    Some(to) => format!("[{to}]"),
+                    None => "[]".to_string(),
+                }))
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!("  {row},"));
         }
-        format!("{header_line}\n{sep_line}\n{}", data_lines.trim_end())
+        lines.push(")".to_string());
+        lines.join("\n")
     }
 
     /// 查询 ACTION 表, 获取当前项集状态在某个终结符下的动作.
@@ -246,6 +627,189 @@ impl<'a> Table<'a> {
         Some(row[non_term_idx])
     }
 
+    /// ACTION 表和 GOTO 表的列对应的符号, 可以把列号映射回符号的名字.
+    #[must_use]
+    pub fn symbols(&self) -> Symbols<'_, 'a> {
+        Symbols {
+            terms: &self.terms,
+            non_terms: &self.non_terms,
+        }
+    }
+
+    /// 使用产生式 `prod` 归约需要的信息 (弹出的状态数量, 头部在 GOTO 表中的列), 产生式不存在时返回 [`None`].
+    ///
+    /// 和 [`ActionCell::Reduce`] 配合使用时不需要查询文法, 见: [`Table::goto_at`].
+    #[must_use]
+    pub fn reduce_info(&self, prod: ProdId) -> Option<ReduceInfo> {
+        self.reduces.get(prod).copied()
+    }
+
+    /// 按照 GOTO 表中的列查询 GOTO, 列号见: [`ReduceInfo::head`].
+    /// 如果 state 或者列不存在, 或者没有这个出边, 那么返回 [`None`].
+    #[must_use]
+    pub fn goto_at(&self, state: StateId, col: usize) -> Option<StateId> {
+        *self.goto.get(state)?.get(col)?
+    }
+
+    /// 遍历一个项集状态的所有非空 GOTO 表项 (非终结符, 到达的项集状态).
+    /// 如果这个项集状态不存在, 那么返回 [`None`].
+    #[must_use]
+    pub fn gotos(
+        &self,
+        state: usize,
+    ) -> Option<impl Iterator<Item = (NonTerminal<'a>, usize)> + '_> {
+        let v = self.goto.get(state)?;
+        Some(
+            v.iter()
+                .enumerate()
+                .filter_map(|(i, to)| to.map(|to| (self.non_terms[i], to))),
+        )
+    }
+
+    /// 解释在项集状态 `state` 遇到终结符 `term` 时的语法错误, 返回多行文本, 可以直接用在编译器的错误输出中:
+    /// 正在分析的结构 (内核项, 不带前瞻符), 期望的终结符, 以及在这里可以开始的产生式 (闭包加入的项).
+    ///
+    /// 如果这个项集状态不存在, 那么返回 [`None`].
+    #[must_use]
+    pub fn explain_failure(&self, state: StateId, term: Terminal) -> Option<String> {
+        let is = self.family.item_set(state)?;
+        let found = if term == EOF {
+            "unexpected end of input".to_string()
+        } else {
+            format!("unexpected `{term}`")
+        };
+        let mut out = format!("{found} in state I_{state}\n");
+        let mut parsing: Vec<_> = is.kernel().map(crate::Item::core_string).collect();
+        parsing.dedup();
+        out += "  while parsing:\n";
+        for item in &parsing {
+            out += &format!("    {item}\n");
+        }
+        let expected: Vec<_> = self
+            .actions(state)
+            .into_iter()
+            .flatten()
+            .map(|(t, _)| {
+                if t == EOF {
+                    "end of input".to_string()
+                } else {
+                    format!("`{t}`")
+                }
+            })
+            .collect();
+        match &expected[..] {
+            [] => out += "  nothing is expected here\n",
+            [one] => out += &format!("  expected {one}\n"),
+            many => out += &format!("  expected one of {}\n", many.join(", ")),
+        }
+        let augmented = self
+            .grammar
+            .augmented_production()
+            .and_then(|p| self.grammar.prod(p));
+        let mut nearby: Vec<_> = is
+            .items()
+            .filter(|item| item.dot() == 0 && augmented != Some(item.prod()))
+            .map(|item| item.prod().to_string())
+            .collect();
+        nearby.sort();
+        nearby.dedup();
+        if !nearby.is_empty() {
+            out += "  could start here:\n";
+            for prod in &nearby {
+                out += &format!("    {prod}\n");
+            }
+        }
+        if let Some(cell) = self.action(state, term).filter(|cell| !cell.is_empty()) {
+            out += &format!("  note: `{term}` is valid here ({cell})\n");
+        }
+        Some(out)
+    }
+
+    /// 检查分析表的内部一致性, 返回发现的所有问题, 没有问题时为空:
+    ///
+    /// - 移入和 GOTO 的目标状态都存在, 归约的产生式编号都在范围内.
+    /// - 每个归约弹出产生式右部之后可能回到的状态都有产生式左部的 GOTO 表项.
+    /// - 从初始状态出发可以到达接受动作.
+    ///
+    /// 检查从缓存中反序列化的分析表见: [`crate::owned::OwnedTable::validate`].
+    #[must_use]
+    pub fn validate(&self) -> Vec<TableDefect> {
+        let actions = self.action.iter().enumerate().flat_map(|(state, row)| {
+            row.iter().enumerate().flat_map(move |(col, cell)| {
+                cell.flatten()
+                    .map(move |leaf| (state, self.terms[col].as_str(), leaf))
+            })
+        });
+        let gotos = self.goto.iter().enumerate().flat_map(|(state, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(col, to)| Some((state, self.non_terms[col].as_str(), (*to)?)))
+        });
+        let prods: Vec<_> = self
+            .grammar
+            .prods()
+            .iter()
+            .map(|p| (p.head().as_str(), p.len()))
+            .collect();
+        validate_cells(self.rows(), &prods, actions, gotos)
+    }
+
+    /// 按行遍历 ACTION 表和 GOTO 表中所有的非空格子 (项集状态, 列符号, 格子内容),
+    /// 每一行中先遍历 ACTION 表部分, 再遍历 GOTO 表部分, 列的顺序和 [`Table::to_markdown`] 一致.
+    pub fn cells(&self) -> impl Iterator<Item = (StateId, Token<'a>, CellRef<'_>)> {
+        (0..self.rows()).flat_map(move |state| {
+            let actions = self
+                .actions(state)
+                .into_iter()
+                .flatten()
+                .map(move |(t, a)| (state, t.into(), CellRef::Action(a)));
+            let gotos = self
+                .gotos(state)
+                .into_iter()
+                .flatten()
+                .map(move |(nt, to)| (state, nt.into(), CellRef::Goto(to)));
+            actions.chain(gotos)
+        })
+    }
+
+    /// 渲染和生成代码时保留的 GOTO 表的列: 去掉整列为空的非终结符 (增广文法的开始符号, 从来不会被归约的非终结符),
+    /// 顺序和 [`Table::non_terms`] 一致. [`Table::goto`] 等查询仍然接受所有非终结符.
+    #[must_use]
+    pub(crate) fn goto_columns(&self) -> Vec<NonTerminal<'a>> {
+        self.goto_column_indexes()
+            .into_iter()
+            .map(|col| self.non_terms[col])
+            .collect()
+    }
+
+    /// [`Table::goto_columns`] 中的非终结符在 GOTO 表中的列号.
+    fn goto_column_indexes(&self) -> Vec<usize> {
+        (0..self.non_terms.len())
+            .filter(|&col| self.goto.iter().any(|row| row[col].is_some()))
+            .collect()
+    }
+
+    /// ACTION 表的列对应的终结符.
+    #[inline]
+    #[must_use]
+    pub(crate) fn terms(&self) -> &[Terminal<'a>] {
+        &self.terms
+    }
+
+    /// GOTO 表的列对应的非终结符.
+    #[inline]
+    #[must_use]
+    pub(crate) fn non_terms(&self) -> &[NonTerminal<'a>] {
+        &self.non_terms
+    }
+
+    /// 终结符在 ACTION 表中的列号.
+    #[inline]
+    #[must_use]
+    pub(crate) fn term_index(&self, term: Terminal) -> Option<usize> {
+        self.term_idxes.get(&term).copied()
+    }
+
     #[inline]
     #[must_use]
     pub(crate) fn family(&self) -> &Family<'a> {
@@ -263,7 +827,11 @@ impl<'a> Table<'a> {
 mod test {
     use bumpalo::Bump;
 
-    use crate::{Family, Grammar, panic::PanicAction, table::Table};
+    use crate::{
+        ActionCell, EOF, Family, Grammar, NonTerminal, Terminal, Token,
+        panic::PanicAction,
+        table::{CellRef, Table, TableDefect},
+    };
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -277,7 +845,7 @@ mod test {
         let grammar = Grammar::from_cfg(input, "program".into(), &bump)
             .unwrap()
             .augmented();
-        let family = Family::from_grammar(&grammar);
+        let family = Family::from_grammar(&grammar).unwrap();
         family.item_sets().iter().enumerate().for_each(|(idx, is)| {
             println!("I_{idx}:");
             is.items().for_each(|i| println!("{}", i));
@@ -296,24 +864,206 @@ mod test {
                 });
             println!();
         });
-        let table = Table::build_from(&family, &grammar);
+        let table = Table::build_from(&family).unwrap();
         assert!(!table.conflict);
         assert_eq!(
             table.to_markdown(),
             r#"
-| | `{` | `}` | `stmts` | `ifstmt` | `assgstmt` | `whilestmt` | `E` | `eof` | `compoundstmt` | `program` | `programprime` | `stmt` |
-| - | - | - | - | - | - | - | - | - | - | - | - | - |
-| $I_{0}$ | s1 |  |  |  |  |  |  |  | 2 | 3 |  |  |
-| $I_{1}$ |  |  | s4 |  |  |  |  |  |  |  |  |  |
-| $I_{2}$ |  |  |  |  |  |  |  | r1 |  |  |  |  |
-| $I_{3}$ |  |  |  |  |  |  |  | acc |  |  |  |  |
-| $I_{4}$ |  | s5 |  |  |  |  |  |  |  |  |  |  |
-| $I_{5}$ |  |  |  |  |  |  |  | r5 |  |  |  |  |
+| | `{` | `}` | `stmts` | `ifstmt` | `assgstmt` | `whilestmt` | `E` | `eof` | `compoundstmt` | `program` |
+| - | - | - | - | - | - | - | - | - | - | - |
+| $I_{0}$ | s1 |  |  |  |  |  |  |  | 2 | 3 |
+| $I_{1}$ |  |  | s4 |  |  |  |  |  |  |  |
+| $I_{2}$ |  |  |  |  |  |  |  | r1 |  |  |
+| $I_{3}$ |  |  |  |  |  |  |  | acc |  |  |
+| $I_{4}$ |  | s5 |  |  |  |  |  |  |  |  |
+| $I_{5}$ |  |  |  |  |  |  |  | r5 |  |  |
 "#
             .trim()
         );
     }
 
+    #[test]
+    fn typst_output() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> \"a\" b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        assert_eq!(
+            table.to_typst(),
+            r#"
+#table(
+  columns: 6,
+  table.header([], raw("b"), raw("\"a\""), raw("E"), raw("eof"), raw("S")),
+  [$I_(0)$], [], [s1], [], [], [2],
+  [$I_(1)$], [s3], [], [], [], [],
+  [$I_(2)$], [], [], [], [acc], [],
+  [$I_(3)$], [], [], [], [r1], [],
+)
+"#
+            .trim()
+        );
+        assert_eq!(
+            family.to_typst().lines().take(5).collect::<Vec<_>>(),
+            [
+                "=== $I_(0)$",
+                r#"- #raw("S -> ⋅ \"a\" b 〈eof〉")"#,
+                r#"- #raw("Sprime -> ⋅ S 〈eof〉")"#,
+                r#"- $I_(0)$ #sym.arrow.r.long#super(raw("\"a\"")) $I_(1)$"#,
+                r#"- $I_(0)$ #sym.arrow.r.long#super(raw("S")) $I_(2)$"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn goto_rows() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b\nA -> a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let row: Vec<_> = table.gotos(0).unwrap().collect();
+        assert_eq!(
+            row,
+            [
+                (
+                    NonTerminal::from("A"),
+                    table.goto(0, "A".into()).unwrap().unwrap()
+                ),
+                (
+                    NonTerminal::from("S"),
+                    table.goto(0, "S".into()).unwrap().unwrap()
+                ),
+            ]
+        );
+        assert!(table.gotos(table.rows()).is_none());
+    }
+
+    #[test]
+    fn reduce_info() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b\nA -> a | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let heads: Vec<_> = (0..grammar.prods().len())
+            .map(|p| {
+                let info = table.reduce_info(p).unwrap();
+                (table.non_terms()[info.head].as_str(), info.len)
+            })
+            .collect();
+        assert_eq!(heads, [("Sprime", 1), ("S", 2), ("A", 1), ("A", 0)]);
+        assert_eq!(table.reduce_info(4), None);
+        let info = table.reduce_info(1).unwrap();
+        assert_eq!(
+            table.goto_at(0, info.head),
+            table.goto(0, "S".into()).flatten()
+        );
+        assert_eq!(table.goto_at(0, table.goto_cols()), None);
+    }
+
+    #[test]
+    fn symbols() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b\nA -> a | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let symbols = table.symbols();
+        let terms: Vec<_> = symbols.terms.iter().map(Terminal::as_str).collect();
+        let non_terms: Vec<_> = symbols.non_terms.iter().map(NonTerminal::as_str).collect();
+        assert_eq!(terms, ["a", "b", "E", "eof"]);
+        assert_eq!(non_terms, ["A", "S", "Sprime"]);
+        assert_eq!(symbols.term_column(EOF), Some(3));
+        assert_eq!(symbols.non_term_column("S".into()), Some(1));
+        assert_eq!(symbols.term_column("c".into()), None);
+        for (col, &t) in symbols.terms.iter().enumerate() {
+            assert_eq!(table.term_index(t), Some(col));
+        }
+    }
+
+    #[test]
+    fn non_empty_cells() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> A b\nA -> a", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let cells: Vec<_> = table.cells().collect();
+        // I_0: a 移入, A 和 S 两个 GOTO; 其余状态: A -> a ⋅ 归约, S -> A ⋅ b 移入, S -> A b ⋅ 归约, 接受.
+        assert_eq!(cells.len(), 7);
+        for (state, tok, cell) in cells {
+            match (tok, cell) {
+                (Token::Terminal(t), CellRef::Action(a)) => {
+                    assert_eq!(table.action(state, t), Some(a));
+                    assert!(!a.is_empty());
+                }
+                (Token::NonTerminal(nt), CellRef::Goto(to)) => {
+                    assert_eq!(table.goto(state, nt), Some(Some(to)));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn ranked_panic_candidates() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        // `{ ID = NUM }` 缺少 `;`, 在读到 `}` 时出错.
+        let input: Vec<Terminal> = ["{", "ID", "=", "NUM", "}"].map(Terminal::from).into();
+        let mut stack = vec![0];
+        let mut cursor = 0;
+        while let Some(ActionCell::Shift(to)) = table.action(*stack.last().unwrap(), input[cursor])
+        {
+            stack.push(*to);
+            cursor += 1;
+        }
+        let candidates = table.panic_candidates(&stack, &input[cursor..]).unwrap();
+        assert!(candidates.windows(2).all(|w| w[0].score >= w[1].score));
+        // 强制归约之后可以插入 `;`, 然后继续读取 `}` 并接受, 优于直接跳过 `}`.
+        assert!(matches!(candidates[0].action, PanicAction::Reduce(_)));
+        assert_eq!(candidates[0].score, 2);
+        assert_eq!(candidates.last().unwrap().action, PanicAction::Empty);
+        assert_eq!(candidates.last().unwrap().score, 0);
+    }
+
+    #[test]
+    fn expected_insertion() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let panic_table = table.panic_table().unwrap();
+        let state_after = |input: &[&str]| {
+            let mut state = 0;
+            for &t in input {
+                let Some(ActionCell::Shift(to)) = table.action(state, t.into()) else {
+                    unreachable!()
+                };
+                state = *to;
+            }
+            state
+        };
+        // `ID = NUM` 之后最可能遗漏的是 `;`.
+        let state = state_after(&["{", "ID", "=", "NUM"]);
+        assert_eq!(panic_table.expected_insertion(state), Some(";".into()));
+        assert_eq!(table.cheapest_insertion(state), Some(";".into()));
+        let state = state_after(&["{", "while"]);
+        assert_eq!(panic_table.expected_insertion(state), Some("(".into()));
+    }
+
     /// ```c
     /// enum ActionType {
     ///     ActionEmpty = 0,
@@ -350,17 +1100,18 @@ mod test {
         let grammar = Grammar::from_cfg(inp, "program".into(), &bump)
             .unwrap()
             .augmented();
-        let family = Family::from_grammar(&grammar);
-        let table = Table::build_from(&family, &grammar);
-        let mut panic_action_table =
-            vec![vec![PanicAction::Empty; table.action_cols()]; table.rows()];
-        for (state, row) in panic_action_table.iter_mut().enumerate() {
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let panic_table = table.panic_table().unwrap();
+        for state in 0..table.rows() {
             for &term in &table.terms {
-                let term_idx = *table.term_idxes.get(&term).unwrap();
-                row[term_idx] = table.panic_action(state, term).unwrap();
+                assert_eq!(
+                    panic_table.action(state, term),
+                    Some(&table.panic_action(state, term).unwrap())
+                );
             }
         }
-        for row in panic_action_table {
+        for row in (0..table.rows()).filter_map(|state| panic_table.row(state)) {
             print!("{{");
             for cell in row {
                 match cell {
@@ -381,4 +1132,112 @@ mod test {
             println!("}},");
         }
     }
+
+    #[test]
+    fn explain_failure() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> if c then S | o", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let shift = |state, term: &str| match table.action(state, term.into()) {
+            Some(ActionCell::Shift(to)) => *to,
+            other => panic!("{other:?}"),
+        };
+        let after_if = shift(0, "if");
+        let after_then = shift(shift(after_if, "c"), "then");
+        assert_eq!(
+            table.explain_failure(after_then, "c".into()).unwrap(),
+            format!(
+                "unexpected `c` in state I_{after_then}\n  while parsing:\n    S -> if c then ⋅ S\n  \
+                 expected one of `o`, `if`\n  could start here:\n    S -> if c then S\n    S -> o\n"
+            )
+        );
+        let explained = table.explain_failure(after_if, EOF).unwrap();
+        assert!(explained.starts_with("unexpected end of input in state I_"));
+        assert!(explained.contains("  expected `c`\n"));
+        assert!(!explained.contains("could start here"));
+        assert!(
+            table
+                .explain_failure(0, "o".into())
+                .unwrap()
+                .ends_with(&format!("  note: `o` is valid here (s{})\n", shift(0, "o")))
+        );
+        assert_eq!(table.explain_failure(family.len(), EOF), None);
+    }
+
+    #[test]
+    fn validate() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let mut table = Table::build_from(&family).unwrap();
+        assert_eq!(table.validate(), []);
+
+        let rows = table.rows();
+        let col = table.non_term_idxes[&NonTerminal::from("program")];
+        let to = table.goto[0][col].unwrap();
+        table.goto[0][col] = None;
+        table.action[1][0] = ActionCell::Shift(rows);
+        table.action[1][1] = ActionCell::Reduce(grammar.prods().len());
+        let defects = table.validate();
+        assert!(defects.contains(&TableDefect::ShiftOutOfRange {
+            state: 1,
+            term: table.terms[0].to_string(),
+            to: rows
+        }));
+        assert!(defects.iter().any(|d| matches!(
+            d,
+            TableDefect::ProductionOutOfRange { state: 1, .. }
+        )));
+        assert!(
+            defects
+                .iter()
+                .any(|d| matches!(d, TableDefect::MissingGoto { from: 0, .. }))
+        );
+        // 去掉了 I_0 经过开始符号的 GOTO, 接受动作不可达.
+        assert_eq!(table.action(to, EOF), Some(&ActionCell::Accept));
+        assert_eq!(defects.last(), Some(&TableDefect::AcceptUnreachable));
+        assert_eq!(
+            defects.last().unwrap().to_string(),
+            "accept is not reachable from I_0"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&table.to_json()).unwrap();
+        assert_eq!(value["prods"][1], "S -> a S");
+        let Some(ActionCell::Shift(to)) = table.action(0, "a".into()) else {
+            panic!("I_0 does not shift `a`");
+        };
+        assert_eq!(value["actions"][0]["a"], serde_json::json!({ "Shift": to }));
+        assert_eq!(
+            value["gotos"][0]["S"],
+            table.goto(0, "S".into()).unwrap().unwrap()
+        );
+
+        let value = serde_json::to_value(&grammar).unwrap();
+        assert_eq!(value["start"], "Sprime");
+        assert_eq!(
+            value["prods"][1],
+            serde_json::json!({"head": "S", "tail": [{"Terminal": "a"}, {"NonTerminal": "S"}]})
+        );
+        let item = family.item_sets()[0].items().next().unwrap();
+        let value = serde_json::to_value(item).unwrap();
+        assert_eq!(value["look_aheads"], serde_json::json!(["eof"]));
+        assert_eq!(value["text"], item.to_string());
+        let value = serde_json::to_value(&family).unwrap();
+        assert_eq!(value["item_sets"].as_array().unwrap().len(), family.len());
+    }
 }
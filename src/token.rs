@@ -1,6 +1,26 @@
 use std::fmt::{Debug, Display};
 
+use crate::error::Error;
+
+/// 检查符号能否出现在 CFG 文法中, 见: [`Terminal::new`].
+fn check_ident(ident: &str) -> Result<(), Error> {
+    let cause = if ident.is_empty() {
+        "empty symbol"
+    } else if ident.chars().any(char::is_whitespace) {
+        "symbol contains whitespace"
+    } else if ident == EPSILON.ident || ident == EOF.ident {
+        "symbol is reserved"
+    } else {
+        return Ok(());
+    };
+    Err(Error::InvalidSymbol {
+        ident: ident.to_string(),
+        cause: cause.to_string(),
+    })
+}
+
 #[derive(PartialEq, Eq, Clone, Hash, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
 pub struct Terminal<'a> {
     ident: &'a str,
 }
@@ -50,13 +70,23 @@ impl<'a> From<&'a str> for Terminal<'a> {
 }
 
 impl<'a> Terminal<'a> {
+    /// 检查过的构造函数, [`From<&str>`] 不做检查, 可能构造出 CFG 文法中不会出现的终结符.
+    ///
+    /// # Errors
+    /// - [`Error::InvalidSymbol`] 符号为空, 包含空白字符, 或者是 [`EPSILON`] / [`EOF`].
+    pub fn new(ident: &'a str) -> Result<Self, Error> {
+        check_ident(ident)?;
+        Ok(Self { ident })
+    }
+
     #[must_use]
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'a str {
         self.ident
     }
 }
 
 #[derive(PartialEq, Eq, Clone, Hash, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
 pub struct NonTerminal<'a> {
     ident: &'a str,
 }
@@ -75,6 +105,8 @@ impl Display for NonTerminal<'_> {
 
 pub const EPSILON: Terminal<'static> = Terminal { ident: "E" };
 pub const EOF: Terminal<'static> = Terminal { ident: "eof" };
+/// 词法分析中无法识别的字符组成的终结符, 不应该出现在文法中, 见: [`crate::lexer::LexerSpec::lex_recover`].
+pub const INVALID: Terminal<'static> = Terminal { ident: "INVALID" };
 
 impl<'a> From<&'a str> for NonTerminal<'a> {
     fn from(ident: &'a str) -> Self {
@@ -83,13 +115,23 @@ impl<'a> From<&'a str> for NonTerminal<'a> {
 }
 
 impl<'a> NonTerminal<'a> {
+    /// 检查过的构造函数, 见: [`Terminal::new`].
+    ///
+    /// # Errors
+    /// - [`Error::InvalidSymbol`] 符号为空, 包含空白字符, 或者是 [`EPSILON`] / [`EOF`].
+    pub fn new(ident: &'a str) -> Result<Self, Error> {
+        check_ident(ident)?;
+        Ok(Self { ident })
+    }
+
     #[must_use]
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'a str {
         self.ident
     }
 }
 
 #[derive(Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Token<'a> {
     Terminal(Terminal<'a>),
     NonTerminal(NonTerminal<'a>),
@@ -190,3 +232,22 @@ impl<'a> From<NonTerminal<'a>> for Token<'a> {
         Self::NonTerminal(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{NonTerminal, Terminal, error::Error};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn checked_constructors() {
+        assert_eq!(Terminal::new("id"), Ok(Terminal::from("id")));
+        assert_eq!(NonTerminal::new("stmt"), Ok(NonTerminal::from("stmt")));
+        for ident in ["", "a b", "\t", "E", "eof"] {
+            assert!(matches!(
+                Terminal::new(ident),
+                Err(Error::InvalidSymbol { .. })
+            ));
+            assert!(NonTerminal::new(ident).is_err());
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! 语法分析树及其文本渲染.
+//!
+//! 根据 [`ParseOutput`] 中按顺序记录的归约操作重建语法分析树,
+//! 并提供缩进 (ASCII), 制表符 (Unicode) 和 DOT 三种输出形式.
+
+use std::fmt::Write;
+
+use crate::{
+    EPSILON, Grammar, NonTerminal, Token,
+    parser::{InputTerm, ParseOutput},
+};
+
+/// 语法分析树的节点.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    /// 输入的终结符.
+    Leaf(InputTerm<'a>),
+    /// 使用 [`EPSILON`] 产生式归约出来的非终结符的唯一子节点.
+    Epsilon,
+    /// 使用编号为 `prod` 的产生式归约出来的非终结符.
+    Inner {
+        head: NonTerminal<'a>,
+        prod: usize,
+        children: Vec<Node<'a>>,
+    },
+}
+
+/// 渲染时使用的树枝符号.
+struct Branches {
+    /// 中间的子节点.
+    mid: &'static str,
+    /// 最后一个子节点.
+    last: &'static str,
+    /// 中间的子节点的后代的缩进.
+    bar: &'static str,
+    /// 最后一个子节点的后代的缩进.
+    space: &'static str,
+}
+
+const ASCII: Branches = Branches {
+    mid: "+-- ",
+    last: "`-- ",
+    bar: "|   ",
+    space: "    ",
+};
+
+const UNICODE: Branches = Branches {
+    mid: "├── ",
+    last: "└── ",
+    bar: "│   ",
+    space: "    ",
+};
+
+impl<'a> Node<'a> {
+    /// 节点对应的符号.
+    #[must_use]
+    pub fn token(&self) -> Token<'a> {
+        match self {
+            Self::Leaf(t) => t.term.into(),
+            Self::Epsilon => EPSILON.into(),
+            Self::Inner { head, .. } => (*head).into(),
+        }
+    }
+
+    /// 渲染时使用的节点标签, 词素和终结符不同的叶子显示为 `NUM("42")` 的形式.
+    #[must_use]
+    pub fn label(&self) -> String {
+        match self {
+            Self::Leaf(t) if t.text != t.term.as_str() => format!("{}({:?})", t.term, t.text),
+            node => node.token().to_string(),
+        }
+    }
+
+    #[must_use]
+    pub fn children(&self) -> &[Node<'a>] {
+        match self {
+            Self::Inner { children, .. } => children,
+            _ => &[],
+        }
+    }
+
+    /// 树的叶子 (终结符) 从左到右的序列, 不包括 [`Node::Epsilon`].
+    pub fn leaves(&self) -> Box<dyn Iterator<Item = &InputTerm<'a>> + '_> {
+        match self {
+            Self::Leaf(t) => Box::new(std::iter::once(t)),
+            Self::Epsilon => Box::new(std::iter::empty()),
+            Self::Inner { children, .. } => Box::new(children.iter().flat_map(Node::leaves)),
+        }
+    }
+
+    /// 去掉增广产生式 `Sprime -> S` 对应的根节点, 返回开始符号的子树, 和增广之前的文法一致.
+    ///
+    /// 根节点不是增广产生式 (或者 `grammar` 没有增广) 时原样返回.
+    #[must_use]
+    pub fn deaugmented(self, grammar: &Grammar<'a>) -> Self {
+        match self {
+            Self::Inner {
+                prod, mut children, ..
+            } if Some(prod) == grammar.augmented_production() && children.len() == 1 => {
+                children.pop().unwrap_or(Self::Epsilon)
+            }
+            node => node,
+        }
+    }
+
+    /// 使用 ASCII 字符 (`+--`, `` `-- ``, `|`) 渲染为缩进的树.
+    #[must_use]
+    pub fn to_ascii(&self) -> String {
+        self.render(&ASCII)
+    }
+
+    /// 使用 Unicode 制表符 (`├──`, `└──`, `│`) 渲染为缩进的树.
+    #[must_use]
+    pub fn to_unicode(&self) -> String {
+        self.render(&UNICODE)
+    }
+
+    fn render(&self, branches: &Branches) -> String {
+        let mut out = format!("{}\n", self.label());
+        self.render_children(branches, "", &mut out);
+        out.trim_end().to_string()
+    }
+
+    fn render_children(&self, branches: &Branches, prefix: &str, out: &mut String) {
+        let children = self.children();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let branch = if last { branches.last } else { branches.mid };
+            // 写入 String 不会失败.
+            let _ = writeln!(out, "{prefix}{branch}{}", child.label());
+            let indent = if last { branches.space } else { branches.bar };
+            child.render_children(branches, &format!("{prefix}{indent}"), out);
+        }
+    }
+
+    /// 渲染为 Graphviz DOT 格式, 终结符使用方框, [`EPSILON`] 使用斜体.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph parse_tree {\n    node [shape=plaintext];\n");
+        let mut next = 0;
+        self.dot_node(&mut out, &mut next);
+        out += "}";
+        out
+    }
+
+    /// 输出当前节点及其子树, 返回当前节点的编号.
+    fn dot_node(&self, out: &mut String, next: &mut usize) -> usize {
+        let id = *next;
+        *next += 1;
+        let label = format!("{:?}", self.label());
+        let _ = match self {
+            Self::Leaf(_) => writeln!(out, "    n{id} [label={label}, shape=box];"),
+            Self::Epsilon => writeln!(out, "    n{id} [label=<<i>ε</i>>];"),
+            Self::Inner { .. } => writeln!(out, "    n{id} [label={label}];"),
+        };
+        for child in self.children() {
+            let child_id = child.dot_node(out, next);
+            let _ = writeln!(out, "    n{id} -> n{child_id};");
+        }
+        id
+    }
+}
+
+impl<'a> ParseOutput<'a> {
+    /// 根据归约过程重建语法分析树, 根节点为文法的开始符号 (不包括增广产生式).
+    ///
+    /// 如果输入没有被接受, 那么返回 [`None`], 这时可以使用 [`ParseOutput::partial_tree`].
+    #[must_use]
+    pub fn tree(&self, grammar: &Grammar<'a>) -> Option<Node<'a>> {
+        if !self.accepted() {
+            return None;
+        }
+        // 最后一次归约是增广产生式, 它唯一的子节点才是开始符号.
+        Some(self.forest(grammar)?.pop()?.deaugmented(grammar))
+    }
+
+    /// 重建出错时已经读取的输入对应的部分语法分析树.
+    ///
+    /// 返回出错时符号栈上的每个符号对应的子树, 从左到右排列, 叶子依次为已经读取的终结符
+    /// (包括恐慌恢复插入的终结符). 它们是完整的子树, 可以用于大纲, 折叠等只需要部分结构的场合.
+    /// 输入被接受时只有一棵以增广产生式的开始符号为根的树, 可以使用 [`Node::deaugmented`] 去掉这一层.
+    #[must_use]
+    pub fn partial_tree(&self, grammar: &Grammar<'a>) -> Vec<Node<'a>> {
+        self.forest(grammar).unwrap_or_default()
+    }
+
+    /// 重放所有归约, 返回最后的符号栈对应的子树, 归约记录和文法不一致时返回 [`None`].
+    fn forest(&self, grammar: &Grammar<'a>) -> Option<Vec<Node<'a>>> {
+        let terms = self.terms();
+        let mut nodes: Vec<Node<'a>> = Vec::new();
+        let mut pos = 0;
+        for reduction in self.reductions() {
+            nodes.extend(
+                terms
+                    .get(pos..reduction.cursor)?
+                    .iter()
+                    .map(|&t| Node::Leaf(t)),
+            );
+            pos = pos.max(reduction.cursor);
+            let prod = grammar.prod(reduction.prod)?;
+            let children = if prod.is_empty() {
+                vec![Node::Epsilon]
+            } else {
+                nodes.split_off(nodes.len().checked_sub(prod.len())?)
+            };
+            nodes.push(Node::Inner {
+                head: prod.head(),
+                prod: reduction.prod,
+                children,
+            });
+        }
+        // 没有被接受时分析在输入结束处停止, 最后一次归约之后读取的终结符都还在栈上.
+        if !self.accepted() {
+            nodes.extend(terms.get(pos..)?.iter().map(|&t| Node::Leaf(t)));
+        }
+        Some(nodes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{Family, Grammar, Parser, Table, Terminal, error::Error, tree::Node};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn render_tree() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b T\nT -> c | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let output = Parser::new(&table)
+            .parse("a b".split_whitespace().map(Terminal::from))
+            .unwrap();
+        let tree = output.tree(&grammar).unwrap();
+        assert_eq!(
+            tree.leaves().map(|t| t.term.as_str()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(
+            tree.to_unicode(),
+            "S\n├── a\n└── S\n    ├── b\n    └── T\n        └── E"
+        );
+        assert_eq!(
+            tree.to_ascii(),
+            "S\n+-- a\n`-- S\n    +-- b\n    `-- T\n        `-- E"
+        );
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph parse_tree {"));
+        assert!(dot.contains("    n1 [label=\"a\", shape=box];"));
+        assert!(dot.contains("    n0 -> n1;"));
+    }
+
+    #[test]
+    fn parse_tree() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg("S -> a S | b T\nT -> c | E", "S".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let parser = Parser::new(&table);
+        let tree = parser
+            .parse_tree("a b c".split_whitespace().map(Terminal::from))
+            .unwrap();
+        let Node::Inner { prod, children, .. } = &tree else {
+            panic!("root is not an inner node");
+        };
+        assert_eq!(*prod, 1);
+        assert!(matches!(children[0], Node::Leaf(t) if t.term.as_str() == "a"));
+        assert_eq!(
+            children[1]
+                .children()
+                .iter()
+                .map(Node::label)
+                .collect::<Vec<_>>(),
+            ["b", "T"]
+        );
+        assert!(matches!(
+            parser.parse_tree("a".split_whitespace().map(Terminal::from)),
+            Err(Error::UnexpectedEof { .. })
+        ));
+    }
+
+    #[test]
+    fn partial_tree() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(
+            "block -> { stmts }\nstmts -> stmt stmts | E\nstmt -> id = id ;",
+            "block".into(),
+            &bump,
+        )
+        .unwrap()
+        .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let output = Parser::new(&table)
+            .parse("{ id = id ; id =".split_whitespace().map(Terminal::from))
+            .unwrap();
+        assert_eq!(output.tree(&grammar), None);
+        let forest = output.partial_tree(&grammar);
+        let roots: Vec<_> = forest.iter().map(|n| n.token().to_string()).collect();
+        assert_eq!(roots, ["{", "stmt", "id", "="]);
+        assert_eq!(forest[1].to_ascii(), "stmt\n+-- id\n+-- =\n+-- id\n`-- ;");
+        let leaves: Vec<_> = forest
+            .iter()
+            .flat_map(Node::leaves)
+            .map(|t| t.term.as_str())
+            .collect();
+        assert_eq!(leaves, ["{", "id", "=", "id", ";", "id", "="]);
+
+        let output = Parser::new(&table)
+            .parse("{ id = id ; }".split_whitespace().map(Terminal::from))
+            .unwrap();
+        let forest = output.partial_tree(&grammar);
+        assert_eq!(forest.len(), 1);
+        assert_eq!(forest[0].token().to_string(), "blockprime");
+        let tree = forest[0].clone().deaugmented(&grammar);
+        assert_eq!(Some(&tree), output.tree(&grammar).as_ref());
+        assert_eq!(tree.token().to_string(), "block");
+        assert_eq!(tree.clone().deaugmented(&grammar), tree);
+    }
+}
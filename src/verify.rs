@@ -0,0 +1,236 @@
+//! 差分验证.
+//!
+//! 根据文法生成句子 (覆盖每个产生式的句子, 随机句子, 以及对它们进行删除, 插入, 交换得到的变异句子),
+//! 分别使用规范 LR(1) 分析表和另一个实现 (oracle) 判断是否接受, 两者不一致时说明分析表的构造可能有错误.
+//!
+//! 内置的 oracle 为 [`AlgorithmOracle`], 使用 [`Comparison`] 推导出的 LALR(1) 或者 SLR(1) 分析表,
+//! 文法属于对应的方法时它们接受的语言和规范 LR(1) 相同. 外部的分析器生成器 (例如 bison) 可以通过实现 [`Oracle`]
+//! (或者直接使用闭包) 接入.
+
+use crate::{
+    ActionCell, EOF, EPSILON, Parser, Table, Terminal,
+    compare::{Algorithm, AlgorithmTable, Comparison},
+    generate::{Generator, Rng},
+};
+
+/// 判断终结符序列是否属于文法的语言.
+pub trait Oracle<'a> {
+    /// 用于报告的名字.
+    fn name(&self) -> String {
+        "oracle".to_string()
+    }
+
+    /// `input` (不包括 [`EOF`]) 是否属于文法的语言, 无法判断时 (例如分析表在这里有冲突) 返回 [`None`].
+    fn accepts(&self, input: &[Terminal<'a>]) -> Option<bool>;
+}
+
+impl<'a, F> Oracle<'a> for F
+where
+    F: Fn(&[Terminal<'a>]) -> Option<bool>,
+{
+    fn accepts(&self, input: &[Terminal<'a>]) -> Option<bool> {
+        self(input)
+    }
+}
+
+/// 使用其他 LR 分析方法的分析表作为 oracle.
+#[derive(Debug, Clone, Copy)]
+pub struct AlgorithmOracle<'c, 't, 'a> {
+    table: &'t Table<'a>,
+    algorithm: &'c AlgorithmTable,
+}
+
+impl<'c, 't, 'a> AlgorithmOracle<'c, 't, 'a> {
+    #[must_use]
+    pub fn new(comparison: &'c Comparison<'t, 'a>, algorithm: Algorithm) -> Self {
+        Self {
+            table: comparison.table(),
+            algorithm: comparison.get(algorithm),
+        }
+    }
+}
+
+impl<'a> Oracle<'a> for AlgorithmOracle<'_, '_, 'a> {
+    fn name(&self) -> String {
+        self.algorithm.algorithm().to_string()
+    }
+
+    fn accepts(&self, input: &[Terminal<'a>]) -> Option<bool> {
+        let prods = self.table.grammar().prods();
+        let mut stack = vec![0];
+        let mut input = input.iter().copied().chain([EOF]).peekable();
+        while let Some(&term) = input.peek() {
+            let Some(col) = self.table.term_index(term) else {
+                return Some(false);
+            };
+            let state = *stack.last()?;
+            match self.algorithm.action(state, col)? {
+                ActionCell::Shift(to) => {
+                    stack.push(*to);
+                    input.next();
+                }
+                ActionCell::Reduce(prod) => {
+                    let prod = prods.get(*prod)?;
+                    stack.truncate(stack.len().checked_sub(prod.len())?);
+                    let col = self
+                        .table
+                        .non_terms()
+                        .iter()
+                        .position(|&nt| nt == prod.head())?;
+                    match self.algorithm.goto(*stack.last()?, col)? {
+                        Some(to) => stack.push(to),
+                        None => return Some(false),
+                    }
+                }
+                ActionCell::Accept => return Some(true),
+                ActionCell::Empty => return Some(false),
+                ActionCell::Conflict(_, _) => return None,
+            }
+        }
+        Some(false)
+    }
+}
+
+/// 规范 LR(1) 分析表和 oracle 判断不一致的句子.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch<'a> {
+    pub sentence: Vec<Terminal<'a>>,
+    /// 规范 LR(1) 分析表是否接受.
+    pub lr1: bool,
+    /// oracle 是否接受.
+    pub oracle: bool,
+}
+
+/// 差分验证的结果.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verification<'a> {
+    /// 两边都给出了判断的句子数量.
+    pub checked: usize,
+    /// 其中被接受的句子数量.
+    pub accepted: usize,
+    /// 任意一边无法判断的句子数量.
+    pub undecided: usize,
+    pub mismatches: Vec<Mismatch<'a>>,
+}
+
+impl Verification<'_> {
+    /// 是否没有发现不一致.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// 规范 LR(1) 分析表是否不经过错误恢复地接受 `input`.
+fn lr1_accepts<'a>(table: &Table<'a>, input: &[Terminal<'a>]) -> Option<bool> {
+    let output = Parser::new(table).parse(input.iter().copied()).ok()?;
+    Some(output.accepted() && output.report().errors() == 0)
+}
+
+/// 对句子进行一次随机的删除, 插入或者交换.
+fn mutate<'a>(
+    sentence: &[Terminal<'a>],
+    terms: &[Terminal<'a>],
+    rng: &mut Rng,
+) -> Vec<Terminal<'a>> {
+    let mut out = sentence.to_vec();
+    match rng.below(3) {
+        0 if !out.is_empty() => {
+            out.remove(rng.below(out.len()));
+        }
+        1 if out.len() >= 2 => {
+            let i = rng.below(out.len() - 1);
+            out.swap(i, i + 1);
+        }
+        _ if !terms.is_empty() => {
+            let pos = rng.below(out.len() + 1);
+            out.insert(pos, terms[rng.below(terms.len())]);
+        }
+        _ => {}
+    }
+    out
+}
+
+/// 使用 `oracle` 对 `table` 进行差分验证.
+///
+/// 检查的句子包括覆盖每个产生式的句子, `count` 个长度不超过 `max_len` 的随机句子, 以及每个句子的一个变异句子,
+/// 给定 `seed` 时结果是确定的.
+#[must_use]
+pub fn verify<'a>(
+    table: &Table<'a>,
+    oracle: &dyn Oracle<'a>,
+    seed: u64,
+    count: usize,
+    max_len: usize,
+) -> Verification<'a> {
+    let mut generator = Generator::new(table.grammar(), seed);
+    let mut sentences = generator.covering_sentences();
+    sentences.extend((0..count).filter_map(|_| generator.sentence(max_len)));
+    let terms: Vec<_> = table
+        .terms()
+        .iter()
+        .copied()
+        .filter(|&t| t != EPSILON && t != EOF)
+        .collect();
+    let mut rng = Rng::new(seed);
+    let mutated: Vec<_> = sentences
+        .iter()
+        .map(|s| mutate(s, &terms, &mut rng))
+        .collect();
+    sentences.extend(mutated);
+
+    let mut result = Verification::default();
+    for sentence in sentences {
+        let (Some(lr1), Some(expected)) =
+            (lr1_accepts(table, &sentence), oracle.accepts(&sentence))
+        else {
+            result.undecided += 1;
+            continue;
+        };
+        result.checked += 1;
+        result.accepted += usize::from(lr1);
+        if lr1 != expected {
+            result.mismatches.push(Mismatch {
+                sentence,
+                lr1,
+                oracle: expected,
+            });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use bumpalo::Bump;
+
+    use crate::{
+        Family, Grammar, Table, Terminal,
+        compare::{Algorithm, Comparison},
+        verify::{AlgorithmOracle, Oracle, verify},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn differential() {
+        let bump = Bump::new();
+        let grammar = Grammar::from_cfg(include_str!("../input.txt"), "program".into(), &bump)
+            .unwrap()
+            .augmented();
+        let family = Family::from_grammar(&grammar).unwrap();
+        let table = Table::build_from(&family).unwrap();
+        let comparison = Comparison::new(&table).unwrap();
+        let lalr = AlgorithmOracle::new(&comparison, Algorithm::Lalr);
+        assert_eq!(lalr.name(), "LALR(1)");
+        let result = verify(&table, &lalr, 7, 20, 30);
+        assert!(result.passed(), "{:?}", result.mismatches);
+        assert_eq!(result.undecided, 0);
+        assert!(result.accepted > 0 && result.accepted < result.checked);
+
+        // 总是接受的 oracle 会在变异出的错误句子上和分析表不一致.
+        let always = |_: &[Terminal]| Some(true);
+        let result = verify(&table, &always, 7, 20, 30);
+        assert!(!result.passed());
+        assert!(result.mismatches.iter().all(|m| !m.lr1 && m.oracle));
+    }
+}